@@ -35,4 +35,19 @@ pub struct LiveSigning {
 
     #[arg(short = 'n', long, default_value = "5")]
     pub samples: usize,
+
+    /// Sign the full fragment set exactly once (live and original) instead of
+    /// sweeping 1..=N fragments, e.g. to attach a profiler to a short run
+    #[arg(long)]
+    pub once: bool,
+
+    /// Regex matching the fragment file names, same as the `--fragment-pattern` of
+    /// c2patool live: init fragments have the index `init` or match an optional <init> group
+    #[arg(long, default_value = r"segment_0*(?P<index>\d+|init)\.m4s")]
+    pub fragment_pattern: String,
+
+    /// Path to the manifest definition JSON used for signing, defaults to the
+    /// bundled `signer/test.json`
+    #[arg(short, long, env = "C2PA_MANIFEST")]
+    pub manifest: Option<PathBuf>,
 }
@@ -7,10 +7,11 @@
 ///     * live_bmff should be roughly like a sawtooth plot (window size)
 ///     * fragmented_bmff should be steadily increasing
 // TODO add ffmpeg script to generate the fragments and add .gitignore for the fragments
-use std::{path::PathBuf, process::Command, time::Instant};
+use std::{ops::RangeInclusive, path::PathBuf, process::Command, time::Instant};
 
 use anyhow::{Context, Result, bail};
 use c2pa::{Builder, Signer};
+use regex::Regex;
 use serde::Serialize;
 
 use crate::{cli::LiveSigning, signer::Config};
@@ -26,17 +27,29 @@ pub struct LiveBenchmark {
     dir: PathBuf,
     output: PathBuf,
     samples: usize,
+    once: bool,
     manifest: String,
+    fragment: Regex,
 }
 
 impl LiveBenchmark {
     pub fn new(args: &LiveSigning) -> Result<Self> {
+        let manifest = match &args.manifest {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read manifest {}", path.display()))?,
+            None => include_str!("../signer/test.json").to_string(),
+        };
+        // fail before any fragment is signed
+        Builder::from_json(&manifest).context("invalid manifest JSON")?;
+
         Ok(Self {
             data: Default::default(),
             dir: args.dir.clone(),
             output: args.output.clone(),
             samples: args.samples,
-            manifest: include_str!("../signer/test.json").to_string(),
+            once: args.once,
+            manifest,
+            fragment: Regex::new(&args.fragment_pattern).context("invalid fragment pattern")?,
         })
     }
 
@@ -56,6 +69,15 @@ impl LiveBenchmark {
         self.run_original()?;
         self.save()?;
 
+        if self.once {
+            log::info!(
+                "signed {} fragment(s) once: live {:?} ms, original {:?} ms",
+                self.get_paths()?.1.len(),
+                self.data.live.concat(),
+                self.data.og.concat()
+            );
+        }
+
         Ok(())
     }
 
@@ -74,11 +96,12 @@ impl LiveBenchmark {
             std::fs::create_dir_all(dir)?;
         }
 
-        for num in 0..self.samples {
-            log::info!("starting live run #{}/{}", num + 1, self.samples);
+        let samples = self.samples();
+        for num in 0..samples {
+            log::info!("starting live run #{}/{}", num + 1, samples);
             let mut data = Vec::new();
 
-            for i in 1..(fragments.len() + 1) {
+            for i in self.fragment_counts(fragments.len()) {
                 log::info!("signing {i} / {} fragment(s)", fragments.len());
                 let mut builder = self.builder()?;
                 let signer = self.signer()?;
@@ -89,7 +112,7 @@ impl LiveBenchmark {
             }
 
             self.data.live.push(data);
-            log::info!("finished live run #{}/{}", num + 1, self.samples);
+            log::info!("finished live run #{}/{}", num + 1, samples);
         }
 
         log::info!("finished live");
@@ -107,16 +130,17 @@ impl LiveBenchmark {
             .join("signed_fragments")
             .join(init.file_name().context("invalid init path")?);
 
-        for num in 0..self.samples {
+        let samples = self.samples();
+        for num in 0..samples {
             let dir = out.parent().context("invalid output")?;
             if !dir.exists() {
                 std::fs::create_dir_all(dir)?;
             }
 
-            log::info!("starting original run #{}/{}", num + 1, self.samples);
+            log::info!("starting original run #{}/{}", num + 1, samples);
             let mut data = Vec::new();
 
-            for i in 1..(fragments.len() + 1) {
+            for i in self.fragment_counts(fragments.len()) {
                 log::info!("signing {i} / {} fragment(s)", fragments.len());
                 let mut builder = self.builder()?;
                 let signer = self.signer()?;
@@ -131,13 +155,23 @@ impl LiveBenchmark {
             }
 
             self.data.og.push(data);
-            log::info!("finished original run #{}/{}", num + 1, self.samples);
+            log::info!("finished original run #{}/{}", num + 1, samples);
         }
 
         log::info!("finished original");
         Ok(())
     }
 
+    /// number of runs, a single one in `--once` mode
+    fn samples(&self) -> usize {
+        if self.once { 1 } else { self.samples }
+    }
+
+    /// numbers of fragments signed per run, only the full set in `--once` mode
+    fn fragment_counts(&self, total: usize) -> RangeInclusive<usize> {
+        if self.once { total..=total } else { 1..=total }
+    }
+
     fn get_paths(&self) -> Result<(PathBuf, Vec<PathBuf>)> {
         let mut init = None;
         let mut fragments = Vec::new();
@@ -145,29 +179,38 @@ impl LiveBenchmark {
         for entry in self.dir.read_dir()? {
             let entry = entry?.path();
 
-            if let Some(file) = entry.file_name() {
-                if file.to_str().context("invalid file name")?.contains("init") {
-                    match init {
-                        None => {
-                            init = Some(entry);
-                            continue;
-                        }
-                        Some(_) => bail!("multiple init fragments found"),
-                    }
-                }
-            }
-
-            if let Some(ext) = entry.extension() {
-                if ext.eq_ignore_ascii_case("m4s") {
-                    fragments.push(entry);
+            let file = entry
+                .file_name()
+                .and_then(|f| f.to_str())
+                .context("invalid file name")?;
+            let Some(capture) = self.fragment.captures(file) else {
+                continue;
+            };
+
+            let is_init = capture.name("init").is_some()
+                || capture.name("index").is_some_and(|i| i.as_str() == "init");
+            if is_init {
+                match init {
+                    None => init = Some(entry),
+                    Some(_) => bail!("multiple init fragments found"),
                 }
+            } else {
+                let index: u32 = capture
+                    .name("index")
+                    .context("fragment without index")?
+                    .as_str()
+                    .parse()
+                    .with_context(|| format!("invalid fragment index in {file}"))?;
+                fragments.push((index, entry));
             }
         }
 
-        fragments.sort();
+        // numeric order, file names aren't necessarily zero-padded
+        fragments.sort_by_key(|(index, _)| *index);
+        let fragments = fragments.into_iter().map(|(_, path)| path).collect();
 
         let Some(init) = init else {
-            bail!("failed to find init fragment! expected one file to have <init> in its name")
+            bail!("failed to find init fragment! expected one file to match the init pattern")
         };
 
         Ok((init, fragments))
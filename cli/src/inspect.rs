@@ -0,0 +1,206 @@
+//! lists the top level boxes of a fragment and decodes its C2PA boxes
+//!
+//! a fragment signed with a Merkle tree carries one `BmffMerkleMap` box per track, a
+//! fragment signed with the rolling hash one `FragmentRollingHash` box (except for the
+//! first fragment of a stream). both are C2PA boxes with the `merkle` purpose, the
+//! fragment is read as either of them.
+
+use std::{fs::File, path::Path};
+
+use anyhow::{Context, Result};
+use c2pa::{
+    assertions::{BmffMerkleMap, FragmentRollingHash},
+    asset_handlers::bmff_io::{
+        read_bmff_fragment_c2pa_boxes, BoxInfoLite, C2PABmffBoxesRollingHash,
+    },
+};
+use c2pa_crypto::base64;
+use treeline::Tree;
+
+/// decoded C2PA boxes of a fragment along with their position
+enum C2paBoxes {
+    Merkle(Vec<(BoxInfoLite, BmffMerkleMap)>),
+    RollingHash(Vec<(BoxInfoLite, FragmentRollingHash)>),
+}
+
+impl C2paBoxes {
+    fn summary(&self) -> String {
+        match self {
+            Self::Merkle(boxes) if !boxes.is_empty() => format!("{} Merkle box(es)", boxes.len()),
+            Self::RollingHash(boxes) if !boxes.is_empty() => "rolling hash box".to_string(),
+            _ => "no Merkle or rolling hash box".to_string(),
+        }
+    }
+
+    /// the decoded box at `offset` as a tree, `None` if it isn't a Merkle or rolling hash box
+    fn node(&self, offset: u64) -> Option<Tree<String>> {
+        match self {
+            Self::Merkle(boxes) => {
+                let (info, mm) = boxes.iter().find(|(info, _)| info.offset == offset)?;
+                let mut node = Tree::root(format!("{} C2PA Merkle map", box_label(info)));
+                node.push(Tree::root(format!(
+                    "unique id {}, local id {}",
+                    mm.unique_id, mm.local_id
+                )));
+                node.push(Tree::root(format!("location {}", mm.location)));
+                node.push(Tree::root(format!(
+                    "proof of {} hash(es)",
+                    mm.hashes.as_ref().map_or(0, |hashes| hashes.len())
+                )));
+                Some(node)
+            }
+            Self::RollingHash(boxes) => {
+                let (info, rh) = boxes.iter().find(|(info, _)| info.offset == offset)?;
+                let mut node = Tree::root(format!("{} C2PA rolling hash", box_label(info)));
+                node.push(Tree::root(match rh.anchor_point() {
+                    Some(anchor_point) => format!("anchor point {}", base64::encode(anchor_point)),
+                    None => "no anchor point".to_string(),
+                }));
+                let xpaths: Vec<_> = rh.exclusions().iter().map(|e| e.xpath.as_str()).collect();
+                node.push(Tree::root(format!("exclusions {}", xpaths.join(", "))));
+                if let Some(metadata) = rh.metadata() {
+                    node.push(Tree::root(format!(
+                        "stream {}, rep {}, index {}",
+                        metadata.stream.as_deref().unwrap_or("-"),
+                        metadata.rep_id.as_deref().unwrap_or("-"),
+                        metadata
+                            .index
+                            .map_or_else(|| "-".to_string(), |index| index.to_string())
+                    )));
+                }
+                Some(node)
+            }
+        }
+    }
+}
+
+fn box_label(info: &BoxInfoLite) -> String {
+    format!("{} @{} ({} bytes)", info.path, info.offset, info.size)
+}
+
+/// Prints the box tree of a fragment with its decoded C2PA boxes
+pub fn inspect<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let mut file = File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+    let size = file.metadata()?.len();
+
+    let (box_infos, manifest_bytes, c2pa_boxes) = match read_bmff_fragment_c2pa_boxes(&mut file) {
+        Ok(boxes) => (
+            boxes.box_infos,
+            boxes.manifest_bytes,
+            C2paBoxes::Merkle(
+                boxes
+                    .bmff_merkle_box_infos
+                    .into_iter()
+                    .zip(boxes.bmff_merkle)
+                    .collect(),
+            ),
+        ),
+        // the boxes don't decode as Merkle maps, try the rolling hash
+        Err(merkle_err) => match C2PABmffBoxesRollingHash::from_reader(&mut file) {
+            Ok(boxes) => (
+                boxes.box_infos,
+                boxes.manifest_bytes,
+                C2paBoxes::RollingHash(
+                    boxes
+                        .bmff_merkle_box_infos
+                        .into_iter()
+                        .zip(boxes.rolling_hashes)
+                        .collect(),
+                ),
+            ),
+            Err(_) => return Err(merkle_err).context("failed to read the boxes of the fragment"),
+        },
+    };
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut label = format!("{name} ({size} bytes), {}", c2pa_boxes.summary());
+    if let Some(manifest) = manifest_bytes {
+        label.push_str(&format!(", C2PA manifest of {} bytes", manifest.len()));
+    }
+
+    let mut root = Tree::root(label);
+    for info in &box_infos {
+        root.push(
+            c2pa_boxes
+                .node(info.offset)
+                .unwrap_or_else(|| Tree::root(box_label(info))),
+        );
+    }
+    Ok(root.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::signer::SignConfig;
+
+    const MANIFEST: &str = r#"{
+        "assertions": [{
+            "label": "c2pa.actions",
+            "data": { "actions": [{ "action": "c2pa.published" }] }
+        }]
+    }"#;
+
+    fn bunny(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps")
+            .join(name)
+    }
+
+    #[test]
+    fn inspect_fragments() {
+        let mut config = SignConfig::from_json(
+            r#"{ "private_key": "es256_private.key", "sign_cert": "es256_certs.pem" }"#,
+        )
+        .unwrap();
+        config.set_base_path("sample");
+        let signer = config.signer().unwrap();
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+
+        let unsigned = inspect(bunny(names[0])).unwrap();
+        assert!(unsigned.contains("no Merkle or rolling hash box"));
+        assert!(unsigned.contains("moof @"));
+
+        // Merkle tree
+        let output = tempfile::tempdir().unwrap();
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        c2pa::Builder::from_json(MANIFEST)
+            .unwrap()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                output.path().join("BigBuckBunny_2s_init.mp4"),
+                Some(0),
+            )
+            .unwrap();
+        let merkle = inspect(output.path().join(names[1])).unwrap();
+        assert!(merkle.contains("1 Merkle box(es)"));
+        assert!(merkle.contains("C2PA Merkle map"));
+        assert!(merkle.contains("location 1"));
+
+        // rolling hash, the second fragment is anchored to the first one
+        let output = tempfile::tempdir().unwrap();
+        let mut builder = c2pa::Builder::from_json(MANIFEST).unwrap();
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    output.path().join("BigBuckBunny_2s_init.mp4"),
+                    None,
+                )
+                .unwrap();
+        }
+        let rolling_hash = inspect(output.path().join(names[1])).unwrap();
+        assert!(rolling_hash.contains("rolling hash box"));
+        assert!(rolling_hash.contains("anchor point "));
+        assert!(rolling_hash.contains("exclusions /uuid"));
+
+        assert!(inspect("sample/es256_certs.pem").is_err());
+    }
+}
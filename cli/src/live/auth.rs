@@ -0,0 +1,146 @@
+use anyhow::{ensure, Result};
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    Request,
+};
+
+/// shared secret protecting the ingest routes
+///
+/// managed by rocket, when `token` is `None` authentication is disabled
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IngestAuth {
+    pub token: Option<String>,
+}
+
+impl IngestAuth {
+    /// fails on an empty token, which a bare `Bearer ` header would match
+    pub fn new(token: Option<String>) -> Result<Self> {
+        let token = token.map(|t| t.trim().to_owned());
+        ensure!(
+            token.as_ref().is_none_or(|t| !t.is_empty()),
+            "the ingest token must not be empty"
+        );
+
+        Ok(Self { token })
+    }
+
+    /// checks the value of an `Authorization` header against the
+    /// configured token
+    ///
+    /// expects `Bearer <token>`
+    fn is_authorized(&self, header: Option<&str>) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let Some(bearer) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+            return false;
+        };
+
+        constant_time_eq(bearer.trim().as_bytes(), token.as_bytes())
+    }
+}
+
+/// request guard for the ingest routes
+///
+/// fails with 401 Unauthorized when a token is configured
+/// and the request doesn't carry it
+pub(crate) struct Authenticated;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authenticated {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(auth) = req.rocket().state::<IngestAuth>() else {
+            // nothing configured
+            return Outcome::Success(Self);
+        };
+
+        if auth.is_authorized(req.headers().get_one("Authorization")) {
+            Outcome::Success(Self)
+        } else {
+            log::warn!("rejected unauthorized request to {}", req.uri());
+            Outcome::Error((Status::Unauthorized, "missing or invalid token"))
+        }
+    }
+}
+
+/// compares both slices without exiting early, to not leak
+/// the position of the first mismatch through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{http::Header, local::blocking::Client};
+
+    use super::*;
+
+    #[rocket::post("/ingest")]
+    fn ingest(_auth: Authenticated) {}
+
+    #[rocket::get("/health")]
+    fn health() {}
+
+    fn client(token: Option<&str>) -> Client {
+        let rocket = rocket::build()
+            .mount("/", rocket::routes![ingest, health])
+            .manage(IngestAuth::new(token.map(str::to_string)).unwrap());
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[test]
+    fn authorized() {
+        let client = client(Some("secret"));
+        let res = client
+            .post("/ingest")
+            .header(Header::new("Authorization", "Bearer secret"))
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+    }
+
+    #[test]
+    fn empty_token() {
+        for token in ["", "  "] {
+            let err = IngestAuth::new(Some(token.to_string())).unwrap_err();
+            assert_eq!(err.to_string(), "the ingest token must not be empty");
+        }
+    }
+
+    #[test]
+    fn unauthorized() {
+        let client = client(Some("secret"));
+
+        let res = client.post("/ingest").dispatch();
+        assert_eq!(res.status(), Status::Unauthorized, "missing header");
+
+        let res = client
+            .post("/ingest")
+            .header(Header::new("Authorization", "Bearer wrong"))
+            .dispatch();
+        assert_eq!(res.status(), Status::Unauthorized, "wrong token");
+
+        let res = client
+            .post("/ingest")
+            .header(Header::new("Authorization", "secret"))
+            .dispatch();
+        assert_eq!(res.status(), Status::Unauthorized, "missing Bearer scheme");
+
+        // health stays open
+        let res = client.get("/health").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+    }
+
+    #[test]
+    fn disabled() {
+        let client = client(None);
+        let res = client.post("/ingest").dispatch();
+        assert_eq!(res.status(), Status::Ok);
+    }
+}
@@ -1,23 +1,656 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, ensure, Context, Result};
 
-#[derive(Debug, Clone)]
+use super::regexp::UriInfo;
+
+/// callback adjusting the manifest of a single fragment before it is signed
+pub(crate) type FragmentHook = dyn Fn(&mut c2pa::Builder, &UriInfo) -> Result<()> + Send + Sync;
+
+/// credentials of a stream signed with its own certificate instead of the template's
+#[derive(Clone, Debug)]
+pub(crate) struct StreamSigner {
+    pub config: crate::SignConfig,
+
+    /// bytes reserved for the signatures, see [C2PABuilder::reserve_size]
+    pub reserve_size: Option<usize>,
+}
+
+#[derive(Clone)]
 pub(crate) struct C2PABuilder {
     pub manifest_json: String,
     pub base_path: PathBuf,
+
+    /// optional per fragment callback, the static template is used as is without it
+    pub fragment_hook: Option<Arc<FragmentHook>>,
+
+    /// format and bytes of the thumbnail generated from the poster image, it is
+    /// embedded in every init manifest and replaces a thumbnail of the template
+    pub poster: Option<(String, Arc<Vec<u8>>)>,
+
+    /// algorithm of the fragment content hashes, the claim algorithm if not set
+    pub content_hash_alg: Option<String>,
+
+    /// bytes reserved for the signatures, the signer's own reserve size if not set
+    pub reserve_size: Option<usize>,
+
+    /// signers of the streams not signed with the credentials of the template, by name
+    pub stream_signers: Arc<HashMap<String, StreamSigner>>,
+}
+
+impl Debug for C2PABuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("C2PABuilder")
+            .field("manifest_json", &self.manifest_json)
+            .field("base_path", &self.base_path)
+            .field("fragment_hook", &self.fragment_hook.is_some())
+            .field("poster", &self.poster.as_ref().map(|(format, _)| format))
+            .field("content_hash_alg", &self.content_hash_alg)
+            .field("reserve_size", &self.reserve_size)
+            .field("stream_signers", &self.stream_signers.keys())
+            .finish()
+    }
 }
 
 impl C2PABuilder {
-    pub fn builder(&self) -> Result<c2pa::Builder> {
+    /// fails if `manifest_json` is not a valid manifest definition, so a bad
+    /// manifest is reported at startup instead of on the first fragment
+    pub fn new(manifest_json: String, base_path: PathBuf) -> Result<Self> {
+        c2pa::Builder::from_json(&manifest_json).context("invalid manifest JSON")?;
+
+        Ok(Self {
+            manifest_json,
+            base_path,
+            fragment_hook: None,
+            poster: None,
+            content_hash_alg: None,
+            reserve_size: None,
+            stream_signers: Arc::default(),
+        })
+    }
+
+    /// hashes the fragment content with `alg`, one of [c2pa::assertions::CONTENT_HASH_ALGS]
+    pub fn with_content_hash_alg(mut self, alg: String) -> Result<Self> {
+        if !c2pa::assertions::CONTENT_HASH_ALGS.contains(&alg.as_str()) {
+            bail!("unsupported content hash algorithm {alg}");
+        }
+
+        self.content_hash_alg = Some(alg);
+        Ok(self)
+    }
+
+    /// reserves the `reserve_size` of the template's and each stream's sign config,
+    /// checked against the probed signature size so a too small reservation fails at
+    /// startup instead of mid-stream
+    ///
+    /// with `probe` the signers without a configured reservation sign a dummy payload
+    /// once and reserve its signature size plus a margin, instead of the signer's fixed
+    /// guess which a timestamp or long chain exceeds
+    ///
+    /// the signers of [C2PABuilder::with_stream_signer] added before are covered as well
+    pub fn with_reserve_sizes(mut self, probe: bool) -> Result<Self> {
+        let mut config = crate::SignConfig::from_json(&self.manifest_json)?;
+        config.set_base_path(self.base_path.clone());
+        self.reserve_size = reserve_size(&config, probe, "the template")?;
+
+        let mut stream_signers = HashMap::with_capacity(self.stream_signers.len());
+        for (name, stream) in self.stream_signers.iter() {
+            let stream = StreamSigner {
+                config: stream.config.clone(),
+                reserve_size: reserve_size(&stream.config, probe, &format!("stream {name}"))?,
+            };
+            stream_signers.insert(name.clone(), stream);
+        }
+        self.stream_signers = Arc::new(stream_signers);
+        Ok(self)
+    }
+
+    /// signs the stream `name` with `config` instead of the credentials of the template,
+    /// the key and certificate are loaded once here so a bad config is reported at startup
+    pub fn with_stream_signer(mut self, name: String, config: crate::SignConfig) -> Result<Self> {
+        ensure!(
+            config.private_key.is_some() && config.sign_cert.is_some(),
+            "the signer of stream {name} needs a private key and a signing certificate"
+        );
+        config
+            .signer()
+            .with_context(|| format!("invalid signer of stream {name}"))?;
+
+        let stream = StreamSigner {
+            reserve_size: config.reserve_size,
+            config,
+        };
+        Arc::make_mut(&mut self.stream_signers).insert(name, stream);
+        Ok(self)
+    }
+
+    /// reads the signers of [C2PABuilder::with_stream_signer] from the JSON object at
+    /// `path`, mapping stream names to sign configs with paths relative to the file
+    pub fn with_stream_signers<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read the stream signers {path:?}"))?;
+        let configs: HashMap<String, crate::SignConfig> =
+            serde_json::from_str(&json).context("invalid stream signers JSON")?;
+
+        let base_path = path.parent().unwrap_or_else(|| Path::new(""));
+        for (name, mut config) in configs {
+            config.set_base_path(base_path);
+            self = self.with_stream_signer(name, config)?;
+        }
+        Ok(self)
+    }
+
+    /// generates the thumbnail of the init manifests from the poster image at `path`,
+    /// the image is scaled down once here instead of for every signed fragment
+    pub fn with_poster<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let format = c2pa::format_from_path(path)
+            .ok_or_else(|| anyhow!("unsupported poster format {path:?}"))?;
+        let mut file = std::fs::File::open(path).context("failed to open the poster")?;
+        let (format, image) = c2pa::make_thumbnail_from_stream(&format, &mut file)
+            .context("failed to generate the poster thumbnail")?;
+
+        self.poster = Some((format, Arc::new(image)));
+        Ok(self)
+    }
+
+    /// sets the callback invoked by [C2PABuilder::fragment_builder]
+    #[allow(dead_code)]
+    pub fn with_fragment_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut c2pa::Builder, &UriInfo) -> Result<()> + Send + Sync + 'static,
+    {
+        self.fragment_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// resources of the stream `name` are resolved within its own directory below
+    /// the base path, streams without one share the base path
+    pub fn stream_base_path(&self, name: &str) -> PathBuf {
+        let stream_path = self.base_path.join(name);
+        if stream_path.is_dir() {
+            stream_path
+        } else {
+            self.base_path.clone()
+        }
+    }
+
+    pub fn builder(&self, name: &str) -> Result<c2pa::Builder> {
         let mut builder = c2pa::Builder::from_json(&self.manifest_json)?;
-        builder.base_path = Some(self.base_path.clone());
+        builder.base_path = Some(self.stream_base_path(name));
+        if let Some((format, image)) = &self.poster {
+            builder.set_thumbnail(format.as_str(), &mut Cursor::new(image.as_slice()))?;
+        }
+        builder
+            .fragment_options
+            .content_hash_alg
+            .clone_from(&self.content_hash_alg);
+        Ok(builder)
+    }
+
+    /// creates the builder from the template and applies the fragment hook, if any
+    ///
+    /// the manifest of an ingested `init` signed before (e.g. by a camera) is
+    /// replaced in the signed init, it is recorded as its parent ingredient
+    pub fn fragment_builder<P>(&self, name: &str, info: &UriInfo, init: P) -> Result<c2pa::Builder>
+    where
+        P: AsRef<Path>,
+    {
+        let mut builder = self.builder(name)?;
+        if let Some(hook) = &self.fragment_hook {
+            hook(&mut builder, info)?;
+        }
+
+        let init = init.as_ref();
+        match c2pa::Reader::from_file(init) {
+            Ok(_) => {
+                let format = c2pa::format_from_path(init).context("unsupported init format")?;
+                let title = init
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .unwrap_or_default();
+                builder.add_ingredient_from_stream(
+                    serde_json::json!({ "title": title, "relationship": "parentOf" }).to_string(),
+                    &format,
+                    &mut std::fs::File::open(init)?,
+                )?;
+            }
+            Err(c2pa::Error::JumbfNotFound) => {}
+            Err(err) => return Err(err).context("failed to read the manifest of the init"),
+        }
+
         Ok(builder)
     }
 
     pub fn signer(&self) -> Result<Box<dyn c2pa::Signer>> {
         let mut config = crate::SignConfig::from_json(&self.manifest_json)?;
         config.set_base_path(self.base_path.clone());
-        config.signer()
+        Ok(reserved(config.signer()?, self.reserve_size))
+    }
+
+    /// the signer of the stream `name`, the template's signer unless the stream has its own
+    pub fn stream_signer(&self, name: &str) -> Result<Box<dyn c2pa::Signer>> {
+        match self.stream_signers.get(name) {
+            Some(stream) => Ok(reserved(stream.config.signer()?, stream.reserve_size)),
+            None => self.signer(),
+        }
+    }
+}
+
+/// the configured reserve size of `config` if it fits the probed signature size, else
+/// the probed one with `probe`
+fn reserve_size(config: &crate::SignConfig, probe: bool, owner: &str) -> Result<Option<usize>> {
+    if config.reserve_size.is_none() && !probe {
+        return Ok(None);
+    }
+
+    let signer = config.signer()?;
+    let probed = signer
+        .probe_reserve_size()
+        .with_context(|| format!("failed to probe the signature size of {owner}"))?;
+    match config.reserve_size {
+        Some(reserve_size) => {
+            ensure!(
+                reserve_size >= probed,
+                "the reserve size of {owner} is {reserve_size} bytes, its signatures need {probed}"
+            );
+            log::info!("reserving {reserve_size} bytes for signatures of {owner}");
+            Ok(Some(reserve_size))
+        }
+        None => {
+            log::info!(
+                "reserving {probed} bytes for signatures of {owner} instead of {}",
+                signer.reserve_size()
+            );
+            Ok(Some(probed))
+        }
+    }
+}
+
+fn reserved(signer: Box<dyn c2pa::Signer>, reserve_size: Option<usize>) -> Box<dyn c2pa::Signer> {
+    match reserve_size {
+        Some(reserve_size) => Box::new(crate::signer::ReservedSigner::new(signer, reserve_size)),
+        None => signer,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use c2pa::{
+        assertions::{labels::BMFF_HASH_2, BmffHash},
+        Reader, SigningAlg,
+    };
+
+    use super::C2PABuilder;
+    use crate::{live::regexp::Regexp, SignConfig};
+
+    const MANIFEST: &str = r#"{
+        "alg": "es256",
+        "private_key": "es256_private.key",
+        "sign_cert": "es256_certs.pem",
+        "claim_generator": "TestApp",
+        "assertions": [
+            {
+                "label": "c2pa.actions",
+                "data": { "actions": [{ "action": "c2pa.published" }] }
+            }
+        ]
+    }"#;
+
+    /// [MANIFEST] with the absolute paths of the sample es256 credentials, it signs
+    /// from any base path
+    fn manifest_with_keys() -> serde_json::Value {
+        let sample = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sample");
+        let mut manifest: serde_json::Value = serde_json::from_str(MANIFEST).unwrap();
+        manifest["private_key"] = sample.join("es256_private.key").to_str().into();
+        manifest["sign_cert"] = sample.join("es256_certs.pem").to_str().into();
+        manifest
+    }
+
+    /// signs the bunny init and two of its fragments as the stream `name` with the
+    /// signer of the stream, the signed files are read back
+    fn sign_bunny(c2pa: &C2PABuilder, name: &str, window: Option<usize>) -> c2pa::Result<Reader> {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let output = tempfile::tempdir().unwrap();
+        let init_output = output.path().join("BigBuckBunny_2s_init.mp4");
+        let fragments = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+
+        c2pa.builder(name).unwrap().sign_live_bmff(
+            c2pa.stream_signer(name).unwrap().as_ref(),
+            bunny.join("BigBuckBunny_2s_init.mp4"),
+            &fragments.iter().map(|f| bunny.join(f)).collect(),
+            init_output.clone(),
+            window,
+        )?;
+
+        let signed: Vec<_> = fragments.iter().map(|f| output.path().join(f)).collect();
+        Reader::from_fragmented_files(&init_output, &signed)
+    }
+
+    #[test]
+    /// assertions added by the hook end up in the signed output
+    fn fragment_hook() {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let output = tempfile::tempdir().unwrap();
+        let init_output = output.path().join("segment_init.m4s");
+
+        let c2pa = C2PABuilder::new(MANIFEST.to_string(), PathBuf::from("sample"))
+            .unwrap()
+            .with_fragment_hook(|builder, info| {
+                builder.add_assertion(
+                    "org.example.fragment",
+                    &serde_json::json!({ "rep": info.rep_id, "index": info.index.to_string() }),
+                )?;
+                Ok(())
+            });
+
+        let info = Regexp::default()
+            .uri("live/video/segment_000000001.m4s")
+            .unwrap();
+        let signer = c2pa.signer().unwrap();
+        let init = bunny.join("BigBuckBunny_2s_init.mp4");
+        c2pa.fragment_builder("live", &info, &init)
+            .unwrap()
+            .sign_live_bmff(
+                signer.as_ref(),
+                init.clone(),
+                &vec![bunny.join("BigBuckBunny_2s1.m4s")],
+                init_output.clone(),
+                None,
+            )
+            .unwrap();
+
+        let reader = Reader::from_file(&init_output).unwrap();
+        let data: serde_json::Value = reader
+            .active_manifest()
+            .unwrap()
+            .find_assertion("org.example.fragment")
+            .unwrap();
+        assert_eq!(data, serde_json::json!({ "rep": "video", "index": "1" }));
+    }
+
+    #[test]
+    /// the manifest of an init signed before is kept as the parent ingredient of the
+    /// signed init
+    fn resign_init() {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let dir = tempfile::tempdir().unwrap();
+        let camera_init = dir.path().join("camera/segment_init.m4s");
+        let init_output = dir.path().join("live/segment_init.m4s");
+
+        let c2pa = C2PABuilder::new(MANIFEST.to_string(), PathBuf::from("sample")).unwrap();
+        let signer = c2pa.signer().unwrap();
+        let info = Regexp::default()
+            .uri("live/video/segment_000000001.m4s")
+            .unwrap();
+        let init = bunny.join("BigBuckBunny_2s_init.mp4");
+        c2pa.fragment_builder("camera", &info, &init)
+            .unwrap()
+            .sign_live_bmff(
+                signer.as_ref(),
+                init,
+                &vec![bunny.join("BigBuckBunny_2s1.m4s")],
+                camera_init.clone(),
+                None,
+            )
+            .unwrap();
+
+        c2pa.fragment_builder("live", &info, &camera_init)
+            .unwrap()
+            .sign_live_bmff(
+                signer.as_ref(),
+                camera_init,
+                &vec![bunny.join("BigBuckBunny_2s10.m4s")],
+                init_output.clone(),
+                None,
+            )
+            .unwrap();
+
+        let reader = Reader::from_fragment(
+            "mp4",
+            std::fs::File::open(&init_output).unwrap(),
+            std::fs::File::open(dir.path().join("live/BigBuckBunny_2s10.m4s")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(reader.validation_status(), None);
+        assert_eq!(reader.manifests().len(), 2);
+
+        let active = reader.active_manifest().unwrap();
+        let ingredient = &active.ingredients()[0];
+        assert_eq!(ingredient.relationship(), &c2pa::Relationship::ParentOf);
+        assert!(ingredient.active_manifest().is_some());
+
+        let bmff_hash: BmffHash = active.find_assertion(BMFF_HASH_2).unwrap();
+        assert!(bmff_hash.previous_hash().is_none());
+    }
+
+    #[test]
+    /// relative resources resolve within the directory of each stream
+    fn stream_base_path() {
+        let sample = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sample");
+        let base = tempfile::tempdir().unwrap();
+
+        // only stream `a` ships the thumbnail
+        std::fs::create_dir(base.path().join("a")).unwrap();
+        std::fs::create_dir(base.path().join("b")).unwrap();
+        std::fs::copy(sample.join("C.jpg"), base.path().join("a/thumb.jpg")).unwrap();
+
+        let mut manifest = manifest_with_keys();
+        manifest["thumbnail"] = serde_json::json!({
+            "format": "image/jpeg",
+            "identifier": "thumb.jpg"
+        });
+        let c2pa = C2PABuilder::new(manifest.to_string(), base.path().to_path_buf()).unwrap();
+
+        assert_eq!(c2pa.stream_base_path("a"), base.path().join("a"));
+        assert_eq!(c2pa.stream_base_path("b"), base.path().join("b"));
+        assert_eq!(c2pa.stream_base_path("c"), base.path());
+
+        sign_bunny(&c2pa, "a", Some(0)).unwrap();
+        assert!(matches!(
+            sign_bunny(&c2pa, "b", Some(0)),
+            Err(c2pa::Error::ResourceNotFound(_))
+        ));
+    }
+
+    #[test]
+    /// the poster thumbnail is part of the signed init manifest
+    fn poster() {
+        let sample = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sample");
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let output = tempfile::tempdir().unwrap();
+        let init_output = output.path().join("segment_init.m4s");
+
+        let c2pa = C2PABuilder::new(MANIFEST.to_string(), PathBuf::from("sample"))
+            .unwrap()
+            .with_poster(sample.join("C.jpg"))
+            .unwrap();
+        let signer = c2pa.signer().unwrap();
+        c2pa.builder("live")
+            .unwrap()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny.join("BigBuckBunny_2s_init.mp4"),
+                &vec![bunny.join("BigBuckBunny_2s1.m4s")],
+                init_output.clone(),
+                None,
+            )
+            .unwrap();
+
+        let reader = Reader::from_file(&init_output).unwrap();
+        assert_eq!(reader.validation_status(), None);
+        let manifest = reader.active_manifest().unwrap();
+        let (format, image) = manifest.thumbnail().unwrap();
+        assert_eq!(format, "image/jpeg");
+        assert_eq!(image.as_slice(), c2pa.poster.unwrap().1.as_slice());
+
+        assert!(
+            C2PABuilder::new(MANIFEST.to_string(), PathBuf::from("sample"))
+                .unwrap()
+                .with_poster(sample.join("missing.jpg"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    /// the fragments are hashed with the configured algorithm and verify
+    fn content_hash_alg() {
+        let c2pa = C2PABuilder::new(manifest_with_keys().to_string(), PathBuf::from("sample"))
+            .unwrap()
+            .with_content_hash_alg("sha384".to_string())
+            .unwrap();
+
+        let reader = sign_bunny(&c2pa, "live", Some(0)).unwrap();
+        assert_eq!(reader.validation_status(), None);
+        let bmff_hash: BmffHash = reader
+            .active_manifest()
+            .unwrap()
+            .find_assertion(BMFF_HASH_2)
+            .unwrap();
+        assert_eq!(bmff_hash.alg().unwrap(), "sha384");
+
+        let err = C2PABuilder::new(MANIFEST.to_string(), PathBuf::from("sample"))
+            .unwrap()
+            .with_content_hash_alg("md5".to_string())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "unsupported content hash algorithm md5");
+    }
+
+    #[test]
+    /// the probed reserve size replaces the signer's own and the fragments still verify
+    fn probe_reserve_size() {
+        let c2pa = C2PABuilder::new(manifest_with_keys().to_string(), PathBuf::from("sample"))
+            .unwrap()
+            .with_reserve_sizes(true)
+            .unwrap();
+
+        let reserve_size = c2pa.reserve_size.unwrap();
+        let signer = c2pa.signer().unwrap();
+        assert_eq!(signer.reserve_size(), reserve_size);
+        assert_eq!(signer.probe_reserve_size().unwrap(), reserve_size);
+
+        let reader = sign_bunny(&c2pa, "live", Some(0)).unwrap();
+        assert_eq!(reader.validation_status(), None);
+    }
+
+    #[test]
+    /// a configured reserve size is kept if it fits the probed signature, a too small
+    /// one fails before anything is signed
+    fn configured_reserve_size() {
+        let sample = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sample");
+        let mut manifest = manifest_with_keys();
+
+        manifest["reserve_size"] = 20000.into();
+        let c2pa = C2PABuilder::new(manifest.to_string(), sample.clone())
+            .unwrap()
+            .with_reserve_sizes(false)
+            .unwrap();
+        assert_eq!(c2pa.reserve_size, Some(20000));
+        assert_eq!(c2pa.signer().unwrap().reserve_size(), 20000);
+
+        manifest["reserve_size"] = 100.into();
+        let err = C2PABuilder::new(manifest.to_string(), sample.clone())
+            .unwrap()
+            .with_reserve_sizes(false)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("the reserve size of the template is 100 bytes, its signatures need "),
+            "{}",
+            err
+        );
+
+        // the stream's own reservation is checked as well
+        let config = SignConfig {
+            private_key: Some(sample.join("es256_private.key")),
+            sign_cert: Some(sample.join("es256_certs.pem")),
+            reserve_size: Some(100),
+            ..Default::default()
+        };
+        let err = C2PABuilder::new(MANIFEST.to_string(), sample)
+            .unwrap()
+            .with_stream_signer("b".to_string(), config)
+            .unwrap()
+            .with_reserve_sizes(false)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("the reserve size of stream b is 100 bytes"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn invalid_manifest() {
+        let err = C2PABuilder::new("{ \"assertions\": 1 }".to_string(), PathBuf::from("sample"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "invalid manifest JSON");
+        assert!(C2PABuilder::new("not json".to_string(), PathBuf::from("sample")).is_err());
+    }
+
+    #[test]
+    /// each stream is signed with its own certificate and verifies against it
+    fn stream_signers() {
+        let sample = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sample");
+
+        // `b` signs with its own ps256 credentials, `a` with the es256 ones of the template
+        let tenant = tempfile::tempdir().unwrap();
+        std::fs::copy(sample.join("ps256.pem"), tenant.path().join("ps256.pem")).unwrap();
+        std::fs::copy(sample.join("ps256.pub"), tenant.path().join("ps256.pub")).unwrap();
+        let signers = tenant.path().join("signers.json");
+        std::fs::write(
+            &signers,
+            r#"{ "b": { "alg": "ps256", "private_key": "ps256.pem", "sign_cert": "ps256.pub" } }"#,
+        )
+        .unwrap();
+
+        let c2pa = C2PABuilder::new(MANIFEST.to_string(), sample.clone())
+            .unwrap()
+            .with_stream_signers(&signers)
+            .unwrap()
+            .with_reserve_sizes(true)
+            .unwrap();
+        let reserve_size = c2pa.stream_signers["b"].reserve_size.unwrap();
+        assert_eq!(
+            c2pa.stream_signer("b").unwrap().reserve_size(),
+            reserve_size
+        );
+
+        for (name, alg, cert) in [
+            ("a", SigningAlg::Es256, sample.join("es256_certs.pem")),
+            ("b", SigningAlg::Ps256, sample.join("ps256.pub")),
+        ] {
+            let reader = sign_bunny(&c2pa, name, Some(0)).unwrap();
+            assert_eq!(reader.validation_status(), None);
+            let info = reader.active_manifest().unwrap().signature_info().unwrap();
+            assert_eq!(info.alg, Some(alg));
+
+            let certs = std::fs::read_to_string(cert).unwrap();
+            let leaf = certs.split("-----END CERTIFICATE-----").next().unwrap();
+            assert!(info.cert_chain().starts_with(leaf.trim()), "{}", name);
+        }
+
+        let err = C2PABuilder::new(MANIFEST.to_string(), sample)
+            .unwrap()
+            .with_stream_signer("c".to_string(), SignConfig::default())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the signer of stream c needs a private key and a signing certificate"
+        );
     }
 }
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use rocket::tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// bounds the number of fragments signed at once
+///
+/// during a burst the ingest waits for a running signature to finish, instead of
+/// piling up signing threads which each read the growing stream directories
+pub(crate) struct InFlight {
+    semaphore: Arc<Semaphore>,
+    max: usize,
+}
+
+impl InFlight {
+    pub fn new(max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            max,
+        }
+    }
+
+    /// waits for a free slot, it is taken until the permit is dropped
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        log::warn!(
+            "{} fragments are being signed, delaying the ingest until one is done",
+            self.max
+        );
+        Ok(self.semaphore.clone().acquire_owned().await?)
+    }
+
+    /// number of fragments being signed
+    pub fn count(&self) -> usize {
+        self.max - self.semaphore.available_permits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::InFlight;
+
+    #[test]
+    /// a burst larger than the limit never has more fragments in flight than allowed
+    fn burst() {
+        let in_flight = InFlight::new(3);
+
+        rocket::execute(async {
+            let mut peak = 0;
+            let mut signing = Vec::new();
+            for _ in 0..12 {
+                let permit = in_flight.acquire().await.unwrap();
+                peak = peak.max(in_flight.count());
+
+                // like `LiveSigner::sign`, the permit is released by the signing thread
+                signing.push(thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(20));
+                    drop(permit);
+                }));
+            }
+            assert_eq!(peak, 3);
+
+            for handle in signing {
+                handle.join().unwrap();
+            }
+        });
+        assert_eq!(in_flight.count(), 0);
+    }
+}
@@ -2,10 +2,12 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 use dashmap::DashMap;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio_retry::{strategy::FibonacciBackoff, Retry};
 
-#[derive(Debug, Serialize, Clone)]
+use crate::live::utility::FragmentTiming;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EventPayload {
     /// optional anchor point base64 encoded
     #[serde(rename = "anchorPoint")]
@@ -15,6 +17,14 @@ pub struct EventPayload {
     #[serde(rename = "rollingHash")]
     rolling_hash: Vec<u8>,
 
+    /// duration of the fragment in `timescale` units, measured while signing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<u64>,
+
+    /// timescale of the track of the fragment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timescale: Option<u32>,
+
     /// starts at 2 (MPD + MediaPlaylist)
     ///     - each read decrements
     ///     - at 0 removed from map
@@ -27,9 +37,25 @@ impl EventPayload {
         Self {
             anchor_point: ap.to_owned(),
             rolling_hash: rh.to_owned(),
+            duration: None,
+            timescale: None,
             count: 1, // TODO change to 2 when including HLS
         }
     }
+
+    pub fn with_timing(mut self, timing: Option<FragmentTiming>) -> Self {
+        self.duration = timing.map(|timing| timing.duration);
+        self.timescale = timing.map(|timing| timing.timescale);
+        self
+    }
+
+    pub fn duration(&self) -> Option<u64> {
+        self.duration
+    }
+
+    pub fn timescale(&self) -> Option<u32> {
+        self.timescale
+    }
 }
 
 #[derive(Default)]
@@ -60,10 +86,9 @@ impl Manifold {
         self.map.remove(rep);
     }
 
-    pub async fn get_json(&self, rep: &str) -> Result<Vec<u8>> {
+    /// waits for the event of `rep`, it is inserted once the fragment is signed
+    pub async fn get_event(&self, rep: &str) -> Result<EventPayload> {
         let strategy = FibonacciBackoff::from_millis(100).max_delay(Duration::from_millis(500));
-        let res = Retry::spawn(strategy, || self.get(rep)).await?;
-
-        Ok(serde_json::to_vec(&res)?)
+        Retry::spawn(strategy, || self.get(rep)).await
     }
 }
@@ -2,7 +2,6 @@ use std::{fs::File, path::Path};
 
 use anyhow::{bail, ensure, Context, Result};
 use c2pa::{
-    assertions::{self, BmffHash},
     asset_handlers::bmff_io::{bmff_to_jumbf_exclusions, read_bmff_c2pa_boxes},
     hash_stream_by_alg,
     utils::hash_utils::concat_and_hash,
@@ -11,16 +10,28 @@ use c2pa::{
 use c2pa_crypto::base64;
 use serde::Serialize;
 
-use super::regexp::{FragmentIndex, UriInfo};
+use super::{
+    regexp::{FragmentIndex, UriInfo},
+    utility::find_fragment_hash,
+    ForwardType,
+};
 
+/// the Merkle tree of the window holding a signed fragment, for a frontend to render
+///
+/// `tree[0]` are the leaves of the window in order, each following layer is half the
+/// size of the previous one up to the root. fragments not received yet and nodes
+/// above only missing fragments are `None`
 #[derive(Debug, Serialize)]
 pub struct MerkleTree {
-    init: MerkleTreeInit,
-    tree: Vec<Vec<Option<MerkleTreeNode>>>,
+    pub init: MerkleTreeInit,
+    pub tree: Vec<Vec<Option<MerkleTreeNode>>>,
 }
 
 impl MerkleTree {
-    pub fn _new<P>(name: &str, info: UriInfo, media: P, window_size: usize) -> Result<Self>
+    /// builds the tree of the window of fragment `info` of the stream `name` from the
+    /// signed fragments in `media` (i.e. `<media>/<name>_signed/`), signed with a window
+    /// of `window_size` fragments
+    pub fn new<P>(name: &str, info: UriInfo, media: P, window_size: usize) -> Result<Self>
     where
         P: AsRef<Path>,
     {
@@ -28,56 +39,40 @@ impl MerkleTree {
             FragmentIndex::Index(i) => i.to_owned() as usize,
             _ => bail!("cannot build MerkleTree from init Segment"),
         };
-        let rep = info.rep_id;
+        ensure!(idx > 0, "fragment indices start at 1");
+        ensure!(window_size > 0, "cannot build MerkleTree without a window");
+        let signed = media
+            .as_ref()
+            .join(format!("{name}_{}", ForwardType::Signed))
+            .join(info.rep_id);
 
         // TODO just set any default for init and replace in on the client with the verification result
-        let init_path = media
-            .as_ref()
-            .join(format!("signed_{name}/{rep}/segment_init.m4s"));
+        let init_path = signed.join("segment_init.m4s");
 
-        let rem = idx % window_size;
-        let from = idx - rem + 1;
-        let to = idx + (window_size - rem);
+        let merkle_idx = (idx - 1) / window_size;
+        let from = merkle_idx * window_size + 1;
+        let to = from + window_size - 1;
 
         let mut fragment_paths = Vec::new();
-        for i in from..=to {
-            fragment_paths.push(
-                media
-                    .as_ref()
-                    .join(format!("signed_{name}/{rep}/segment_{i:09}.m4s")),
-            );
+        for i in from..=idx {
+            fragment_paths.push(signed.join(format!("segment_{i:09}.m4s")));
         }
 
-        if let Err(err) = Reader::from_fragmented_files(&init_path, &fragment_paths[..rem].to_vec())
-        {
+        if let Err(err) = Reader::from_fragmented_files(&init_path, &fragment_paths) {
             log::error!("Frag: {err}");
         }
-        if let Err(err) = Reader::from_file(
-            media
-                .as_ref()
-                .join(format!("signed_{name}/{rep}/segment_init.m4s")),
-        ) {
-            log::error!("File: {err}");
-        }
 
         // read Init Manifest
-        let init = Reader::from_file(
-            media
-                .as_ref()
-                .join(format!("signed_{name}/{rep}/segment_init.m4s")),
-        )?;
-        let init = init.active_manifest().context("missing active manifest")?;
-
-        let bmff_hash: BmffHash = init.find_assertion(assertions::labels::BMFF_HASH_2)?;
+        let init = Reader::from_file(&init_path)?;
+        let bmff_hash = find_fragment_hash(&init)?;
         let merkle = bmff_hash.merkle().context("missing MerkleMaps")?;
+        let alg = bmff_hash.alg().context("missing algorithm")?;
 
-        // FIXME don't like this
-        let merkle_idx = (idx - 1) / window_size;
         ensure!(
-            merkle.len() - 1 == merkle_idx,
+            merkle.len() == merkle_idx + 1,
             "Merkle does not have enough Trees, expected {} got {}",
-            merkle.len() - 1,
-            merkle_idx
+            merkle_idx + 1,
+            merkle.len()
         );
         let merkle = &merkle[merkle_idx];
 
@@ -95,11 +90,7 @@ impl MerkleTree {
 
         let mut leaves = Vec::new();
         for i in from..=to {
-            let Ok(mut file) = File::open(
-                media
-                    .as_ref()
-                    .join(format!("signed_{name}/{rep}/segment_{i:09}.m4s")),
-            ) else {
+            let Ok(mut file) = File::open(signed.join(format!("segment_{i:09}.m4s"))) else {
                 leaves.push(None);
                 continue;
             };
@@ -112,82 +103,152 @@ impl MerkleTree {
 
             let leave = &leave.bmff_merkle[0];
 
-            let proofs = match &leave.hashes {
-                Some(hashes) => hashes.iter().map(|hash| base64::encode(hash)).collect(),
-                None => vec!["-NONE-".to_string()],
-            };
+            let proofs = leave
+                .hashes
+                .as_ref()
+                .map(|hashes| hashes.iter().map(|hash| base64::encode(hash)).collect());
 
             let exclusions = bmff_to_jumbf_exclusions(&mut file, bmff_hash.exclusions(), true)?;
-            let hash = hash_stream_by_alg(
-                bmff_hash.alg().context("missing algorithm")?,
-                &mut file,
-                Some(exclusions),
-                true,
-            )?;
+            let hash = hash_stream_by_alg(alg, &mut file, Some(exclusions), true)?;
 
             leaves.push(Some(MerkleTreeNode {
                 hash: base64::encode(&hash),
-                proofs: Some(proofs),
+                proofs,
                 name: format!("Fragment {i}"),
-                is_current: Some(i == idx),
+                is_current: i == idx,
             }));
         }
 
+        // a node without sibling is promoted to the next layer as is
         let mut num = leaves.len();
         let mut tree = vec![leaves];
-        let mut current = 0;
-        loop {
+        while tree[tree.len() - 1].len() > 1 {
             let mut layer = Vec::new();
-            'i: for (i, left) in tree[current].iter().step_by(2).enumerate() {
-                let Some(left) = left else {
-                    break 'i;
-                };
-                match &tree[current][i + 1] {
-                    Some(right) => {
-                        layer.push(Some(MerkleTreeNode {
+            for pair in tree[tree.len() - 1].chunks(2) {
+                let node = match (&pair[0], pair.get(1).and_then(Option::as_ref)) {
+                    (Some(left), Some(right)) => {
+                        let name = format!("Hash {num}");
+                        num += 1;
+                        Some(MerkleTreeNode {
                             hash: base64::encode(&concat_and_hash(
-                                bmff_hash.alg().context("missing alg")?,
+                                alg,
                                 &base64::decode(&left.hash)?,
                                 Some(&base64::decode(&right.hash)?),
                             )),
                             proofs: None,
-                            name: format!("Hash {num}"),
-                            is_current: None,
-                        }));
-                        num += 1;
+                            name,
+                            is_current: left.is_current || right.is_current,
+                        })
                     }
-                    None => layer.push(Some(MerkleTreeNode {
-                        hash: left.hash.clone(),
+                    (Some(node), None) => Some(MerkleTreeNode {
                         proofs: None,
-                        name: left.name.clone(),
-                        is_current: None,
-                    })),
-                }
+                        ..node.clone()
+                    }),
+                    (None, _) => None,
+                };
+                layer.push(node);
             }
             tree.push(layer);
-            current += 1;
-            if tree[current].len() == 1 {
-                break;
-            }
         }
 
         Ok(Self { init, tree })
     }
 }
 
+/// the Merkle map of the window stored in the init manifest, hashes are base64 encoded
 #[derive(Debug, Serialize)]
 pub struct MerkleTreeInit {
-    count: u32,
-    merkle: Vec<String>,
-    unique_id: u32,
-    local_id: u32,
-    init_hash: String,
+    pub count: u32,
+    pub merkle: Vec<String>,
+    pub unique_id: u32,
+    pub local_id: u32,
+    pub init_hash: String,
 }
 
-#[derive(Debug, Serialize)]
+/// a node of [MerkleTree], hashes are base64 encoded
+#[derive(Clone, Debug, Serialize)]
 pub struct MerkleTreeNode {
-    hash: String,
-    proofs: Option<Vec<String>>,
-    name: String,
-    is_current: Option<bool>,
+    pub hash: String,
+
+    /// proof hashes stored in the fragment of a leaf, `None` for the nodes above and
+    /// fragments stored without proof
+    pub proofs: Option<Vec<String>>,
+    pub name: String,
+
+    /// whether the node is the requested fragment or one of its ancestors
+    pub is_current: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::MerkleTree;
+    use crate::live::{regexp::Regexp, tests::sample_c2pa};
+
+    #[test]
+    /// three fragments of a window of four, the second one requested
+    fn tree_shape() {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let input = tempfile::tempdir().unwrap();
+        let media = tempfile::tempdir().unwrap();
+        let signed = media.path().join("live_signed/video");
+
+        let init = input.path().join("segment_init.m4s");
+        std::fs::copy(bunny.join("BigBuckBunny_2s_init.mp4"), &init).unwrap();
+        let mut fragments = Vec::new();
+        for (i, name) in ["2s1", "2s10", "2s11"].iter().enumerate() {
+            let fragment = input.path().join(format!("segment_{:09}.m4s", i + 1));
+            std::fs::copy(bunny.join(format!("BigBuckBunny_{name}.m4s")), &fragment).unwrap();
+            fragments.push(fragment);
+        }
+
+        let c2pa = sample_c2pa();
+        let signer = c2pa.signer().unwrap();
+        c2pa.builder("live")
+            .unwrap()
+            .sign_live_bmff(
+                signer.as_ref(),
+                init,
+                &fragments,
+                signed.join("segment_init.m4s"),
+                Some(4),
+            )
+            .unwrap();
+
+        let info = Regexp::default()
+            .uri("live/video/segment_000000002.m4s")
+            .unwrap();
+        let merkle_tree = MerkleTree::new("live", info, media.path(), 4).unwrap();
+        let tree = &merkle_tree.tree;
+        assert_eq!(tree.iter().map(Vec::len).collect::<Vec<_>>(), vec![4, 2, 1]);
+
+        let leaves: Vec<_> = tree[0].iter().flatten().collect();
+        assert_eq!(leaves.len(), 3);
+        assert!(tree[0][3].is_none());
+        assert_eq!(
+            leaves
+                .iter()
+                .map(|leaf| leaf.is_current)
+                .collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
+        assert!(leaves.iter().all(|leaf| leaf.proofs.is_some()));
+
+        // the third fragment has no sibling and is promoted as is
+        let hash = tree[1][0].as_ref().unwrap();
+        assert_eq!(hash.name, "Hash 4");
+        assert!(hash.is_current && hash.proofs.is_none());
+        let promoted = tree[1][1].as_ref().unwrap();
+        assert_eq!(promoted.name, "Fragment 3");
+        assert_eq!(promoted.hash, leaves[2].hash);
+        assert!(!promoted.is_current && promoted.proofs.is_none());
+
+        // the root matches the one stored in the init manifest
+        let root = tree[2][0].as_ref().unwrap();
+        assert!(root.is_current);
+        assert_eq!(merkle_tree.init.count, 3);
+        assert_eq!(merkle_tree.init.merkle, vec![root.hash.clone()]);
+    }
 }
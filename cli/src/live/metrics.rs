@@ -0,0 +1,152 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use dashmap::DashMap;
+
+use super::HashMode;
+
+/// name, help text and value of an exposed counter
+type Metric = (&'static str, &'static str, fn(&Counters) -> u64);
+
+const METRICS: [Metric; 5] = [
+    (
+        "c2pa_live_fragments_signed_total",
+        "Ingested fragments signed",
+        Counters::fragments,
+    ),
+    (
+        "c2pa_live_fragments_forwarded_total",
+        "Signed fragments forwarded, including the ones forwarded again with a Merkle window",
+        Counters::forwarded_fragments,
+    ),
+    (
+        "c2pa_live_bytes_signed_total",
+        "Bytes of the signed fragments forwarded",
+        Counters::bytes,
+    ),
+    (
+        "c2pa_live_sign_errors_total",
+        "Failed signings or forwards",
+        Counters::errors,
+    ),
+    (
+        "c2pa_live_manifest_divergences_total",
+        "Manifests listing other fragments than the signed ones",
+        Counters::divergences,
+    ),
+];
+
+/// running totals of one stream signed with one [HashMode]
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    fragments: AtomicU64,
+    forwarded: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+    divergences: AtomicU64,
+}
+
+impl Counters {
+    /// `fragments` ingested since the last signing were signed
+    pub fn signed(&self, fragments: u64) {
+        self.fragments.fetch_add(fragments, Ordering::Relaxed);
+    }
+
+    /// `fragments` of `bytes` were forwarded after signing
+    pub fn forwarded(&self, fragments: u64, bytes: u64) {
+        self.forwarded.fetch_add(fragments, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// signing or forwarding failed
+    pub fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// a manifest diverged from the signed fragments
+    pub fn diverged(&self) {
+        self.divergences.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn fragments(&self) -> u64 {
+        self.fragments.load(Ordering::Relaxed)
+    }
+
+    pub fn forwarded_fragments(&self) -> u64 {
+        self.forwarded.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    pub fn divergences(&self) -> u64 {
+        self.divergences.load(Ordering::Relaxed)
+    }
+}
+
+/// signing totals per stream `name` and [HashMode], served at `GET /metrics`
+///
+/// a Merkle window re-signs its earlier fragments, they are only counted again
+/// as forwarded
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    counters: DashMap<(String, HashMode), Arc<Counters>>,
+}
+
+impl Metrics {
+    /// the counters of the stream `name` signed with `mode`, shared with the signing threads
+    pub fn counters(&self, name: &str, mode: HashMode) -> Arc<Counters> {
+        self.counters
+            .entry((name.to_owned(), mode))
+            .or_default()
+            .clone()
+    }
+
+    /// the totals in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut counters: Vec<_> = self
+            .counters
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        counters.sort_by_key(|((name, mode), _)| (name.clone(), mode_label(*mode)));
+
+        let mut out = String::new();
+        for (metric, help, counter) in METRICS {
+            out.push_str(&format!(
+                "# HELP {metric} {help}\n# TYPE {metric} counter\n"
+            ));
+            for ((name, mode), counters) in &counters {
+                out.push_str(&format!(
+                    "{metric}{{stream=\"{}\",mode=\"{}\"}} {}\n",
+                    escape(name),
+                    mode_label(*mode),
+                    counter(counters)
+                ));
+            }
+        }
+        out
+    }
+}
+
+fn mode_label(mode: HashMode) -> &'static str {
+    match mode {
+        HashMode::Merkle => "merkle",
+        HashMode::RollingHash => "rolling-hash",
+    }
+}
+
+/// escapes a Prometheus label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -1,28 +1,48 @@
 use std::{
-    cmp::Ordering,
+    collections::BTreeSet,
     fmt::Display,
+    io::Cursor,
     iter::FromIterator,
     path::{Path, PathBuf},
     sync::Arc,
     thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{bail, ensure, Context, Result};
-use reqwest::{Body, IntoUrl, Response};
+use c2pa::{hash_stream_by_alg, validation_status, Reader};
+use dashmap::{mapref::entry::Entry, DashMap};
+use reqwest::{header::HeaderMap, Body, IntoUrl, Response};
+use rocket::{data::ByteUnit, tokio::sync::OwnedSemaphorePermit};
+use serde::Serialize;
 use url::Url;
-use utility::{is_fragment, is_init};
 
+pub(crate) mod auth;
 pub(crate) mod c2pa_builder;
+pub(crate) mod in_flight;
 pub(crate) mod manifold;
 pub(crate) mod merkle_tree;
+pub(crate) mod metrics;
 pub(crate) mod regexp;
 pub(crate) mod routes;
+pub(crate) mod sign_queue;
+pub(crate) mod sink;
 pub(crate) mod utility;
 
 use c2pa_builder::C2PABuilder;
-use regexp::{Regexp, UriInfo};
-
-use crate::live::{manifold::Manifold, utility::get_event_data};
+use in_flight::InFlight;
+use metrics::Metrics;
+use regexp::{FragmentIndex, Regexp, UriInfo};
+use sign_queue::{SignQueues, Ticket};
+use sink::{FileNameTransform, ForwardHeaders, OutputKey, OutputSink};
+
+use crate::live::{
+    manifold::Manifold,
+    utility::{
+        check_init_hash, emsg_exclusion, fill_emsg, find_fragment_hash, forward_signed,
+        fragment_timing, get_event_data, insert_emsg_placeholder, read_signed,
+    },
+};
 
 /// FFmpeg -window_size argument
 ///
@@ -30,6 +50,12 @@ use crate::live::{manifold::Manifold, utility::get_event_data};
 pub(super) const SEGMENT_LIST_NUM: usize = 5;
 pub(super) const ROLLING_HASH_SCHEME_URI: &str = "fame.c2pa.rolling-hash";
 
+/// local path of a signed file and its location in the output
+type ForwardPair = (PathBuf, OutputKey);
+
+/// bytes of a signed file and its location in the output
+type SignedFile = (OutputKey, Vec<u8>);
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum ForwardType {
@@ -51,6 +77,54 @@ impl Display for ForwardType {
     }
 }
 
+/// how the fragments of a signed stream are bound to its init
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum HashMode {
+    Merkle,
+    RollingHash,
+}
+
+/// where the rolling hash and anchor point of the newest fragment are signaled
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub(crate) enum EventSignaling {
+    /// `EventStream` in the MPD
+    Mpd,
+    /// `emsg` box in each rolling hash signed fragment
+    Emsg,
+    /// both `EventStream` and `emsg`
+    Both,
+}
+
+impl EventSignaling {
+    pub fn mpd(self) -> bool {
+        matches!(self, Self::Mpd | Self::Both)
+    }
+
+    pub fn emsg(self) -> bool {
+        matches!(self, Self::Emsg | Self::Both)
+    }
+}
+
+/// order of the fragments of a representation in the Merkle Tree
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub(crate) enum FragmentOrder {
+    /// numeric index in the file name
+    Name,
+    /// order of the ingest requests, or their `Fragment-Sequence` header
+    Arrival,
+}
+
+/// result of [LiveSigner::verify_fragment]
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct FragmentCheck {
+    pub valid: bool,
+    pub mode: HashMode,
+
+    /// why the fragment is invalid
+    pub reason: Option<String>,
+}
+
 pub(crate) struct LiveSigner {
     /// local directory where to save the stream to
     pub media: PathBuf,
@@ -61,8 +135,14 @@ pub(crate) struct LiveSigner {
     /// async `reqwest::Client` used to post to CDN
     pub client: reqwest::Client,
 
-    /// sync `reqwest::blocking::Client` used to post to CDN
-    pub sync_client: Arc<reqwest::blocking::Client>,
+    /// destination of the signed stream
+    pub sink: Arc<dyn OutputSink>,
+
+    /// names the signed files in the destination
+    pub file_name: Arc<dyn FileNameTransform>,
+
+    /// headers of the files posted to the CDN
+    pub forward_headers: ForwardHeaders,
 
     /// C2PA signer
     pub c2pa: C2PABuilder,
@@ -75,6 +155,58 @@ pub(crate) struct LiveSigner {
 
     /// C2PA Data distributer (used for writing Rolling Hash into Manifests)
     pub manifold: Arc<Manifold>,
+
+    /// hash of the last ingested init segment per `<name>/<rep_id>`
+    pub init_hashes: DashMap<String, Vec<u8>>,
+
+    /// maximum size of an ingested request body
+    pub max_fragment_size: ByteUnit,
+
+    /// maximum number of concurrent posts when forwarding signed fragments
+    pub forward_concurrency: usize,
+
+    /// record the stream name, rep id and index in the rolling hash box of each fragment
+    pub fragment_metadata: bool,
+
+    /// `styp` brands accepted for ingested segments, empty to accept any segment
+    pub allowed_brands: Vec<String>,
+
+    /// signings in flight (rolling hash and Merkle), ingests wait while the limit is reached
+    pub in_flight: InFlight,
+
+    /// where the rolling hash of the newest fragment is signaled
+    pub event_signaling: EventSignaling,
+
+    /// fragments of a representation arriving within this period are Merkle signed
+    /// at once, `None` signs the Merkle Tree for every fragment
+    pub sign_debounce: Option<Duration>,
+
+    /// fragments waiting for the debounced Merkle signing per `<name>/<rep_id>`
+    pub pending_signs: DashMap<String, Vec<PathBuf>>,
+
+    /// verify the signed fragments before forwarding them, nothing is forwarded
+    /// when one of them doesn't verify
+    pub verify_after_sign: bool,
+
+    /// how the fragments of a representation are ordered for signing
+    pub fragment_order: FragmentOrder,
+
+    /// position of each ingested fragment by its local path, only recorded
+    /// in [FragmentOrder::Arrival]
+    pub arrivals: DashMap<PathBuf, u64>,
+
+    /// next arrival position per `<name>/<rep_id>`
+    pub next_arrival: DashMap<String, u64>,
+
+    /// signing totals per stream, served at `GET /metrics`
+    pub metrics: Metrics,
+
+    /// rolling hash signing turns per `<name>/<rep_id>`
+    pub rolling_hash_queues: SignQueues,
+
+    /// Merkle signings per `<name>/<rep_id>`, they don't wait for their turn but
+    /// an init change waits for the ones in flight
+    pub merkle_queues: SignQueues,
 }
 
 impl LiveSigner {
@@ -125,8 +257,8 @@ impl LiveSigner {
     /// creates the output directory path of the original content
     ///
     /// `<media>/<name>/`
-    fn local(&self, name: &str, rep_id: u8) -> PathBuf {
-        self.media.join(name).join(rep_id.to_string())
+    fn local(&self, name: &str, rep_id: &str) -> PathBuf {
+        self.media.join(name).join(rep_id)
     }
 
     /// finds all paths associated with the given uri
@@ -141,7 +273,7 @@ impl LiveSigner {
         let mut fragments = Vec::new();
 
         for path in self.paths(name, uri)? {
-            if is_init(&path) {
+            if self.regex.is_init(&path) {
                 match init {
                     Some(_) => bail!("found multiple init files"),
                     None => {
@@ -155,44 +287,178 @@ impl LiveSigner {
 
         let init = init.context("missing init file")?;
 
-        fragments.sort();
+        fragments.sort_by_cached_key(|path| self.sign_order(path));
 
         Ok((init, fragments))
     }
 
-    /// collects all local signed paths + forward CDN URL pairs
+    /// records the position of an ingested fragment, either its explicit
+    /// `sequence` number or the next one after the fragments so far
+    ///
+    /// only done in [FragmentOrder::Arrival], a fragment ingested again keeps
+    /// its first position unless an explicit sequence number is given
+    pub fn record_arrival<P>(&self, name: &str, uri: P, sequence: Option<u64>) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        if self.fragment_order != FragmentOrder::Arrival {
+            return Ok(());
+        }
+
+        let UriInfo { rep_id, index: _ } = self.regex.uri(&uri)?;
+        let path = self.local_path(name, &uri, None);
+
+        let mut next = self
+            .next_arrival
+            .entry(format!("{name}/{rep_id}"))
+            .or_default();
+        let position = match (sequence, self.arrivals.get(&path)) {
+            (Some(sequence), _) => sequence,
+            (None, Some(position)) => *position,
+            (None, None) => *next,
+        };
+        let after = position
+            .checked_add(1)
+            .context("fragment sequence number out of range")?;
+        *next = (*next).max(after);
+        self.arrivals.insert(path, position);
+
+        Ok(())
+    }
+
+    /// drops the recorded position of a deleted fragment
+    pub fn forget_arrival<P>(&self, name: &str, uri: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.arrivals.remove(&self.local_path(name, uri, None));
+    }
+
+    /// position of the local `path` within its representation: the init first,
+    /// then fragments ingested without a recorded arrival by their numeric index,
+    /// then the recorded ones by their arrival
+    fn sign_order<P>(&self, path: P) -> (u8, u64)
+    where
+        P: AsRef<Path>,
+    {
+        if let Some(position) = self.arrivals.get(path.as_ref()) {
+            return (2, *position);
+        }
+        match self.regex.stream_order(path) {
+            (0, _) => (0, 0),
+            (1, index) => (1, index.into()),
+            _ => (3, 0),
+        }
+    }
+
+    /// tracks the ingested init segment of a representation
+    ///
+    /// when the init changed compared to the previously ingested one
+    /// (e.g. codec or encryption change) the signing of the fragments in
+    /// flight is awaited, then those fragments are moved out of the signing
+    /// window and the signed init files are removed, so the next fragment
+    /// starts a new rolling hash chain (and Merkle Tree) against the new init
+    /// instead of referencing a stale init hash
+    ///
+    /// returns whether the init changed
+    pub async fn track_init<P>(&self, name: &str, uri: P) -> Result<bool>
+    where
+        P: AsRef<Path>,
+    {
+        let UriInfo { rep_id, index: _ } = self.regex.uri(&uri)?;
+        let init = self.local_path(name, &uri, None);
+
+        let mut file = std::fs::File::open(&init)?;
+        let hash = hash_stream_by_alg("sha256", &mut file, None, true)?;
+
+        let key = format!("{name}/{rep_id}");
+        let changed = match self.init_hashes.insert(key.clone(), hash.clone()) {
+            Some(previous) => previous != hash,
+            None => false,
+        };
+
+        if changed {
+            log::warn!("init segment of {name}/{rep_id} changed, starting new chain");
+
+            // the tickets are held until the fragments are moved, later rolling hash
+            // signings wait for them
+            let tickets =
+                [&self.rolling_hash_queues, &self.merkle_queues].map(|queues| queues.ticket(&key));
+            let _tickets = rocket::tokio::task::spawn_blocking(move || {
+                tickets.iter().for_each(Ticket::wait);
+                tickets
+            })
+            .await?;
+
+            self.supersede_fragments(name, &rep_id)?;
+            let local = self.local(name, &rep_id);
+            self.arrivals.retain(|path, _| !path.starts_with(&local));
+            self.next_arrival.remove(&key);
+            for ty in [ForwardType::Signed, ForwardType::RollingHash] {
+                let signed = self.path_to_signed_path(name, &init, ty)?;
+                if signed.exists() {
+                    std::fs::remove_file(signed)?;
+                }
+            }
+            self.manifold.remove(&rep_id);
+        }
+
+        Ok(changed)
+    }
+
+    /// moves the ingested fragments of `rep_id` to `<media>/<name>/<rep_id>/superseded_<ms>/`,
+    /// out of the signing window of the next init
+    fn supersede_fragments(&self, name: &str, rep_id: &str) -> Result<()> {
+        let fragments: Vec<_> = self
+            .rep_paths(name, rep_id)?
+            .into_iter()
+            .filter(|path| !self.regex.is_init(path))
+            .collect();
+        if fragments.is_empty() {
+            return Ok(());
+        }
+
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let superseded = self
+            .local(name, rep_id)
+            .join(format!("superseded_{}", since_epoch.as_millis()));
+        std::fs::create_dir_all(&superseded)?;
+        for path in fragments {
+            let file_name = path.file_name().context("fragment without file name")?;
+            std::fs::rename(&path, superseded.join(file_name))?;
+        }
+
+        Ok(())
+    }
+
+    /// collects all local signed paths + output key pairs
     ///
     /// this only includes the last Merkle Tree group, according
     /// to the configured window_size
     ///
-    /// returns Vec<(local path, forward URL)>
-    fn forward<P>(&self, name: &str, uri: P, ty: ForwardType) -> Result<Vec<(PathBuf, Url)>>
+    /// returns Vec<(local path, output key)>
+    fn forward<P>(&self, name: &str, uri: P, ty: ForwardType) -> Result<Vec<ForwardPair>>
     where
         P: AsRef<Path>,
     {
-        let mut pairs = Vec::new();
+        let mut paths = self.paths(name, &uri)?;
 
-        for path in self.paths(name, uri)? {
+        // sort in ascending order, init fragment first
+        paths.sort_by_cached_key(|path| self.sign_order(path));
+
+        let mut pairs = Vec::new();
+        for path in &paths {
             pairs.push((
-                self.path_to_signed_path(name, &path, ty)?,
-                self.path_to_cdn_url(path, name, &Some(ty))?,
+                self.path_to_signed_path(name, path, ty)?,
+                self.path_to_output_key(path, name, ty)?,
             ));
         }
 
-        // sort in ascending order, init fragment first
-        pairs.sort_by(|a, b| {
-            // init always the very first
-            if is_init(&a.0) {
-                return Ordering::Less;
-            }
-            if is_init(&b.0) {
-                return Ordering::Greater;
-            }
-            a.0.cmp(&b.0)
-        });
-
         let init = pairs[0].clone();
-        ensure!(is_init(&init.0), "first forward pair is not init");
+        ensure!(
+            self.regex.is_init(&init.0),
+            "first forward pair is not init"
+        );
 
         if self.window_size == 0 {
             return Ok(pairs);
@@ -208,12 +474,27 @@ impl LiveSigner {
                 };
                 pairs.split_off(cutoff)
             }
-            // get the final group, which is being newly signed
-            _ => pairs[1..]
-                .chunks(self.window_size)
-                .last()
-                .context("missing fragments")?
-                .to_vec(),
+            // get the groups from the one of `uri` up to the final one, which are
+            // being newly signed
+            _ => {
+                let fragments = &pairs[1..];
+                ensure!(!fragments.is_empty(), "missing fragments");
+                let signed =
+                    self.path_to_signed_path(name, self.local_path(name, &uri, None), ty)?;
+                let pos = fragments
+                    .iter()
+                    .position(|(path, _)| *path == signed)
+                    .unwrap_or(fragments.len() - 1);
+                let start = pos / self.window_size * self.window_size;
+
+                // the fragments before the window are never signed again, they are
+                // ordered before it without their arrival
+                for path in &paths[1..=start] {
+                    self.arrivals.remove(path);
+                }
+
+                fragments[start..].to_vec()
+            }
         };
 
         pairs.push(init);
@@ -231,38 +512,40 @@ impl LiveSigner {
     where
         P: AsRef<Path>,
     {
-        let parts = path
-            .as_ref()
-            .to_str()
-            .context("invalid path")?
-            .split("/")
-            .map(|p| {
-                if p == name {
-                    format!("{name}_{ty}")
-                } else {
-                    p.to_string()
-                }
-            });
+        let parts = path.as_ref().components().map(|p| {
+            let p = p.as_os_str();
+            if p == name {
+                format!("{name}_{ty}").into()
+            } else {
+                p.to_owned()
+            }
+        });
         Ok(PathBuf::from_iter(parts))
     }
 
-    /// converts a local path to the corresponding CDN URL
+    /// converts a local path to the location of its signed file in the output
     ///
-    /// /path/to/media/<uri..> -> http://<target..>/<uri..>
-    fn path_to_cdn_url<P>(&self, path: P, name: &str, ty: &Option<ForwardType>) -> Result<Url>
+    /// /path/to/media/<name>/<rep_id>/<file> -> <name>_<ty>/<rep_id>/<transformed file>
+    fn path_to_output_key<P>(&self, path: P, name: &str, ty: ForwardType) -> Result<OutputKey>
     where
         P: AsRef<Path>,
     {
-        let uri = path
+        let UriInfo { rep_id, index } = self.regex.uri(&path)?;
+        let file_name = path
             .as_ref()
-            .strip_prefix(&self.media)?
-            .to_str()
-            .context("failed strip prefix")?;
-        let uri = match ty {
-            Some(t) => &uri.replace(name, &format!("{name}_{t}")),
-            None => uri,
+            .file_name()
+            .and_then(|f| f.to_str())
+            .context("invalid file name")?;
+
+        let mut key = OutputKey {
+            stream: format!("{name}_{ty}"),
+            rep_id,
+            index,
+            file_name: file_name.to_owned(),
         };
-        Ok(self.target.join(uri)?)
+        key.file_name = self.file_name.file_name(&key);
+
+        Ok(key)
     }
 
     /// reads all paths associated with the same RepID
@@ -270,20 +553,27 @@ impl LiveSigner {
     where
         P: AsRef<Path>,
     {
-        let mut paths = Vec::new();
         let UriInfo { rep_id, index: _ } = self.regex.uri(uri)?;
 
+        self.rep_paths(name, &rep_id)
+    }
+
+    /// reads all paths of the given RepID
+    fn rep_paths(&self, name: &str, rep_id: &str) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+
         for entry in self.local(name, rep_id).read_dir()? {
             let entry = entry?;
             let path = entry.path();
 
-            if !is_fragment(&path) {
-                continue;
-            }
-            let UriInfo {
+            // skip anything not matching the fragment pattern (e.g. playlists)
+            let Ok(UriInfo {
                 rep_id: comp,
                 index: _,
-            } = self.regex.uri(&path)?;
+            }) = self.regex.uri(&path)
+            else {
+                continue;
+            };
             if rep_id != comp {
                 continue;
             }
@@ -294,14 +584,118 @@ impl LiveSigner {
         Ok(paths)
     }
 
-    pub async fn post<U, T>(&self, url: U, body: Option<T>) -> Result<Response>
+    /// lists the indices of all ingested fragments of the given RepID
+    ///
+    /// used for monitoring, a missing index is a gap in the ingested stream
+    pub fn fragment_indices(&self, name: &str, rep_id: &str) -> Result<BTreeSet<u32>> {
+        let mut indices = BTreeSet::new();
+
+        for path in self.rep_paths(name, rep_id)? {
+            if let FragmentIndex::Index(index) = self.regex.uri(&path)?.index {
+                indices.insert(index);
+            }
+        }
+
+        Ok(indices)
+    }
+
+    /// cross-checks the segment URIs listed in a manifest for `rep_id` against
+    /// the fragments signed for it as type `ty`
+    ///
+    /// only the index range both list is compared, older fragments already left
+    /// the manifest window or the signed window and newer ones are still in flight.
+    /// A representation without signed fragments yet passes.
+    pub fn check_manifest_fragments(
+        &self,
+        name: &str,
+        rep_id: &str,
+        uris: &[String],
+        ty: ForwardType,
+    ) -> Result<()> {
+        let mut listed = BTreeSet::new();
+        for uri in uris {
+            let UriInfo {
+                rep_id: comp,
+                index,
+            } = self.regex.uri(uri)?;
+            ensure!(comp == rep_id, "manifest lists {uri} for RepID {rep_id}");
+            if let FragmentIndex::Index(index) = index {
+                listed.insert(index);
+            }
+        }
+
+        let signed_name = format!("{name}_{ty}");
+        if !self.local(&signed_name, rep_id).is_dir() {
+            return Ok(());
+        }
+        let signed = self.fragment_indices(&signed_name, rep_id)?;
+
+        let (Some(&first), Some(&last)) = (
+            listed.first().max(signed.first()),
+            listed.last().min(signed.last()),
+        ) else {
+            return Ok(());
+        };
+        if first > last {
+            return Ok(());
+        }
+
+        let listed: BTreeSet<_> = listed.range(first..=last).copied().collect();
+        let signed: BTreeSet<_> = signed.range(first..=last).copied().collect();
+        let unsigned: Vec<_> = listed.difference(&signed).collect();
+        let omitted: Vec<_> = signed.difference(&listed).collect();
+        ensure!(
+            unsigned.is_empty() && omitted.is_empty(),
+            "manifest of {name}/{rep_id} diverges from the {ty} fragments: \
+             unsigned {unsigned:?}, omitted {omitted:?}"
+        );
+
+        Ok(())
+    }
+
+    /// [LiveSigner::check_manifest_fragments] for both signed streams, a divergence
+    /// is logged and counted in the metrics
+    pub fn check_manifest(&self, name: &str, rep_id: &str, uris: &[String]) {
+        for (ty, mode) in [
+            (ForwardType::Signed, HashMode::Merkle),
+            (ForwardType::RollingHash, HashMode::RollingHash),
+        ] {
+            if let Err(err) = self.check_manifest_fragments(name, rep_id, uris, ty) {
+                log::error!("{err}");
+                self.metrics.counters(name, mode).diverged();
+            }
+        }
+    }
+
+    /// verifies a fragment of the signed stream `name` (i.e. `<name>_<type>`) against
+    /// the manifest of its signed init
+    ///
+    /// fails if the stream has no signed init of `rep_id`, a rolling hash init only
+    /// holds the hash of its newest fragment, older fragments don't verify against it
+    pub fn verify_fragment(
+        &self,
+        name: &str,
+        rep_id: &str,
+        fragment: Vec<u8>,
+    ) -> Result<FragmentCheck> {
+        let init = self
+            .rep_paths(name, rep_id)?
+            .into_iter()
+            .find(|path| self.regex.is_init(path))
+            .with_context(|| format!("missing signed init of {name}/{rep_id}"))?;
+
+        check_fragment(init, fragment)
+    }
+
+    pub async fn post<U, T>(&self, url: U, body: Option<T>, headers: HeaderMap) -> Result<Response>
     where
         U: IntoUrl,
         T: Into<Body>,
     {
+        let req = self.client.post(url).headers(headers);
         let res = match body {
-            Some(body) => self.client.post(url).body(body).send().await?,
-            None => self.client.post(url).send().await?,
+            Some(body) => req.body(body).send().await?,
+            None => req.send().await?,
         };
         Ok(res)
     }
@@ -321,7 +715,7 @@ impl LiveSigner {
         let init = self
             .paths(name, &uri)?
             .iter()
-            .find(|p| is_init(p))
+            .find(|p| self.regex.is_init(p))
             .context("missing init file")?
             .to_owned();
 
@@ -330,121 +724,345 @@ impl LiveSigner {
         Ok((init, fragment))
     }
 
-    fn rolling_hash_forward_urls<P1, P2>(
+    fn rolling_hash_forward<P1, P2>(
         &self,
         name: &str,
         init: P1,
         fragment: P2,
-    ) -> Result<Vec<(PathBuf, Url)>>
+    ) -> Result<Vec<ForwardPair>>
     where
         P1: AsRef<Path>,
         P2: AsRef<Path>,
     {
         let mut vec = Vec::new();
 
-        let fragment_url =
-            self.path_to_cdn_url(&fragment, name, &Some(ForwardType::RollingHash))?;
+        let fragment_key = self.path_to_output_key(&fragment, name, ForwardType::RollingHash)?;
         let fragment_path = self.path_to_signed_path(name, &fragment, ForwardType::RollingHash)?;
 
-        vec.push((fragment_path, fragment_url));
+        vec.push((fragment_path, fragment_key));
 
-        let init_url = self.path_to_cdn_url(&init, name, &Some(ForwardType::RollingHash))?;
+        let init_key = self.path_to_output_key(&init, name, ForwardType::RollingHash)?;
         let init_path = self.path_to_signed_path(name, &init, ForwardType::RollingHash)?;
 
-        vec.push((init_path, init_url));
+        vec.push((init_path, init_key));
 
         Ok(vec)
     }
 
-    pub async fn sign<P>(&self, name: &str, uri: P) -> Result<()>
+    /// splits forward pairs into the init and fragment pairs
+    fn split_init(&self, pairs: Vec<ForwardPair>) -> (Vec<ForwardPair>, Vec<ForwardPair>) {
+        pairs
+            .into_iter()
+            .partition(|(path, _)| self.regex.is_init(path))
+    }
+
+    pub async fn sign<P>(self: &Arc<Self>, name: &str, uri: P) -> Result<()>
     where
         P: AsRef<Path>,
     {
+        let rolling_hash_permit = self.in_flight.acquire().await?;
+        log::debug!(
+            "signing {:?}, {} signing(s) in flight",
+            uri.as_ref(),
+            self.in_flight.count()
+        );
+
         // Rolling Hash signing
 
         let builder = self.c2pa.clone();
         let (init, fragment) = self.rolling_hash_input_paths(name, &uri)?;
         // let output_dir = self.local_path(name, rep_id.to_string(), Some(ForwardType::RollingHash));
         let output = self.output(name, &init, ForwardType::RollingHash)?;
-        let signed_forward = self.rolling_hash_forward_urls(name, &init, &fragment)?;
-        let client = self.sync_client.clone();
+        let (signed_init, signed_fragments) =
+            self.split_init(self.rolling_hash_forward(name, &init, &fragment)?);
+        let sink = self.sink.clone();
+        let concurrency = self.forward_concurrency;
+        let fragment_metadata = self.fragment_metadata;
+        let event_signaling = self.event_signaling;
+        let verify_after_sign = self.verify_after_sign;
+        let signed_fragment =
+            self.path_to_signed_path(name, &fragment, ForwardType::RollingHash)?;
         let manifold = self.manifold.clone();
-        let UriInfo { rep_id, index: _ } = self.regex.uri(&uri)?;
+        let info = self.regex.uri(&uri)?;
+        let stream = name.to_owned();
+        let counters = self.metrics.counters(name, HashMode::RollingHash);
+        // each fragment extends the chain of the previous one, taken in ingest order
+        let key = format!("{name}/{}", info.rep_id);
+        let ticket = self.rolling_hash_queues.ticket(&key);
         thread::Builder::new()
             .name(format!("Rolling Hash {name} - {:?}", uri.as_ref()))
             .spawn(move || -> Result<()> {
-                let signer = builder.signer()?;
-                let mut c2pa = builder.builder()?;
-
-                // sign
-                if let Err(err) = c2pa.sign_live_bmff(
-                    signer.as_ref(),
-                    init,
-                    &vec![fragment],
-                    output.clone(),
-                    None,
-                ) {
-                    log::error!("Sign: {err}");
-                    bail!("Sign: {err}")
-                }
+                let _permit = rolling_hash_permit;
+                ticket.wait();
+                let sign = || -> Result<()> {
+                    let signer = builder.stream_signer(&stream)?;
+                    let mut c2pa = builder.fragment_builder(&stream, &info, &init)?;
+                    if fragment_metadata {
+                        c2pa.fragment_options.metadata = Some(info.fragment_metadata(&stream));
+                    }
 
-                // TODO extract rolling hash and anchor point and write manifold
-                let event_data = get_event_data(output)?;
-                manifold.insert(&rep_id.to_string(), event_data);
+                    // every fragment is measured, the fragments of variable length GOPs differ
+                    let timing =
+                        fragment_timing(&std::fs::read(&init)?, &std::fs::read(&fragment)?)
+                            .inspect_err(|err| log::warn!("Timing: {err}"))
+                            .ok()
+                            .flatten();
+
+                    // the emsg is signed blank and excluded from the hash, it is filled in
+                    // once the rolling hash is known
+                    let fragment = if event_signaling.emsg() {
+                        c2pa.fragment_options.exclusions.push(emsg_exclusion());
+                        insert_emsg_placeholder(&fragment, &signed_fragment)?;
+                        signed_fragment.clone()
+                    } else {
+                        fragment
+                    };
+
+                    // sign
+                    if let Err(err) = c2pa.sign_live_bmff(
+                        signer.as_ref(),
+                        init,
+                        &vec![fragment],
+                        output.clone(),
+                        None,
+                    ) {
+                        log::error!("Sign: {err}");
+                        bail!("Sign: {err}")
+                    }
 
-                // forward signed fragments to signed
-                for (path, url) in signed_forward {
-                    let buf = std::fs::read(path)?;
-                    client.post(url).body(buf).send()?;
-                }
+                    // TODO extract rolling hash and anchor point and write manifold
+                    let event_data = get_event_data(output)?.with_timing(timing);
+                    if event_signaling.emsg() {
+                        fill_emsg(&signed_fragment, &event_data)?;
+                    }
+                    if event_signaling.mpd() {
+                        manifold.insert(&info.rep_id, event_data);
+                    }
+
+                    // the signed bytes are held from here on, the files may change meanwhile
+                    let signed_init = read_signed(signed_init)?;
+                    let signed_fragments = read_signed(signed_fragments)?;
+
+                    if verify_after_sign {
+                        verify_signed(&signed_init, &signed_fragments).inspect_err(|err| {
+                            log::error!("Self-check failed, not forwarding: {err}")
+                        })?;
+                    }
+
+                    // forward signed fragments to signed
+                    let fragments = signed_fragments.len() as u64;
+                    let bytes =
+                        forward_signed(sink.as_ref(), signed_init, signed_fragments, concurrency)
+                            .inspect_err(|err| log::error!("Forward: {err}"))?;
+                    counters.signed(fragments);
+                    counters.forwarded(fragments, bytes);
 
-                Ok(())
+                    Ok(())
+                };
+
+                sign().inspect_err(|_| counters.error())
             })?;
 
         // Optimized Merkle Tree signing
 
+        let Some(debounce) = self.sign_debounce else {
+            return self.sign_merkle(name, uri.as_ref().to_path_buf()).await;
+        };
+
+        // the first fragment of a burst signs the ones arriving during the debounce as well
+        match self.pending_signs.entry(key.clone()) {
+            Entry::Occupied(mut batch) => {
+                batch.get_mut().push(uri.as_ref().to_path_buf());
+                log::debug!("{:?} is Merkle signed with the pending batch", uri.as_ref());
+                return Ok(());
+            }
+            Entry::Vacant(batch) => {
+                batch.insert(vec![uri.as_ref().to_path_buf()]);
+            }
+        }
+
+        // the ingest is answered right away, the batch is signed once the debounce is over
+        let signer = self.clone();
+        let name = name.to_owned();
+        rocket::tokio::spawn(async move {
+            rocket::tokio::time::sleep(debounce).await;
+            let sign = async {
+                let permit = signer.in_flight.acquire().await?;
+                let (_, batch) = signer
+                    .pending_signs
+                    .remove(&key)
+                    .context("missing pending batch")?;
+                let new_fragments = batch.len() as u64;
+                log::debug!(
+                    "Merkle signing {} fragment(s) of {key} at once",
+                    batch.len()
+                );
+
+                // the groups from the one of the earliest fragment on are forwarded
+                let uri = batch
+                    .into_iter()
+                    .min_by_key(|uri| signer.sign_order(signer.local_path(&name, uri, None)))
+                    .context("empty pending batch")?;
+                signer.sign_merkle_with(&name, uri, new_fragments, permit)
+            };
+            if let Err(err) = sign.await {
+                log::error!("Merkle signing {key}: {err}");
+                signer.metrics.counters(&name, HashMode::Merkle).error();
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Merkle signs the window of the fragment `uri` in a thread of its own
+    async fn sign_merkle(&self, name: &str, uri: PathBuf) -> Result<()> {
+        let permit = self.in_flight.acquire().await?;
+        self.sign_merkle_with(name, uri, 1, permit)
+    }
+
+    /// [LiveSigner::sign_merkle] holding the in flight `permit` already, `new_fragments`
+    /// were ingested since the last signing, the rest of the window is signed again
+    fn sign_merkle_with(
+        &self,
+        name: &str,
+        uri: PathBuf,
+        new_fragments: u64,
+        permit: OwnedSemaphorePermit,
+    ) -> Result<()> {
+        let merkle_info = self.regex.uri(&uri)?;
         let (init, fragments) = self.paths_to_sign(name, &uri)?;
         let output = self.output(name, &init, ForwardType::Signed)?;
-        let signed_forward = self.forward(name, &uri, ForwardType::Signed)?;
-        let client = self.sync_client.clone();
+        let (signed_init, signed_fragments) =
+            self.split_init(self.forward(name, &uri, ForwardType::Signed)?);
+        let sink = self.sink.clone();
+        let concurrency = self.forward_concurrency;
         let window_size = self.window_size;
+        let verify_after_sign = self.verify_after_sign;
         let builder = self.c2pa.clone();
+        let stream = name.to_owned();
+        let counters = self.metrics.counters(name, HashMode::Merkle);
+        // held while signing, an init change waits for it
+        let ticket = self
+            .merkle_queues
+            .ticket(&format!("{name}/{}", merkle_info.rep_id));
         thread::Builder::new()
-            .name(format!("Merkle: {name} - {:?}", uri.as_ref()))
+            .name(format!("Merkle: {name} - {:?}", uri))
             .spawn(move || -> Result<()> {
-                let signer = builder.signer()?;
-                let mut c2pa = builder.builder()?;
+                let _permit = permit;
+                let _ticket = ticket;
+                let sign = || -> Result<()> {
+                    let signer = builder.stream_signer(&stream)?;
+                    let mut c2pa = builder.fragment_builder(&stream, &merkle_info, &init)?;
+
+                    if window_size == 0 {
+                        clear_dir(&output)?;
+                    }
 
-                if window_size == 0 {
-                    clear_dir(&output)?;
-                }
+                    // sign
+                    if let Err(err) = c2pa.sign_live_bmff(
+                        signer.as_ref(),
+                        init,
+                        &fragments,
+                        output,
+                        Some(window_size),
+                    ) {
+                        log::error!("Sign: {err}");
+                        bail!("Sign: {err}")
+                    }
 
-                // sign
-                if let Err(err) = c2pa.sign_live_bmff(
-                    signer.as_ref(),
-                    init,
-                    &fragments,
-                    output,
-                    Some(window_size),
-                ) {
-                    log::error!("Sign: {err}");
-                    bail!("Sign: {err}")
-                }
+                    // the signed bytes are held from here on, the files may change meanwhile
+                    let signed_init = read_signed(signed_init)?;
+                    let signed_fragments = read_signed(signed_fragments)?;
 
-                // forward signed fragments to signed
-                for (path, url) in signed_forward {
-                    // println!("Merkle: {path:?} {}", path.exists());
-                    let buf = std::fs::read(path)?;
-                    client.post(url).body(buf).send()?;
-                }
+                    if verify_after_sign {
+                        verify_signed(&signed_init, &signed_fragments).inspect_err(|err| {
+                            log::error!("Self-check failed, not forwarding: {err}")
+                        })?;
+                    }
+
+                    // forward signed fragments to signed
+                    let fragments = signed_fragments.len() as u64;
+                    let bytes =
+                        forward_signed(sink.as_ref(), signed_init, signed_fragments, concurrency)
+                            .inspect_err(|err| log::error!("Forward: {err}"))?;
+                    counters.signed(new_fragments);
+                    counters.forwarded(fragments, bytes);
+
+                    Ok(())
+                };
 
-                Ok(())
+                sign().inspect_err(|_| counters.error())
             })?;
 
         Ok(())
     }
 }
 
+/// verifies a signed `fragment` against the manifest of the signed `init`
+fn check_fragment<P>(init: P, fragment: Vec<u8>) -> Result<FragmentCheck>
+where
+    P: AsRef<Path>,
+{
+    let init = init.as_ref();
+    let bmff_hash = find_fragment_hash(&Reader::from_file(init)?)?;
+    let mode = match (bmff_hash.merkle(), bmff_hash.rolling_hash()) {
+        (Some(_), None) => HashMode::Merkle,
+        (None, Some(_)) => HashMode::RollingHash,
+        _ => bail!("{init:?} is neither Merkle nor rolling hash signed"),
+    };
+
+    let format = c2pa::format_from_path(init).context("unsupported init format")?;
+    let init = std::fs::File::open(init)?;
+    let reason = match Reader::from_fragment(&format, init, Cursor::new(fragment)) {
+        Ok(reader) => reader.validation_status().map(|statuses| {
+            statuses
+                .iter()
+                .map(|status| status.explanation().unwrap_or(status.code()))
+                .collect::<Vec<_>>()
+                .join("; ")
+        }),
+        Err(err) => Some(err.to_string()),
+    };
+
+    Ok(FragmentCheck {
+        valid: reason.is_none(),
+        mode,
+        reason,
+    })
+}
+
+/// self-check of the freshly signed files before they are forwarded, the signed
+/// init has to validate and every fragment has to verify against it
+fn verify_signed(init: &[SignedFile], fragments: &[SignedFile]) -> Result<()> {
+    let (init_key, init) = init.first().context("missing signed init")?;
+    check_init_hash(init).with_context(|| init_key.path())?;
+    let reader = Reader::from_stream("mp4", Cursor::new(init))?;
+    // an init on its own never matches its BMFF hash, the init hash is checked
+    // along with each fragment below
+    if let Some(status) = reader.validation_status().and_then(|statuses| {
+        statuses
+            .iter()
+            .find(|status| status.code() != validation_status::ASSERTION_BMFFHASH_MISMATCH)
+    }) {
+        bail!(
+            "{} doesn't validate: {}",
+            init_key.path(),
+            status.explanation().unwrap_or(status.code())
+        );
+    }
+    let bmff_hash = find_fragment_hash(&reader)?;
+
+    for (key, fragment) in fragments {
+        bmff_hash
+            .verify_stream_segment(&mut Cursor::new(init), &mut Cursor::new(fragment), None)
+            .with_context(|| {
+                format!("{} doesn't verify against {}", key.path(), init_key.path())
+            })?;
+    }
+
+    Ok(())
+}
+
 fn clear_dir<P>(init: P) -> Result<()>
 where
     P: AsRef<Path>,
@@ -453,3 +1071,675 @@ where
     std::fs::remove_dir_all(dir)?;
     Ok(())
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// creates a [LiveSigner] writing to `media`, not connected to any CDN
+    pub(crate) fn live_signer<P>(media: P, window_size: usize) -> LiveSigner
+    where
+        P: AsRef<Path>,
+    {
+        LiveSigner {
+            media: media.as_ref().to_path_buf(),
+            target: Url::parse("http://localhost:6363/ingest/").unwrap(),
+            client: reqwest::Client::new(),
+            sink: Arc::new(sink::tests::MemorySink::default()),
+            file_name: Arc::new(sink::Identity),
+            forward_headers: Default::default(),
+            c2pa: C2PABuilder::new("{}".to_string(), media.as_ref().to_path_buf()).unwrap(),
+            regex: Default::default(),
+            window_size,
+            manifold: Default::default(),
+            init_hashes: Default::default(),
+            max_fragment_size: ByteUnit::Mebibyte(64),
+            forward_concurrency: 4,
+            fragment_metadata: false,
+            allowed_brands: Vec::new(),
+            in_flight: InFlight::new(16),
+            event_signaling: EventSignaling::Mpd,
+            sign_debounce: None,
+            pending_signs: Default::default(),
+            verify_after_sign: false,
+            fragment_order: FragmentOrder::Name,
+            arrivals: Default::default(),
+            next_arrival: Default::default(),
+            metrics: Default::default(),
+            rolling_hash_queues: Default::default(),
+            merkle_queues: Default::default(),
+        }
+    }
+
+    /// creates a [C2PABuilder] signing with the sample es256 certificate
+    pub(crate) fn sample_c2pa() -> C2PABuilder {
+        let cli = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let manifest = serde_json::json!({
+            "alg": "es256",
+            "private_key": cli.join("sample/es256_private.key"),
+            "sign_cert": cli.join("sample/es256_certs.pem"),
+            "claim_generator": "TestApp",
+            "assertions": [{
+                "label": "c2pa.actions",
+                "data": { "actions": [{ "action": "c2pa.published" }] }
+            }]
+        });
+        C2PABuilder::new(manifest.to_string(), cli.join("sample")).unwrap()
+    }
+
+    #[test]
+    fn track_init() {
+        let media = tempfile::tempdir().unwrap();
+        let signer = live_signer(media.path(), 4);
+        let uri = "0/segment_init.m4s";
+        let track_init = || rocket::execute(signer.track_init("live", uri)).unwrap();
+
+        let init = signer.local_path("live", uri, None);
+        std::fs::create_dir_all(init.parent().unwrap()).unwrap();
+        std::fs::write(&init, b"original init").unwrap();
+
+        let signed = [ForwardType::Signed, ForwardType::RollingHash]
+            .map(|ty| signer.local_path("live", uri, Some(ty)));
+        for path in &signed {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"signed init").unwrap();
+        }
+        let fragment = signer.local_path("live", "0/segment_000000001.m4s", None);
+        std::fs::write(&fragment, b"fragment").unwrap();
+
+        // first init and repeated ingest of the same init
+        assert!(!track_init());
+        assert!(!track_init());
+        assert!(signed.iter().all(|p| p.exists()));
+
+        // init replaced mid-stream, the fragments of the old one leave the window
+        std::fs::write(&init, b"changed init").unwrap();
+        assert!(track_init());
+        assert!(signed.iter().all(|p| !p.exists()));
+        assert!(!fragment.exists());
+        assert!(signer.fragment_indices("live", "0").unwrap().is_empty());
+
+        // the new init is the reference from now on
+        assert!(!track_init());
+    }
+
+    #[test]
+    /// the Merkle tree of the new init holds none of the fragments of the old one
+    fn track_init_merkle() {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../sdk/tests/fixtures/bunny");
+        let media = tempfile::tempdir().unwrap();
+        let sink = Arc::new(sink::tests::MemorySink::default());
+        let mut signer = live_signer(media.path(), 4);
+        signer.c2pa = sample_c2pa();
+        signer.sink = sink.clone();
+        let signer = Arc::new(signer);
+
+        let ingest = |uri: &str, file: &str| {
+            let path = signer.local_path("live", uri, None);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::copy(bunny.join(file), path).unwrap();
+            if signer.regex.is_init(uri) {
+                rocket::execute(signer.track_init("live", uri)).unwrap();
+            } else {
+                rocket::execute(signer.sign("live", uri)).unwrap();
+                wait_signed(&signer);
+            }
+        };
+
+        ingest(
+            "0/segment_init.m4s",
+            "bunny_89283bps/BigBuckBunny_2s_init.mp4",
+        );
+        ingest(
+            "0/segment_000000001.m4s",
+            "bunny_89283bps/BigBuckBunny_2s1.m4s",
+        );
+        ingest(
+            "0/segment_000000002.m4s",
+            "bunny_89283bps/BigBuckBunny_2s10.m4s",
+        );
+
+        // switch to another rendition
+        ingest(
+            "0/segment_init.m4s",
+            "bunny_595491bps/BigBuckBunny_2s_init.mp4",
+        );
+        let forwarded = sink.files.lock().unwrap().len();
+        ingest(
+            "0/segment_000000003.m4s",
+            "bunny_595491bps/BigBuckBunny_2s128.m4s",
+        );
+
+        let (init, fragments): (Vec<_>, Vec<_>) = sink.files.lock().unwrap()[forwarded..]
+            .iter()
+            .filter(|(key, _)| key.stream == "live_signed")
+            .cloned()
+            .partition(|(key, _)| key.index == FragmentIndex::Init);
+        let indices: Vec<_> = fragments.iter().map(|(key, _)| key.index).collect();
+        assert_eq!(indices, [FragmentIndex::Index(3)]);
+        verify_signed(&init, &fragments).unwrap();
+    }
+
+    #[test]
+    fn fragment_indices() {
+        let media = tempfile::tempdir().unwrap();
+        let signer = live_signer(media.path(), 4);
+
+        for uri in [
+            "0/segment_init.m4s",
+            "0/segment_000000001.m4s",
+            "0/segment_000000002.m4s",
+            "0/segment_000000004.m4s",
+            "0/playlist.m3u8",
+            "1/segment_000000003.m4s",
+        ] {
+            let path = signer.local_path("live", uri, None);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"fragment").unwrap();
+        }
+
+        // the init and other representations are not listed, index 3 is missing
+        let indices = signer.fragment_indices("live", "0").unwrap();
+        assert_eq!(indices, BTreeSet::from([1, 2, 4]));
+
+        assert_eq!(
+            signer.fragment_indices("live", "1").unwrap(),
+            BTreeSet::from([3])
+        );
+        assert!(signer.fragment_indices("live", "2").is_err());
+    }
+
+    #[test]
+    /// fragments are signed in numeric order, file names aren't necessarily zero-padded
+    fn paths_to_sign_order() {
+        let media = tempfile::tempdir().unwrap();
+        let signer = live_signer(media.path(), 4);
+
+        for uri in [
+            "0/segment_init.m4s",
+            "0/segment_100.m4s",
+            "0/segment_42.m4s",
+            "0/segment_9.m4s",
+            "0/segment_000000010.m4s",
+        ] {
+            let path = signer.local_path("live", uri, None);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"fragment").unwrap();
+        }
+
+        let (init, fragments) = signer.paths_to_sign("live", "0/segment_9.m4s").unwrap();
+        assert_eq!(init, signer.local_path("live", "0/segment_init.m4s", None));
+
+        let names: Vec<_> = fragments
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "segment_9.m4s",
+                "segment_000000010.m4s",
+                "segment_42.m4s",
+                "segment_100.m4s",
+            ]
+        );
+    }
+
+    #[test]
+    /// in arrival order the fragments are signed as ingested, an explicit sequence
+    /// number overrides the arrival
+    fn paths_to_sign_arrival_order() {
+        let media = tempfile::tempdir().unwrap();
+        let mut signer = live_signer(media.path(), 4);
+        signer.fragment_order = FragmentOrder::Arrival;
+
+        let names = |fragments: &[PathBuf]| -> Vec<String> {
+            fragments
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        for uri in [
+            "0/segment_init.m4s",
+            "0/segment_3.m4s",
+            "0/segment_1.m4s",
+            "0/segment_2.m4s",
+        ] {
+            let path = signer.local_path("live", uri, None);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"fragment").unwrap();
+            if !signer.regex.is_init(uri) {
+                signer.record_arrival("live", uri, None).unwrap();
+            }
+        }
+
+        let (_, fragments) = signer.paths_to_sign("live", "0/segment_2.m4s").unwrap();
+        assert_eq!(
+            names(&fragments),
+            ["segment_3.m4s", "segment_1.m4s", "segment_2.m4s"]
+        );
+
+        let pairs = signer
+            .forward("live", "0/segment_2.m4s", ForwardType::Signed)
+            .unwrap();
+        let forwarded: Vec<_> = pairs.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            names(&forwarded),
+            [
+                "segment_init.m4s",
+                "segment_2.m4s",
+                "segment_1.m4s",
+                "segment_3.m4s"
+            ]
+        );
+
+        // an explicit sequence number moves a fragment, later arrivals follow it
+        signer
+            .record_arrival("live", "0/segment_1.m4s", Some(10))
+            .unwrap();
+        let path = signer.local_path("live", "0/segment_4.m4s", None);
+        std::fs::write(path, b"fragment").unwrap();
+        signer
+            .record_arrival("live", "0/segment_4.m4s", None)
+            .unwrap();
+
+        let (_, fragments) = signer.paths_to_sign("live", "0/segment_4.m4s").unwrap();
+        assert_eq!(
+            names(&fragments),
+            [
+                "segment_3.m4s",
+                "segment_2.m4s",
+                "segment_1.m4s",
+                "segment_4.m4s"
+            ]
+        );
+
+        // no position is left after the last sequence number
+        assert!(signer
+            .record_arrival("live", "0/segment_4.m4s", Some(u64::MAX))
+            .is_err());
+    }
+
+    #[test]
+    /// the positions are dropped once the fragments leave the window, are deleted
+    /// or belong to a replaced init
+    fn arrivals_pruned() {
+        let media = tempfile::tempdir().unwrap();
+        let mut signer = live_signer(media.path(), 2);
+        signer.fragment_order = FragmentOrder::Arrival;
+
+        let ingest = |uri: &str| {
+            let path = signer.local_path("live", uri, None);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, uri).unwrap();
+            if !signer.regex.is_init(uri) {
+                signer.record_arrival("live", uri, None).unwrap();
+            }
+        };
+        let arrivals = || -> BTreeSet<PathBuf> {
+            let live = media.path().join("live");
+            signer
+                .arrivals
+                .iter()
+                .map(|entry| entry.key().strip_prefix(&live).unwrap().to_path_buf())
+                .collect()
+        };
+
+        ingest("0/segment_init.m4s");
+        ingest("1/segment_init.m4s");
+        for uri in [
+            "0/segment_2.m4s",
+            "0/segment_1.m4s",
+            "0/segment_3.m4s",
+            "1/segment_1.m4s",
+        ] {
+            ingest(uri);
+        }
+
+        // the window of segment_3 starts with it
+        signer
+            .forward("live", "0/segment_3.m4s", ForwardType::Signed)
+            .unwrap();
+        assert_eq!(
+            arrivals(),
+            BTreeSet::from(["0/segment_3.m4s".into(), "1/segment_1.m4s".into()])
+        );
+        let (_, fragments) = signer.paths_to_sign("live", "0/segment_3.m4s").unwrap();
+        let names: Vec<_> = fragments
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        // the fragments before the window are ordered by index, still ahead of it
+        assert_eq!(names, ["segment_1.m4s", "segment_2.m4s", "segment_3.m4s"]);
+
+        signer.forget_arrival("live", "0/segment_3.m4s");
+        assert_eq!(arrivals(), BTreeSet::from(["1/segment_1.m4s".into()]));
+
+        // the init of representation 1 is replaced
+        rocket::execute(signer.track_init("live", "1/segment_init.m4s")).unwrap();
+        std::fs::write(
+            signer.local_path("live", "1/segment_init.m4s", None),
+            b"changed",
+        )
+        .unwrap();
+        assert!(rocket::execute(signer.track_init("live", "1/segment_init.m4s")).unwrap());
+        assert!(arrivals().is_empty());
+        assert!(!signer.next_arrival.contains_key("live/1"));
+        assert!(signer.next_arrival.contains_key("live/0"));
+    }
+
+    #[test]
+    /// the self-check passes freshly signed fragments and catches corrupted ones
+    fn verify_signed_self_check() {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let c2pa = sample_c2pa();
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+
+        for window_size in [Some(0), None] {
+            let dir = tempfile::tempdir().unwrap();
+            let init = dir.path().join("BigBuckBunny_2s_init.mp4");
+            let mut builder = c2pa.builder("live").unwrap();
+            // the rolling hash init holds the hash of the newest fragment
+            let batches = match window_size {
+                Some(_) => vec![names.to_vec()],
+                None => names.iter().map(|name| vec![*name]).collect(),
+            };
+            for batch in batches {
+                let fragments: Vec<_> = batch.iter().map(|name| bunny.join(name)).collect();
+                builder
+                    .sign_live_bmff(
+                        c2pa.signer().unwrap().as_ref(),
+                        bunny.join("BigBuckBunny_2s_init.mp4"),
+                        &fragments,
+                        init.clone(),
+                        window_size,
+                    )
+                    .unwrap();
+            }
+
+            let pair = |path: PathBuf, index| {
+                let key = OutputKey {
+                    stream: "live_signed".to_string(),
+                    rep_id: "0".to_string(),
+                    index,
+                    file_name: path.file_name().unwrap().to_string_lossy().into_owned(),
+                };
+                (path, key)
+            };
+            let signed_init = vec![pair(init.clone(), FragmentIndex::Init)];
+            let init_name = signed_init[0].1.path();
+            let fragment = dir.path().join(names[1]);
+            let signed_fragments = match window_size {
+                Some(_) => vec![
+                    pair(dir.path().join(names[0]), FragmentIndex::Index(1)),
+                    pair(fragment.clone(), FragmentIndex::Index(10)),
+                ],
+                None => vec![pair(fragment.clone(), FragmentIndex::Index(10))],
+            };
+            let signed_init = read_signed(signed_init).unwrap();
+            let signed_fragments = read_signed(signed_fragments).unwrap();
+            verify_signed(&signed_init, &signed_fragments).unwrap();
+
+            // flip the last byte of the media data
+            let mut corrupted = signed_fragments.clone();
+            let (_, bytes) = corrupted.last_mut().unwrap();
+            *bytes.last_mut().unwrap() ^= 0xff;
+            let err = verify_signed(&signed_init, &corrupted).unwrap_err();
+            assert!(err.to_string().contains(names[1]), "{}", err);
+
+            // the init is checked as well, flip a byte of its movie header
+            let mut corrupted = signed_init.clone();
+            let (_, bytes) = corrupted.first_mut().unwrap();
+            let mvhd = bytes
+                .windows(4)
+                .position(|box_type| box_type == b"mvhd")
+                .unwrap();
+            bytes[mvhd + 20] ^= 0xff;
+            let err = verify_signed(&corrupted, &signed_fragments).unwrap_err();
+            assert!(err.to_string().contains(&init_name), "{}", err);
+        }
+    }
+
+    #[test]
+    /// the output keys are renamed, the local signed paths keep the ingested names
+    fn output_file_name() {
+        struct Infix;
+        impl FileNameTransform for Infix {
+            fn file_name(&self, key: &OutputKey) -> String {
+                format!("{}.{}", key.index, key.file_name)
+            }
+        }
+
+        let media = tempfile::tempdir().unwrap();
+        let mut signer = live_signer(media.path(), 4);
+        signer.file_name = Arc::new(Infix);
+
+        for uri in ["0/segment_init.m4s", "0/segment_000000001.m4s"] {
+            let path = signer.local_path("live", uri, None);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"fragment").unwrap();
+        }
+
+        let pairs = signer
+            .forward("live", "0/segment_000000001.m4s", ForwardType::Signed)
+            .unwrap();
+        let names: Vec<_> = pairs
+            .iter()
+            .map(|(path, key)| {
+                (
+                    path.strip_prefix(media.path()).unwrap().to_path_buf(),
+                    key.path(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (
+                    PathBuf::from("live_signed/0/segment_init.m4s"),
+                    "live_signed/0/init.segment_init.m4s".to_string()
+                ),
+                (
+                    PathBuf::from("live_signed/0/segment_000000001.m4s"),
+                    "live_signed/0/1.segment_000000001.m4s".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_manifest_fragments() {
+        let media = tempfile::tempdir().unwrap();
+        let signer = live_signer(media.path(), 4);
+        let ty = ForwardType::RollingHash;
+
+        for i in 1..=4 {
+            let uri = format!("0/segment_{i:09}.m4s");
+            let path = signer.local_path("live", uri, Some(ty));
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"signed").unwrap();
+        }
+        let uris = |indices: &[u32]| -> Vec<String> {
+            indices
+                .iter()
+                .map(|i| format!("0/segment_{i:09}.m4s"))
+                .collect()
+        };
+
+        signer
+            .check_manifest_fragments("live", "0", &uris(&[1, 2, 3, 4]), ty)
+            .unwrap();
+        // older fragments have left the manifest window
+        signer
+            .check_manifest_fragments("live", "0", &uris(&[3, 4]), ty)
+            .unwrap();
+
+        // fragment 3 was signed but is missing in the manifest
+        let err = signer
+            .check_manifest_fragments("live", "0", &uris(&[1, 2, 4]), ty)
+            .unwrap_err();
+        assert!(err.to_string().contains("unsigned [], omitted [3]"));
+
+        // fragment 5 is still in flight
+        signer
+            .check_manifest_fragments("live", "0", &uris(&[3, 4, 5]), ty)
+            .unwrap();
+
+        // fragment 2 is listed but wasn't signed
+        std::fs::remove_file(signer.local_path("live", "0/segment_000000002.m4s", Some(ty)))
+            .unwrap();
+        let err = signer
+            .check_manifest_fragments("live", "0", &uris(&[1, 2, 3, 4]), ty)
+            .unwrap_err();
+        assert!(err.to_string().contains("unsigned [2], omitted []"));
+
+        // nothing was signed for the representation yet
+        signer
+            .check_manifest_fragments("live", "1", &["1/segment_000000001.m4s".into()], ty)
+            .unwrap();
+
+        // a segment of another representation
+        assert!(signer
+            .check_manifest_fragments("live", "0", &["1/segment_000000001.m4s".into()], ty)
+            .is_err());
+    }
+
+    /// the signing threads are detached, they are done once the permits are released
+    fn wait_signed(signer: &LiveSigner) {
+        let start = std::time::Instant::now();
+        while signer.in_flight.count() > 0 {
+            assert!(start.elapsed() < Duration::from_secs(30));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    /// fragments arriving within the debounce are Merkle signed once, the rolling
+    /// hash still signs each of them
+    fn sign_debounce() {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let media = tempfile::tempdir().unwrap();
+        let sink = Arc::new(sink::tests::MemorySink::default());
+        let mut signer = live_signer(media.path(), 4);
+        signer.c2pa = sample_c2pa();
+        signer.sink = sink.clone();
+        let debounce = Duration::from_millis(500);
+        signer.sign_debounce = Some(debounce);
+        let signer = Arc::new(signer);
+
+        for (uri, file) in [
+            ("0/segment_init.m4s", "BigBuckBunny_2s_init.mp4"),
+            ("0/segment_000000001.m4s", "BigBuckBunny_2s1.m4s"),
+            ("0/segment_000000002.m4s", "BigBuckBunny_2s10.m4s"),
+            ("0/segment_000000003.m4s", "BigBuckBunny_2s11.m4s"),
+        ] {
+            let path = signer.local_path("live", uri, None);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::copy(bunny.join(file), path).unwrap();
+        }
+
+        rocket::execute(async {
+            let start = std::time::Instant::now();
+            let (a, b, c) = rocket::futures::join!(
+                signer.sign("live", "0/segment_000000001.m4s"),
+                signer.sign("live", "0/segment_000000002.m4s"),
+                signer.sign("live", "0/segment_000000003.m4s"),
+            );
+            a.and(b).and(c).unwrap();
+            // the ingests don't wait for the debounce, the batch is signed in the background
+            assert!(start.elapsed() < debounce);
+            while !signer.pending_signs.is_empty() {
+                rocket::tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        wait_signed(&signer);
+        assert!(signer.pending_signs.is_empty());
+
+        let files = sink.files.lock().unwrap();
+        let written = |stream: &str, init: bool| {
+            files
+                .iter()
+                .filter(|(key, _)| key.stream == stream)
+                .filter(|(key, _)| (key.index == FragmentIndex::Init) == init)
+                .count()
+        };
+        assert_eq!(written("live_signed", true), 1);
+        assert_eq!(written("live_signed", false), 3);
+        assert_eq!(written("live_rolling-hash", false), 3);
+    }
+
+    #[test]
+    /// the totals of both hash modes advance with every signed fragment, failures are
+    /// counted as errors
+    fn metrics() {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let media = tempfile::tempdir().unwrap();
+        let mut signer = live_signer(media.path(), 4);
+        signer.c2pa = sample_c2pa();
+        let signer = Arc::new(signer);
+
+        let ingest = |uri: &str, bytes: Vec<u8>| {
+            let path = signer.local_path("live", uri, None);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, bytes).unwrap();
+            if !signer.regex.is_init(uri) {
+                rocket::execute(signer.sign("live", uri)).unwrap();
+                wait_signed(&signer);
+            }
+        };
+        let total = |mode, counter: fn(&metrics::Counters) -> u64| {
+            counter(&signer.metrics.counters("live", mode))
+        };
+
+        ingest(
+            "0/segment_init.m4s",
+            std::fs::read(bunny.join("BigBuckBunny_2s_init.mp4")).unwrap(),
+        );
+        for (uri, file) in [
+            ("0/segment_000000001.m4s", "BigBuckBunny_2s1.m4s"),
+            ("0/segment_000000002.m4s", "BigBuckBunny_2s10.m4s"),
+        ] {
+            ingest(uri, std::fs::read(bunny.join(file)).unwrap());
+        }
+
+        // the Merkle window forwards the first fragment again with the second one
+        for mode in [HashMode::RollingHash, HashMode::Merkle] {
+            assert_eq!(total(mode, metrics::Counters::fragments), 2);
+        }
+        assert_eq!(
+            total(
+                HashMode::RollingHash,
+                metrics::Counters::forwarded_fragments
+            ),
+            2
+        );
+        assert_eq!(
+            total(HashMode::Merkle, metrics::Counters::forwarded_fragments),
+            3
+        );
+        for mode in [HashMode::RollingHash, HashMode::Merkle] {
+            assert!(total(mode, metrics::Counters::bytes) > 0);
+            assert_eq!(total(mode, metrics::Counters::errors), 0);
+        }
+
+        ingest("0/segment_000000003.m4s", b"not a fragment".to_vec());
+        assert_eq!(total(HashMode::RollingHash, metrics::Counters::errors), 1);
+        assert_eq!(total(HashMode::Merkle, metrics::Counters::errors), 1);
+
+        let rendered = signer.metrics.render();
+        assert!(rendered.contains("# TYPE c2pa_live_fragments_signed_total counter\n"));
+        assert!(rendered
+            .contains("c2pa_live_fragments_signed_total{stream=\"live\",mode=\"merkle\"} 2\n"));
+        assert!(rendered
+            .contains("c2pa_live_fragments_forwarded_total{stream=\"live\",mode=\"merkle\"} 3\n"));
+        assert!(rendered
+            .contains("c2pa_live_sign_errors_total{stream=\"live\",mode=\"rolling-hash\"} 1\n"));
+    }
+}
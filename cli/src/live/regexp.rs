@@ -1,15 +1,29 @@
 #![allow(dead_code)]
 use std::{fmt::Display, path::Path, str::FromStr};
 
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{bail, ensure, Context, Error, Result};
 use regex::Regex;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct UriInfo {
-    pub(crate) rep_id: u8,
+    pub(crate) rep_id: String,
     pub(crate) index: FragmentIndex,
 }
 
+impl UriInfo {
+    /// metadata recorded in the rolling hash box of the fragment of stream `name`
+    pub(crate) fn fragment_metadata(&self, name: &str) -> c2pa::assertions::FragmentMetadata {
+        c2pa::assertions::FragmentMetadata {
+            stream: Some(name.to_owned()),
+            rep_id: Some(self.rep_id.clone()),
+            index: match self.index {
+                FragmentIndex::Index(index) => Some(index),
+                _ => None,
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum FragmentIndex {
     Index(u32),
@@ -64,6 +78,30 @@ pub(crate) struct Regexp {
 }
 
 impl Regexp {
+    /// creates a [Regexp] with a custom fragment pattern
+    ///
+    /// the pattern needs a `rep` group and an `index` group, an init segment
+    /// either has the index `init` or matches the optional `init` group, e.g.
+    /// `(?P<rep>[^/]+)/(?:segment_(?P<index>\d+)\.m4s|(?P<init>header)\.mp4)`
+    pub fn new(fragment: &str) -> Result<Self> {
+        let fragment = Regex::new(fragment).context("invalid fragment pattern")?;
+
+        let names: Vec<_> = fragment.capture_names().flatten().collect();
+        ensure!(
+            names.contains(&"rep"),
+            "fragment pattern has no <rep> group"
+        );
+        ensure!(
+            names.contains(&"index"),
+            "fragment pattern has no <index> group"
+        );
+
+        Ok(Self {
+            fragment,
+            ..Default::default()
+        })
+    }
+
     pub fn uri<P>(&self, uri: P) -> Result<UriInfo>
     where
         P: AsRef<Path>,
@@ -71,17 +109,48 @@ impl Regexp {
         let uri = uri.as_ref().to_str().context("invalid URI")?;
         let capture = self.fragment.captures(uri).context("no matches uri")?;
 
-        let index = match &capture["index"] {
-            "init" => FragmentIndex::Init,
-            i => FragmentIndex::Index(i.parse()?),
+        let index = match (capture.name("init"), capture.name("index")) {
+            (Some(_), _) => FragmentIndex::Init,
+            (None, Some(i)) if i.as_str() == "init" => FragmentIndex::Init,
+            (None, Some(i)) => FragmentIndex::Index(i.as_str().parse()?),
+            (None, None) => bail!("no fragment index in uri"),
         };
 
         Ok(UriInfo {
-            rep_id: capture["rep"].parse()?,
+            rep_id: capture["rep"].to_owned(),
             index,
         })
     }
 
+    /// checks whether `uri` is an init segment according to the fragment pattern
+    pub fn is_init<P>(&self, uri: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        matches!(
+            self.uri(uri),
+            Ok(UriInfo {
+                index: FragmentIndex::Init,
+                ..
+            })
+        )
+    }
+
+    /// position of `uri` within its representation: the init first, then the
+    /// fragments by their numeric index, independent of zero-padded file names
+    ///
+    /// URIs not matching the fragment pattern are sorted last
+    pub fn stream_order<P>(&self, uri: P) -> (u8, u32)
+    where
+        P: AsRef<Path>,
+    {
+        match self.uri(uri).map(|info| info.index) {
+            Ok(FragmentIndex::Init) => (0, 0),
+            Ok(FragmentIndex::Index(index)) => (1, index),
+            _ => (2, 0),
+        }
+    }
+
     pub fn manifest<P>(&self, url: P) -> Result<UriInfo>
     where
         P: AsRef<Path>,
@@ -89,12 +158,12 @@ impl Regexp {
         let url = url.as_ref().to_string_lossy().to_string();
         if url.contains(".mpd") {
             Ok(UriInfo {
-                rep_id: 0,
+                rep_id: String::new(),
                 index: FragmentIndex::Manifest(ManifestTypes::Mpd),
             })
         } else if url.contains("master.m3u8") {
             Ok(UriInfo {
-                rep_id: 0,
+                rep_id: String::new(),
                 index: FragmentIndex::Manifest(ManifestTypes::Master),
             })
         } else if url.contains("media_") {
@@ -104,7 +173,7 @@ impl Regexp {
                 .context("no matches manifest")?;
 
             Ok(UriInfo {
-                rep_id: capture["rep"].parse()?,
+                rep_id: capture["rep"].to_owned(),
                 index: FragmentIndex::Manifest(ManifestTypes::Media),
             })
         } else {
@@ -116,8 +185,75 @@ impl Regexp {
 impl Default for Regexp {
     fn default() -> Self {
         Self {
-            fragment: Regex::new(r"(?P<rep>\d+)/segment_0*(?P<index>\d+|init)\.m4s").unwrap(),
-            playlist: Regex::new(r"media_(?P<rep>\d+)\.m3u8").unwrap(),
+            fragment: Regex::new(r"(?P<rep>[^/]+)/segment_0*(?P<index>\d+|init)\.m4s").unwrap(),
+            playlist: Regex::new(r"media_(?P<rep>[^/]+)\.m3u8").unwrap(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_numeric_rep_id() {
+        let regex = Regexp::default();
+
+        let info = regex.manifest("live/media_video_hd.m3u8").unwrap();
+        assert_eq!(info.rep_id, "video_hd");
+        assert_eq!(info.index, FragmentIndex::Manifest(ManifestTypes::Media));
+
+        let info = regex.uri("live/video_hd/segment_000000042.m4s").unwrap();
+        assert_eq!(info.rep_id, "video_hd");
+        assert_eq!(info.index, FragmentIndex::Index(42));
+
+        let info = regex.uri("live/0/segment_init.m4s").unwrap();
+        assert_eq!(info.rep_id, "0");
+        assert_eq!(info.index, FragmentIndex::Init);
+    }
+
+    #[test]
+    fn fragment_metadata() {
+        let regex = Regexp::default();
+
+        let info = regex.uri("live/video/segment_000000042.m4s").unwrap();
+        let metadata = info.fragment_metadata("live");
+        assert_eq!(metadata.stream.as_deref(), Some("live"));
+        assert_eq!(metadata.rep_id.as_deref(), Some("video"));
+        assert_eq!(metadata.index, Some(42));
+
+        let info = regex.uri("live/video/segment_init.m4s").unwrap();
+        assert_eq!(info.fragment_metadata("live").index, None);
+    }
+
+    #[test]
+    fn init_detection() {
+        let regex = Regexp::default();
+
+        assert!(regex.is_init("live/0/segment_init.m4s"));
+        assert!(!regex.is_init("live/0/segment_000000001.m4s"));
+
+        // file names merely containing "init" are no init segments
+        assert!(!regex.is_init("live/0/reinitialize.m4s"));
+        assert!(!regex.is_init("live/0/segment_initial.mp4"));
+    }
+
+    #[test]
+    fn custom_init_pattern() {
+        let regex =
+            Regexp::new(r"(?P<rep>[^/]+)/(?:chunk_(?P<index>\d+)\.m4s|(?P<init>header)\.mp4)")
+                .unwrap();
+
+        // init segments without "init" in their name
+        assert!(regex.is_init("live/video/header.mp4"));
+        assert!(!Regexp::default().is_init("live/video/header.mp4"));
+
+        let info = regex.uri("live/video/chunk_7.m4s").unwrap();
+        assert_eq!(info.rep_id, "video");
+        assert_eq!(info.index, FragmentIndex::Index(7));
+        assert!(!regex.is_init("live/video/chunk_7.m4s"));
+
+        assert!(Regexp::new(r"segment_(?P<index>\d+)\.m4s").is_err());
+        assert!(Regexp::new(r"(?P<rep>[^/]+)/segment\.m4s").is_err());
+    }
+}
@@ -1,70 +1,133 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 use c2pa_crypto::base64;
 use dash_mpd::{Event, EventStream};
-use rocket::{http::Status, Data, State};
+use reqwest::Body;
+use rocket::{
+    http::{ContentType, Status},
+    request::{FromRequest, Outcome},
+    serde::json::Json,
+    tokio::fs::{read, remove_file, File},
+    Data, Request, State,
+};
 
 use crate::{
     live::{
+        merkle_tree::MerkleTree,
         regexp::{FragmentIndex, ManifestTypes, UriInfo},
-        ROLLING_HASH_SCHEME_URI,
+        ForwardType, FragmentCheck, ROLLING_HASH_SCHEME_URI,
     },
     log_err,
 };
 
 use super::{
-    utility::{is_init, process_request_body},
+    auth::Authenticated,
+    utility::{
+        body_status, check_styp_brands, hls_media_uris, last_segment_timing, mpd_media_uris,
+        process_request_body, save_request_body,
+    },
     LiveSigner,
 };
 
 pub(super) type Result<T> = core::result::Result<T, Status>;
 
+/// explicit position of an ingested fragment within its representation,
+/// from the optional `Fragment-Sequence` header
+pub(crate) struct FragmentSequence(Option<u64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for FragmentSequence {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.headers().get_one("Fragment-Sequence") {
+            None => Outcome::Success(Self(None)),
+            // the last value is reserved, the fragment after it would have no position
+            Some(value) => match value.trim().parse() {
+                Ok(sequence) if sequence < u64::MAX => Outcome::Success(Self(Some(sequence))),
+                _ => Outcome::Error((Status::BadRequest, "invalid Fragment-Sequence")),
+            },
+        }
+    }
+}
+
 #[rocket::post("/<name>/<uri..>", data = "<body>")]
 pub(crate) async fn post_ingest(
     name: &str,
     uri: PathBuf,
     body: Data<'_>,
-    state: &State<LiveSigner>,
+    state: &State<Arc<LiveSigner>>,
+    _auth: Authenticated,
+    sequence: FragmentSequence,
 ) -> Result<()> {
     let local = state.local_path(name, &uri, None);
-
-    // read body and save to local disk
-    let buf = log_err!(
-        process_request_body(body, local).await,
-        "process request body"
-    )?;
-
-    // forward everything unchanged
     let url = log_err!(state.cdn_url(name, &uri, None), "cdn url <None>")?;
-    log_err!(state.post(url, Some(buf.clone())).await, "post OG content")?;
 
-    if let Ok(UriInfo { rep_id: _, index }) = state.regex.manifest(&uri) {
+    if let Ok(UriInfo { rep_id, index }) = state.regex.manifest(&uri) {
         // this is a manifest request
+        let headers = state.forward_headers.headers(&index, false);
 
         // insert C2PA data into Manifests
-        let res = match index {
+        let res: Body = match index {
             FragmentIndex::Manifest(ManifestTypes::Mpd) => {
+                // read body and save to local disk, the MPD is needed in memory
+                let buf = process_request_body(body, local, state.max_fragment_size)
+                    .await
+                    .map_err(|err| {
+                        log::error!("process request body: {err}");
+                        body_status(&err)
+                    })?;
+
+                // forward everything unchanged
+                log_err!(
+                    state.post(url, Some(buf.clone()), headers.clone()).await,
+                    "post OG content"
+                )?;
+
                 // TODO put this in the LiveSigner
                 let xml = log_err!(String::from_utf8(buf), "MPD payload not UTF-8")?;
                 let mut mpd = log_err!(dash_mpd::parse(&xml), "parse MPD")?;
 
                 for period in mpd.periods.as_mut_slice() {
                     let mut event = Vec::new();
-                    for adaptation in period.adaptations.as_mut_slice() {
-                        for representation in adaptation.representations.as_mut_slice() {
+                    for adaptation in &period.adaptations {
+                        for representation in &adaptation.representations {
                             let Some(rep_id) = &representation.id else {
                                 continue;
                             };
 
-                            let json =
-                                log_err!(state.manifold.get_json(rep_id).await, "fetch c2pa data")?;
+                            // the MPD must list exactly the fragments signed for this rep
+                            state.check_manifest(
+                                name,
+                                rep_id,
+                                &mpd_media_uris(adaptation, representation),
+                            );
+
+                            // the rolling hash is only signaled inband
+                            if !state.event_signaling.mpd() {
+                                continue;
+                            }
+
+                            let payload = log_err!(
+                                state.manifold.get_event(rep_id).await,
+                                "fetch c2pa data"
+                            )?;
+                            let json = log_err!(serde_json::to_vec(&payload), "encode c2pa data")?;
+
+                            // the event belongs to the newest segment of a $Time$ based timeline,
+                            // without one it lasts as long as the fragment measured while signing
+                            let timing = last_segment_timing(adaptation, representation);
 
                             event.push(Event {
                                 id: Some(rep_id.to_owned()),
-                                presentationTime: None,
+                                presentationTime: timing.map(|(time, _, _)| time),
                                 presentationTimeOffset: None,
-                                duration: None,
-                                timescale: None,
+                                duration: timing
+                                    .map(|(_, duration, _)| duration)
+                                    .or(payload.duration()),
+                                timescale: timing
+                                    .map(|(_, _, timescale)| timescale)
+                                    .or(payload.timescale().map(u64::from)),
                                 contentEncoding: Some("base64".to_string()),
                                 messageData: Some(base64::encode(&json)),
                                 SelectionInfo: None,
@@ -75,6 +138,9 @@ pub(crate) async fn post_ingest(
                             });
                         }
                     }
+                    if event.is_empty() {
+                        continue;
+                    }
                     period.event_streams.push(EventStream {
                         // reference to an external EventStream element
                         href: None,
@@ -95,35 +161,92 @@ pub(crate) async fn post_ingest(
                     });
                 }
 
-                let s = mpd.to_string();
-                s.as_bytes().to_vec()
+                mpd.to_string().into()
             }
-            FragmentIndex::Manifest(ManifestTypes::Master) => buf,
-            FragmentIndex::Manifest(ManifestTypes::Media) => {
+            FragmentIndex::Manifest(ManifestTypes::Master | ManifestTypes::Media) => {
+                // stream the manifest to local disk, it's forwarded unchanged
+                save_request_body(body, &local, state.max_fragment_size)
+                    .await
+                    .map_err(|err| {
+                        log::error!("save request body: {err}");
+                        body_status(&err)
+                    })?;
+
+                let file = log_err!(File::open(&local).await, "open saved manifest")?;
+                log_err!(
+                    state.post(url, Some(file), headers.clone()).await,
+                    "post OG content"
+                )?;
+
+                // the media playlist must list exactly the fragments signed for this rep
+                if index == FragmentIndex::Manifest(ManifestTypes::Media) {
+                    let playlist = log_err!(read(&local).await, "read saved playlist")?;
+                    match hls_media_uris(&playlist) {
+                        Ok(uris) => state.check_manifest(name, &rep_id, &uris),
+                        Err(err) => log::error!("{err}"),
+                    }
+                }
+
                 // TODO HLS Event stream signaling (ala Ad-Insertion)
-                buf
+                log_err!(File::open(&local).await, "open saved manifest")?.into()
             }
             _ => unreachable!("{} is not possible", index),
         };
 
         // post Manifests to CDN
         let url = log_err!(
-            state.cdn_url(name, &uri, Some(crate::live::ForwardType::RollingHash)),
+            state.cdn_url(name, &uri, Some(ForwardType::RollingHash)),
             "cdn url RollingHash"
         )?;
         log_err!(
-            state.post(url, Some(res)).await,
+            state.post(url, Some(res), headers).await,
             "post RollingHash manifests"
         )?;
 
         return Ok(());
     }
 
-    if is_init(&uri) {
+    // stream the segment to local disk, the signer reads it from there
+    save_request_body(body, &local, state.max_fragment_size)
+        .await
+        .map_err(|err| {
+            log::error!("save request body: {err}");
+            body_status(&err)
+        })?;
+
+    // non-conformant segments may break players once signed, they are not forwarded
+    if !state.regex.is_init(&uri) {
+        if let Err(err) = check_styp_brands(&local, &state.allowed_brands) {
+            log::error!("check styp brands of {}: {err}", uri.display());
+            log_err!(remove_file(&local).await, "remove rejected segment")?;
+            return Err(body_status(&err));
+        }
+    }
+
+    // forward everything unchanged, streamed from disk
+    let headers = match state.regex.uri(&uri) {
+        Ok(UriInfo { rep_id: _, index }) => state.forward_headers.headers(&index, false),
+        Err(_) => Default::default(),
+    };
+    let file = log_err!(File::open(&local).await, "open saved segment")?;
+    log_err!(
+        state.post(url, Some(file), headers).await,
+        "post OG content"
+    )?;
+
+    if state.regex.is_init(&uri) {
+        // restart signing if the init changed mid-stream
+        log_err!(state.track_init(name, &uri).await, "track init")?;
+
         // skip init, need at least one fragment for signing
         return Ok(());
     }
 
+    log_err!(
+        state.record_arrival(name, &uri, sequence.0),
+        "record arrival"
+    )?;
+
     log_err!(state.sign(name, uri).await, "signing fragment")
 }
 
@@ -131,11 +254,352 @@ pub(crate) async fn post_ingest(
 pub(crate) async fn delete_ingest(
     name: &str,
     uri: PathBuf,
-    state: &State<LiveSigner>,
+    state: &State<Arc<LiveSigner>>,
+    _auth: Authenticated,
 ) -> Result<()> {
     let target = log_err!(state.cdn_url(name, &uri, None), "cdn url <None>")?;
 
+    state.forget_arrival(name, &uri);
     log_err!(state.delete(target).await, "forward delete")?;
 
     Ok(())
 }
+
+/// verifies a fragment of the signed stream `<name>` (e.g. `live_signed`),
+/// the fragment is posted as body
+#[rocket::post("/<name>/<uri..>", data = "<body>")]
+pub(crate) async fn post_verify(
+    name: &str,
+    uri: PathBuf,
+    body: Data<'_>,
+    state: &State<Arc<LiveSigner>>,
+) -> Result<Json<FragmentCheck>> {
+    let UriInfo { rep_id, index } =
+        log_err!(state.regex.uri(&uri), "verify uri", Status::BadRequest)?;
+    if index == FragmentIndex::Init {
+        return Err(Status::BadRequest);
+    }
+
+    let fragment = log_err!(
+        body.open(state.max_fragment_size).into_bytes().await,
+        "read fragment"
+    )?;
+    if !fragment.is_complete() {
+        return Err(Status::PayloadTooLarge);
+    }
+
+    let check = log_err!(
+        state.verify_fragment(name, &rep_id, fragment.into_inner()),
+        "verify fragment",
+        Status::NotFound
+    )?;
+
+    Ok(Json(check))
+}
+
+/// the Merkle tree of the window holding the signed fragment `<uri..>` of the ingested
+/// stream `<name>` (e.g. `live`), read from the signed stream `<name>_signed`
+#[rocket::get("/<name>/<uri..>")]
+pub(crate) fn get_merkle_tree(
+    name: &str,
+    uri: PathBuf,
+    state: &State<Arc<LiveSigner>>,
+) -> Result<Json<MerkleTree>> {
+    let info = log_err!(state.regex.uri(&uri), "merkle uri", Status::BadRequest)?;
+    let tree = log_err!(
+        MerkleTree::new(name, info, &state.media, state.window_size),
+        "merkle tree",
+        Status::NotFound
+    )?;
+
+    Ok(Json(tree))
+}
+
+/// signing totals per stream in the Prometheus text format
+#[rocket::get("/metrics")]
+pub(crate) fn get_metrics(state: &State<Arc<LiveSigner>>) -> (ContentType, String) {
+    (ContentType::Plain, state.metrics.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    use rocket::{data::ByteUnit, http::Status, local::blocking::Client};
+
+    use crate::live::tests::{live_signer, sample_c2pa};
+
+    /// signs the first bunny fragment as the signed stream `<media>/<name>/0`,
+    /// Merkle signed with a window size and rolling hash signed without
+    ///
+    /// returns the path of the signed fragment
+    fn sign_stream(media: &Path, name: &str, window_size: Option<usize>) -> PathBuf {
+        let cli = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let bunny = cli.join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let input = media.join("live/0");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::copy(
+            bunny.join("BigBuckBunny_2s_init.mp4"),
+            input.join("segment_init.m4s"),
+        )
+        .unwrap();
+        std::fs::copy(
+            bunny.join("BigBuckBunny_2s1.m4s"),
+            input.join("segment_000000001.m4s"),
+        )
+        .unwrap();
+
+        let c2pa = sample_c2pa();
+        let output = media.join(name).join("0");
+        c2pa.builder(name)
+            .unwrap()
+            .sign_live_bmff(
+                c2pa.signer().unwrap().as_ref(),
+                input.join("segment_init.m4s"),
+                &vec![input.join("segment_000000001.m4s")],
+                output.join("segment_init.m4s"),
+                window_size,
+            )
+            .unwrap();
+
+        output.join("segment_000000001.m4s")
+    }
+
+    #[test]
+    /// bodies over the maximum fragment size are rejected and not kept on disk
+    fn max_fragment_size() {
+        let media = tempfile::tempdir().unwrap();
+        let mut signer = live_signer(media.path(), 4);
+        signer.max_fragment_size = ByteUnit::Kibibyte(1);
+
+        let rocket = rocket::build()
+            .mount("/ingest", rocket::routes![super::post_ingest])
+            .manage(Arc::new(signer));
+        let client = Client::tracked(rocket).unwrap();
+
+        let res = client
+            .post("/ingest/live/0/segment_000000001.m4s")
+            .body(vec![0_u8; 1025])
+            .dispatch();
+        assert_eq!(res.status(), Status::PayloadTooLarge);
+        assert!(!media.path().join("live/0/segment_000000001.m4s").exists());
+
+        let res = client
+            .post("/ingest/live/stream.mpd")
+            .body(vec![0_u8; 4096])
+            .dispatch();
+        assert_eq!(res.status(), Status::PayloadTooLarge);
+        assert!(!media.path().join("live/stream.mpd").exists());
+    }
+
+    #[rocket::async_test]
+    /// a large segment is written to disk without blocking the executor, other
+    /// requests are served in the meantime
+    async fn large_body_concurrent() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        use rocket::local::asynchronous::Client;
+
+        let media = tempfile::tempdir().unwrap();
+        let rocket = rocket::build()
+            .mount("/ingest", rocket::routes![super::post_ingest])
+            .mount("/verify", rocket::routes![super::post_verify])
+            .manage(Arc::new(live_signer(media.path(), 4)));
+        let client = Client::untracked(rocket).await.unwrap();
+        let segment = media.path().join("live/0/segment_000000001.m4s");
+        let written = AtomicBool::new(false);
+
+        let ingest = async {
+            // the segment isn't forwarded, there is no CDN
+            client
+                .post("/ingest/live/0/segment_000000001.m4s")
+                .body(vec![0_u8; 32 * 1024 * 1024])
+                .dispatch()
+                .await;
+            written.store(true, Ordering::SeqCst);
+        };
+        let concurrent = async {
+            while !segment.exists() {
+                rocket::tokio::task::yield_now().await;
+            }
+            let res = client
+                .post("/verify/live_signed/0/segment_init.m4s")
+                .body(b"init")
+                .dispatch()
+                .await;
+            assert_eq!(res.status(), Status::BadRequest);
+            assert!(!written.load(Ordering::SeqCst), "served after the write");
+        };
+        rocket::tokio::join!(ingest, concurrent);
+
+        assert_eq!(std::fs::metadata(&segment).unwrap().len(), 32 * 1024 * 1024);
+    }
+
+    #[test]
+    /// segments without an allowed styp brand are rejected and not kept on disk
+    fn allowed_brands() {
+        let media = tempfile::tempdir().unwrap();
+        let mut signer = live_signer(media.path(), 4);
+        signer.allowed_brands = vec!["cmfs".to_string()];
+
+        let rocket = rocket::build()
+            .mount("/ingest", rocket::routes![super::post_ingest])
+            .manage(Arc::new(signer));
+        let client = Client::tracked(rocket).unwrap();
+
+        let styp = [&24_u32.to_be_bytes()[..], b"stypmsdh\0\0\0\0msdhmsix"].concat();
+        let res = client
+            .post("/ingest/live/0/segment_000000001.m4s")
+            .body(styp)
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        assert!(!media.path().join("live/0/segment_000000001.m4s").exists());
+
+        let res = client
+            .post("/ingest/live/0/segment_000000002.m4s")
+            .body(vec![0_u8; 64])
+            .dispatch();
+        assert_eq!(res.status(), Status::UnprocessableEntity);
+        assert!(!media.path().join("live/0/segment_000000002.m4s").exists());
+    }
+
+    #[test]
+    /// signed fragments verify in both modes, tampered ones report the reason
+    fn verify() {
+        let media = tempfile::tempdir().unwrap();
+        let merkle = sign_stream(media.path(), "live_signed", Some(0));
+        let rolling_hash = sign_stream(media.path(), "live_rolling-hash", None);
+
+        let rocket = rocket::build()
+            .mount("/verify", rocket::routes![super::post_verify])
+            .manage(Arc::new(live_signer(media.path(), 4)));
+        let client = Client::tracked(rocket).unwrap();
+        let verify = |name: &str, fragment: Vec<u8>| {
+            let res = client
+                .post(format!("/verify/{name}/0/segment_000000001.m4s"))
+                .body(fragment)
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            res.into_json::<serde_json::Value>().unwrap()
+        };
+
+        assert_eq!(
+            verify("live_signed", std::fs::read(&merkle).unwrap()),
+            serde_json::json!({ "valid": true, "mode": "merkle", "reason": null })
+        );
+        assert_eq!(
+            verify("live_rolling-hash", std::fs::read(&rolling_hash).unwrap()),
+            serde_json::json!({ "valid": true, "mode": "rolling-hash", "reason": null })
+        );
+
+        // tamper with the last byte of the mdat
+        for (name, path) in [("live_signed", merkle), ("live_rolling-hash", rolling_hash)] {
+            let mut fragment = std::fs::read(path).unwrap();
+            *fragment.last_mut().unwrap() ^= 0xff;
+            let check = verify(name, fragment);
+            assert_eq!(check["valid"], false);
+            assert!(check["reason"].is_string(), "{}", check);
+        }
+
+        // no signed stream to verify against, or not a fragment
+        let res = client
+            .post("/verify/other_signed/0/segment_000000001.m4s")
+            .body(b"fragment")
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let res = client
+            .post("/verify/live_signed/0/segment_init.m4s")
+            .body(b"init")
+            .dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    /// the Merkle tree of a signed fragment's window is served as JSON
+    fn merkle_tree() {
+        let media = tempfile::tempdir().unwrap();
+        sign_stream(media.path(), "live_signed", Some(4));
+
+        let rocket = rocket::build()
+            .mount("/merkle", rocket::routes![super::get_merkle_tree])
+            .manage(Arc::new(live_signer(media.path(), 4)));
+        let client = Client::tracked(rocket).unwrap();
+
+        let res = client
+            .get("/merkle/live/0/segment_000000001.m4s")
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        let tree = res.into_json::<serde_json::Value>().unwrap();
+        assert_eq!(tree["init"]["count"], 1);
+        assert_eq!(tree["tree"][0][0]["is_current"], true);
+        assert_eq!(tree["tree"][0][0]["hash"], tree["tree"][2][0]["hash"]);
+
+        // no signed stream or not a fragment
+        let res = client
+            .get("/merkle/other/0/segment_000000001.m4s")
+            .dispatch();
+        assert_eq!(res.status(), Status::NotFound);
+        let res = client.get("/merkle/live/0/stream.mpd").dispatch();
+        assert_eq!(res.status(), Status::BadRequest);
+    }
+
+    #[test]
+    /// media playlists are cross-checked against both signed streams, fragments
+    /// in flight and representations without signed fragments are no divergence
+    fn media_playlist_divergence() {
+        use httpmock::MockServer;
+
+        use crate::live::{ForwardType, HashMode};
+
+        let cdn = MockServer::start();
+        cdn.mock(|_, then| {
+            then.status(200);
+        });
+
+        let media = tempfile::tempdir().unwrap();
+        let mut signer = live_signer(media.path(), 4);
+        signer.target = cdn.url("/").parse().unwrap();
+        for ty in [ForwardType::Signed, ForwardType::RollingHash] {
+            for i in 1..=3 {
+                let path = signer.local_path("live", format!("0/segment_{i:09}.m4s"), Some(ty));
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, b"signed").unwrap();
+            }
+        }
+
+        let rocket = rocket::build()
+            .mount("/ingest", rocket::routes![super::post_ingest])
+            .manage(Arc::new(signer));
+        let client = Client::tracked(rocket).unwrap();
+        let post_playlist = |rep_id: &str, indices: &[u32]| {
+            let mut playlist = "#EXTM3U\n#EXT-X-VERSION:7\n#EXT-X-TARGETDURATION:2\n".to_string();
+            for i in indices {
+                playlist.push_str(&format!("#EXTINF:2.0,\n{rep_id}/segment_{i:09}.m4s\n"));
+            }
+            let res = client
+                .post(format!("/ingest/live/media_{rep_id}.m3u8"))
+                .body(playlist)
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+        };
+        let state = client.rocket().state::<Arc<super::LiveSigner>>().unwrap();
+        let divergences = |mode| state.metrics.counters("live", mode).divergences();
+
+        // fragment 4 is still in flight
+        post_playlist("0", &[1, 2, 3, 4]);
+        // nothing was signed for the representation yet
+        post_playlist("1", &[1, 2]);
+        for mode in [HashMode::Merkle, HashMode::RollingHash] {
+            assert_eq!(divergences(mode), 0);
+        }
+
+        // fragment 2 was signed but is missing in the playlist
+        post_playlist("0", &[1, 3]);
+        for mode in [HashMode::Merkle, HashMode::RollingHash] {
+            assert_eq!(divergences(mode), 1);
+        }
+    }
+}
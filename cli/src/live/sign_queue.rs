@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use dashmap::DashMap;
+
+/// lets the rolling hash signing threads of a representation take turns
+///
+/// every fragment is anchored to the rolling hash of the previous one, two
+/// fragments of the same `<name>/<rep_id>` signed at once would both anchor to
+/// the same one. The threads wait for their turn in the order the tickets were taken.
+///
+/// a ticket taken without waiting for its turn only marks work in flight, a later
+/// ticket waits for it to be dropped (e.g. an init change for the Merkle signings)
+#[derive(Debug, Default)]
+pub(crate) struct SignQueues {
+    queues: DashMap<String, Arc<Queue>>,
+}
+
+impl SignQueues {
+    /// takes the next ticket of the representation `key`
+    pub fn ticket(&self, key: &str) -> Ticket {
+        let queue = self.queues.entry(key.to_owned()).or_default().clone();
+        let number = {
+            let mut state = queue.lock();
+            state.issued += 1;
+            state.issued - 1
+        };
+
+        Ticket { queue, number }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    turn: Condvar,
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    issued: u64,
+    serving: u64,
+    /// tickets dropped before their turn came
+    done: BTreeSet<u64>,
+}
+
+/// a place in the queue of a representation, its turn ends when it is dropped
+///
+/// a ticket dropped before its turn (e.g. the signing thread failed to spawn)
+/// is skipped instead of blocking the ones taken after it
+#[derive(Debug)]
+pub(crate) struct Ticket {
+    queue: Arc<Queue>,
+    number: u64,
+}
+
+impl Ticket {
+    /// blocks until all tickets taken before this one are dropped
+    pub fn wait(&self) {
+        let mut state = self.queue.lock();
+        while state.serving != self.number {
+            state = self
+                .queue
+                .turn
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut state = self.queue.lock();
+        let state = &mut *state;
+        state.done.insert(self.number);
+        while state.done.remove(&state.serving) {
+            state.serving += 1;
+        }
+        self.queue.turn.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use super::SignQueues;
+
+    #[test]
+    /// threads started in reverse run in ticket order, other representations don't wait
+    fn ticket_order() {
+        let queues = SignQueues::default();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let mut tickets: Vec<_> = (0..4).map(|i| (i, queues.ticket("live/0"))).collect();
+        let other = queues.ticket("live/1");
+
+        // a ticket given up before its turn doesn't block the later ones
+        drop(tickets.remove(2));
+
+        let mut signing = Vec::new();
+        for (i, ticket) in tickets.into_iter().rev() {
+            let order = order.clone();
+            signing.push(thread::spawn(move || {
+                ticket.wait();
+                thread::sleep(Duration::from_millis(10));
+                order.lock().unwrap().push(i);
+            }));
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // the first ticket of another representation has its turn right away
+        other.wait();
+        drop(other);
+
+        for handle in signing {
+            handle.join().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 3]);
+    }
+}
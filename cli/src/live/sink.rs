@@ -0,0 +1,467 @@
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context, Result};
+use reqwest::header::{HeaderMap, HeaderValue, CACHE_CONTROL, CONTENT_TYPE};
+use url::Url;
+
+use super::regexp::{FragmentIndex, ManifestTypes};
+
+/// location of a signed init segment or fragment in the output
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OutputKey {
+    /// signed stream, `<name>_<type>`
+    pub stream: String,
+    pub rep_id: String,
+    pub index: FragmentIndex,
+    pub file_name: String,
+}
+
+impl OutputKey {
+    /// `<stream>/<rep_id>/<file_name>`
+    pub fn path(&self) -> String {
+        format!("{}/{}/{}", self.stream, self.rep_id, self.file_name)
+    }
+}
+
+/// names the signed files in the output, e.g. to tell them apart from the ingested ones
+///
+/// only the output is renamed, the signed files in the media directory keep the
+/// ingested names
+pub(crate) trait FileNameTransform: Send + Sync {
+    /// output file name of `key`, whose `file_name` is the ingested one
+    fn file_name(&self, key: &OutputKey) -> String;
+}
+
+/// keeps the ingested file names
+pub(crate) struct Identity;
+
+impl FileNameTransform for Identity {
+    fn file_name(&self, key: &OutputKey) -> String {
+        key.file_name.clone()
+    }
+}
+
+/// names the signed files by a template, e.g. `{stem}.signed.{ext}`
+///
+/// placeholders:
+/// - `{stem}` ingested file name without the extension
+/// - `{ext}` extension of the ingested file name
+/// - `{rep_id}` RepID of the file
+pub(crate) struct FileNameTemplate(String);
+
+impl FileNameTemplate {
+    const PLACEHOLDERS: [&'static str; 3] = ["{stem}", "{ext}", "{rep_id}"];
+
+    pub fn new(template: &str) -> Result<Self> {
+        ensure!(
+            !template.contains('/'),
+            "file name template {template} must not contain a '/'"
+        );
+        let rest = Self::PLACEHOLDERS
+            .iter()
+            .fold(template.to_owned(), |rest, placeholder| {
+                rest.replace(placeholder, "")
+            });
+        ensure!(
+            !rest.contains(['{', '}']),
+            "unknown placeholder in file name template {template}"
+        );
+        Ok(Self(template.to_owned()))
+    }
+}
+
+impl FileNameTransform for FileNameTemplate {
+    fn file_name(&self, key: &OutputKey) -> String {
+        let (stem, ext) = key
+            .file_name
+            .rsplit_once('.')
+            .unwrap_or((&key.file_name, ""));
+
+        self.0
+            .replace("{stem}", stem)
+            .replace("{ext}", ext)
+            .replace("{rep_id}", &key.rep_id)
+    }
+}
+
+/// path of the signed files in the output, by a template
+///
+/// placeholders:
+/// - `{stream}` signed stream, `<name>_<type>`
+/// - `{rep_id}` RepID of the file
+/// - `{file_name}` output file name
+///
+/// the manifests are still posted to `<name>_<type>/<uri>`, the layout has to keep the
+/// segment URIs they list resolvable, e.g. a flat layout needs a flat manifest
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OutputLayout(String);
+
+impl OutputLayout {
+    const PLACEHOLDERS: [&'static str; 3] = ["{stream}", "{rep_id}", "{file_name}"];
+
+    pub fn new(template: &str) -> Result<Self> {
+        ensure!(
+            template.contains("{file_name}"),
+            "output layout {template} must contain {{file_name}}"
+        );
+        ensure!(
+            !template.starts_with('/') && !template.split('/').any(|part| part == ".."),
+            "output layout {template} must stay within the output"
+        );
+        let rest = Self::PLACEHOLDERS
+            .iter()
+            .fold(template.to_owned(), |rest, placeholder| {
+                rest.replace(placeholder, "")
+            });
+        ensure!(
+            !rest.contains(['{', '}']),
+            "unknown placeholder in output layout {template}"
+        );
+        Ok(Self(template.to_owned()))
+    }
+
+    /// path of `key` relative to the output
+    pub fn path(&self, key: &OutputKey) -> String {
+        self.0
+            .replace("{stream}", &key.stream)
+            .replace("{rep_id}", &key.rep_id)
+            .replace("{file_name}", &key.file_name)
+    }
+}
+
+/// `<stream>/<rep_id>/<file_name>`, see [OutputKey::path]
+impl Default for OutputLayout {
+    fn default() -> Self {
+        Self("{stream}/{rep_id}/{file_name}".to_owned())
+    }
+}
+
+/// destination of the signed stream, e.g. a CDN or an object storage
+pub(crate) trait OutputSink: Send + Sync {
+    /// stores a signed init segment, it is written before the fragments signed with it
+    fn write_init(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()>;
+
+    /// stores a signed fragment, fragments may be written concurrently
+    fn write_fragment(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()>;
+}
+
+/// `Cache-Control` of the files posted to the CDN, `None` leaves it to the CDN
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ForwardHeaders {
+    /// ingested init segments
+    pub init: Option<HeaderValue>,
+
+    /// fragments, they don't change once published
+    pub fragment: Option<HeaderValue>,
+
+    /// manifests and signed init segments, both are updated with every fragment
+    pub manifest: Option<HeaderValue>,
+}
+
+impl ForwardHeaders {
+    pub fn new(init: Option<&str>, fragment: Option<&str>, manifest: Option<&str>) -> Result<Self> {
+        let parse = |value: Option<&str>| -> Result<Option<HeaderValue>> {
+            value
+                .map(|v| HeaderValue::from_str(v).context("invalid Cache-Control"))
+                .transpose()
+        };
+
+        Ok(Self {
+            init: parse(init)?,
+            fragment: parse(fragment)?,
+            manifest: parse(manifest)?,
+        })
+    }
+
+    /// headers of the file with `index`, `signed` if it's part of a signed stream
+    pub fn headers(&self, index: &FragmentIndex, signed: bool) -> HeaderMap {
+        let (content_type, cache_control) = match index {
+            FragmentIndex::Init if signed => ("video/mp4", &self.manifest),
+            FragmentIndex::Init => ("video/mp4", &self.init),
+            FragmentIndex::Index(_) => ("video/iso.segment", &self.fragment),
+            FragmentIndex::Manifest(ManifestTypes::Mpd) => ("application/dash+xml", &self.manifest),
+            FragmentIndex::Manifest(ManifestTypes::Master | ManifestTypes::Media) => {
+                ("application/vnd.apple.mpegurl", &self.manifest)
+            }
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        if let Some(cache_control) = cache_control {
+            headers.insert(CACHE_CONTROL, cache_control.clone());
+        }
+        headers
+    }
+}
+
+/// posts the signed files to `<target>/<layout path>`
+pub(crate) struct HttpSink {
+    client: reqwest::blocking::Client,
+    target: Url,
+    headers: ForwardHeaders,
+    layout: OutputLayout,
+}
+
+impl HttpSink {
+    pub fn new(client: reqwest::blocking::Client, target: Url, headers: ForwardHeaders) -> Self {
+        Self {
+            client,
+            target,
+            headers,
+            layout: OutputLayout::default(),
+        }
+    }
+
+    pub fn with_layout(mut self, layout: OutputLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    fn post(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()> {
+        let url = self.target.join(&self.layout.path(key))?;
+        self.client
+            .post(url)
+            .headers(self.headers.headers(&key.index, true))
+            .body(bytes)
+            .send()?;
+        Ok(())
+    }
+}
+
+impl OutputSink for HttpSink {
+    fn write_init(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()> {
+        self.post(key, bytes)
+    }
+
+    fn write_fragment(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()> {
+        self.post(key, bytes)
+    }
+}
+
+/// writes the signed files to `<dir>/<layout path>`
+pub(crate) struct FsSink {
+    dir: PathBuf,
+    layout: OutputLayout,
+}
+
+impl FsSink {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            layout: OutputLayout::default(),
+        }
+    }
+
+    pub fn with_layout(mut self, layout: OutputLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    fn write(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()> {
+        let path = self.dir.join(self.layout.path(key));
+        std::fs::create_dir_all(path.parent().context("missing dir")?)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+impl OutputSink for FsSink {
+    fn write_init(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()> {
+        self.write(key, bytes)
+    }
+
+    fn write_fragment(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()> {
+        self.write(key, bytes)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// keeps the written files in memory, in the order they were written
+    #[derive(Default)]
+    pub(crate) struct MemorySink {
+        pub files: Mutex<Vec<(OutputKey, Vec<u8>)>>,
+    }
+
+    impl OutputSink for MemorySink {
+        fn write_init(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()> {
+            assert_eq!(key.index, FragmentIndex::Init);
+            self.files.lock().unwrap().push((key.clone(), bytes));
+            Ok(())
+        }
+
+        fn write_fragment(&self, key: &OutputKey, bytes: Vec<u8>) -> Result<()> {
+            assert!(matches!(key.index, FragmentIndex::Index(_)));
+            self.files.lock().unwrap().push((key.clone(), bytes));
+            Ok(())
+        }
+    }
+
+    pub(crate) fn key(file_name: &str, index: FragmentIndex) -> OutputKey {
+        OutputKey {
+            stream: "live_signed".to_string(),
+            rep_id: "0".to_string(),
+            index,
+            file_name: file_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn file_name_template() {
+        let template = FileNameTemplate::new("{rep_id}_{stem}.signed.{ext}").unwrap();
+        assert_eq!(
+            template.file_name(&key("segment_000000001.m4s", FragmentIndex::Index(1))),
+            "0_segment_000000001.signed.m4s"
+        );
+        assert_eq!(
+            Identity.file_name(&key("segment_init.m4s", FragmentIndex::Init)),
+            "segment_init.m4s"
+        );
+
+        assert!(FileNameTemplate::new("{name}.m4s").is_err());
+        assert!(FileNameTemplate::new("signed/{stem}.{ext}").is_err());
+    }
+
+    #[test]
+    fn output_layout() {
+        let fragment = key("segment_000000001.m4s", FragmentIndex::Index(1));
+        assert_eq!(OutputLayout::default().path(&fragment), fragment.path());
+
+        // flat, below a CDN prefix
+        let layout = OutputLayout::new("cdn/{stream}/{rep_id}_{file_name}").unwrap();
+        assert_eq!(
+            layout.path(&fragment),
+            "cdn/live_signed/0_segment_000000001.m4s"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let sink = FsSink::new(dir.path().to_path_buf()).with_layout(layout);
+        sink.write_fragment(&fragment, b"fragment".to_vec())
+            .unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("cdn/live_signed/0_segment_000000001.m4s")).unwrap(),
+            b"fragment"
+        );
+
+        assert!(OutputLayout::new("{stream}/{rep_id}").is_err());
+        assert!(OutputLayout::new("{stream}/{index}/{file_name}").is_err());
+        assert!(OutputLayout::new("/{file_name}").is_err());
+        assert!(OutputLayout::new("../{file_name}").is_err());
+    }
+
+    #[test]
+    /// signed inits are cached like manifests, they're re-signed with every fragment
+    fn http_sink_headers() {
+        use std::{
+            io::{BufRead, BufReader, Read, Write},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut lines = Vec::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    let line = line.trim_end().to_lowercase();
+                    if let Some(l) = line.strip_prefix("content-length:") {
+                        length = l.trim().parse().unwrap();
+                    }
+                    lines.push(line);
+                }
+                reader.read_exact(&mut vec![0; length]).unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                    .unwrap();
+                requests.push(lines);
+            }
+            requests
+        });
+
+        let headers = ForwardHeaders::new(
+            Some("public, max-age=31536000, immutable"),
+            Some("max-age=60"),
+            Some("max-age=1"),
+        )
+        .unwrap();
+        let sink = HttpSink::new(reqwest::blocking::Client::new(), target, headers.clone());
+        sink.write_init(
+            &key("segment_init.m4s", FragmentIndex::Init),
+            b"init".to_vec(),
+        )
+        .unwrap();
+        sink.write_fragment(
+            &key("segment_000000001.m4s", FragmentIndex::Index(1)),
+            b"fragment".to_vec(),
+        )
+        .unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(
+            requests[0][0],
+            "post /live_signed/0/segment_init.m4s http/1.1"
+        );
+        assert!(requests[0].contains(&"cache-control: max-age=1".to_string()));
+        assert!(requests[0].contains(&"content-type: video/mp4".to_string()));
+        assert_eq!(
+            requests[1][0],
+            "post /live_signed/0/segment_000000001.m4s http/1.1"
+        );
+        assert!(requests[1].contains(&"cache-control: max-age=60".to_string()));
+        assert!(requests[1].contains(&"content-type: video/iso.segment".to_string()));
+
+        // the ingested init doesn't change
+        let init = headers.headers(&FragmentIndex::Init, false);
+        assert_eq!(init[CACHE_CONTROL], "public, max-age=31536000, immutable");
+        let mpd = headers.headers(&FragmentIndex::Manifest(ManifestTypes::Mpd), false);
+        assert_eq!(mpd[CONTENT_TYPE], "application/dash+xml");
+        assert_eq!(mpd[CACHE_CONTROL], "max-age=1");
+
+        // without Cache-Control it's left to the CDN
+        let fragment = ForwardHeaders::default().headers(&FragmentIndex::Index(1), true);
+        assert!(!fragment.contains_key(CACHE_CONTROL));
+
+        assert!(ForwardHeaders::new(Some("max-age=1\n"), None, None).is_err());
+    }
+
+    #[test]
+    fn fs_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = FsSink::new(dir.path().to_path_buf());
+
+        sink.write_init(
+            &key("segment_init.m4s", FragmentIndex::Init),
+            b"init".to_vec(),
+        )
+        .unwrap();
+        sink.write_fragment(
+            &key("segment_000000001.m4s", FragmentIndex::Index(1)),
+            b"fragment".to_vec(),
+        )
+        .unwrap();
+
+        let rep = dir.path().join("live_signed/0");
+        assert_eq!(
+            std::fs::read(rep.join("segment_init.m4s")).unwrap(),
+            b"init"
+        );
+        assert_eq!(
+            std::fs::read(rep.join("segment_000000001.m4s")).unwrap(),
+            b"fragment"
+        );
+    }
+}
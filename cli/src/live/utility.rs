@@ -1,24 +1,33 @@
 use std::{
+    convert::{TryFrom, TryInto},
+    fmt::Display,
     fs::{read_dir, remove_dir_all},
+    io::{Cursor, Read},
     path::Path,
+    sync::Mutex,
+    thread,
 };
 
-use anyhow::{bail, Context, Result};
-use bytes::{Buf, Bytes};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use c2pa::{
-    assertions::{labels::BMFF_HASH_2, BmffHash},
+    assertions::{BmffHash, DataMap, ExclusionsMap},
+    asset_handlers::bmff_io::read_bmff_c2pa_boxes,
     Reader,
 };
+use regex::Regex;
 use rocket::{
     data::ByteUnit,
+    http::Status,
     tokio::{
-        fs::{create_dir_all, File},
+        fs::{create_dir_all, remove_file, File},
         io::{AsyncReadExt, AsyncWriteExt},
     },
     Data,
 };
 
-use crate::live::manifold::EventPayload;
+use crate::live::{
+    manifold::EventPayload, sink::OutputSink, ForwardPair, SignedFile, ROLLING_HASH_SCHEME_URI,
+};
 
 const MAX_CHUNK_SIZE: usize = u16::MAX as usize;
 
@@ -40,12 +49,15 @@ macro_rules! log_err {
 
 /// cleans up all media created during runtime
 ///
-/// deletes all subdirectories found in `dir`
+/// deletes all subdirectories found in `dir`, symlinks and
+/// anything resolving outside of `dir` are left untouched
 pub fn clear_media<P>(dir: P) -> Result<()>
 where
     P: AsRef<Path>,
 {
-    for entry in read_dir(dir)? {
+    let dir = dir.as_ref().canonicalize()?;
+
+    for entry in read_dir(&dir)? {
         let entry = match entry {
             Ok(e) => e,
             Err(err) => {
@@ -54,40 +66,216 @@ where
             }
         };
 
-        if !entry.metadata()?.is_dir() {
+        // doesn't follow symlinks
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let path = entry.path().canonicalize()?;
+        if !path.starts_with(&dir) {
+            log::warn!("skipping {} outside of {}", path.display(), dir.display());
             continue;
         }
 
-        remove_dir_all(entry.path())?;
+        remove_dir_all(path)?;
     }
     Ok(())
 }
 
 /// reads the request body, copies it to local disc and returns it as buffer
-pub(crate) async fn process_request_body<P>(body: Data<'_>, path: P) -> Result<Vec<u8>>
+///
+/// only used when the bytes are needed right away (i.e. the MPD),
+/// anything forwarded unchanged should use [save_request_body]
+pub(crate) async fn process_request_body<P>(
+    body: Data<'_>,
+    path: P,
+    limit: ByteUnit,
+) -> Result<Vec<u8>>
 where
     P: AsRef<Path>,
 {
-    let mut file = create_file(path).await?;
+    let mut file = create_file(&path).await?;
 
-    let mut body = body.open(ByteUnit::max_value());
+    // one byte over the limit tells an exceeding body from one of exactly `limit`
+    let mut body = body.open(limit + 1);
     let mut buf = Vec::new();
+    let mut chunk = vec![0; MAX_CHUNK_SIZE];
     loop {
-        let mut chunk = vec![0; MAX_CHUNK_SIZE];
         let read = body.read(&mut chunk).await?;
         if read == 0 {
             // EOS
             break;
         }
+        if (buf.len() + read) as u64 > limit.as_u64() {
+            return Err(reject_body(file, path, limit).await);
+        }
 
         let chunk = &chunk[..read];
         buf.extend_from_slice(chunk);
         file.write_all(chunk).await?;
     }
+    // the write may still be in flight when the file is dropped
+    file.flush().await?;
 
     Ok(buf)
 }
 
+/// streams the request body to local disc without keeping it in memory
+///
+/// returns the number of bytes written, used for segments which
+/// are read back from disk by the signer
+pub(crate) async fn save_request_body<P>(body: Data<'_>, path: P, limit: ByteUnit) -> Result<u64>
+where
+    P: AsRef<Path>,
+{
+    let mut file = create_file(&path).await?;
+
+    let mut body = body.open(limit + 1);
+    let mut chunk = vec![0; MAX_CHUNK_SIZE];
+    let mut len = 0;
+    loop {
+        let read = body.read(&mut chunk).await?;
+        if read == 0 {
+            // EOS
+            break;
+        }
+        if len + read as u64 > limit.as_u64() {
+            return Err(reject_body(file, path, limit).await);
+        }
+
+        file.write_all(&chunk[..read]).await?;
+        len += read as u64;
+    }
+    file.flush().await?;
+
+    Ok(len)
+}
+
+/// a request body larger than the configured maximum fragment size
+#[derive(Debug)]
+pub(crate) struct BodyTooLarge(pub ByteUnit);
+
+impl Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request body exceeds {}", self.0)
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+/// a segment whose `styp` brands are none of the allowed ones
+#[derive(Debug)]
+pub(crate) struct UnexpectedBrands {
+    /// brands of the `styp` box, `None` if the segment has none
+    pub found: Option<Vec<String>>,
+    pub expected: Vec<String>,
+}
+
+impl Display for UnexpectedBrands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.found {
+            Some(found) => write!(
+                f,
+                "unexpected styp brands {found:?}, expected one of {:?}",
+                self.expected
+            ),
+            None => write!(
+                f,
+                "missing styp box, expected one of the brands {:?}",
+                self.expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UnexpectedBrands {}
+
+/// maps an error of reading a request body to its response status
+///
+/// 413 Payload Too Large for [BodyTooLarge], 422 Unprocessable Entity for
+/// [UnexpectedBrands], 500 otherwise
+pub(crate) fn body_status(err: &anyhow::Error) -> Status {
+    if err.is::<BodyTooLarge>() {
+        Status::PayloadTooLarge
+    } else if err.is::<UnexpectedBrands>() {
+        Status::UnprocessableEntity
+    } else {
+        Status::InternalServerError
+    }
+}
+
+/// upper bound of the `styp` box size, it only lists a few brands
+const MAX_STYP_SIZE: usize = 1024;
+
+/// major and compatible brands of the `styp` box starting the segment at `path`,
+/// `None` if it doesn't start with one
+pub(crate) fn styp_brands<P>(path: P) -> Result<Option<Vec<String>>>
+where
+    P: AsRef<Path>,
+{
+    let mut file = std::fs::File::open(path)?;
+
+    let mut header = [0; 8];
+    if file.read_exact(&mut header).is_err() || &header[4..] != b"styp" {
+        return Ok(None);
+    }
+
+    let size = u32::from_be_bytes(header[..4].try_into()?) as usize;
+    ensure!(
+        (16..=MAX_STYP_SIZE).contains(&size) && size.is_multiple_of(4),
+        "invalid styp box size {size}"
+    );
+    let mut payload = vec![0; size - 8];
+    file.read_exact(&mut payload)?;
+
+    // the minor version follows the major brand
+    let brands = payload
+        .chunks(4)
+        .enumerate()
+        .filter(|(i, _)| *i != 1)
+        .map(|(_, brand)| String::from_utf8_lossy(brand).into_owned())
+        .collect();
+
+    Ok(Some(brands))
+}
+
+/// fails with [UnexpectedBrands] unless the major or a compatible brand of the
+/// segment at `path` is one of `allowed`, an empty list disables the check
+pub(crate) fn check_styp_brands<P>(path: P, allowed: &[String]) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    if allowed.is_empty() {
+        return Ok(());
+    }
+
+    let found = styp_brands(path)?;
+    if let Some(brands) = &found {
+        if brands.iter().any(|brand| allowed.contains(brand)) {
+            return Ok(());
+        }
+    }
+
+    Err(UnexpectedBrands {
+        found,
+        expected: allowed.to_vec(),
+    }
+    .into())
+}
+
+/// removes the partially written file of a body exceeding `limit`
+async fn reject_body<P>(file: File, path: P, limit: ByteUnit) -> anyhow::Error
+where
+    P: AsRef<Path>,
+{
+    drop(file);
+    if let Err(err) = remove_file(path).await {
+        return err.into();
+    }
+
+    BodyTooLarge(limit).into()
+}
+
 /// creates the file at `path`
 ///
 /// creates the path to file, if it doesn't exist
@@ -113,111 +301,377 @@ where
     Ok(create_dir_all(dir).await?)
 }
 
-/// checks wether `uri` is a fragment path
-///
-/// path extension equal `m4s`
-pub(crate) fn is_fragment<P>(uri: P) -> bool
-where
-    P: AsRef<Path>,
-{
-    let Some(ext) = uri.as_ref().extension() else {
-        return false;
-    };
-    match ext.to_str() {
-        Some(ext) => ext == "m4s",
-        None => false,
+/// header of a top level BMFF box, the payload follows the header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BmffBox {
+    pub name: [u8; 4],
+    pub offset: usize,
+    pub header_len: usize,
+    pub payload_len: usize,
+}
+
+impl BmffBox {
+    /// range of the whole box, header and payload
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.offset..self.payload().end
+    }
+
+    /// range of the payload
+    pub fn payload(&self) -> std::ops::Range<usize> {
+        self.offset + self.header_len..self.offset + self.header_len + self.payload_len
     }
 }
 
-/// checks wether `uri` is a init fragment path
+/// iterates over the top level boxes of a BMFF buffer
 ///
-/// file name contains `init`
-pub(crate) fn is_init<P>(uri: P) -> bool
+/// handles 64-bit large sizes and boxes extending to the end of the buffer (size 0),
+/// stops after the first malformed or truncated box
+pub(crate) struct BoxScanner<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BoxScanner<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, offset: 0 }
+    }
+
+    fn next_box(&self) -> Result<BmffBox> {
+        let rest = &self.buf[self.offset..];
+        let header = |range: std::ops::Range<usize>| {
+            rest.get(range)
+                .context(format!("truncated box header at {}", self.offset))
+        };
+
+        let size = u32::from_be_bytes(header(0..4)?.try_into()?);
+        let name: [u8; 4] = header(4..8)?.try_into()?;
+
+        let (size, header_len) = match size {
+            0 => (rest.len() as u64, 8),
+            1 => (u64::from_be_bytes(header(8..16)?.try_into()?), 16),
+            size => (size as u64, 8),
+        };
+
+        ensure!(
+            size >= header_len as u64 && size <= rest.len() as u64,
+            "invalid size {size} of box {:?} at {}",
+            String::from_utf8_lossy(&name),
+            self.offset
+        );
+
+        Ok(BmffBox {
+            name,
+            offset: self.offset,
+            header_len,
+            payload_len: size as usize - header_len,
+        })
+    }
+}
+
+impl Iterator for BoxScanner<'_> {
+    type Item = Result<BmffBox>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.buf.len() {
+            return None;
+        }
+
+        let next = self.next_box();
+        self.offset = match &next {
+            Ok(bmff_box) => bmff_box.range().end,
+            Err(_) => self.buf.len(),
+        };
+
+        Some(next)
+    }
+}
+
+/// size of the `message_data` reserved in the rolling hash `emsg`, enough for the
+/// JSON [EventPayload] of two SHA-512 hashes
+pub(crate) const EMSG_MESSAGE_SIZE: usize = 1024;
+
+/// payload of a version 0 `emsg` with the [ROLLING_HASH_SCHEME_URI] scheme up to the
+/// `message_data`, i.e. scheme, empty value, timescale, presentation time delta,
+/// event duration and id
+fn emsg_header() -> Vec<u8> {
+    let mut header = vec![0; 4];
+    header.extend_from_slice(ROLLING_HASH_SCHEME_URI.as_bytes());
+    header.extend_from_slice(&[0, 0]);
+    header.extend_from_slice(&1_u32.to_be_bytes());
+    header.extend_from_slice(&[0; 12]);
+    header
+}
+
+/// excludes the rolling hash `emsg` from the fragment hash, identified by its scheme
+pub(crate) fn emsg_exclusion() -> ExclusionsMap {
+    let mut emsg = ExclusionsMap::new("/emsg".to_owned());
+    emsg.data = Some(vec![DataMap {
+        offset: 12,
+        value: [ROLLING_HASH_SCHEME_URI.as_bytes(), &[0]].concat(),
+    }]);
+    emsg
+}
+
+/// range of the `message_data` of the rolling hash `emsg` in a fragment
+pub(crate) fn emsg_message(buf: &[u8]) -> Result<Option<std::ops::Range<usize>>> {
+    let header = emsg_header();
+    for bmff_box in BoxScanner::new(buf) {
+        let bmff_box = bmff_box?;
+        let payload = bmff_box.payload();
+        if bmff_box.name == *b"emsg" && buf[payload.clone()].starts_with(&header) {
+            return Ok(Some(payload.start + header.len()..payload.end));
+        }
+    }
+    Ok(None)
+}
+
+/// copies `fragment` to `dest` with a blank rolling hash `emsg` in front of its `moof`
+pub(crate) fn insert_emsg_placeholder<P1, P2>(fragment: P1, dest: P2) -> Result<()>
 where
-    P: AsRef<Path>,
+    P1: AsRef<Path>,
+    P2: AsRef<Path>,
 {
-    match uri.as_ref().file_name() {
-        Some(s) => match s.to_str() {
-            Some(s) => s.contains("init"),
-            None => false,
-        },
-        None => false,
+    let buf = std::fs::read(fragment)?;
+
+    let mut moof = None;
+    for bmff_box in BoxScanner::new(&buf) {
+        let bmff_box = bmff_box?;
+        if bmff_box.name == *b"moof" {
+            moof = Some(bmff_box.offset);
+            break;
+        }
     }
+    let moof = moof.context("fragment without moof")?;
+
+    let payload = [emsg_header(), vec![b' '; EMSG_MESSAGE_SIZE]].concat();
+    let emsg = [
+        &u32::try_from(payload.len() + 8)?.to_be_bytes()[..],
+        b"emsg",
+        &payload,
+    ]
+    .concat();
+
+    std::fs::create_dir_all(dest.as_ref().parent().context("missing dir")?)?;
+    std::fs::write(dest, [&buf[..moof], &emsg, &buf[moof..]].concat())?;
+    Ok(())
 }
 
-pub(crate) fn _extract_c2pa_box<P>(path: P) -> Result<Vec<u8>>
+/// writes `event` as JSON into the blank rolling hash `emsg` of the signed fragment,
+/// padded with spaces
+pub(crate) fn fill_emsg<P>(path: P, event: &EventPayload) -> Result<()>
 where
     P: AsRef<Path>,
 {
-    let buf = std::fs::read(&path)?;
-    let mut buf = Bytes::copy_from_slice(&buf);
-    let mut c2pa = None;
+    let mut buf = std::fs::read(&path)?;
+    let message = emsg_message(&buf)?.context("missing rolling hash emsg")?;
 
-    loop {
-        let size = buf.get_u32();
-        let name = buf.copy_to_bytes(4);
+    let json = serde_json::to_vec(event)?;
+    ensure!(
+        json.len() <= message.len(),
+        "event of {} bytes exceeds the emsg",
+        json.len()
+    );
+    buf[message.clone()].fill(b' ');
+    buf[message.start..message.start + json.len()].copy_from_slice(&json);
 
-        let (size, hdr) = match size {
-            1 => (buf.get_u64(), 8),
-            _ => (size as u64, 4),
-        };
+    std::fs::write(path, buf)?;
+    Ok(())
+}
 
-        let payload_size = size as usize - hdr - 4;
+/// timing of the first track of a fragment, in the timescale of the track
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FragmentTiming {
+    /// `baseMediaDecodeTime` of the `tfdt`
+    pub decode_time: u64,
+    /// sum of the sample durations of the `trun` boxes
+    pub duration: u64,
+    /// `timescale` of the `mdhd` of the track
+    pub timescale: u32,
+}
 
-        if *name == *b"uuid" {
-            // FIXME ideally handle large size as well but unlikely to happen
-            let mut size = (size as u32).to_be_bytes().to_vec();
-            let mut name = name.to_vec();
-            let mut payload = buf.copy_to_bytes(payload_size).to_vec();
+/// payloads of the boxes at `path`, e.g. all `trak` boxes of the `moov`
+pub(crate) fn find_boxes<'a>(buf: &'a [u8], path: &[&[u8; 4]]) -> Result<Vec<&'a [u8]>> {
+    let mut found = Vec::new();
+    let Some((name, rest)) = path.split_first() else {
+        return Ok(found);
+    };
 
-            size.append(&mut name);
-            size.append(&mut payload);
-            c2pa.replace(size);
-            break;
+    for bmff_box in BoxScanner::new(buf) {
+        let bmff_box = bmff_box?;
+        if bmff_box.name != **name {
+            continue;
+        }
+        let payload = &buf[bmff_box.payload()];
+        if rest.is_empty() {
+            found.push(payload);
+        } else {
+            found.extend(find_boxes(payload, rest)?);
         }
+    }
+    Ok(found)
+}
+
+pub(crate) fn read_u32(buf: &[u8], at: usize) -> Result<u32> {
+    let bytes = buf.get(at..at + 4).context("truncated box")?;
+    Ok(u32::from_be_bytes(bytes.try_into()?))
+}
+
+fn read_u64(buf: &[u8], at: usize) -> Result<u64> {
+    let bytes = buf.get(at..at + 8).context("truncated box")?;
+    Ok(u64::from_be_bytes(bytes.try_into()?))
+}
 
-        buf.advance(payload_size);
+/// flags of a full box
+fn box_flags(payload: &[u8]) -> Result<u32> {
+    Ok(read_u32(payload, 0)? & 0x00ff_ffff)
+}
+
+/// reads the timing of the first track of `fragment`, `None` if it has no `traf`
+///
+/// the sample durations default to the `tfhd` and then to the `trex` of `init`, so
+/// fragments of variable length GOPs get their actual duration
+pub(crate) fn fragment_timing(init: &[u8], fragment: &[u8]) -> Result<Option<FragmentTiming>> {
+    let Some(traf) = find_boxes(fragment, &[b"moof", b"traf"])?
+        .into_iter()
+        .next()
+    else {
+        return Ok(None);
+    };
+
+    let tfhd = find_boxes(traf, &[b"tfhd"])?
+        .into_iter()
+        .next()
+        .context("traf without tfhd")?;
+    let flags = box_flags(tfhd)?;
+    let track_id = read_u32(tfhd, 4)?;
+    // base data offset and sample description index precede the default duration
+    let mut at = 8;
+    if flags & 0x01 != 0 {
+        at += 8;
+    }
+    if flags & 0x02 != 0 {
+        at += 4;
     }
+    let tfhd_duration = match flags & 0x08 {
+        0 => None,
+        _ => Some(read_u32(tfhd, at)?),
+    };
 
-    if let Some(c2pa) = c2pa {
-        Ok(c2pa)
-    } else {
-        bail!("missing c2pa box in {:?}", path.as_ref())
+    // the track of the init segment with the timescale and the trex defaults
+    let mut timescale = None;
+    for trak in find_boxes(init, &[b"moov", b"trak"])? {
+        let tkhd = find_boxes(trak, &[b"tkhd"])?;
+        let tkhd = tkhd.first().context("trak without tkhd")?;
+        let id_at = if tkhd.first() == Some(&1) { 20 } else { 12 };
+        if read_u32(tkhd, id_at)? != track_id {
+            continue;
+        }
+        let mdhd = find_boxes(trak, &[b"mdia", b"mdhd"])?;
+        let mdhd = mdhd.first().context("trak without mdhd")?;
+        let timescale_at = if mdhd.first() == Some(&1) { 20 } else { 12 };
+        timescale = Some(read_u32(mdhd, timescale_at)?);
+    }
+    let timescale = timescale.with_context(|| format!("init without track {track_id}"))?;
+    let mut trex_duration = None;
+    for trex in find_boxes(init, &[b"moov", b"mvex", b"trex"])? {
+        if read_u32(trex, 4)? == track_id {
+            trex_duration = Some(read_u32(trex, 12)?);
+        }
+    }
+    let default_duration = tfhd_duration.or(trex_duration);
+
+    let decode_time = match find_boxes(traf, &[b"tfdt"])?.first() {
+        Some(tfdt) if tfdt.first() == Some(&1) => read_u64(tfdt, 4)?,
+        Some(tfdt) => read_u32(tfdt, 4)? as u64,
+        None => 0,
+    };
+
+    let mut duration = 0;
+    for trun in find_boxes(traf, &[b"trun"])? {
+        let flags = box_flags(trun)?;
+        let sample_count = read_u32(trun, 4)?;
+        // data offset and first sample flags precede the samples
+        let mut at = 8;
+        if flags & 0x01 != 0 {
+            at += 4;
+        }
+        if flags & 0x04 != 0 {
+            at += 4;
+        }
+
+        if flags & 0x100 == 0 {
+            let default_duration = default_duration.context("no default sample duration")?;
+            duration += sample_count as u64 * default_duration as u64;
+            continue;
+        }
+        let sample_len = 4 * [0x100, 0x200, 0x400, 0x800]
+            .iter()
+            .filter(|flag| flags & *flag != 0)
+            .count();
+        for sample in 0..sample_count as usize {
+            duration += read_u32(trun, at + sample * sample_len)? as u64;
+        }
     }
+
+    Ok(Some(FragmentTiming {
+        decode_time,
+        duration,
+        timescale,
+    }))
 }
 
-#[allow(dead_code)]
-pub(crate) fn replace_uuid_content<P>(path: P, new_content: &[u8]) -> Result<Vec<u8>>
+pub(crate) fn _extract_c2pa_box<P>(path: P) -> Result<Vec<u8>>
 where
     P: AsRef<Path>,
 {
     let buf = std::fs::read(&path)?;
-    let mut buf = Bytes::copy_from_slice(&buf);
-
-    let mut vec = Vec::new();
-    while buf.has_remaining() {
-        let size = buf.get_u32();
-        let name = buf.copy_to_bytes(4);
 
-        if size == 1 {
-            unimplemented!("large boxes")
+    for bmff_box in BoxScanner::new(&buf) {
+        let bmff_box = bmff_box?;
+        if bmff_box.name == *b"uuid" {
+            return Ok(buf[bmff_box.range()].to_vec());
         }
+    }
 
-        let payload_size = size as usize - 8;
+    bail!("missing c2pa box in {:?}", path.as_ref())
+}
 
-        if *name == *b"uuid" {
-            let new_len = new_content.len() as u32 + 8;
+/// replaces the content of the `uuid` boxes of the fragment at `path`, all other
+/// boxes are kept byte for byte
+///
+/// only `uuid` boxes starting with the C2PA identifier are excluded from the fragment
+/// hash, so a signed fragment stays valid as long as `new_content` starts with that
+/// identifier and keeps the `BmffMerkleMap` or rolling hash of the fragment
+#[allow(dead_code)]
+pub(crate) fn replace_uuid_content<P>(path: P, new_content: &[u8]) -> Result<Vec<u8>>
+where
+    P: AsRef<Path>,
+{
+    let buf = std::fs::read(&path)?;
 
-            vec.append(&mut new_len.to_be_bytes().to_vec());
-            vec.append(&mut name.into());
-            vec.append(&mut new_content.to_vec());
+    let mut vec = Vec::with_capacity(buf.len() + new_content.len());
+    for bmff_box in BoxScanner::new(&buf) {
+        let bmff_box = bmff_box?;
 
-            buf.advance(payload_size);
-        } else {
-            vec.append(&mut size.to_be_bytes().to_vec());
-            vec.append(&mut name.into());
-            vec.append(&mut buf.copy_to_bytes(payload_size).into());
+        if bmff_box.name != *b"uuid" {
+            vec.extend_from_slice(&buf[bmff_box.range()]);
+            continue;
+        }
+
+        // a large size is only used if the new content requires it
+        let size = new_content.len() as u64 + 8;
+        match u32::try_from(size) {
+            Ok(size) => {
+                vec.extend_from_slice(&size.to_be_bytes());
+                vec.extend_from_slice(b"uuid");
+            }
+            Err(_) => {
+                vec.extend_from_slice(&1_u32.to_be_bytes());
+                vec.extend_from_slice(b"uuid");
+                vec.extend_from_slice(&(size + 8).to_be_bytes());
+            }
         }
+        vec.extend_from_slice(new_content);
     }
 
     Ok(vec)
@@ -236,15 +690,135 @@ pub(crate) fn mpd_num_reps(mpd: &dash_mpd::MPD) -> usize {
     num
 }
 
+/// Timing of the newest segment in a `$Time$` based `SegmentTimeline`.
+///
+/// Returns `(presentation_time, duration, timescale)` relative to the period start, the
+/// `SegmentTemplate` of the representation takes precedence over the one of the adaptation set.
+pub(crate) fn last_segment_timing(
+    adaptation: &dash_mpd::AdaptationSet,
+    representation: &dash_mpd::Representation,
+) -> Option<(u64, u64, u64)> {
+    let template = representation
+        .SegmentTemplate
+        .as_ref()
+        .or(adaptation.SegmentTemplate.as_ref())?;
+    let timeline = template.SegmentTimeline.as_ref()?;
+
+    let mut time = 0_u64;
+    let mut last = None;
+    for s in &timeline.segments {
+        if let Some(t) = s.t {
+            time = t;
+        }
+        // a negative repeat count lasts until the next S@t, which resets the time anyway
+        let repeat = s.r.unwrap_or(0).max(0) as u64;
+        time = time.saturating_add(s.d.saturating_mul(repeat));
+        last = Some((time, s.d));
+        time = time.saturating_add(s.d);
+    }
+
+    let (start, duration) = last?;
+    let offset = template.presentationTimeOffset.unwrap_or(0);
+
+    Some((
+        start.saturating_sub(offset),
+        duration,
+        template.timescale.unwrap_or(1),
+    ))
+}
+
+/// lists the segment URIs of an HLS media playlist
+pub(crate) fn hls_media_uris(playlist: &[u8]) -> Result<Vec<String>> {
+    let playlist = m3u8_rs::parse_media_playlist_res(playlist)
+        .map_err(|_| anyhow!("invalid media playlist"))?;
+    Ok(playlist
+        .segments
+        .into_iter()
+        .map(|segment| segment.uri)
+        .collect())
+}
+
+/// Media URIs of the segments the MPD lists for `representation`, relative to the MPD.
+///
+/// Covers a `SegmentList` and a `SegmentTemplate` with a `SegmentTimeline`, the segments of a
+/// template without timeline depend on the wall clock and are not listed.
+pub(crate) fn mpd_media_uris(
+    adaptation: &dash_mpd::AdaptationSet,
+    representation: &dash_mpd::Representation,
+) -> Vec<String> {
+    if let Some(list) = representation
+        .SegmentList
+        .as_ref()
+        .or(adaptation.SegmentList.as_ref())
+    {
+        return list
+            .segment_urls
+            .iter()
+            .filter_map(|url| url.media.clone())
+            .collect();
+    }
+
+    let Some(template) = representation
+        .SegmentTemplate
+        .as_ref()
+        .or(adaptation.SegmentTemplate.as_ref())
+    else {
+        return Vec::new();
+    };
+    let (Some(media), Some(timeline)) = (&template.media, &template.SegmentTimeline) else {
+        return Vec::new();
+    };
+    let rep_id = representation.id.as_deref().unwrap_or_default();
+
+    let mut uris = Vec::new();
+    let mut number = template.startNumber.unwrap_or(1);
+    let mut time = 0_u64;
+    for s in &timeline.segments {
+        if let Some(t) = s.t {
+            time = t;
+        }
+        let repeat = s.r.unwrap_or(0).max(0) as u64;
+        for _ in 0..=repeat {
+            uris.push(fill_segment_template(media, rep_id, number, time));
+            number += 1;
+            time = time.saturating_add(s.d);
+        }
+    }
+
+    uris
+}
+
+/// expands the `$RepresentationID$`, `$Number$` and `$Time$` identifiers of a
+/// `SegmentTemplate`, including a `%0<width>d` format tag
+fn fill_segment_template(template: &str, rep_id: &str, number: u64, time: u64) -> String {
+    let identifier = Regex::new(r"\$(RepresentationID|Number|Time)(?:%0(\d+)d)?\$").unwrap();
+
+    identifier
+        .replace_all(template, |capture: &regex::Captures| {
+            let value = match &capture[1] {
+                "RepresentationID" => return rep_id.to_owned(),
+                "Number" => number,
+                _ => time,
+            };
+            let width = capture
+                .get(2)
+                .and_then(|w| w.as_str().parse().ok())
+                .unwrap_or(0);
+            format!("{value:0width$}")
+        })
+        .replace("$$", "$")
+}
+
+/// finds the [BmffHash] binding the fragments to a signed init, see [BmffHash::from_reader]
+pub(crate) fn find_fragment_hash(reader: &Reader) -> Result<BmffHash> {
+    BmffHash::from_reader(reader).context("no manifest holds a Merkle or rolling hash BmffHash")
+}
+
 pub(crate) fn get_event_data<P>(init: P) -> Result<EventPayload>
 where
     P: AsRef<Path>,
 {
-    let manifest = Reader::from_file(init)?;
-    let manifest = manifest
-        .active_manifest()
-        .context("missing active manifest")?;
-    let hash = manifest.find_assertion::<BmffHash>(BMFF_HASH_2)?;
+    let hash = find_fragment_hash(&Reader::from_file(init)?)?;
     let rh = hash.rolling_hash().context("not rolling hash signed")?;
 
     let rolling_hash = rh.rolling_hash().context("missing rolling hash")?;
@@ -254,8 +828,360 @@ where
     Ok(EventPayload::new(rolling_hash, &anchor_point))
 }
 
+/// fails if the init hash of the signed `init` is still the placeholder inserted while
+/// signing, the fragments forwarded along with it would never verify
+pub(crate) fn check_init_hash(init: &[u8]) -> Result<()> {
+    let c2pa_boxes = read_bmff_c2pa_boxes(&mut Cursor::new(init))?;
+    let manifest = c2pa_boxes
+        .manifest_bytes
+        .context("signed init has no manifest")?;
+    let hash = BmffHash::from_manifest_bytes(&manifest)?.context("signed init has no BMFF hash")?;
+    ensure!(
+        !hash.has_placeholder_init_hash(),
+        "the init hash of the signed init was not finalized"
+    );
+    Ok(())
+}
+
+/// reads the signed files into memory
+///
+/// the bytes are read once right after signing and forwarded as is, the files may
+/// be rewritten meanwhile (e.g. by signing the next fragment of the window)
+pub(crate) fn read_signed(pairs: Vec<ForwardPair>) -> Result<Vec<SignedFile>> {
+    pairs
+        .into_iter()
+        .map(|(path, key)| {
+            let bytes = std::fs::read(&path).with_context(|| format!("failed to read {path:?}"))?;
+            Ok((key, bytes))
+        })
+        .collect()
+}
+
+/// checks the init hashes of the signed files and writes them to the output sink
+///
+/// returns the number of fragment bytes written
+pub(crate) fn forward_signed(
+    sink: &dyn OutputSink,
+    init: Vec<SignedFile>,
+    fragments: Vec<SignedFile>,
+    concurrency: usize,
+) -> Result<u64> {
+    for (key, bytes) in &init {
+        check_init_hash(bytes).with_context(|| key.path())?;
+    }
+
+    let bytes = fragments.iter().map(|(_, bytes)| bytes.len() as u64).sum();
+    write_signed(sink, init, fragments, concurrency)?;
+    Ok(bytes)
+}
+
+/// writes the signed files to the output sink
+///
+/// the init segments are written first, afterwards the fragments
+/// with up to `concurrency` writes in flight
+pub(crate) fn write_signed(
+    sink: &dyn OutputSink,
+    init: Vec<SignedFile>,
+    fragments: Vec<SignedFile>,
+    concurrency: usize,
+) -> Result<()> {
+    for (key, bytes) in init {
+        sink.write_init(&key, bytes)?;
+    }
+
+    let workers = concurrency.max(1).min(fragments.len());
+    let queue = Mutex::new(fragments.into_iter());
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| -> Result<()> {
+                    loop {
+                        let next = queue
+                            .lock()
+                            .map_err(|_| anyhow!("forward queue poisoned"))?
+                            .next();
+                        let Some((key, bytes)) = next else {
+                            return Ok(());
+                        };
+                        sink.write_fragment(&key, bytes)?;
+                    }
+                })
+            })
+            .collect();
+
+        handles.into_iter().try_for_each(|handle| {
+            handle
+                .join()
+                .map_err(|_| anyhow!("forward thread panicked"))?
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use std::path::{Path, PathBuf};
+
+    use c2pa::assertions::{labels::BMFF_HASH_2, BmffHash};
+    use rocket::{http::Status, local::blocking::Client, Data, State};
+
+    use crate::live::{
+        manifold::EventPayload,
+        regexp::FragmentIndex,
+        sink::{
+            tests::{key, MemorySink},
+            HttpSink,
+        },
+        tests::sample_c2pa,
+    };
+
+    #[rocket::post("/", data = "<body>")]
+    async fn save(body: Data<'_>, path: &State<PathBuf>) -> String {
+        super::save_request_body(body, path.inner(), super::ByteUnit::max_value())
+            .await
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    /// body larger than a single chunk is written completely
+    fn save_request_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("segment.m4s");
+        let rocket = rocket::build()
+            .mount("/", rocket::routes![save])
+            .manage(path.clone());
+        let client = Client::tracked(rocket).unwrap();
+        let body = (0..3 * super::MAX_CHUNK_SIZE + 7)
+            .map(|i| i as u8)
+            .collect::<Vec<_>>();
+
+        let res = client.post("/").body(&body).dispatch();
+        assert_eq!(res.into_string().unwrap(), body.len().to_string());
+
+        assert_eq!(std::fs::read(path).unwrap(), body);
+    }
+
+    #[test]
+    fn last_segment_timing() {
+        let mpd = dash_mpd::parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011">
+  <Period id="0" start="PT0S">
+    <AdaptationSet mimeType="video/mp4">
+      <SegmentTemplate timescale="90000" presentationTimeOffset="90000" media="$RepresentationID$/$Time$.m4s" initialization="$RepresentationID$/init.mp4">
+        <SegmentTimeline>
+          <S t="90000" d="180000" r="2" />
+          <S d="90000" />
+        </SegmentTimeline>
+      </SegmentTemplate>
+      <Representation id="video" bandwidth="89283" />
+      <Representation id="other" bandwidth="89283">
+        <SegmentTemplate timescale="1000" media="$RepresentationID$/$Time$.m4s">
+          <SegmentTimeline>
+            <S t="0" d="2000" r="-1" />
+          </SegmentTimeline>
+        </SegmentTemplate>
+      </Representation>
+    </AdaptationSet>
+    <AdaptationSet mimeType="audio/mp4">
+      <SegmentTemplate timescale="48000" duration="96000" media="$RepresentationID$/$Number$.m4s" />
+      <Representation id="audio" bandwidth="64000" />
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+        )
+        .unwrap();
+
+        let video = &mpd.periods[0].adaptations[0];
+        // 90000 + 3 * 180000 - presentationTimeOffset
+        assert_eq!(
+            super::last_segment_timing(video, &video.representations[0]),
+            Some((540000, 90000, 90000))
+        );
+        assert_eq!(
+            super::last_segment_timing(video, &video.representations[1]),
+            Some((0, 2000, 1000))
+        );
+
+        // $Number$ based templates have no timeline
+        let audio = &mpd.periods[0].adaptations[1];
+        assert_eq!(
+            super::last_segment_timing(audio, &audio.representations[0]),
+            None
+        );
+    }
+
+    /// full box payload of version 0 with `flags` followed by big endian `fields`
+    fn full_box(flags: u32, fields: &[u32]) -> Vec<u8> {
+        let mut payload = flags.to_be_bytes().to_vec();
+        for field in fields {
+            payload.extend_from_slice(&field.to_be_bytes());
+        }
+        payload
+    }
+
+    #[test]
+    /// fragments of variable length GOPs get their own duration
+    fn fragment_timing() {
+        let mdhd = bmff_box(b"mdhd", &full_box(0, &[0, 0, 90000]), false);
+        let trak = bmff_box(
+            b"trak",
+            &[
+                bmff_box(b"tkhd", &full_box(0, &[0, 0, 1]), false),
+                bmff_box(b"mdia", &mdhd, false),
+            ]
+            .concat(),
+            false,
+        );
+        let trex = bmff_box(b"trex", &full_box(0, &[1, 1, 3000, 0, 0]), false);
+        let init = bmff_box(
+            b"moov",
+            &[trak, bmff_box(b"mvex", &trex, false)].concat(),
+            false,
+        );
+        let fragment = |tfhd: Vec<u8>, decode_time: u32, trun: Vec<u8>| {
+            let traf = [
+                bmff_box(b"tfhd", &tfhd, false),
+                bmff_box(b"tfdt", &full_box(0, &[decode_time]), false),
+                bmff_box(b"trun", &trun, false),
+            ]
+            .concat();
+            bmff_box(b"moof", &bmff_box(b"traf", &traf, false), false)
+        };
+
+        // default duration of the trex
+        let trex_default = fragment(full_box(0, &[1]), 0, full_box(0, &[60]));
+        // durations of the samples, with their sizes
+        let sample_durations = fragment(
+            full_box(0, &[1]),
+            180000,
+            full_box(0x301, &[3, 0, 3000, 10, 3003, 10, 6000, 10]),
+        );
+        // default duration of the tfhd, after the base data offset
+        let tfhd_default = fragment(
+            full_box(0x09, &[1, 0, 0, 1500]),
+            192003,
+            full_box(0x04, &[40, 0]),
+        );
+
+        let timings: Vec<_> = [&trex_default, &sample_durations, &tfhd_default]
+            .iter()
+            .map(|fragment| super::fragment_timing(&init, fragment).unwrap().unwrap())
+            .map(|timing| (timing.decode_time, timing.duration, timing.timescale))
+            .collect();
+        assert_eq!(
+            timings,
+            [
+                (0, 180000, 90000),
+                (180000, 12003, 90000),
+                (192003, 60000, 90000)
+            ]
+        );
+
+        // the duration is carried by the event
+        let timing =
+            super::fragment_timing(&init, &fragment(full_box(0, &[1]), 0, full_box(0, &[2])))
+                .unwrap();
+        let event = EventPayload::new(&[0], &None).with_timing(timing);
+        let json = serde_json::to_value(event).unwrap();
+        assert_eq!(json["duration"], 6000);
+        assert_eq!(json["timescale"], 90000);
+
+        // the bunny fragments last two seconds
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let init = std::fs::read(bunny.join("BigBuckBunny_2s_init.mp4")).unwrap();
+        let fragment = std::fs::read(bunny.join("BigBuckBunny_2s10.m4s")).unwrap();
+        let timing = super::fragment_timing(&init, &fragment).unwrap().unwrap();
+        let seconds = timing.duration as f64 / timing.timescale as f64;
+        assert!((seconds - 2.0).abs() < 0.1, "{}", seconds);
+
+        // no traf, no timing
+        assert_eq!(super::fragment_timing(&init, &init).unwrap(), None);
+        // the track is missing from the init
+        assert!(super::fragment_timing(&trex, &trex_default).is_err());
+    }
+
+    #[test]
+    fn mpd_media_uris() {
+        let mpd = dash_mpd::parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011">
+  <Period id="0" start="PT0S">
+    <AdaptationSet mimeType="video/mp4">
+      <SegmentTemplate timescale="1000" startNumber="7" media="$RepresentationID$/segment_$Number%09d$.m4s">
+        <SegmentTimeline>
+          <S t="0" d="2000" r="1" />
+          <S d="1000" />
+        </SegmentTimeline>
+      </SegmentTemplate>
+      <Representation id="video" bandwidth="89283" />
+      <Representation id="time" bandwidth="89283">
+        <SegmentTemplate timescale="1000" media="$RepresentationID$/$Time$.m4s">
+          <SegmentTimeline>
+            <S t="4000" d="2000" r="1" />
+          </SegmentTimeline>
+        </SegmentTemplate>
+      </Representation>
+    </AdaptationSet>
+    <AdaptationSet mimeType="audio/mp4">
+      <SegmentTemplate timescale="48000" duration="96000" media="$RepresentationID$/$Number$.m4s" />
+      <Representation id="audio" bandwidth="64000" />
+      <Representation id="list" bandwidth="64000">
+        <SegmentList duration="2">
+          <SegmentURL media="list/a.m4s" />
+          <SegmentURL media="list/b.m4s" />
+        </SegmentList>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+        )
+        .unwrap();
+
+        let video = &mpd.periods[0].adaptations[0];
+        assert_eq!(
+            super::mpd_media_uris(video, &video.representations[0]),
+            [
+                "video/segment_000000007.m4s",
+                "video/segment_000000008.m4s",
+                "video/segment_000000009.m4s",
+            ]
+        );
+        assert_eq!(
+            super::mpd_media_uris(video, &video.representations[1]),
+            ["time/4000.m4s", "time/6000.m4s"]
+        );
+
+        // $Number$ based templates without timeline are not listed
+        let audio = &mpd.periods[0].adaptations[1];
+        assert!(super::mpd_media_uris(audio, &audio.representations[0]).is_empty());
+        assert_eq!(
+            super::mpd_media_uris(audio, &audio.representations[1]),
+            ["list/a.m4s", "list/b.m4s"]
+        );
+    }
+
+    #[test]
+    fn clear_media() {
+        let media = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("keep.m4s"), b"keep").unwrap();
+
+        std::fs::create_dir_all(media.path().join("stream/video")).unwrap();
+        std::fs::write(media.path().join("stream/video/1.m4s"), b"segment").unwrap();
+        std::fs::write(media.path().join("stream.mpd"), b"manifest").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(outside.path(), media.path().join("link")).unwrap();
+
+        super::clear_media(media.path()).unwrap();
+
+        assert!(!media.path().join("stream").exists());
+        assert!(media.path().join("stream.mpd").exists());
+        assert!(outside.path().join("keep.m4s").exists());
+    }
+
     #[test]
     /// test for only normal box sizes
     fn replace_uuid_content_normal() {
@@ -301,4 +1227,457 @@ mod tests {
 
         std::fs::remove_file(path).unwrap();
     }
+
+    /// a box with a 32-bit size, or a 64-bit large size
+    fn bmff_box(name: &[u8; 4], payload: &[u8], large: bool) -> Vec<u8> {
+        match large {
+            false => [&(payload.len() as u32 + 8).to_be_bytes()[..], name, payload].concat(),
+            true => [
+                &1_u32.to_be_bytes()[..],
+                name,
+                &(payload.len() as u64 + 16).to_be_bytes(),
+                payload,
+            ]
+            .concat(),
+        }
+    }
+
+    #[test]
+    fn box_scanner() {
+        let buf = [
+            bmff_box(b"ftyp", b"iso6", false),
+            bmff_box(b"uuid", b"c2pa", true),
+            bmff_box(b"mdat", b"media data", false),
+        ]
+        .concat();
+
+        let boxes: Vec<_> = super::BoxScanner::new(&buf)
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            boxes,
+            [
+                super::BmffBox {
+                    name: *b"ftyp",
+                    offset: 0,
+                    header_len: 8,
+                    payload_len: 4,
+                },
+                super::BmffBox {
+                    name: *b"uuid",
+                    offset: 12,
+                    header_len: 16,
+                    payload_len: 4,
+                },
+                super::BmffBox {
+                    name: *b"mdat",
+                    offset: 32,
+                    header_len: 8,
+                    payload_len: 10,
+                },
+            ]
+        );
+        assert_eq!(&buf[boxes[1].payload()], b"c2pa");
+
+        // size 0 extends to the end
+        let mut last = bmff_box(b"mdat", b"media data", false);
+        last[..4].copy_from_slice(&0_u32.to_be_bytes());
+        let buf = [bmff_box(b"ftyp", b"iso6", false), last].concat();
+        let boxes: Vec<_> = super::BoxScanner::new(&buf).map(Result::unwrap).collect();
+        assert_eq!(boxes[1].payload_len, 10);
+
+        // truncated boxes end the scan with an error
+        let buf = bmff_box(b"mdat", b"media data", true);
+        let mut scanner = super::BoxScanner::new(&buf[..20]);
+        assert!(scanner.next().unwrap().is_err());
+        assert!(scanner.next().is_none());
+        assert!(super::BoxScanner::new(&buf[..12]).next().unwrap().is_err());
+    }
+
+    #[test]
+    /// boxes with large sizes are extracted and kept unchanged
+    fn large_boxes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fragment.m4s");
+        let uuid = bmff_box(b"uuid", b"the original uuid content", true);
+        std::fs::write(
+            &path,
+            [
+                bmff_box(b"ftyp", b"iso6", true),
+                uuid.clone(),
+                bmff_box(b"mdat", b"media data", true),
+            ]
+            .concat(),
+        )
+        .unwrap();
+
+        assert_eq!(super::_extract_c2pa_box(&path).unwrap(), uuid);
+
+        let rep = super::replace_uuid_content(&path, b"new content").unwrap();
+        assert_eq!(
+            rep,
+            [
+                bmff_box(b"ftyp", b"iso6", true),
+                bmff_box(b"uuid", b"new content", false),
+                bmff_box(b"mdat", b"media data", true),
+            ]
+            .concat()
+        );
+
+        std::fs::write(&path, bmff_box(b"mdat", b"media data", false)).unwrap();
+        assert!(super::_extract_c2pa_box(&path).is_err());
+    }
+
+    #[test]
+    fn check_styp_brands() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("segment.m4s");
+        let allowed = ["cmfs".to_string(), "msdh".to_string()];
+
+        // major brand, minor version and a compatible brand
+        let styp = bmff_box(b"styp", b"iso6\0\0\0\0cmfs", false);
+        std::fs::write(&path, [styp, bmff_box(b"moof", b"", false)].concat()).unwrap();
+        assert_eq!(
+            super::styp_brands(&path).unwrap().unwrap(),
+            ["iso6", "cmfs"]
+        );
+        super::check_styp_brands(&path, &allowed).unwrap();
+        super::check_styp_brands(&path, &allowed[1..]).unwrap_err();
+
+        let err = super::check_styp_brands(&path, &["dash".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"unexpected styp brands ["iso6", "cmfs"], expected one of ["dash"]"#
+        );
+        assert_eq!(super::body_status(&err), Status::UnprocessableEntity);
+
+        // segments without styp only pass if the check is disabled
+        std::fs::write(&path, bmff_box(b"moof", b"", false)).unwrap();
+        assert_eq!(super::styp_brands(&path).unwrap(), None);
+        let err = super::check_styp_brands(&path, &allowed).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(super::UnexpectedBrands { found: None, .. })
+        ));
+        super::check_styp_brands(&path, &[]).unwrap();
+    }
+
+    #[test]
+    /// the rolling hash emsg is signed blank and filled in afterwards, the fragment
+    /// still verifies and the emsg holds the event of the fragment
+    fn rolling_hash_emsg() {
+        let dir = tempfile::tempdir().unwrap();
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let init = dir.path().join("segment_init.mp4");
+        let c2pa = sample_c2pa();
+        let signer = c2pa.signer().unwrap();
+        let mut builder = c2pa.builder("live").unwrap();
+        builder
+            .fragment_options
+            .exclusions
+            .push(super::emsg_exclusion());
+
+        for name in ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"] {
+            let fragment = dir.path().join(name);
+            super::insert_emsg_placeholder(bunny.join(name), &fragment).unwrap();
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny.join("BigBuckBunny_2s_init.mp4"),
+                    &vec![fragment.clone()],
+                    init.clone(),
+                    None,
+                )
+                .unwrap();
+            let event = super::get_event_data(&init).unwrap();
+            super::fill_emsg(&fragment, &event).unwrap();
+
+            // the emsg precedes the moof and holds the JSON event
+            let buf = std::fs::read(&fragment).unwrap();
+            let names: Vec<_> = super::BoxScanner::new(&buf)
+                .map(|bmff_box| bmff_box.unwrap().name)
+                .collect();
+            let position = |name: &[u8; 4]| names.iter().position(|n| n == name).unwrap();
+            assert!(position(b"emsg") < position(b"moof"));
+
+            let message = super::emsg_message(&buf).unwrap().unwrap();
+            assert_eq!(message.len(), super::EMSG_MESSAGE_SIZE);
+            let parsed: EventPayload = serde_json::from_slice(&buf[message]).unwrap();
+            assert_eq!(
+                serde_json::to_value(parsed).unwrap(),
+                serde_json::to_value(event).unwrap()
+            );
+
+            let reader = c2pa::Reader::from_fragment(
+                "mp4",
+                std::fs::File::open(&init).unwrap(),
+                std::fs::File::open(&fragment).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(reader.validation_status(), None);
+        }
+    }
+
+    #[test]
+    /// a signed init whose init hash was left at the zeroed placeholder isn't forwarded
+    fn check_init_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let c2pa = sample_c2pa();
+        let signer = c2pa.signer().unwrap();
+
+        for (mode, window_size) in [("merkle", Some(0)), ("rolling_hash", None)] {
+            std::fs::create_dir_all(dir.path().join(mode)).unwrap();
+            let init = dir.path().join(mode).join("BigBuckBunny_2s_init.mp4");
+            c2pa.builder("live")
+                .unwrap()
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny.join("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny.join("BigBuckBunny_2s1.m4s")],
+                    init.clone(),
+                    window_size,
+                )
+                .unwrap();
+            super::check_init_hash(&std::fs::read(&init).unwrap()).unwrap();
+
+            // the manifest as it was before the init hash was finalized
+            let hash = super::find_fragment_hash(&c2pa::Reader::from_file(&init).unwrap()).unwrap();
+            let init_hash = match hash.merkle() {
+                Some(merkle) => merkle[0].init_hash.clone().unwrap().into_vec(),
+                None => hash.rolling_hash().unwrap().init_hash().unwrap().clone(),
+            };
+            let mut buf = std::fs::read(&init).unwrap();
+            let pos = buf
+                .windows(init_hash.len())
+                .position(|w| w == init_hash.as_slice())
+                .unwrap();
+            buf[pos..pos + init_hash.len()].fill(0);
+            let err = super::check_init_hash(&buf).unwrap_err();
+            assert!(err.to_string().contains("was not finalized"), "{}", err);
+        }
+
+        let unsigned = std::fs::read(bunny.join("BigBuckBunny_2s_init.mp4")).unwrap();
+        let err = super::check_init_hash(&unsigned).unwrap_err();
+        assert!(err.to_string().contains("has no manifest"), "{}", err);
+    }
+
+    /// signs the first bunny fragment with a rolling hash into `<dir>/live`, then signs
+    /// that init again with a plain [c2pa::Builder] using it as parent ingredient
+    ///
+    /// returns the path of the resigned init, its active manifest has no fragment hash
+    fn multi_manifest_init(dir: &Path) -> PathBuf {
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let c2pa = sample_c2pa();
+        let signer = c2pa.signer().unwrap();
+
+        let live = dir.join("live/segment_init.m4s");
+        c2pa.builder("live")
+            .unwrap()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny.join("BigBuckBunny_2s_init.mp4"),
+                &vec![bunny.join("BigBuckBunny_2s1.m4s")],
+                live.clone(),
+                None,
+            )
+            .unwrap();
+
+        let init = dir.join("segment_init.mp4");
+        let mut builder = c2pa.builder("resigned").unwrap();
+        builder
+            .add_ingredient_from_stream(
+                serde_json::json!({ "title": "live", "relationship": "parentOf" }).to_string(),
+                "mp4",
+                &mut std::fs::File::open(&live).unwrap(),
+            )
+            .unwrap();
+        builder.sign_file(signer.as_ref(), &live, &init).unwrap();
+        init
+    }
+
+    #[test]
+    /// the fragment hash is found in the ingredient when the active manifest lacks it
+    fn find_fragment_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let init = multi_manifest_init(dir.path());
+
+        let reader = c2pa::Reader::from_file(&init).unwrap();
+        assert!(reader.manifests().len() > 1);
+        let active = reader.active_manifest().unwrap();
+        assert!(active
+            .find_assertion::<BmffHash>(BMFF_HASH_2)
+            .map_or(true, |hash| hash.rolling_hash().is_none()));
+
+        let hash = super::find_fragment_hash(&reader).unwrap();
+        assert!(hash.rolling_hash().is_some());
+        assert!(super::get_event_data(&init).is_ok());
+
+        // a store without any fragment hash
+        let plain = dir.path().join("plain.mp4");
+        let c2pa = sample_c2pa();
+        c2pa.builder("plain")
+            .unwrap()
+            .sign_file(
+                c2pa.signer().unwrap().as_ref(),
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("../sdk/tests/fixtures/bunny/bunny_89283bps/BigBuckBunny_2s_init.mp4"),
+                &plain,
+            )
+            .unwrap();
+        let err = super::find_fragment_hash(&c2pa::Reader::from_file(&plain).unwrap());
+        assert!(err.is_err());
+    }
+
+    #[test]
+    /// fragments are forwarded concurrently, but only after the init
+    fn write_signed_http() {
+        use std::{
+            io::{BufRead, BufReader, Read, Write},
+            net::TcpListener,
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc, Mutex,
+            },
+            time::Duration,
+        };
+
+        const RESPONSE: &[u8] =
+            b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = url::Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+        let active = Arc::new(AtomicUsize::new(0));
+        let max_active = Arc::new(AtomicUsize::new(0));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        {
+            let (active, max_active, received) =
+                (active.clone(), max_active.clone(), received.clone());
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let (active, max_active, received) =
+                        (active.clone(), max_active.clone(), received.clone());
+                    std::thread::spawn(move || {
+                        let mut stream = stream.unwrap();
+                        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                        let mut request_line = String::new();
+                        reader.read_line(&mut request_line).unwrap();
+                        let mut length = 0;
+                        loop {
+                            let mut header = String::new();
+                            reader.read_line(&mut header).unwrap();
+                            if header == "\r\n" {
+                                break;
+                            }
+                            if let Some(l) = header.to_lowercase().strip_prefix("content-length:") {
+                                length = l.trim().parse().unwrap();
+                            }
+                        }
+                        let mut body = vec![0; length];
+                        reader.read_exact(&mut body).unwrap();
+
+                        let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_active.fetch_max(now, Ordering::SeqCst);
+                        received.lock().unwrap().push(request_line);
+                        std::thread::sleep(Duration::from_millis(100));
+                        active.fetch_sub(1, Ordering::SeqCst);
+
+                        stream.write_all(RESPONSE).unwrap();
+                    });
+                }
+            });
+        }
+
+        let file = |name: &str, index| (key(name, index), name.as_bytes().to_vec());
+        let init = vec![file("segment_init.m4s", FragmentIndex::Init)];
+        let fragments = (1..=6)
+            .map(|i| file(&format!("segment_{i}.m4s"), FragmentIndex::Index(i)))
+            .collect();
+
+        let sink = HttpSink::new(reqwest::blocking::Client::new(), base, Default::default());
+        super::write_signed(&sink, init, fragments, 3).unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 7);
+        assert!(received[0].contains("/live_signed/0/segment_init.m4s"));
+        assert_eq!(max_active.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    /// a custom sink receives the init first and every fragment with its index
+    fn write_signed_memory() {
+        let file = |name: &str, index| (key(name, index), name.as_bytes().to_vec());
+        let init = vec![file("segment_init.m4s", FragmentIndex::Init)];
+        let fragments = (1..=3)
+            .map(|i| file(&format!("segment_{i}.m4s"), FragmentIndex::Index(i)))
+            .collect();
+
+        let sink = MemorySink::default();
+        super::write_signed(&sink, init, fragments, 2).unwrap();
+
+        let mut files = sink.files.into_inner().unwrap();
+        assert_eq!(files[0].0.index, FragmentIndex::Init);
+        assert_eq!(files[0].1, b"segment_init.m4s");
+
+        files.sort_by_key(|(key, _)| key.file_name.clone());
+        let indices: Vec<_> = files.iter().map(|(key, _)| key.index).collect();
+        assert_eq!(
+            indices,
+            [
+                FragmentIndex::Index(1),
+                FragmentIndex::Index(2),
+                FragmentIndex::Index(3),
+                FragmentIndex::Init,
+            ]
+        );
+        assert!(files
+            .iter()
+            .all(|(key, bytes)| key.file_name.as_bytes() == bytes));
+    }
+
+    #[test]
+    /// the bytes read right after signing are forwarded, not a file rewritten before forwarding
+    fn forward_signed() {
+        let dir = tempfile::tempdir().unwrap();
+        let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+        let c2pa = sample_c2pa();
+        let init = dir.path().join("BigBuckBunny_2s_init.mp4");
+        let fragment = dir.path().join("BigBuckBunny_2s1.m4s");
+        c2pa.builder("live")
+            .unwrap()
+            .sign_live_bmff(
+                c2pa.signer().unwrap().as_ref(),
+                bunny.join("BigBuckBunny_2s_init.mp4"),
+                &vec![bunny.join("BigBuckBunny_2s1.m4s")],
+                init.clone(),
+                None,
+            )
+            .unwrap();
+
+        // the sign step holds on to the signed bytes
+        let signed_init =
+            super::read_signed(vec![(init, key("segment_init.m4s", FragmentIndex::Init))]).unwrap();
+        let signed_fragments = super::read_signed(vec![(
+            fragment.clone(),
+            key("segment_1.m4s", FragmentIndex::Index(1)),
+        )])
+        .unwrap();
+        let signed = signed_fragments[0].1.clone();
+
+        // the file changes before it is forwarded
+        std::fs::write(&fragment, b"rewritten").unwrap();
+
+        let sink = MemorySink::default();
+        super::forward_signed(&sink, signed_init, signed_fragments, 1).unwrap();
+
+        let files = sink.files.into_inner().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].1, signed);
+    }
 }
@@ -48,12 +48,15 @@ use crate::{
 };
 
 mod info;
+mod inspect;
 mod tree;
 
 mod callback_signer;
 mod signer;
 
 mod live;
+mod pipe;
+mod vod;
 
 /// Tool for displaying and creating C2PA manifests.
 #[derive(Parser, Debug)]
@@ -194,6 +197,29 @@ enum Commands {
         #[arg(long = "fragments_glob", verbatim_doc_comment)]
         fragments_glob: Option<PathBuf>,
     },
+    /// Sub-command to sign fragments piped through stdin with the rolling hash
+    ///
+    /// Fragments of the init segment at <PATH> are read from stdin and the signed fragments are
+    /// written to stdout, in the same order. Every fragment is framed by its length as a
+    /// big-endian u32, in both directions. The signed init segment is written to the output folder.
+    ///
+    /// c2patool -m test2.json -o /my_output_folder my_init.mp4 pipe < fragments > signed_fragments
+    Pipe,
+    /// Sub-command to list the boxes of a fragment and decode its C2PA boxes
+    ///
+    /// Shows the Merkle map (location, proof length) or the rolling hash (anchor point,
+    /// exclusions) carried by the fragment at <PATH>, e.g. to diagnose a hash mismatch.
+    ///
+    /// c2patool my_fragment.m4s inspect
+    Inspect,
+    /// Sub-command to finalize a captured live stream as a VOD asset
+    ///
+    /// The init segment and all fragments in the capture folder at <PATH> are signed again as
+    /// a single Merkle tree, replacing the per-window (or rolling hash) signatures of the live
+    /// capture. The finalized files are written to the output folder, the capture is untouched.
+    ///
+    /// c2patool -m test2.json -o /my_output_folder /my_capture finalize-vod
+    FinalizeVod,
     Live {
         /// listen address, receiver of FFMpeg output
         #[arg(short, long, default_value = "[::]:6262")]
@@ -206,6 +232,114 @@ enum Commands {
         /// the size of the Merkle Tree Groups
         #[arg(short = 'w', long = "window")]
         window_size: usize,
+
+        /// bearer token required on the ingest routes (disabled if not set)
+        #[arg(long, env = "C2PA_INGEST_TOKEN", hide_env_values = true)]
+        auth_token: Option<String>,
+
+        /// remove all streams from the media dir on shutdown
+        #[arg(long, env = "C2PA_CLEANUP", value_parser = clap::builder::FalseyValueParser::new())]
+        cleanup_on_exit: bool,
+
+        /// maximum size of an ingested segment or manifest, larger bodies are rejected (e.g. 512KiB)
+        #[arg(long, env = "C2PA_MAX_FRAGMENT_SIZE", default_value = "64MiB", value_parser = byte_unit)]
+        max_fragment_size: rocket::data::ByteUnit,
+
+        /// regex matching the ingested segments, needs a <rep> and an <index> group,
+        /// init segments have the index `init` or match an optional <init> group
+        #[arg(long, env = "C2PA_FRAGMENT_PATTERN")]
+        fragment_pattern: Option<String>,
+
+        /// maximum number of concurrent posts when forwarding signed fragments to the target
+        #[arg(long, env = "C2PA_FORWARD_CONCURRENCY", default_value_t = 4)]
+        forward_concurrency: usize,
+
+        /// record the stream name, rep id and index in the rolling hash box of each fragment,
+        /// the first fragment of a stream carries no box
+        #[arg(long, env = "C2PA_FRAGMENT_METADATA", value_parser = clap::builder::FalseyValueParser::new())]
+        fragment_metadata: bool,
+
+        /// write the signed stream to this directory instead of posting it to the target
+        #[arg(long, env = "C2PA_SINK_DIR")]
+        sink_dir: Option<PathBuf>,
+
+        /// embed a thumbnail of this poster image in the signed init segments
+        #[arg(long, env = "C2PA_POSTER")]
+        poster: Option<PathBuf>,
+
+        /// comma separated `styp` brands of which ingested segments must list at least one
+        /// (e.g. cmfs,msdh), segments are not checked if not set
+        #[arg(long, env = "C2PA_ALLOWED_BRANDS", value_delimiter = ',', value_parser = styp_brand)]
+        allowed_brands: Vec<String>,
+
+        /// algorithm of the fragment content hashes, the claim algorithm is used if not set
+        #[arg(
+            long,
+            env = "C2PA_CONTENT_HASH_ALG",
+            value_parser = clap::builder::PossibleValuesParser::new(c2pa::assertions::CONTENT_HASH_ALGS)
+        )]
+        content_hash_alg: Option<String>,
+
+        /// sign a dummy payload at startup and reserve its signature size plus a margin,
+        /// instead of the fixed size guessed by the signer
+        #[arg(long, env = "C2PA_PROBE_RESERVE_SIZE")]
+        probe_reserve_size: bool,
+
+        /// JSON file mapping stream names to their own sign config (alg, private_key,
+        /// sign_cert, ta_url, reserve_size), the other streams are signed with the
+        /// manifest's credentials
+        #[arg(long, env = "C2PA_STREAM_SIGNERS")]
+        stream_signers: Option<PathBuf>,
+
+        /// maximum number of signings at once, a rolling hash per fragment and a Merkle Tree
+        /// per fragment or batch, further ingests are delayed until one of them is done
+        #[arg(long, env = "C2PA_MAX_IN_FLIGHT", default_value_t = 16)]
+        max_in_flight: usize,
+
+        /// Merkle sign the fragments of a representation arriving within this many
+        /// milliseconds at once, the batch is signed once the debounce of the first is over
+        #[arg(long, env = "C2PA_SIGN_DEBOUNCE_MS")]
+        sign_debounce_ms: Option<u64>,
+
+        /// verify the signed fragments before forwarding them, fragments failing the
+        /// self-check are logged and not forwarded
+        #[arg(long, env = "C2PA_VERIFY_AFTER_SIGN", value_parser = clap::builder::FalseyValueParser::new())]
+        verify_after_sign: bool,
+
+        /// order of the fragments in the Merkle Tree, `arrival` follows the ingest
+        /// requests (or their `Fragment-Sequence` header) instead of the file names
+        #[arg(long, env = "C2PA_FRAGMENT_ORDER", value_enum, default_value_t = live::FragmentOrder::Name)]
+        fragment_order: live::FragmentOrder,
+
+        /// where the rolling hash of the newest fragment is signaled, `emsg` boxes are
+        /// excluded from the hash and filled in after signing
+        #[arg(long, env = "C2PA_EVENT_SIGNALING", value_enum, default_value_t = live::EventSignaling::Mpd)]
+        event_signaling: live::EventSignaling,
+
+        /// file name of the signed files in the target, by default the ingested name,
+        /// e.g. `{stem}.signed.{ext}`, with the placeholders `{stem}`, `{ext}` and `{rep_id}`
+        #[arg(long, env = "C2PA_SIGNED_FILE_NAME")]
+        signed_file_name: Option<String>,
+
+        /// path of the signed files in the target, by default `{stream}/{rep_id}/{file_name}`,
+        /// e.g. `cdn/{stream}/{file_name}`, with the placeholders `{stream}`, `{rep_id}` and
+        /// `{file_name}`
+        #[arg(long, env = "C2PA_OUTPUT_LAYOUT")]
+        output_layout: Option<String>,
+
+        /// Cache-Control of ingested init segments posted to the target, e.g.
+        /// `public, max-age=31536000, immutable`
+        #[arg(long, env = "C2PA_CACHE_CONTROL_INIT")]
+        cache_control_init: Option<String>,
+
+        /// Cache-Control of fragments posted to the target
+        #[arg(long, env = "C2PA_CACHE_CONTROL_FRAGMENT")]
+        cache_control_fragment: Option<String>,
+
+        /// Cache-Control of manifests and signed init segments posted to the target,
+        /// both are updated with every fragment, e.g. `max-age=1`
+        #[arg(long, env = "C2PA_CACHE_CONTROL_MANIFEST")]
+        cache_control_manifest: Option<String>,
     },
 }
 
@@ -215,6 +349,18 @@ fn trailing_slash_url(s: &str) -> Result<Url> {
     Url::parse(&s).context("failed parsing URL")
 }
 
+fn byte_unit(s: &str) -> Result<rocket::data::ByteUnit> {
+    s.parse()
+        .map_err(|err| anyhow!("failed parsing byte unit: {err:?}"))
+}
+
+fn styp_brand(s: &str) -> Result<String> {
+    if s.len() != 4 || !s.is_ascii() {
+        bail!("a brand has 4 ASCII characters: {s:?}");
+    }
+    Ok(s.to_string())
+}
+
 #[derive(Debug, Default, Deserialize)]
 // Add fields that are not part of the standard Manifest
 struct ManifestDef {
@@ -569,14 +715,7 @@ fn main() -> Result<()> {
     let args = CliArgs::parse();
 
     // check for is not live first to skip <PATH> verification, not used anyways for live
-    let is_live = matches!(
-        args.command,
-        Some(Commands::Live {
-            bind: _,
-            target: _,
-            window_size: _
-        })
-    );
+    let is_live = matches!(args.command, Some(Commands::Live { .. }));
 
     // set RUST_LOG=debug to get detailed debug logging
     if !is_live {
@@ -610,9 +749,14 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
+    if let Some(Commands::Inspect) = &args.command {
+        println!("{}", inspect::inspect(path)?);
+        return Ok(());
+    }
+
     let is_fragment = matches!(
         &args.command,
-        Some(Commands::Fragment { fragments_glob: _ })
+        Some(Commands::Fragment { fragments_glob: _ } | Commands::Pipe | Commands::FinalizeVod)
     );
 
     // configure the SDK
@@ -746,10 +890,55 @@ fn main() -> Result<()> {
                 } else {
                     bail!("fragments_glob must be set");
                 }
+            } else if let Some(Commands::Pipe) = &args.command {
+                if output.exists() && !output.is_dir() {
+                    bail!("Output cannot point to existing file, must be a directory");
+                }
+
+                let count = pipe::sign_piped(
+                    &mut builder,
+                    signer.as_ref(),
+                    &args.path,
+                    &output,
+                    std::io::stdin().lock(),
+                    std::io::stdout().lock(),
+                )?;
+                debug!("signed {count} piped fragment(s)");
+                return Ok(());
+            } else if let Some(Commands::FinalizeVod) = &args.command {
+                if output.exists() && !output.is_dir() {
+                    bail!("Output cannot point to existing file, must be a directory");
+                }
+
+                let count = vod::finalize_vod(&mut builder, signer.as_ref(), &args.path, &output)?;
+                println!("finalized {count} fragment(s) into {}", output.display());
+                return Ok(());
             } else if let Some(Commands::Live {
                 bind,
                 target,
                 window_size,
+                auth_token,
+                cleanup_on_exit,
+                max_fragment_size,
+                fragment_pattern,
+                forward_concurrency,
+                fragment_metadata,
+                sink_dir,
+                poster,
+                allowed_brands,
+                content_hash_alg,
+                probe_reserve_size,
+                stream_signers,
+                max_in_flight,
+                sign_debounce_ms,
+                verify_after_sign,
+                fragment_order,
+                event_signaling,
+                signed_file_name,
+                output_layout,
+                cache_control_init,
+                cache_control_fragment,
+                cache_control_manifest,
             }) = &args.command
             {
                 let rocket_config = rocket::Config {
@@ -768,34 +957,99 @@ fn main() -> Result<()> {
                     .to_cors()
                     .expect("failed to create cors");
 
-                let re = Arc::new(live::regexp::Regexp::default());
+                let re = Arc::new(match fragment_pattern {
+                    Some(pattern) => live::regexp::Regexp::new(pattern)?,
+                    None => live::regexp::Regexp::default(),
+                });
+                let mut c2pa = live::c2pa_builder::C2PABuilder::new(
+                    json,
+                    base_path.expect("missing base path"),
+                )?;
+                if let Some(alg) = content_hash_alg {
+                    c2pa = c2pa.with_content_hash_alg(alg.clone())?;
+                }
+                if let Some(path) = stream_signers {
+                    c2pa = c2pa.with_stream_signers(path)?;
+                }
+                c2pa = c2pa.with_reserve_sizes(*probe_reserve_size)?;
+                let forward_headers = live::sink::ForwardHeaders::new(
+                    cache_control_init.as_deref(),
+                    cache_control_fragment.as_deref(),
+                    cache_control_manifest.as_deref(),
+                )?;
+                let layout = match output_layout {
+                    Some(template) => live::sink::OutputLayout::new(template)?,
+                    None => Default::default(),
+                };
                 let rocket = rocket::custom(rocket_config)
                     .mount(
                         "/ingest",
                         rocket::routes![live::routes::post_ingest, live::routes::delete_ingest],
                     )
-                    // .mount("/", rocket::routes![live::routes::get_merkle_tree])
-                    .manage(live::LiveSigner {
+                    .mount("/verify", rocket::routes![live::routes::post_verify])
+                    .mount("/", rocket::routes![live::routes::get_metrics])
+                    .mount("/merkle", rocket::routes![live::routes::get_merkle_tree])
+                    .manage(Arc::new(live::LiveSigner {
                         media: output.clone(),
                         target: target.to_owned(),
                         client: reqwest::Client::new(),
-                        sync_client: Arc::new(reqwest::blocking::Client::new()),
-                        c2pa: live::c2pa_builder::C2PABuilder {
-                            manifest_json: json,
-                            base_path: base_path.expect("missing base path"),
+                        sink: match sink_dir {
+                            Some(dir) => {
+                                Arc::new(live::sink::FsSink::new(dir.clone()).with_layout(layout))
+                            }
+                            None => Arc::new(
+                                live::sink::HttpSink::new(
+                                    reqwest::blocking::Client::new(),
+                                    target.to_owned(),
+                                    forward_headers.clone(),
+                                )
+                                .with_layout(layout),
+                            ),
+                        },
+                        c2pa: match poster {
+                            Some(poster) => c2pa.with_poster(poster)?,
+                            None => c2pa,
+                        },
+                        file_name: match signed_file_name {
+                            Some(template) => {
+                                Arc::new(live::sink::FileNameTemplate::new(template)?)
+                            }
+                            None => Arc::new(live::sink::Identity),
                         },
+                        forward_headers,
                         regex: re.clone(),
                         window_size: *window_size,
                         manifold: Default::default(),
-                    })
-                    .attach(rocket::fairing::AdHoc::on_shutdown("media cleaner", |_| {
+                        init_hashes: Default::default(),
+                        max_fragment_size: *max_fragment_size,
+                        forward_concurrency: *forward_concurrency,
+                        fragment_metadata: *fragment_metadata,
+                        allowed_brands: allowed_brands.clone(),
+                        in_flight: live::in_flight::InFlight::new(*max_in_flight),
+                        event_signaling: *event_signaling,
+                        sign_debounce: sign_debounce_ms.map(std::time::Duration::from_millis),
+                        pending_signs: Default::default(),
+                        verify_after_sign: *verify_after_sign,
+                        fragment_order: *fragment_order,
+                        arrivals: Default::default(),
+                        next_arrival: Default::default(),
+                        metrics: Default::default(),
+                        rolling_hash_queues: Default::default(),
+                        merkle_queues: Default::default(),
+                    }))
+                    .manage(live::auth::IngestAuth::new(auth_token.clone())?)
+                    .attach(cors);
+                let rocket = if *cleanup_on_exit {
+                    rocket.attach(rocket::fairing::AdHoc::on_shutdown("media cleaner", |_| {
                         Box::pin(async move {
                             if let Err(err) = live::utility::clear_media(output) {
                                 log::error!("failed to clean up media: {err}");
                             }
                         })
                     }))
-                    .attach(cors);
+                } else {
+                    rocket
+                };
                 rocket::execute(rocket.launch())?;
             } else {
                 if ext_normal(&output) != ext_normal(&args.path) {
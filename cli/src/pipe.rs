@@ -0,0 +1,132 @@
+//! signing of fragments piped through stdin/stdout
+//!
+//! both directions use the same framing, every fragment is sent as
+//!
+//! ```text
+//! +----------------------+------------------------+
+//! | length (u32, big e.) | fragment (length bytes)|
+//! +----------------------+------------------------+
+//! ```
+//!
+//! the stream ends on EOF at a frame boundary, EOF within a frame is an error.
+//! each fragment is signed with the rolling hash and written back in the same
+//! order, the signed init segment is kept up to date in the output directory.
+
+use std::{
+    convert::TryFrom,
+    io::{ErrorKind, Read, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use c2pa::{Builder, Signer};
+
+/// reads the next frame, `None` on EOF at a frame boundary
+pub(crate) fn read_frame<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    let mut read = 0;
+    while read < len.len() {
+        match reader.read(&mut len[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    match read {
+        0 => return Ok(None),
+        4 => {}
+        _ => bail!("stream ended within a frame header"),
+    }
+
+    let mut frame = vec![0u8; u32::from_be_bytes(len) as usize];
+    reader
+        .read_exact(&mut frame)
+        .context("stream ended within a frame")?;
+
+    Ok(Some(frame))
+}
+
+pub(crate) fn write_frame<W: Write>(writer: &mut W, frame: &[u8]) -> Result<()> {
+    let len = u32::try_from(frame.len()).context("frame too large")?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(frame)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// signs every fragment read from `input` and writes it to `output`
+///
+/// returns the number of signed fragments
+pub(crate) fn sign_piped<R, W>(
+    builder: &mut Builder,
+    signer: &dyn Signer,
+    init: &Path,
+    output_dir: &Path,
+    mut input: R,
+    mut output: W,
+) -> Result<usize>
+where
+    R: Read,
+    W: Write,
+{
+    let init_output = output_dir.join(init.file_name().context("invalid init path")?);
+
+    // fragments are staged on disk, the SDK signs files
+    let staging = tempfile::tempdir()?;
+
+    let mut count = 0;
+    while let Some(frame) = read_frame(&mut input)? {
+        let name = format!("fragment{count}.m4s");
+        let fragment = staging.path().join(&name);
+        std::fs::write(&fragment, &frame)?;
+
+        builder
+            .sign_live_bmff(signer, init, &vec![fragment], &init_output, None)
+            .with_context(|| format!("failed to sign fragment #{count}"))?;
+
+        // the signed fragment isn't needed once it is piped out
+        let signed = output_dir.join(&name);
+        write_frame(&mut output, &std::fs::read(&signed)?)?;
+        std::fs::remove_file(signed)?;
+
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn frames() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"first").unwrap();
+        write_frame(&mut buf, b"").unwrap();
+        write_frame(&mut buf, b"third").unwrap();
+        assert_eq!(&buf[..4], &[0, 0, 0, 5]);
+
+        let mut reader = Cursor::new(buf);
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"first");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"third");
+        assert!(read_frame(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"fragment").unwrap();
+        buf.truncate(buf.len() - 2);
+
+        assert!(read_frame(&mut Cursor::new(&buf)).is_err());
+
+        // within the length
+        assert!(read_frame(&mut Cursor::new(&buf[..2])).is_err());
+    }
+}
@@ -17,7 +17,7 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use c2pa::{create_signer, Signer, SigningAlg};
+use c2pa::{create_signer, dynamic_assertion::DynamicAssertion, Signer, SigningAlg};
 use serde::Deserialize;
 
 // Pull in default certs so the binary can self config
@@ -27,7 +27,7 @@ const DEFAULT_KEY: &[u8] = include_bytes!("../sample/es256_private.key");
 pub fn get_ta_url() -> Option<String> {
     std::env::var("C2PA_TA_URL").ok()
 }
-#[derive(Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct SignConfig {
     /// Signing algorithm to use - must match the associated certs
     ///
@@ -40,6 +40,9 @@ pub struct SignConfig {
     pub sign_cert: Option<PathBuf>,
     /// A Url to a Time Authority to use when signing the manifest
     pub ta_url: Option<String>,
+    /// Bytes reserved for the signature when live signing, checked against the
+    /// probed signature size at startup
+    pub reserve_size: Option<usize>,
 }
 
 impl SignConfig {
@@ -112,6 +115,73 @@ impl SignConfig {
     }
 }
 
+/// forwards to a signer, but reserves `reserve_size` bytes for its signatures
+///
+/// see [Signer::probe_reserve_size]
+pub struct ReservedSigner {
+    signer: Box<dyn Signer>,
+    reserve_size: usize,
+}
+
+impl ReservedSigner {
+    pub fn new(signer: Box<dyn Signer>, reserve_size: usize) -> Self {
+        Self {
+            signer,
+            reserve_size,
+        }
+    }
+}
+
+impl Signer for ReservedSigner {
+    fn sign(&self, data: &[u8]) -> c2pa::Result<Vec<u8>> {
+        self.signer.sign(data)
+    }
+
+    fn alg(&self) -> SigningAlg {
+        self.signer.alg()
+    }
+
+    fn certs(&self) -> c2pa::Result<Vec<Vec<u8>>> {
+        self.signer.certs()
+    }
+
+    fn reserve_size(&self) -> usize {
+        self.reserve_size
+    }
+
+    fn time_authority_url(&self) -> Option<String> {
+        self.signer.time_authority_url()
+    }
+
+    fn timestamp_request_headers(&self) -> Option<Vec<(String, String)>> {
+        self.signer.timestamp_request_headers()
+    }
+
+    fn timestamp_request_body(&self, message: &[u8]) -> c2pa::Result<Vec<u8>> {
+        self.signer.timestamp_request_body(message)
+    }
+
+    fn send_timestamp_request(&self, message: &[u8]) -> Option<c2pa::Result<Vec<u8>>> {
+        self.signer.send_timestamp_request(message)
+    }
+
+    fn ocsp_val(&self) -> Option<Vec<u8>> {
+        self.signer.ocsp_val()
+    }
+
+    fn direct_cose_handling(&self) -> bool {
+        self.signer.direct_cose_handling()
+    }
+
+    fn dynamic_assertions(&self) -> Vec<Box<dyn DynamicAssertion>> {
+        self.signer.dynamic_assertions()
+    }
+
+    fn raw_signer(&self) -> Option<Box<&dyn c2pa_crypto::raw_signature::RawSigner>> {
+        self.signer.raw_signer()
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     #![allow(clippy::unwrap_used)]
@@ -0,0 +1,147 @@
+//! finalization of a captured live stream into a VOD asset
+//!
+//! during the capture every window of fragments is signed on its own (or every fragment
+//! with the rolling hash). once the stream has ended, the init segment and all fragments
+//! of the capture are re-signed as a single Merkle tree, so a player can validate the
+//! whole asset against one manifest.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use c2pa::{Builder, Signer};
+
+use crate::live::utility::{find_boxes, read_u32, BoxScanner};
+
+/// uuid of the C2PA boxes, i.e. the Merkle map or rolling hash of a fragment
+const C2PA_UUID: [u8; 16] = [
+    0xd8, 0xfe, 0xc3, 0xd6, 0x1b, 0x0e, 0x48, 0x3c, 0x92, 0x97, 0x58, 0x28, 0x87, 0x7e, 0xc4, 0x81,
+];
+
+/// kind of a file of the capture, told apart by its top level boxes
+enum Segment {
+    Init,
+    /// fragment with the sequence number of its `mfhd`
+    Fragment(u32),
+}
+
+/// `None` for files that aren't part of the media, e.g. the manifest of the stream
+fn classify(path: &Path) -> Result<Option<Segment>> {
+    let buf = fs::read(path).with_context(|| format!("failed to read {path:?}"))?;
+
+    let mut names = Vec::new();
+    for bmff_box in BoxScanner::new(&buf) {
+        match bmff_box {
+            Ok(bmff_box) => names.push(bmff_box.name),
+            Err(_) => return Ok(None),
+        }
+    }
+
+    if names.contains(b"moov") {
+        return Ok(Some(Segment::Init));
+    }
+    if !names.contains(b"moof") {
+        return Ok(None);
+    }
+
+    let mfhd = find_boxes(&buf, &[b"moof", b"mfhd"])?;
+    let sequence = mfhd
+        .first()
+        .context(format!("fragment {path:?} has no mfhd"))
+        .and_then(|mfhd| read_u32(mfhd, 4))?;
+    Ok(Some(Segment::Fragment(sequence)))
+}
+
+/// init segment and fragments of `dir`, the fragments in order of their sequence number
+fn capture_files(dir: &Path) -> Result<(PathBuf, Vec<PathBuf>)> {
+    let mut init = None;
+    let mut fragments = Vec::new();
+
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to list {dir:?}"))? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        match classify(&path)? {
+            Some(Segment::Init) => {
+                if let Some(other) = init.replace(path.clone()) {
+                    bail!("more than one init segment in {dir:?}: {other:?} and {path:?}");
+                }
+            }
+            Some(Segment::Fragment(sequence)) => fragments.push((sequence, path)),
+            None => {}
+        }
+    }
+
+    let init = init.context(format!("no init segment in {dir:?}"))?;
+    ensure!(!fragments.is_empty(), "no fragments in {dir:?}");
+    fragments.sort();
+
+    Ok((init, fragments.into_iter().map(|(_, path)| path).collect()))
+}
+
+/// copies `fragment` to `dest` without its C2PA boxes
+///
+/// the Merkle map of a window or the rolling hash of the capture is replaced by the
+/// Merkle map of the whole asset
+fn copy_unsigned(fragment: &Path, dest: &Path) -> Result<()> {
+    let buf = fs::read(fragment).with_context(|| format!("failed to read {fragment:?}"))?;
+
+    let mut unsigned = Vec::with_capacity(buf.len());
+    for bmff_box in BoxScanner::new(&buf) {
+        let bmff_box = bmff_box?;
+        if bmff_box.name == *b"uuid" && buf[bmff_box.payload()].starts_with(&C2PA_UUID) {
+            continue;
+        }
+        unsigned.extend_from_slice(&buf[bmff_box.range()]);
+    }
+
+    Ok(fs::write(dest, unsigned)?)
+}
+
+/// re-signs the capture in `capture_dir` as a single Merkle tree into `output_dir`
+///
+/// the capture itself is left untouched, returns the number of fragments
+pub(crate) fn finalize_vod(
+    builder: &mut Builder,
+    signer: &dyn Signer,
+    capture_dir: &Path,
+    output_dir: &Path,
+) -> Result<usize> {
+    ensure!(capture_dir.is_dir(), "{capture_dir:?} is not a directory");
+    if output_dir.exists() {
+        ensure!(
+            fs::canonicalize(capture_dir)? != fs::canonicalize(output_dir)?,
+            "the output folder must differ from the capture folder"
+        );
+    }
+
+    let (init, fragments) = capture_files(capture_dir)?;
+    let init_output = output_dir.join(init.file_name().context("init segment has no name")?);
+
+    // existing fragments of the output would be signed in place
+    for path in fragments.iter().chain(Some(&init)) {
+        let dest = output_dir.join(path.file_name().context("segment has no name")?);
+        ensure!(!dest.exists(), "{dest:?} already exists");
+    }
+
+    // the fragments are signed into the output folder, sign unsigned copies of them
+    let unsigned = tempfile::tempdir()?;
+    let mut copies = Vec::with_capacity(fragments.len());
+    for fragment in &fragments {
+        let dest = unsigned
+            .path()
+            .join(fragment.file_name().context("fragment has no name")?);
+        copy_unsigned(fragment, &dest)?;
+        copies.push(dest);
+    }
+
+    // a window of 0 puts all fragments into one tree
+    builder
+        .sign_live_bmff(signer, init, &copies, init_output, Some(0))
+        .context("failed to finalize the capture")?;
+
+    Ok(copies.len())
+}
@@ -571,3 +571,172 @@ fn tool_read_image_with_details_with_cawg_data() -> Result<(), Box<dyn Error>> {
         .stdout(str::contains("IdentityClaimsAggregationCredential"));
     Ok(())
 }
+
+#[test]
+// c2patool -c '{...}' -o out init.mp4 pipe < fragments
+fn tool_sign_piped_fragments() -> Result<(), Box<dyn Error>> {
+    let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+    let fragments = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s", "BigBuckBunny_2s11.m4s"];
+
+    // length-prefixed fragments
+    let mut input = Vec::new();
+    for fragment in fragments {
+        let data = fs::read(bunny.join(fragment))?;
+        input.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        input.extend_from_slice(&data);
+    }
+
+    let out = temp_path("piped");
+    fs::remove_dir_all(&out).ok();
+    let output = assert_cmd::Command::cargo_bin("c2patool")?
+        .arg(bunny.join("BigBuckBunny_2s_init.mp4"))
+        .arg("-c")
+        .arg(
+            r#"{
+                "alg": "es256",
+                "private_key": "sample/es256_private.key",
+                "sign_cert": "sample/es256_certs.pem",
+                "title": "Piped",
+                "assertions": [{
+                    "label": "c2pa.actions",
+                    "data": { "actions": [{ "action": "c2pa.published" }] }
+                }]
+            }"#,
+        )
+        .arg("-o")
+        .arg(&out)
+        .arg("pipe")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    // C2PA uuid box identifier
+    let c2pa_uuid = [
+        216, 254, 195, 214, 27, 14, 72, 60, 146, 151, 88, 40, 135, 126, 196, 129,
+    ];
+
+    let mut signed = output.as_slice();
+    for (index, fragment) in fragments.iter().enumerate() {
+        let (len, rest) = signed.split_at(4);
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let (frame, rest) = rest.split_at(len);
+        signed = rest;
+
+        // the first fragment has nothing to anchor to and carries no box
+        let unsigned = fs::read(bunny.join(fragment))?;
+        if index == 0 {
+            assert_eq!(frame, unsigned.as_slice());
+        } else {
+            assert!(len > unsigned.len());
+            assert!(frame.windows(c2pa_uuid.len()).any(|w| w == c2pa_uuid));
+        }
+    }
+    assert!(signed.is_empty());
+    assert!(out.join("BigBuckBunny_2s_init.mp4").exists());
+    Ok(())
+}
+
+#[test]
+// c2patool -c '{...}' -o out capture finalize-vod
+fn tool_finalize_vod() -> Result<(), Box<dyn Error>> {
+    use c2pa::{
+        assertions::{labels::BMFF_HASH_2, BmffHash},
+        Reader, SigningAlg, ValidationState,
+    };
+
+    let bunny = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../sdk/tests/fixtures/bunny/bunny_89283bps");
+    let init = "BigBuckBunny_2s_init.mp4";
+    let names = [
+        "BigBuckBunny_2s1.m4s",
+        "BigBuckBunny_2s10.m4s",
+        "BigBuckBunny_2s11.m4s",
+        "BigBuckBunny_2s100.m4s",
+    ];
+    let fragments: Vec<_> = names.iter().map(|name| bunny.join(name)).collect();
+
+    // live capture, one Merkle tree per window of two fragments
+    let root = temp_path("finalize_vod");
+    fs::remove_dir_all(&root).ok();
+    let capture = root.join("capture");
+    let signer = c2pa::create_signer::from_files(
+        "sample/es256_certs.pem",
+        "sample/es256_private.key",
+        SigningAlg::Es256,
+        None,
+    )?;
+    let mut builder = c2pa::Builder::from_json(
+        r#"{
+            "title": "Live",
+            "assertions": [{
+                "label": "c2pa.actions",
+                "data": { "actions": [{ "action": "c2pa.published" }] }
+            }]
+        }"#,
+    )?;
+    for count in 1..=fragments.len() {
+        builder.sign_live_bmff(
+            signer.as_ref(),
+            bunny.join(init),
+            &fragments[..count].to_vec(),
+            capture.join(init),
+            Some(2),
+        )?;
+    }
+    // not part of the media
+    fs::write(capture.join("stream.mpd"), "<MPD/>")?;
+    let captured = fs::read(capture.join(names[0]))?;
+
+    let out = root.join("vod");
+    Command::cargo_bin("c2patool")?
+        .arg(&capture)
+        .arg("-c")
+        .arg(
+            r#"{
+                "alg": "es256",
+                "private_key": "sample/es256_private.key",
+                "sign_cert": "sample/es256_certs.pem",
+                "title": "VOD",
+                "assertions": [{
+                    "label": "c2pa.actions",
+                    "data": { "actions": [{ "action": "c2pa.published" }] }
+                }]
+            }"#,
+        )
+        .arg("-o")
+        .arg(&out)
+        .arg("finalize-vod")
+        .assert()
+        .success()
+        .stdout(str::contains("finalized 4 fragment(s)"));
+
+    let finalized: Vec<_> = names.iter().map(|name| out.join(name)).collect();
+    let reader = Reader::from_fragmented_files(out.join(init), &finalized)?;
+    assert_ne!(reader.validation_state(), ValidationState::Invalid);
+
+    // a single tree over the whole capture
+    let bmff_hash: BmffHash = reader
+        .active_manifest()
+        .ok_or("no active manifest")?
+        .find_assertion(BMFF_HASH_2)?;
+    let merkle = bmff_hash.merkle().ok_or("no Merkle map")?;
+    assert_eq!(merkle.len(), 1);
+    assert_eq!(merkle[0].count, names.len() as u32);
+
+    // the capture is left untouched and the finalized files aren't overwritten
+    assert_eq!(fs::read(capture.join(names[0]))?, captured);
+    Command::cargo_bin("c2patool")?
+        .arg(&capture)
+        .arg("-c")
+        .arg(r#"{ "alg": "es256", "private_key": "sample/es256_private.key", "sign_cert": "sample/es256_certs.pem" }"#)
+        .arg("-o")
+        .arg(&out)
+        .arg("finalize-vod")
+        .assert()
+        .failure();
+    Ok(())
+}
@@ -0,0 +1,241 @@
+//! End to end test of `c2patool live`: the server ingests a stream and forwards the
+//! original and the signed segments to a mock CDN running in the test process.
+//!
+//! The test spawns the server and binds local ports, it only runs when asked for:
+//! `cargo test -p c2patool --test live -- --ignored`
+
+#![cfg(not(target_os = "wasi"))]
+use std::{
+    io::Cursor,
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use assert_cmd::prelude::*;
+use c2pa::Reader;
+use rocket::{data::ByteUnit, fairing::AdHoc, Data, State};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn bunny_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../sdk/tests/fixtures/bunny/bunny_89283bps")
+        .join(name)
+}
+
+/// posts received by the mock CDN, in the order they were received
+#[derive(Default)]
+struct Cdn {
+    posts: Mutex<Vec<(PathBuf, Vec<u8>)>>,
+}
+
+impl Cdn {
+    fn contains(&self, path: &str) -> bool {
+        self.posts
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(p, _)| p == Path::new(path))
+    }
+
+    /// waits until all `paths` were posted
+    fn wait_for(&self, paths: &[&str]) {
+        let start = Instant::now();
+        while !paths.iter().all(|path| self.contains(path)) {
+            assert!(start.elapsed() < TIMEOUT, "CDN never received {:?}", paths);
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// pairs every fragment posted to `stream` with the init posted to it before
+    fn signed_pairs(&self, stream: &str) -> Vec<(PathBuf, Vec<u8>, Vec<u8>)> {
+        let mut init = None;
+        let mut pairs = Vec::new();
+        for (path, body) in self.posts.lock().unwrap().iter() {
+            if !path.starts_with(stream) {
+                continue;
+            }
+            if path.ends_with("segment_init.m4s") {
+                init = Some(body.clone());
+            } else {
+                let init = init.clone().expect("fragment posted before its init");
+                pairs.push((path.clone(), init, body.clone()));
+            }
+        }
+        pairs
+    }
+}
+
+#[rocket::post("/<path..>", data = "<body>")]
+async fn capture(path: PathBuf, body: Data<'_>, cdn: &State<Arc<Cdn>>) {
+    let body = body.open(ByteUnit::max_value()).into_bytes().await.unwrap();
+    cdn.posts.lock().unwrap().push((path, body.into_inner()));
+}
+
+/// launches the mock CDN on a free port, returns its captured posts and port
+fn mock_cdn() -> (Arc<Cdn>, u16) {
+    let cdn = Arc::new(Cdn::default());
+    let (tx, rx) = mpsc::channel();
+
+    let config = rocket::Config {
+        address: Ipv4Addr::LOCALHOST.into(),
+        port: 0,
+        log_level: rocket::config::LogLevel::Off,
+        ..Default::default()
+    };
+    let rocket = rocket::custom(config)
+        .mount("/", rocket::routes![capture])
+        .manage(cdn.clone())
+        .attach(AdHoc::on_liftoff("port", move |rocket| {
+            Box::pin(async move {
+                tx.send(rocket.config().port).unwrap();
+            })
+        }));
+    thread::spawn(move || {
+        if let Err(err) = rocket::execute(rocket.launch()) {
+            eprintln!("mock CDN: {err}");
+        }
+    });
+
+    let port = rx.recv_timeout(TIMEOUT).expect("mock CDN didn't start");
+    (cdn, port)
+}
+
+/// the `c2patool live` process, killed on drop
+struct LiveServer {
+    child: Child,
+    addr: SocketAddr,
+}
+
+impl LiveServer {
+    fn spawn(media: &Path, manifest: &Path, cdn_port: u16, window_size: usize) -> Self {
+        let addr = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .unwrap();
+
+        let child = Command::cargo_bin("c2patool")
+            .unwrap()
+            .arg("-m")
+            .arg(manifest)
+            .arg("-o")
+            .arg(media)
+            .arg(media)
+            .arg("live")
+            .arg("--bind")
+            .arg(addr.to_string())
+            .arg("--target")
+            .arg(format!("http://127.0.0.1:{cdn_port}/"))
+            .arg("--window")
+            .arg(window_size.to_string())
+            .env_remove("C2PA_INGEST_TOKEN")
+            .env_remove("C2PA_SINK_DIR")
+            .env_remove("C2PA_ALLOWED_BRANDS")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let server = Self { child, addr };
+
+        let start = Instant::now();
+        while TcpStream::connect(addr).is_err() {
+            assert!(start.elapsed() < TIMEOUT, "live server didn't start");
+            thread::sleep(Duration::from_millis(50));
+        }
+        server
+    }
+
+    fn ingest(&self, uri: &str, body: Vec<u8>) {
+        let res = reqwest::blocking::Client::new()
+            .post(format!("http://{}/ingest/live/0/{uri}", self.addr))
+            .body(body)
+            .send()
+            .unwrap();
+        assert!(
+            res.status().is_success(),
+            "ingest {}: {}",
+            uri,
+            res.status()
+        );
+    }
+}
+
+impl Drop for LiveServer {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+#[test]
+#[ignore = "spawns c2patool live and binds local ports"]
+/// an init and two fragments are ingested, the CDN receives them unchanged and
+/// signed with both hash modes, every signed fragment verifies against its init
+fn live_ingest_forward() {
+    let dir = tempfile::tempdir().unwrap();
+    let sample = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sample");
+    let manifest = dir.path().join("manifest.json");
+    std::fs::write(
+        &manifest,
+        serde_json::json!({
+            "alg": "es256",
+            "private_key": sample.join("es256_private.key"),
+            "sign_cert": sample.join("es256_certs.pem"),
+            "claim_generator": "TestApp",
+            "assertions": [{
+                "label": "c2pa.actions",
+                "data": { "actions": [{ "action": "c2pa.published" }] }
+            }]
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let media = dir.path().join("media");
+    std::fs::create_dir_all(&media).unwrap();
+
+    let (cdn, cdn_port) = mock_cdn();
+    let server = LiveServer::spawn(&media, &manifest, cdn_port, 2);
+
+    server.ingest(
+        "segment_init.m4s",
+        std::fs::read(bunny_path("BigBuckBunny_2s_init.mp4")).unwrap(),
+    );
+    // each fragment is signed in the background, wait for it before ingesting the
+    // next one so the signed inits and fragments are posted in order
+    for (index, fragment) in [(1, "BigBuckBunny_2s1.m4s"), (2, "BigBuckBunny_2s10.m4s")] {
+        let uri = format!("segment_{index:09}.m4s");
+        server.ingest(&uri, std::fs::read(bunny_path(fragment)).unwrap());
+        cdn.wait_for(&[
+            &format!("live/0/{uri}"),
+            &format!("live_signed/0/{uri}"),
+            &format!("live_rolling-hash/0/{uri}"),
+        ]);
+    }
+    assert!(cdn.contains("live/0/segment_init.m4s"));
+
+    for stream in ["live_signed", "live_rolling-hash"] {
+        let pairs = cdn.signed_pairs(stream);
+        for index in 1..=2 {
+            let uri = format!("{stream}/0/segment_{index:09}.m4s");
+            assert!(
+                pairs.iter().any(|(path, _, _)| path == Path::new(&uri)),
+                "{} was not signed",
+                uri
+            );
+        }
+
+        for (path, init, fragment) in pairs {
+            let reader = Reader::from_fragment("mp4", Cursor::new(init), Cursor::new(fragment))
+                .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+            assert_eq!(
+                reader.validation_status(),
+                None,
+                "{} doesn't verify",
+                path.display()
+            );
+        }
+    }
+}
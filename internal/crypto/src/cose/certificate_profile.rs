@@ -112,8 +112,9 @@ pub fn check_certificate_profile(
         // A valid time stamp was associated with this signature: Ensure that the
         // timestamp was valid at that time.
         let signing_time = generalized_time_to_datetime(tst_info.gen_time.clone());
+        let signing_time = ctp.skewed_time(signcert.validity(), signing_time.timestamp());
         if !signcert.validity().is_valid_at(
-            x509_parser::time::ASN1Time::from_timestamp(signing_time.timestamp())
+            x509_parser::time::ASN1Time::from_timestamp(signing_time)
                 .map_err(|_| CertificateProfileError::InvalidCertificate)?,
         ) {
             log_item!(
@@ -138,8 +139,9 @@ pub fn check_certificate_profile(
             ));
         };
 
+        let now = ctp.skewed_time(signcert.validity(), now.as_secs() as i64);
         if !signcert.validity().is_valid_at(
-            x509_parser::time::ASN1Time::from_timestamp(now.as_secs() as i64)
+            x509_parser::time::ASN1Time::from_timestamp(now)
                 .map_err(|_| CertificateProfileError::InvalidCertificate)?,
         ) {
             log_item!(
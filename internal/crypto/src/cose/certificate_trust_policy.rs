@@ -18,7 +18,7 @@ use std::{collections::HashSet, error::Error, fmt, io::BufRead, str::FromStr};
 use asn1_rs::{oid, Oid};
 use async_generic::async_generic;
 use thiserror::Error;
-use x509_parser::{extensions::ExtendedKeyUsage, pem::Pem};
+use x509_parser::{certificate::Validity, extensions::ExtendedKeyUsage, pem::Pem};
 
 use crate::{base64, hash::sha256};
 
@@ -36,6 +36,10 @@ pub struct CertificateTrustPolicy {
 
     /// Additional extended key usage (EKU) OIDs.
     additional_ekus: HashSet<String>,
+
+    /// Seconds a signing time may lie outside of a certificate's period of
+    /// validity.
+    allowed_clock_skew: u64,
 }
 
 impl Default for CertificateTrustPolicy {
@@ -44,6 +48,7 @@ impl Default for CertificateTrustPolicy {
             trust_anchor_ders: vec![],
             end_entity_cert_set: HashSet::default(),
             additional_ekus: HashSet::default(),
+            allowed_clock_skew: 0,
         };
 
         this.add_valid_ekus(include_bytes!("./valid_eku_oids.cfg"));
@@ -72,6 +77,7 @@ impl CertificateTrustPolicy {
             trust_anchor_ders: vec![],
             end_entity_cert_set: HashSet::default(),
             additional_ekus: HashSet::default(),
+            allowed_clock_skew: 0,
         }
     }
 
@@ -271,6 +277,39 @@ impl CertificateTrustPolicy {
         }
     }
 
+    /// Set how many seconds a signing time may lie outside of a certificate's
+    /// period of validity and still be accepted.
+    ///
+    /// This tolerates small differences between the clock of the time stamp
+    /// authority (or the validator if there is no time stamp) and the clock
+    /// the certificate was issued with. The default of 0 accepts no skew.
+    pub fn set_allowed_clock_skew(&mut self, seconds: u64) {
+        self.allowed_clock_skew = seconds;
+    }
+
+    /// Return the allowed clock skew in seconds.
+    pub fn allowed_clock_skew(&self) -> u64 {
+        self.allowed_clock_skew
+    }
+
+    /// Return the time (in Unix seconds) to evaluate `validity` at.
+    ///
+    /// A `time` within the allowed clock skew of the period of validity is
+    /// moved onto its boundary, any other `time` is returned as is.
+    pub(crate) fn skewed_time(&self, validity: &Validity, time: i64) -> i64 {
+        let skew = i64::try_from(self.allowed_clock_skew).unwrap_or(i64::MAX);
+        let not_before = validity.not_before.timestamp();
+        let not_after = validity.not_after.timestamp();
+
+        if time < not_before && not_before.saturating_sub(time) <= skew {
+            not_before
+        } else if time > not_after && time.saturating_sub(not_after) <= skew {
+            not_after
+        } else {
+            time
+        }
+    }
+
     /// Remove all trust anchors, private credentials, and EKUs previously
     /// configured.
     pub fn clear(&mut self) {
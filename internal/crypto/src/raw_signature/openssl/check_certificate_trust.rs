@@ -44,6 +44,12 @@ pub(crate) fn check_certificate_trust(
     verify_param.set_flags(X509VerifyFlags::X509_STRICT)?;
 
     if let Some(st) = signing_time_epoch {
+        // openssl evaluates the whole chain at one time, tolerate the skew
+        // for the end-entity certificate
+        let st = match x509_parser::parse_x509_certificate(cert_der) {
+            Ok((_, end_entity_cert)) => ctp.skewed_time(end_entity_cert.validity(), st),
+            Err(_) => st,
+        };
         verify_param.set_time(st);
     } else {
         verify_param.set_flags(X509VerifyFlags::NO_CHECK_TIME)?;
@@ -78,6 +78,7 @@ pub(crate) fn check_certificate_trust(
 
         // Make sure the certificate was not expired.
         if let Some(signing_time) = signing_time_epoch {
+            let signing_time = ctp.skewed_time(chain_cert.validity(), signing_time);
             if !chain_cert.validity().is_valid_at(
                 x509_parser::time::ASN1Time::from_timestamp(signing_time)
                     .map_err(|_| CertificateTrustError::CertificateNotTrusted)?,
@@ -14,7 +14,9 @@
 use asn1_rs::{oid, Oid};
 #[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
 use wasm_bindgen_test::wasm_bindgen_test;
-use x509_parser::{extensions::ExtendedKeyUsage, pem::Pem};
+use x509_parser::{
+    certificate::X509Certificate, extensions::ExtendedKeyUsage, pem::Pem, prelude::FromDer,
+};
 
 use crate::{
     cose::{CertificateTrustError, CertificateTrustPolicy, InvalidCertificateError},
@@ -673,6 +675,35 @@ async fn test_allowed_list_hashes_async() {
         .unwrap();
 }
 
+#[test]
+fn allowed_clock_skew() {
+    let mut ctp = CertificateTrustPolicy::default();
+    assert_eq!(ctp.allowed_clock_skew(), 0);
+
+    let certs = cert_ders_from_pem(include_bytes!("../fixtures/raw_signature/ps256.pub"));
+    let (_, cert) = X509Certificate::from_der(&certs[0]).unwrap();
+    let validity = cert.validity();
+    let not_before = validity.not_before.timestamp();
+    let not_after = validity.not_after.timestamp();
+
+    // signed shortly before the certificate became valid
+    let signing_time = not_before - 5;
+    assert!(ctp
+        .check_certificate_trust(&certs[1..], &certs[0], Some(signing_time))
+        .is_err());
+    assert_eq!(ctp.skewed_time(validity, signing_time), signing_time);
+
+    ctp.set_allowed_clock_skew(10);
+    ctp.check_certificate_trust(&certs[1..], &certs[0], Some(signing_time))
+        .unwrap();
+    assert_eq!(ctp.skewed_time(validity, signing_time), not_before);
+    assert_eq!(ctp.skewed_time(validity, not_after + 10), not_after);
+
+    // larger differences are not tolerated
+    assert_eq!(ctp.skewed_time(validity, not_before - 11), not_before - 11);
+    assert_eq!(ctp.skewed_time(validity, not_after + 11), not_after + 11);
+}
+
 fn cert_ders_from_pem(cert_chain: &[u8]) -> Vec<Vec<u8>> {
     Pem::iter_from_buffer(cert_chain)
         .map(|r| r.unwrap().contents)
@@ -12,12 +12,13 @@
 // each license.
 
 use std::{
-    collections::{hash_map::Entry::Vacant, HashMap},
+    collections::HashMap,
     fmt,
     io::{BufReader, Cursor, Read, Seek},
-    ops::Deref,
+    ops::{ControlFlow, Deref},
 };
 
+use c2pa_status_tracker::{log_item, StatusTracker};
 use mp4::*;
 use serde::{
     de::{SeqAccess, Visitor},
@@ -31,10 +32,12 @@ use crate::{
     assertion::{Assertion, AssertionBase, AssertionCbor},
     assertions::labels,
     asset_handlers::bmff_io::{
-        bmff_to_jumbf_exclusions, read_bmff_c2pa_boxes, BoxInfoLite, C2PABmffBoxesRollingHash,
+        bmff_to_jumbf_exclusions, read_bmff_c2pa_boxes, read_bmff_fragment_c2pa_boxes,
+        signed_fragment_len, BoxInfoLite, C2PABmffBoxesRollingHash,
     },
     asset_io::CAIRead,
     cbor_types::UriT,
+    settings::get_settings_value,
     utils::{
         hash_utils::{
             concat_and_hash, hash_stream_by_alg, vec_compare, verify_stream_by_alg, HashRange,
@@ -43,7 +46,7 @@ use crate::{
         io_utils::stream_len,
         merkle::C2PAMerkleTree,
     },
-    Error,
+    validation_status, Error,
 };
 
 const ASSERTION_CREATION_VERSION: usize = 2;
@@ -165,57 +168,147 @@ impl MerkleMap {
         location: u32,
         proof: &Option<VecByteBuf>,
     ) -> bool {
+        match self.climb_proof(
+            alg,
+            hash,
+            location,
+            proof,
+            self.hashes.len(),
+            &mut |_, _| {},
+        ) {
+            Some((index, hash)) => self.hash_check(index, &hash),
+            None => false,
+        }
+    }
+
+    /// Checks that the proof of the leaf at `location` reduces to `expected_root`,
+    /// instead of the row stored in this MerkleMap (i.e. a root received out of band).
+    pub fn verify_proof_to_root(
+        &self,
+        alg: &str,
+        leaf_hash: &[u8],
+        location: u32,
+        proof: &Option<VecByteBuf>,
+        expected_root: &[u8],
+    ) -> bool {
+        match self.climb_proof(alg, leaf_hash, location, proof, 1, &mut |_, _| {}) {
+            Some((index, root)) => index == 0 && vec_compare(&root, expected_root),
+            None => false,
+        }
+    }
+
+    /// Plays back the proof of the leaf at `location` like [MerkleMap::check_merkle_tree],
+    /// to tell whether the leaf hash, the proof or the stored row is wrong.
+    ///
+    /// Returns one [`ClimbLevel`] per level climbed, starting with the leaf. The climb
+    /// stops where a proof hash is missing, a proof with unused hashes doesn't match
+    /// the stored row either.
+    pub fn debug_climb(
+        &self,
+        alg: &str,
+        leaf_hash: &[u8],
+        location: u32,
+        proof: &Option<VecByteBuf>,
+    ) -> Vec<ClimbLevel> {
+        let row_len = self.hashes.len();
+        let row_level = C2PAMerkleTree::to_layout(self.count as usize)
+            .iter()
+            .position(|layer| *layer == row_len);
+
+        let mut levels = Vec::new();
+        let climbed = self.climb_proof(
+            alg,
+            leaf_hash,
+            location,
+            proof,
+            row_len,
+            &mut |index, hash| {
+                let level = levels.len();
+                let stored = (Some(level) == row_level).then(|| self.hashes.get(index as usize));
+                levels.push(ClimbLevel {
+                    level,
+                    computed: hash.to_vec(),
+                    expected: stored.flatten().map(|h| h.to_vec()),
+                    matched: stored.map(|_| self.hash_check(index, hash)),
+                });
+            },
+        );
+
+        if climbed.is_none() {
+            if let Some(ClimbLevel {
+                matched: matched @ Some(_),
+                ..
+            }) = levels.last_mut()
+            {
+                *matched = Some(false);
+            }
+        }
+        levels
+    }
+
+    /// plays back the proof from the leaf at `location` up to the tree row
+    /// with `row_len` nodes, returns the index and hash reached in that row
+    ///
+    /// the proof must hold exactly one hash per level with a sibling on the way
+    /// up, which depends on `location` and `count` (e.g. the last leaf of an odd
+    /// sized tree has none on the first level)
+    ///
+    /// `trace` receives the index and hash of every level climbed, starting with the leaf
+    fn climb_proof(
+        &self,
+        alg: &str,
+        hash: &[u8],
+        location: u32,
+        proof: &Option<VecByteBuf>,
+        row_len: usize,
+        trace: &mut dyn FnMut(u32, &[u8]),
+    ) -> Option<(u32, Vec<u8>)> {
         if location >= self.count {
-            return false;
+            return None;
         }
 
         let mut index = location;
         let mut hash = hash.to_vec();
         let layers = C2PAMerkleTree::to_layout(self.count as usize);
+        if !layers.contains(&row_len) {
+            return None;
+        }
+        let hashes = proof.as_ref().map(|p| p.as_slice()).unwrap_or_default();
 
-        if let Some(hashes) = proof {
-            // playback proof
-            let mut proof_index = 0;
-            for layer in layers {
-                let is_right = index % 2 == 1;
+        // playback proof
+        trace(index, &hash);
+        let mut proof_index = 0;
+        for layer in layers {
+            let is_right = index % 2 == 1;
 
-                if layer == self.hashes.len() {
-                    break;
-                }
+            if layer == row_len {
+                break;
+            }
 
-                if is_right {
-                    if index - 1 < layer as u32 {
-                        // make sure proof structure is valid
-                        if let Some(proof_hash) = hashes.get(proof_index) {
-                            hash = concat_and_hash(alg, proof_hash, Some(&hash));
-                            proof_index += 1;
-                        } else {
-                            return false;
-                        }
-                    }
-                } else if index + 1 < layer as u32 {
+            if is_right {
+                if index - 1 < layer as u32 {
                     // make sure proof structure is valid
-                    if let Some(proof_hash) = hashes.get(proof_index) {
-                        hash = concat_and_hash(alg, &hash, Some(proof_hash));
-                        proof_index += 1;
-                    } else {
-                        return false;
-                    }
-                }
-
-                index /= 2;
-            }
-        } else {
-            //empty proof playback
-            for layer in layers {
-                if layer == self.hashes.len() {
-                    break;
+                    let proof_hash = hashes.get(proof_index)?;
+                    hash = concat_and_hash(alg, proof_hash, Some(&hash));
+                    proof_index += 1;
                 }
-                index /= 2;
+            } else if index + 1 < layer as u32 {
+                // make sure proof structure is valid
+                let proof_hash = hashes.get(proof_index)?;
+                hash = concat_and_hash(alg, &hash, Some(proof_hash));
+                proof_index += 1;
             }
+
+            index /= 2;
+            trace(index, &hash);
         }
 
-        self.hash_check(index, &hash)
+        // a longer proof doesn't belong to this position in the tree
+        if proof_index != hashes.len() {
+            return None;
+        }
+
+        Some((index, hash))
     }
 }
 
@@ -230,6 +323,78 @@ pub struct BmffMerkleMap {
     pub location: u32,
 
     pub hashes: Option<VecByteBuf>,
+
+    /// Number of top level boxes of the fragment as signed, including this box. Boxes
+    /// appended after them are ignored with `verify.tolerate_trailing_boxes`.
+    #[serde(rename = "boxCount", default, skip_serializing_if = "Option::is_none")]
+    pub box_count: Option<u32>,
+}
+
+/// Verification result of a single fragment, see [`BmffHash::verify_stream_segments_report`].
+#[derive(Debug)]
+pub struct FragmentVerification {
+    /// Path of the fragment.
+    pub path: std::path::PathBuf,
+
+    /// Location of the fragment in the Merkle tree, `None` if no `BmffMerkleMap` was found.
+    pub location: Option<u32>,
+
+    /// Local id of the Merkle tree, `None` if no `BmffMerkleMap` was found.
+    pub local_id: Option<u32>,
+
+    /// Outcome of the verification.
+    pub result: crate::Result<()>,
+
+    /// Top level boxes altered since signing, located with [`BmffHash::locate_tampering`]
+    /// for a failed fragment signed with [`FragmentOptions::box_digests`], `None` otherwise.
+    pub tampered_boxes: Option<Vec<TamperedBox>>,
+}
+
+impl FragmentVerification {
+    pub fn is_valid(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Size of a Merkle tree built by [`BmffHash::add_merkle_for_fragmented`].
+///
+/// The proofs are stored in the C2PA box of every fragment, so they are the
+/// per fragment storage overhead of a window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MerkleStats {
+    /// Number of fragments in the tree.
+    pub count: usize,
+
+    /// Number of layers above the fragments up to the stored row, see
+    /// [`FragmentOptions::merkle_row`], the hashes of that row are stored in the manifest.
+    pub depth: usize,
+
+    /// Largest number of proof hashes stored in a fragment.
+    pub max_proof_len: usize,
+
+    /// Size of the proof hashes stored in all fragments.
+    pub total_proof_bytes: usize,
+}
+
+/// Options of [`BmffHash::verify_stream_segments_with_options`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerifyOptions {
+    /// Keep verifying the remaining fragments after a fragment failed.
+    pub continue_on_error: bool,
+
+    /// Number of failed fragments collected before verification stops, only used
+    /// with `continue_on_error`.
+    pub max_errors: usize,
+}
+
+/// Failed fragments found by [`BmffHash::verify_stream_segments_with_options`].
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// Failed fragments in verification order.
+    pub failures: Vec<FragmentVerification>,
+
+    /// Verification stopped at the last failure, the fragments after it weren't verified.
+    pub truncated: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -245,6 +410,190 @@ pub struct SubsetMap {
     pub length: u32,
 }
 
+/// Position of the C2PA box inserted into a fragment.
+///
+/// The box is excluded from hashing by the `/uuid` exclusion wherever it is
+/// placed. Some players are strict about box ordering and may require one
+/// position over the other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum C2paBoxPosition {
+    /// Immediately before the first `moof` box.
+    #[default]
+    BeforeMoof,
+
+    /// Immediately after the `mdat` box.
+    AfterMdat,
+
+    /// At the very start of the fragment.
+    FileStart,
+}
+
+impl C2paBoxPosition {
+    /// Returns the offset at which the C2PA box is inserted into a fragment
+    /// with the given top level boxes.
+    fn offset(&self, box_infos: &[BoxInfoLite]) -> crate::Result<u64> {
+        match self {
+            Self::BeforeMoof => box_infos
+                .iter()
+                .find(|b| b.path == "moof")
+                .map(|b| b.offset)
+                .ok_or(Error::BadParam("expected 1 moof in fragment".to_string())),
+            Self::AfterMdat => box_infos
+                .iter()
+                .find(|b| b.path == "mdat")
+                .map(|b| b.offset + b.size)
+                .ok_or(Error::BadParam("expected 1 mdat in fragment".to_string())),
+            Self::FileStart => Ok(0),
+        }
+    }
+}
+
+// Number of top level boxes of a fragment with the `box_infos` once its C2PA box is inserted,
+// recorded in the box so boxes appended after signing can be told apart
+fn signed_box_count(box_infos: &[BoxInfoLite], has_c2pa_box: bool) -> u32 {
+    box_infos.len() as u32 + u32::from(!has_c2pa_box)
+}
+
+/// Algorithms supported for hashing the content of BMFF fragments.
+pub const CONTENT_HASH_ALGS: [&str; 3] = ["sha256", "sha384", "sha512"];
+
+/// Options used when embedding the C2PA boxes into BMFF fragments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FragmentOptions {
+    /// Where the C2PA box is inserted into each fragment.
+    pub box_position: C2paBoxPosition,
+
+    /// Leave fragments already signed in the output directory untouched, unless
+    /// their Merkle box changes, instead of rewriting every fragment of the window.
+    pub skip_signed_fragments: bool,
+
+    /// Optional per-stream salt mixed into every Merkle leaf and rolling hash input,
+    /// so identical fragments of different streams don't produce identical hashes.
+    /// The salt is stored in the assertion for verification.
+    pub salt: Option<Vec<u8>>,
+
+    /// Optional metadata recorded in the rolling hash box of the fragment, for
+    /// correlating fragments with their stream. Not used during verification.
+    /// The first fragment of a stream carries no rolling hash box and no metadata.
+    pub metadata: Option<FragmentMetadata>,
+
+    /// Optional algorithm of the content hashes, i.e. the init hash, the Merkle leaves
+    /// and the rolling hash, one of [`CONTENT_HASH_ALGS`]. The claim algorithm is used
+    /// if not set. The algorithm is stored in the assertion for verification.
+    pub content_hash_alg: Option<String>,
+
+    /// Additional exclusions of the assertion, e.g. for boxes filled in after signing.
+    /// They are added to the mandatory exclusions and stored in the assertion.
+    pub exclusions: Vec<ExclusionsMap>,
+
+    /// Optional row of the Merkle tree stored in the assertion, counted from the leaves.
+    /// Row 0 stores the hash of every fragment and the fragments carry no proof, every
+    /// row above halves the stored hashes and adds a hash to the proofs. The root is
+    /// stored if not set or if the tree has fewer rows.
+    pub merkle_row: Option<usize>,
+
+    /// Record a digest of every top level box of the signed fragments in the assertion,
+    /// so [`BmffHash::locate_tampering`] can tell which box of a fragment failing
+    /// verification was altered. Grows the assertion by a hash per box and fragment.
+    pub box_digests: bool,
+
+    /// Minimum payload size of the `mdat` of a fragment, fragments with less media data
+    /// are rejected. A fragment with an empty `mdat` is rejected even if not set.
+    pub min_mdat_size: u64,
+}
+
+impl FragmentOptions {
+    /// The Merkle row stored for a tree over `count` fragments, i.e. the length of
+    /// the proofs, see [`FragmentOptions::merkle_row`].
+    pub(crate) fn stored_merkle_row(&self, count: usize) -> usize {
+        let root = (count as f32).log2().ceil() as usize;
+        self.merkle_row.map_or(root, |row| row.min(root))
+    }
+
+    /// The content hash algorithm, `claim_alg` if none is set.
+    pub(crate) fn content_hash_alg<'a>(&'a self, claim_alg: &'a str) -> crate::Result<&'a str> {
+        match self.content_hash_alg.as_deref() {
+            None => Ok(claim_alg),
+            Some(alg) if CONTENT_HASH_ALGS.contains(&alg) => Ok(alg),
+            Some(alg) => Err(Error::BadParam(format!(
+                "unsupported content hash algorithm {alg}"
+            ))),
+        }
+    }
+}
+
+/// Stream a rolling hash signed fragment belongs to, see [`FragmentOptions::metadata`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct FragmentMetadata {
+    /// Name of the stream.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<String>,
+
+    /// Id of the representation within the stream.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rep_id: Option<String>,
+
+    /// Index of the segment within the representation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+}
+
+/// Digests of the top level boxes of a signed fragment, see [`FragmentOptions::box_digests`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FragmentBoxDigests {
+    /// Local id of the Merkle tree of the fragment, `None` for the newest rolling hash
+    /// signed fragment.
+    #[serde(rename = "localId", skip_serializing_if = "Option::is_none")]
+    pub local_id: Option<u32>,
+
+    /// Location of the fragment in the Merkle tree.
+    pub location: u32,
+
+    /// Algorithm of the digests.
+    pub alg: String,
+
+    /// Digests of the top level boxes in file order, excluded boxes are left out.
+    pub boxes: Vec<BoxDigest>,
+}
+
+/// Digest of a top level box of a fragment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BoxDigest {
+    /// Box type, e.g. `moof`.
+    pub path: String,
+
+    /// Hash of the whole box, header included.
+    pub hash: ByteBuf,
+}
+
+/// Top level box of a fragment which doesn't match its digest recorded at signing,
+/// see [`BmffHash::locate_tampering`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TamperedBox {
+    /// Box type, e.g. `mdat`.
+    pub path: String,
+
+    /// Offset of the box in the fragment, `None` if the box was removed.
+    pub offset: Option<u64>,
+}
+
+/// Level of a Merkle proof played back by [`MerkleMap::debug_climb`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClimbLevel {
+    /// Level in the tree, 0 for the leaf.
+    pub level: usize,
+
+    /// Hash computed on this level.
+    pub computed: Vec<u8>,
+
+    /// Hash stored in the MerkleMap on this level, only known for the stored row and
+    /// `None` if the climb reached an index outside of it.
+    pub expected: Option<Vec<u8>>,
+
+    /// Whether `computed` matches the stored hash, only known for the stored row.
+    pub matched: Option<bool>,
+}
+
 /// Helper class to create BmffHash assertion. (These are auto-generated by the SDK.)
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct BmffHash {
@@ -262,9 +611,15 @@ pub struct BmffHash {
     #[serde(skip_serializing_if = "Option::is_none")]
     rolling_hash: Option<RollingHash>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    salt: Option<ByteBuf>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    box_digests: Option<Vec<FragmentBoxDigests>>,
+
     #[serde(skip_serializing)]
     url: Option<UriT>, // deprecated in V2 and not to be used
 
@@ -282,12 +637,101 @@ impl BmffHash {
             hash: None,
             merkle: None,
             rolling_hash: None,
+            salt: None,
             name: Some(name.to_string()),
+            box_digests: None,
             url,
             bmff_version: ASSERTION_CREATION_VERSION,
         }
     }
 
+    /// Returns the BMFF hash assertion binding the fragments of the given manifest
+    /// store JUMBF, e.g. the bytes of a sidecar or of the init segment's C2PA box.
+    /// The manifests are searched as by [`BmffHash::from_reader`].
+    ///
+    /// Returns `None` if no manifest has a Merkle or rolling hash BMFF hash assertion.
+    pub fn from_manifest_bytes(manifest_bytes: &[u8]) -> crate::Result<Option<Self>> {
+        let mut validation_log = StatusTracker::default();
+        let store = crate::store::Store::from_jumbf(manifest_bytes, &mut validation_log)?;
+        if store.provenance_claim().is_none() {
+            return Err(Error::ProvenanceMissing);
+        }
+
+        let reader = crate::Reader::from_store(store, &validation_log)?;
+        Ok(Self::from_reader(&reader))
+    }
+
+    /// Returns the BMFF hash assertion binding the fragments of the manifests read by
+    /// `reader`.
+    ///
+    /// An init segment may carry several manifests, e.g. when it was signed again as
+    /// ingredient of a new manifest. The first BMFF hash with `MerkleMap`s or a
+    /// `RollingHash` is returned, the manifests are searched in this order:
+    /// 1. the active manifest
+    /// 2. the manifests of its ingredients, breadth-first in ingredient order
+    /// 3. all other manifests of the store, ordered by label
+    pub fn from_reader(reader: &crate::Reader) -> Option<Self> {
+        let mut order: Vec<&str> = Vec::new();
+        let mut queue: std::collections::VecDeque<&str> =
+            reader.active_label().into_iter().collect();
+        while let Some(label) = queue.pop_front() {
+            if order.contains(&label) {
+                continue;
+            }
+            order.push(label);
+
+            if let Some(manifest) = reader.get_manifest(label) {
+                queue.extend(
+                    manifest
+                        .ingredients()
+                        .iter()
+                        .filter_map(|ingredient| ingredient.active_manifest()),
+                );
+            }
+        }
+
+        let mut rest: Vec<&str> = reader
+            .manifests()
+            .keys()
+            .map(String::as_str)
+            .filter(|label| !order.contains(label))
+            .collect();
+        rest.sort_unstable();
+        order.extend(rest);
+
+        order
+            .into_iter()
+            .filter_map(|label| reader.get_manifest(label))
+            .filter_map(|manifest| {
+                manifest
+                    .find_assertion::<BmffHash>(labels::BMFF_HASH_2)
+                    .ok()
+            })
+            .find(|hash| hash.merkle().is_some() || hash.rolling_hash().is_some())
+            .map(|mut hash| {
+                // the version is part of the label, not of the assertion data
+                hash.set_bmff_version(2);
+                hash
+            })
+    }
+
+    /// Returns true if an init hash is still the zeroed placeholder inserted while
+    /// signing, i.e. it wasn't updated once the manifest was embedded into the init
+    /// segment (see `BmffHash::update_fragmented_inithash`).
+    pub fn has_placeholder_init_hash(&self) -> bool {
+        let is_placeholder = |hash: &[u8]| hash.iter().all(|b| *b == 0);
+
+        self.merkle
+            .iter()
+            .flatten()
+            .filter_map(|mm| mm.init_hash.as_deref())
+            .any(|hash| is_placeholder(hash))
+            || self
+                .rolling_hash()
+                .and_then(RollingHash::init_hash)
+                .is_some_and(|hash| is_placeholder(hash))
+    }
+
     pub fn exclusions(&self) -> &[ExclusionsMap] {
         self.exclusions.as_ref()
     }
@@ -341,6 +785,18 @@ impl BmffHash {
         self.url.is_some()
     }
 
+    pub fn salt(&self) -> Option<&Vec<u8>> {
+        self.salt.as_deref()
+    }
+
+    pub fn set_salt(&mut self, salt: Vec<u8>) {
+        self.salt = Some(ByteBuf::from(salt));
+    }
+
+    pub fn box_digests(&self) -> Option<&Vec<FragmentBoxDigests>> {
+        self.box_digests.as_ref()
+    }
+
     pub fn set_merkle(&mut self, merkle: Vec<MerkleMap>) {
         self.merkle = Some(merkle);
     }
@@ -352,6 +808,31 @@ impl BmffHash {
         }
     }
 
+    /// Returns the algorithm of every `MerkleMap` by `local_id`, falling back to the
+    /// top-level algorithm (or sha256 if there is none) like the hashing does.
+    ///
+    /// A `RollingHash` has no `local_id` and is reported with a `local_id` of 0.
+    pub fn effective_algs(&self) -> Vec<(u32, String)> {
+        let default_alg = self.alg.clone().unwrap_or_else(|| "sha256".to_string());
+
+        let mut algs: Vec<(u32, String)> = self
+            .merkle
+            .iter()
+            .flatten()
+            .map(|mm| {
+                let alg = mm.alg.clone().unwrap_or_else(|| default_alg.clone());
+                (mm.local_id, alg)
+            })
+            .collect();
+
+        if let Some(rh) = &self.rolling_hash {
+            let alg = rh.alg().map(str::to_owned).unwrap_or(default_alg);
+            algs.push((0, alg));
+        }
+
+        algs
+    }
+
     /// Generate the hash value for the asset using the range from the BmffHash.
     #[cfg(feature = "file_io")]
     pub fn gen_hash(&mut self, asset_path: &std::path::Path) -> crate::error::Result<()> {
@@ -401,71 +882,81 @@ impl BmffHash {
         }
     }
 
+    /// Hashes an init segment minus the BMFF exclusions.
+    ///
+    /// Used to finalize the init hash of both Merkle and RollingHash signed assets,
+    /// so the same init segment and `bmff_version` always produce the same hash.
+    pub fn hash_init_segment(
+        &self,
+        alg: &str,
+        init_stream: &mut dyn CAIRead,
+    ) -> crate::error::Result<Vec<u8>> {
+        init_stream.rewind()?;
+        let exclusions =
+            bmff_to_jumbf_exclusions(init_stream, &self.exclusions, self.bmff_version > 1)?;
+        init_stream.rewind()?;
+
+        hash_stream_by_alg(alg, init_stream, Some(exclusions), true)
+    }
+
     #[cfg(feature = "file_io")]
     pub fn update_fragmented_inithash(
         &mut self,
         asset_path: &std::path::Path,
     ) -> crate::error::Result<()> {
-        if let Some(mm) = &mut self.merkle {
-            let mut init_stream = std::fs::File::open(asset_path)?;
+        let mut init_stream = std::fs::File::open(asset_path)?;
+        self.set_fragmented_inithash(&mut init_stream)
+    }
+
+    /// In memory counterpart of [`BmffHash::update_fragmented_inithash`].
+    pub fn update_fragmented_inithash_memory(
+        &mut self,
+        init_segment: &[u8],
+    ) -> crate::error::Result<()> {
+        self.set_fragmented_inithash(&mut Cursor::new(init_segment))
+    }
+
+    // sets the init hash of all MerkleMaps or the RollingHash
+    fn set_fragmented_inithash(
+        &mut self,
+        init_stream: &mut dyn CAIRead,
+    ) -> crate::error::Result<()> {
+        let default_alg = match &self.alg {
+            Some(a) => a.to_owned(),
+            None => "sha256".to_string(),
+        };
 
+        let curr_alg = if let Some(mm) = &self.merkle {
             // TODO better: ensure all MerkleMap's have the same alg
             // get the first MerkleMap with Some alg
-            let m_alg = mm.iter().find(|m| m.alg.is_some());
-            let curr_alg = match m_alg {
-                Some(m) => match m.alg.clone() {
-                    Some(a) => a,
-                    None => unreachable!("already verified to be Some"),
-                },
-                None => match &self.alg {
-                    Some(a) => a.to_owned(),
-                    None => "sha256".to_string(),
-                },
-            };
+            match mm.iter().find_map(|m| m.alg.clone()) {
+                Some(a) => a,
+                None => default_alg,
+            }
+        } else if let Some(rh) = &self.rolling_hash {
+            match rh.alg() {
+                Some(a) => a.to_owned(),
+                None => default_alg,
+            }
+        } else {
+            return Err(Error::BadParam(
+                "expected MerkleMap or RollingHash object".to_string(),
+            ));
+        };
 
-            // create the initHash only once
-            let exclusions = bmff_to_jumbf_exclusions(
-                &mut init_stream,
-                &self.exclusions,
-                self.bmff_version > 1,
-            )?;
-            init_stream.rewind()?;
-            let hash = hash_stream_by_alg(&curr_alg, &mut init_stream, Some(exclusions), true)?;
+        // create the initHash only once
+        let hash = self.hash_init_segment(&curr_alg, init_stream)?;
 
+        if let Some(mm) = &mut self.merkle {
             // set it on all MerkleMap's
             for mpd_mm in mm.iter_mut() {
                 mpd_mm.init_hash = Some(ByteBuf::from(hash.clone()));
             }
-
-            Ok(())
         } else if let Some(rh) = &mut self.rolling_hash {
-            let mut init_stream = std::fs::File::open(asset_path)?;
-
-            let curr_alg = match rh.alg() {
-                Some(alg) => alg.to_owned(),
-                None => match &self.alg {
-                    Some(a) => a.to_string(),
-                    None => "sha256".to_string(),
-                },
-            };
-
-            // create the initHash only once
-            let exclusions = bmff_to_jumbf_exclusions(
-                &mut init_stream,
-                &self.exclusions,
-                self.bmff_version > 1,
-            )?;
-            init_stream.rewind()?;
-            let hash = hash_stream_by_alg(&curr_alg, &mut init_stream, Some(exclusions), true)?;
-
             rh.set_init_hash(hash);
-
-            Ok(())
-        } else {
-            Err(Error::BadParam(
-                "expected MerkleMap or RollingHash object".to_string(),
-            ))
         }
+
+        Ok(())
     }
 
     pub fn verify_in_memory_hash(
@@ -475,7 +966,61 @@ impl BmffHash {
     ) -> crate::error::Result<()> {
         let mut reader = Cursor::new(data);
 
-        self.verify_stream_hash(&mut reader, alg)
+        self.verify_stream_hash(&mut reader, alg, None)
+    }
+
+    /// Verifies an init segment on its own, without any fragments, against the init
+    /// hash of the `MerkleMap`s or of the `RollingHash`.
+    ///
+    /// The init segment is hashed minus the BMFF exclusions, as done by
+    /// [`BmffHash::hash_init_segment`] when the init hash was set.
+    ///
+    /// # Errors
+    /// * Returns [`Error::BadParam`] if the assertion has no init hash.
+    /// * Returns [`Error::HashMismatch`] if the init hash is still a placeholder or
+    ///   doesn't match the init segment.
+    pub fn verify_init(&self, init_stream: &mut dyn CAIRead) -> crate::Result<()> {
+        let default_alg = self.alg.as_deref().unwrap_or("sha256");
+
+        let init_hashes: Vec<(&str, &[u8])> = if let Some(mm) = &self.merkle {
+            mm.iter()
+                .filter_map(|mm| {
+                    let init_hash: &[u8] = mm.init_hash.as_deref()?;
+                    Some((mm.alg.as_deref().unwrap_or(default_alg), init_hash))
+                })
+                .collect()
+        } else if let Some(rh) = &self.rolling_hash {
+            rh.init_hash()
+                .map(|init_hash| (rh.alg().unwrap_or(default_alg), init_hash.as_slice()))
+                .into_iter()
+                .collect()
+        } else {
+            return Err(Error::BadParam(
+                "expected MerkleMap or RollingHash object".to_string(),
+            ));
+        };
+
+        if init_hashes.is_empty() {
+            return Err(Error::BadParam("no init hash recorded".to_string()));
+        }
+        if self.has_placeholder_init_hash() {
+            return Err(Error::HashMismatch(
+                "BMFF inithash is still a placeholder".to_string(),
+            ));
+        }
+
+        // the Merkle maps of all windows share the init hash, hash once per algorithm
+        let mut hashes: HashMap<&str, Vec<u8>> = HashMap::new();
+        for (alg, init_hash) in init_hashes {
+            if !hashes.contains_key(alg) {
+                hashes.insert(alg, self.hash_init_segment(alg, init_stream)?);
+            }
+            if hashes[alg].as_slice() != init_hash {
+                return Err(Error::HashMismatch("BMFF inithash mismatch".to_string()));
+            }
+        }
+
+        Ok(())
     }
 
     // The BMFFMerklMaps are stored contiguous in the file.  Break this Vec into groups based on
@@ -507,14 +1052,11 @@ impl BmffHash {
     fn split_fragment_boxes(boxes: &[BoxInfoLite]) -> Vec<Vec<BoxInfoLite>> {
         let mut moof_list = Vec::new();
 
-        // start from 1st moof
+        // start from 1st moof, which may also be the very first box
+        // of a fragment emitted without a leading styp
         if let Some(pos) = boxes.iter().position(|b| b.path == "moof") {
             let mut box_list = vec![boxes[pos].clone()];
 
-            if pos == 0 {
-                return moof_list; // this does not contain fragmented content
-            }
-
             for b in boxes[pos + 1..].iter() {
                 if b.path == "moof" {
                     moof_list.push(box_list); // save box list
@@ -534,7 +1076,7 @@ impl BmffHash {
         alg: Option<&str>,
     ) -> crate::error::Result<()> {
         let mut data = std::fs::File::open(asset_path)?;
-        self.verify_stream_hash(&mut data, alg)
+        self.verify_stream_hash(&mut data, alg, None)
     }
 
     /* Verifies BMFF hashes from a single file asset.  The following variants are handled
@@ -543,11 +1085,29 @@ impl BmffHash {
             Timed media (Merkle hashes over track chunks)
             Untimed media (Merkle hashes over iloc locations)
         A single BMFF asset containing all fragments (Merkle hashes over moof ranges).
+
+        If a `validation_log` is given, the result of every Merkle leaf (fragment or chunk)
+        is logged with its location. Mismatches are then only returned as errors if the
+        log stops on the first error.
     */
     pub fn verify_stream_hash(
         &self,
         reader: &mut dyn CAIRead,
         alg: Option<&str>,
+        validation_log: Option<&mut StatusTracker>,
+    ) -> crate::error::Result<()> {
+        self.verify_stream_hash_tracks(reader, alg, false, validation_log)
+    }
+
+    /// Same as [`BmffHash::verify_stream_hash`], for timed media tracks without a
+    /// `MerkleMap` are ignored unless `require_all_tracks` is set, in which case every
+    /// track of the asset must be signed.
+    pub fn verify_stream_hash_tracks(
+        &self,
+        reader: &mut dyn CAIRead,
+        alg: Option<&str>,
+        require_all_tracks: bool,
+        mut validation_log: Option<&mut StatusTracker>,
     ) -> crate::error::Result<()> {
         if self.is_remote_hash() {
             return Err(Error::BadParam(
@@ -588,6 +1148,27 @@ impl BmffHash {
             let first_moof = box_infos.iter().find(|b| b.path == "moof");
             let is_fragmented = first_moof.is_some();
 
+            if is_fragmented {
+                // a fragment of a multi-file asset only holds its own leaves of the
+                // Merkle tree and needs its init segment to be validated
+                if !box_infos.iter().any(|b| b.path == "moov") {
+                    return Err(Error::BadParam(
+                        "BMFF fragment without init segment, use verify_stream_segment".to_owned(),
+                    ));
+                }
+
+                // a single file contains all fragments, so there must be a 1-1 mapping
+                // of moof chunks and Merkle values
+                let moof_count = BmffHash::split_fragment_boxes(&box_infos).len();
+                if mm_vec.iter().any(|mm| {
+                    moof_count != mm.count as usize || bmff_merkle.len() != mm.count as usize
+                }) {
+                    return Err(Error::HashMismatch(
+                        "Incorrect number of fragments hashes".to_owned(),
+                    ));
+                }
+            }
+
             // check initialization segments (must do here in separate loop since MP4 will consume the reader)
             for mm in mm_vec {
                 let alg = match &mm.alg {
@@ -634,48 +1215,30 @@ impl BmffHash {
 
                     let moof_chunks = BmffHash::split_fragment_boxes(&box_infos);
 
-                    // make sure there is a 1-1 mapping of moof chunks and Merkle values
-                    if moof_chunks.len() != mm.count as usize
-                        || bmff_merkle.len() != mm.count as usize
-                    {
-                        return Err(Error::HashMismatch(
-                            "Incorrect number of fragments hashes".to_owned(),
-                        ));
-                    }
-
                     // build Merkle tree for the moof chucks minus the excluded ranges
                     for (index, boxes) in moof_chunks.iter().enumerate() {
-                        // include just the range of this chunk so exclude boxes before and after
-                        let mut curr_exclusions = exclusions.clone();
-
-                        // before box exclusion starts at beginning of file until the start of this chunk
-                        let before_box_start = 0;
-                        let before_box_len = match boxes.first() {
-                            Some(first) => first.offset as usize,
-                            None => 0,
-                        };
-                        let before_box_exclusion = HashRange::new(before_box_start, before_box_len);
-                        curr_exclusions.push(before_box_exclusion);
-
-                        // after box exclusion continues to the end of the file
-                        let after_box_start = match boxes.last() {
-                            Some(last) => last.offset + last.size,
-                            None => 0,
-                        };
-                        let after_box_len = size - after_box_start;
-                        let after_box_exclusion =
-                            HashRange::new(after_box_start as usize, after_box_len as usize);
-                        curr_exclusions.push(after_box_exclusion);
-
-                        // hash the specified range
-                        let hash = hash_stream_by_alg(alg, reader, Some(curr_exclusions), true)?;
+                        let hash = hash_fragment_chunk(alg, reader, &exclusions, boxes, size)?;
+                        let hash = salted_hash(alg, self.salt.as_ref(), hash);
 
                         let bmff_mm = &bmff_merkle[index];
 
                         // check MerkleMap for the hash
-                        if !mm.check_merkle_tree(alg, &hash, bmff_mm.location, &bmff_mm.hashes) {
-                            return Err(Error::HashMismatch("Fragment not valid".to_string()));
-                        }
+                        let result = if mm.check_merkle_tree(
+                            alg,
+                            &hash,
+                            bmff_mm.location,
+                            &bmff_mm.hashes,
+                        ) {
+                            Ok(())
+                        } else {
+                            Err(Error::HashMismatch("Fragment not valid".to_string()))
+                        };
+                        log_merkle_leaf(
+                            validation_log.as_deref_mut(),
+                            self.name().map_or("unnamed", |n| n.as_str()),
+                            bmff_mm.location,
+                            result,
+                        )?;
                     }
                 }
                 return Ok(());
@@ -694,6 +1257,19 @@ impl BmffHash {
                     .map_err(|_e| Error::InvalidAsset("Could not parse BMFF".to_string()))?;
                 let track_count = mp4.tracks().len();
 
+                // unsigned tracks are only an error when all tracks must be signed
+                if require_all_tracks {
+                    if let Some(track_id) = mp4
+                        .tracks()
+                        .keys()
+                        .find(|id| !mm_vec.iter().any(|mm| mm.local_id == **id))
+                    {
+                        return Err(Error::HashMismatch(format!(
+                            "track {track_id} has no MerkleMap"
+                        )));
+                    }
+                }
+
                 for mm in mm_vec {
                     let alg = match &mm.alg {
                         Some(a) => a,
@@ -704,102 +1280,41 @@ impl BmffHash {
 
                     if track_count > 0 {
                         // timed media case
-                        let track = {
-                            // clone so we can borrow later
-                            let tt = mp4.tracks().get(&mm.local_id).ok_or(Error::HashMismatch(
-                                "Merkle location not found".to_owned(),
-                            ))?;
-
-                            Mp4Track {
-                                trak: tt.trak.clone(),
-                                trafs: tt.trafs.clone(),
-                                default_sample_duration: tt.default_sample_duration,
-                            }
-                        };
-
-                        let sample_cnt = track.sample_count();
-                        if sample_cnt == 0 {
-                            return Err(Error::InvalidAsset("No samples".to_string()));
-                        }
-
-                        let track_id = track.track_id();
-
-                        // create sample to chunk mapping
-                        // create the Merkle tree per samples in a chunk
-                        let mut chunk_hash_map: HashMap<u32, Hasher> = HashMap::new();
-                        let stsc = &track.trak.mdia.minf.stbl.stsc;
-                        for sample_id in 1..=sample_cnt {
-                            let stsc_idx = stsc_index(&track, sample_id)?;
-
-                            let stsc_entry = &stsc.entries[stsc_idx];
-
-                            let first_chunk = stsc_entry.first_chunk;
-                            let first_sample = stsc_entry.first_sample;
-                            let samples_per_chunk = stsc_entry.samples_per_chunk;
-
-                            let chunk_id =
-                                first_chunk + (sample_id - first_sample) / samples_per_chunk;
-
-                            // add chunk Hasher if needed
-                            if let Vacant(e) = chunk_hash_map.entry(chunk_id) {
-                                // get hasher for algorithm
-                                let hasher_enum = match alg.as_str() {
-                                    "sha256" => Hasher::SHA256(Sha256::new()),
-                                    "sha384" => Hasher::SHA384(Sha384::new()),
-                                    "sha512" => Hasher::SHA512(Sha512::new()),
-                                    _ => {
-                                        return Err(Error::HashMismatch(
-                                            "no algorithm found".to_string(),
-                                        ))
-                                    }
-                                };
-
-                                e.insert(hasher_enum);
-                            }
-
-                            if let Ok(Some(sample)) = &mp4.read_sample(track_id, sample_id) {
-                                let h = chunk_hash_map.get_mut(&chunk_id).ok_or(
-                                    Error::HashMismatch(
-                                        "Bad Merkle tree sample mapping".to_string(),
-                                    ),
-                                )?;
-                                // add sample data to hash
-                                h.update(&sample.bytes);
-                            } else {
-                                return Err(Error::HashMismatch(
-                                    "Merle location not found".to_owned(),
-                                ));
-                            }
-                        }
-
-                        // finalize leaf hashes
-                        let mut leaf_hashes = Vec::new();
-                        for chunk_bmff_mm in &track_to_bmff_merkle_map[&track_id] {
-                            match chunk_hash_map.remove(&(chunk_bmff_mm.location + 1)) {
-                                Some(h) => {
-                                    let h = Hasher::finalize(h);
-                                    leaf_hashes.push(h);
-                                }
-                                None => {
-                                    return Err(Error::HashMismatch(
-                                        "Could not generate hash".to_owned(),
-                                    ))
-                                }
-                            }
-                        }
-
-                        for chunk_bmff_mm in &track_to_bmff_merkle_map[&track_id] {
-                            let hash = &leaf_hashes[chunk_bmff_mm.location as usize];
+                        let track_id = mm.local_id;
+                        let chunk_hashes = track_chunk_hashes(&mut mp4, track_id, alg)?;
+
+                        let chunk_bmff_mms = track_to_bmff_merkle_map
+                            .get(&track_id)
+                            .ok_or(Error::HashMismatch("Merkle location not found".to_owned()))?;
+
+                        // the leaves are looked up by location, the order of the boxes doesn't matter
+                        for chunk_bmff_mm in chunk_bmff_mms {
+                            let Some(hash) = chunk_hashes.get(chunk_bmff_mm.location as usize)
+                            else {
+                                return Err(Error::HashMismatch(format!(
+                                    "track {track_id} has no chunk at Merkle location {}",
+                                    chunk_bmff_mm.location
+                                )));
+                            };
+                            let hash = salted_hash(alg, self.salt.as_ref(), hash.clone());
 
                             // check MerkleMap for the hash
-                            if !mm.check_merkle_tree(
+                            let result = if mm.check_merkle_tree(
                                 alg,
-                                hash,
+                                &hash,
                                 chunk_bmff_mm.location,
                                 &chunk_bmff_mm.hashes,
                             ) {
-                                return Err(Error::HashMismatch("Fragment not valid".to_string()));
-                            }
+                                Ok(())
+                            } else {
+                                Err(Error::HashMismatch("Fragment not valid".to_string()))
+                            };
+                            log_merkle_leaf(
+                                validation_log.as_deref_mut(),
+                                self.name().map_or("unnamed", |n| n.as_str()),
+                                chunk_bmff_mm.location,
+                                result,
+                            )?;
                         }
                     }
                 }
@@ -822,12 +1337,229 @@ impl BmffHash {
         Ok(())
     }
 
+    /// Computes a single digest over a signed stream, e.g. as an integrity stamp for
+    /// archiving it.
+    ///
+    /// The init segment and the fragments are hashed one after the other and chained
+    /// like a rolling hash: `hash(previous digest + hash(part))`. The digest is therefore
+    /// order-sensitive, the same files in a different order produce a different digest.
+    /// It is independent of the Merkle or rolling hashes of the manifest.
+    #[cfg(feature = "file_io")]
+    pub fn stream_digest(
+        init_stream: &mut dyn CAIRead,
+        fragment_paths: &[std::path::PathBuf],
+        alg: &str,
+    ) -> crate::Result<Vec<u8>> {
+        if !matches!(alg, "sha256" | "sha384" | "sha512") {
+            return Err(Error::UnsupportedType);
+        }
+
+        let mut digest = hash_stream_by_alg(alg, init_stream, None, true)?;
+        for fp in fragment_paths {
+            let mut fragment_stream = std::fs::File::open(fp)?;
+            let hash = hash_stream_by_alg(alg, &mut fragment_stream, None, true)?;
+            digest = concat_and_hash(alg, &digest, Some(&hash));
+        }
+
+        Ok(digest)
+    }
+
+    /// Verifies fragmented BMFF assets spread across multiple files.
+    ///
+    /// If a `validation_log` is given, the result of every fragment is logged with its
+    /// location. Mismatches are then only returned as errors if the log stops on the
+    /// first error.
     #[cfg(feature = "file_io")]
     pub fn verify_stream_segments(
         &self,
         init_stream: &mut dyn CAIRead,
         fragment_paths: &Vec<std::path::PathBuf>,
         alg: Option<&str>,
+        mut validation_log: Option<&mut StatusTracker>,
+    ) -> crate::Result<()> {
+        self.verify_each_segment(init_stream, fragment_paths, alg, &mut |verification| {
+            // fragments that couldn't be read or have no MerkleMap have no leaf to log
+            let Some(location) = verification.location else {
+                return verification.result.map(|_| ControlFlow::Continue(()));
+            };
+
+            log_merkle_leaf(
+                validation_log.as_deref_mut(),
+                &verification.path.to_string_lossy(),
+                location,
+                verification.result,
+            )?;
+            Ok(ControlFlow::Continue(()))
+        })
+    }
+
+    /// Same as [`BmffHash::verify_stream_segments`] but validates every fragment
+    /// instead of stopping at the first failure.
+    ///
+    /// Returns one [`FragmentVerification`] per `BmffMerkleMap` found in the fragments,
+    /// or a single entry without location for fragments that couldn't be read or
+    /// have no `BmffMerkleMap`. Problems with the assertion itself are still returned
+    /// as `Err`.
+    #[cfg(feature = "file_io")]
+    pub fn verify_stream_segments_report(
+        &self,
+        init_stream: &mut dyn CAIRead,
+        fragment_paths: &Vec<std::path::PathBuf>,
+        alg: Option<&str>,
+    ) -> crate::Result<Vec<FragmentVerification>> {
+        let mut report = Vec::with_capacity(fragment_paths.len());
+        self.verify_each_segment(init_stream, fragment_paths, alg, &mut |verification| {
+            report.push(verification);
+            Ok(ControlFlow::Continue(()))
+        })?;
+
+        Ok(report)
+    }
+
+    /// Same as [`BmffHash::verify_stream_segments`], the `options` decide whether
+    /// verification goes on after a failed fragment.
+    ///
+    /// With [`VerifyOptions::continue_on_error`] set, up to
+    /// [`VerifyOptions::max_errors`] failed fragments are collected while all fragments
+    /// are verified. One more failure stops the verification, as does the first failure
+    /// without `continue_on_error`. The failures collected so far are returned along
+    /// with the one that stopped the verification, [`VerifyReport::truncated`] is set.
+    #[cfg(feature = "file_io")]
+    pub fn verify_stream_segments_with_options(
+        &self,
+        init_stream: &mut dyn CAIRead,
+        fragment_paths: &Vec<std::path::PathBuf>,
+        alg: Option<&str>,
+        options: &VerifyOptions,
+    ) -> crate::Result<VerifyReport> {
+        let max_errors = if options.continue_on_error {
+            options.max_errors
+        } else {
+            0
+        };
+
+        let mut report = VerifyReport::default();
+        self.verify_each_segment(init_stream, fragment_paths, alg, &mut |verification| {
+            if verification.is_valid() {
+                return Ok(ControlFlow::Continue(()));
+            }
+
+            report.truncated = report.failures.len() >= max_errors;
+            report.failures.push(verification);
+            Ok(if report.truncated {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            })
+        })?;
+
+        Ok(report)
+    }
+
+    /// Verifies the fragments of a single Merkle tree window, e.g. the newest window of
+    /// a live stream signed with [`Builder::sign_live_bmff`](crate::Builder::sign_live_bmff).
+    ///
+    /// Windows group the signed fragments in chunks of `window_size`, the window is
+    /// selected by the position of its first fragment in the signed stream, starting at 0.
+    /// `fragment_paths` are the fragments of that window in signing order, the last
+    /// window of a live stream may be incomplete. A `window_size` of 0 denotes a
+    /// single tree spanning the whole stream.
+    #[cfg(feature = "file_io")]
+    pub fn verify_window(
+        &self,
+        init_stream: &mut dyn CAIRead,
+        fragment_paths: &[std::path::PathBuf],
+        window_start_index: usize,
+        window_size: usize,
+        alg: Option<&str>,
+    ) -> crate::Result<()> {
+        let local_id = match window_size {
+            0 if window_start_index == 0 => 1,
+            0 => {
+                return Err(Error::BadParam(
+                    "a single tree window starts at 0".to_string(),
+                ))
+            }
+            _ if window_start_index % window_size != 0 => {
+                return Err(Error::BadParam(format!(
+                    "{window_start_index} is not the start of a window of size {window_size}"
+                )))
+            }
+            _ => window_start_index / window_size + 1,
+        };
+        if fragment_paths.is_empty() || (window_size > 0 && fragment_paths.len() > window_size) {
+            return Err(Error::BadParam(format!(
+                "expected 1 to {window_size} fragments, got {}",
+                fragment_paths.len()
+            )));
+        }
+
+        let curr_alg = match &self.alg {
+            Some(a) => a.clone(),
+            None => alg.unwrap_or("sha256").to_owned(),
+        };
+
+        // handle file level hashing
+        if self.hash().is_some() {
+            return Err(Error::HashMismatch(
+                "Hash value should not be present for a fragmented BMFF asset".to_string(),
+            ));
+        }
+
+        let Some(mm_vec) = self.merkle() else {
+            return Err(Error::HashMismatch(
+                "Merkle value must be present for a fragmented BMFF asset".to_string(),
+            ));
+        };
+
+        // inithash cache to prevent duplicate work.
+        let mut init_hashes = std::collections::HashSet::new();
+
+        for (location, fp) in fragment_paths.iter().enumerate() {
+            let mut fragment_stream = std::fs::File::open(fp)?;
+            let c2pa_boxes = read_bmff_fragment_c2pa_boxes(&mut fragment_stream)?;
+
+            // the fragment must be signed at this position of the window
+            let Some(bmff_mm) = c2pa_boxes
+                .bmff_merkle
+                .iter()
+                .find(|bmff_mm| bmff_mm.local_id as usize == local_id)
+            else {
+                return Err(Error::HashMismatch(format!(
+                    "{} is not part of window {local_id}",
+                    fp.display()
+                )));
+            };
+            if bmff_mm.location as usize != location {
+                return Err(Error::HashMismatch(format!(
+                    "{} is at location {} of window {local_id}, expected {location}",
+                    fp.display(),
+                    bmff_mm.location
+                )));
+            }
+
+            self.verify_merkle_fragment(
+                mm_vec,
+                bmff_mm,
+                &curr_alg,
+                init_stream,
+                &mut fragment_stream,
+                &mut init_hashes,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // validates the fragments one at a time, handing every result to `on_result`
+    // which may stop the verification by breaking or returning an error
+    #[cfg(feature = "file_io")]
+    fn verify_each_segment(
+        &self,
+        init_stream: &mut dyn CAIRead,
+        fragment_paths: &Vec<std::path::PathBuf>,
+        alg: Option<&str>,
+        on_result: &mut dyn FnMut(FragmentVerification) -> crate::Result<ControlFlow<()>>,
     ) -> crate::Result<()> {
         let curr_alg = match &self.alg {
             Some(a) => a.clone(),
@@ -844,104 +1576,247 @@ impl BmffHash {
             ));
         }
 
-        // Merkle hashed BMFF
-        if let Some(mm_vec) = self.merkle() {
-            // inithash cache to prevent duplicate work.
-            let mut init_hashes = std::collections::HashSet::new();
+        let Some(mm_vec) = self.merkle() else {
+            return Err(Error::HashMismatch(
+                "Merkle value must be present for a fragmented BMFF asset".to_string(),
+            ));
+        };
 
-            for fp in fragment_paths {
-                let mut fragment_stream = std::fs::File::open(fp)?;
+        // inithash cache to prevent duplicate work.
+        let mut init_hashes = std::collections::HashSet::new();
 
-                // get merkle boxes from segment
-                let c2pa_boxes = read_bmff_c2pa_boxes(&mut fragment_stream)?;
-                let bmff_merkle = c2pa_boxes.bmff_merkle;
+        for fp in fragment_paths {
+            let failed = |err| FragmentVerification {
+                path: fp.to_path_buf(),
+                location: None,
+                local_id: None,
+                result: Err(err),
+                tampered_boxes: None,
+            };
 
-                if bmff_merkle.is_empty() {
-                    return Err(Error::HashMismatch("Fragment had no MerkleMap".to_string()));
+            // get merkle boxes from segment
+            let read = std::fs::File::open(fp)
+                .map_err(Error::from)
+                .and_then(|mut f| {
+                    read_bmff_fragment_c2pa_boxes(&mut f).map(|b| (f, b.bmff_merkle))
+                });
+            let (mut fragment_stream, bmff_merkle) = match read {
+                Ok(read) => read,
+                Err(err) => {
+                    if on_result(failed(err))?.is_break() {
+                        return Ok(());
+                    }
+                    continue;
                 }
+            };
 
-                for bmff_mm in bmff_merkle {
-                    // find matching MerkleMap for this uniqueId & localId
-                    if let Some(mm) = mm_vec.iter().find(|mm| {
-                        mm.unique_id == bmff_mm.unique_id && mm.local_id == bmff_mm.local_id
-                    }) {
-                        let alg = match &mm.alg {
-                            Some(a) => a,
-                            None => &curr_alg,
-                        };
-
-                        // check the inithash (for fragmented MP4 with multiple files this is the hash of the init_segment minus any exclusions)
-                        if let Some(init_hash) = &mm.init_hash {
-                            let bmff_exclusions = &self.exclusions;
-
-                            let init_hash_str = extfmt::Hexlify(init_hash).to_string();
-                            if !init_hashes.contains(&init_hash_str) {
-                                // convert BMFF exclusion map to flat exclusion list
-                                init_stream.rewind()?;
-                                let exclusions = bmff_to_jumbf_exclusions(
-                                    init_stream,
-                                    bmff_exclusions,
-                                    self.bmff_version > 1,
-                                )?;
-
-                                if !verify_stream_by_alg(
-                                    alg,
-                                    init_hash,
-                                    init_stream,
-                                    Some(exclusions),
-                                    true,
-                                ) {
-                                    return Err(Error::HashMismatch(
-                                        "BMFF inithash mismatch".to_string(),
-                                    ));
-                                }
-
-                                init_hashes.insert(init_hash_str);
-                            }
-
-                            // check the segments
-                            fragment_stream.rewind()?;
-                            let fragment_exclusions = bmff_to_jumbf_exclusions(
-                                &mut fragment_stream,
-                                bmff_exclusions,
-                                self.bmff_version > 1,
-                            )?;
+            if bmff_merkle.is_empty() {
+                let missing = failed(Error::HashMismatch("Fragment had no MerkleMap".to_string()));
+                if on_result(missing)?.is_break() {
+                    return Ok(());
+                }
+                continue;
+            }
 
-                            // hash the entire fragment minus exclusions
-                            let hash = hash_stream_by_alg(
-                                alg,
-                                &mut fragment_stream,
-                                Some(fragment_exclusions),
-                                true,
-                            )?;
+            for bmff_mm in bmff_merkle {
+                let result = self.verify_merkle_fragment(
+                    mm_vec,
+                    &bmff_mm,
+                    &curr_alg,
+                    init_stream,
+                    &mut fragment_stream,
+                    &mut init_hashes,
+                );
 
-                            // check MerkleMap for the hash
-                            if !mm.check_merkle_tree(alg, &hash, bmff_mm.location, &bmff_mm.hashes)
-                            {
-                                return Err(Error::HashMismatch("Fragment not valid".to_string()));
-                            }
-                        }
-                    } else {
-                        return Err(Error::HashMismatch("Fragment had no MerkleMap".to_string()));
+                // narrow a failure down to the altered boxes, if their digests were recorded
+                let tampered_boxes = match result {
+                    Err(_) if self.box_digests.is_some() => {
+                        self.locate_tampering(&mut fragment_stream).ok()
                     }
+                    _ => None,
+                };
+
+                let verification = FragmentVerification {
+                    path: fp.to_path_buf(),
+                    location: Some(bmff_mm.location),
+                    local_id: Some(bmff_mm.local_id),
+                    result,
+                    tampered_boxes,
+                };
+                if on_result(verification)?.is_break() {
+                    return Ok(());
                 }
             }
-        } else {
+        }
+
+        Ok(())
+    }
+
+    /// validates a single fragment against the matching MerkleMap
+    ///
+    /// successfully verified init hashes are cached in `init_hashes`
+    #[cfg(feature = "file_io")]
+    fn verify_merkle_fragment(
+        &self,
+        mm_vec: &[MerkleMap],
+        bmff_mm: &BmffMerkleMap,
+        curr_alg: &str,
+        init_stream: &mut dyn CAIRead,
+        fragment_stream: &mut dyn CAIRead,
+        init_hashes: &mut std::collections::HashSet<String>,
+    ) -> crate::Result<()> {
+        let mut signed_fragment = without_trailing_boxes(fragment_stream)?;
+        let fragment_stream: &mut dyn CAIRead = match signed_fragment.as_mut() {
+            Some(signed) => signed,
+            None => fragment_stream,
+        };
+
+        // find matching MerkleMap for this uniqueId & localId
+        let Some(mm) = mm_vec
+            .iter()
+            .find(|mm| mm.unique_id == bmff_mm.unique_id && mm.local_id == bmff_mm.local_id)
+        else {
+            return Err(Error::HashMismatch("Fragment had no MerkleMap".to_string()));
+        };
+
+        let alg = match &mm.alg {
+            Some(a) => a,
+            None => curr_alg,
+        };
+
+        // check the inithash (for fragmented MP4 with multiple files this is the hash of the init_segment minus any exclusions)
+        if let Some(init_hash) = &mm.init_hash {
+            let bmff_exclusions = &self.exclusions;
+
+            let init_hash_str = extfmt::Hexlify(init_hash).to_string();
+            if !init_hashes.contains(&init_hash_str) {
+                // convert BMFF exclusion map to flat exclusion list
+                init_stream.rewind()?;
+                let exclusions =
+                    bmff_to_jumbf_exclusions(init_stream, bmff_exclusions, self.bmff_version > 1)?;
+
+                if !verify_stream_by_alg(alg, init_hash, init_stream, Some(exclusions), true) {
+                    return Err(Error::HashMismatch("BMFF inithash mismatch".to_string()));
+                }
+
+                init_hashes.insert(init_hash_str);
+            }
+
+            // check the segments
+            fragment_stream.rewind()?;
+            let fragment_exclusions =
+                bmff_to_jumbf_exclusions(fragment_stream, bmff_exclusions, self.bmff_version > 1)?;
+
+            // hash the entire fragment minus exclusions
+            let hash = hash_stream_by_alg(alg, fragment_stream, Some(fragment_exclusions), true)?;
+            let hash = salted_hash(alg, self.salt.as_ref(), hash);
+
+            // check MerkleMap for the hash
+            if !mm.check_merkle_tree(alg, &hash, bmff_mm.location, &bmff_mm.hashes) {
+                return Err(Error::HashMismatch("Fragment not valid".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verifies a Merkle signed fragment against externally published roots instead of
+    /// the hashes stored in the manifest.
+    ///
+    /// `roots` lists the published Merkle root of every window in signing order, a
+    /// fragment of the window with local id `n` is checked against `roots[n - 1]`.
+    /// The manifest only provides the layout of the window (its fragment count), the
+    /// exclusions and the salt, its root hashes and init hash are not used.
+    pub fn verify_fragment_with_roots(
+        &self,
+        fragment_stream: &mut dyn CAIRead,
+        roots: &[Vec<u8>],
+        alg: Option<&str>,
+    ) -> crate::Result<()> {
+        let mut signed_fragment = without_trailing_boxes(fragment_stream)?;
+        let fragment_stream: &mut dyn CAIRead = match signed_fragment.as_mut() {
+            Some(signed) => signed,
+            None => fragment_stream,
+        };
+
+        let curr_alg = match &self.alg {
+            Some(a) => a.clone(),
+            None => alg.unwrap_or("sha256").to_owned(),
+        };
+
+        let Some(mm_vec) = self.merkle() else {
             return Err(Error::HashMismatch(
                 "Merkle value must be present for a fragmented BMFF asset".to_string(),
             ));
+        };
+
+        let c2pa_boxes = read_bmff_fragment_c2pa_boxes(fragment_stream)?;
+        if c2pa_boxes.bmff_merkle.is_empty() {
+            return Err(Error::HashMismatch("Fragment had no MerkleMap".to_string()));
+        }
+
+        for bmff_mm in &c2pa_boxes.bmff_merkle {
+            let Some(root) = (bmff_mm.local_id as usize)
+                .checked_sub(1)
+                .and_then(|window| roots.get(window))
+            else {
+                return Err(Error::HashMismatch(format!(
+                    "no published root for window {}",
+                    bmff_mm.local_id
+                )));
+            };
+
+            // the MerkleMap only provides the layout of the window
+            let Some(mm) = mm_vec
+                .iter()
+                .find(|mm| mm.unique_id == bmff_mm.unique_id && mm.local_id == bmff_mm.local_id)
+            else {
+                return Err(Error::HashMismatch("Fragment had no MerkleMap".to_string()));
+            };
+            let alg = mm.alg.as_deref().unwrap_or(&curr_alg);
+
+            // hash the entire fragment minus exclusions
+            fragment_stream.rewind()?;
+            let fragment_exclusions =
+                bmff_to_jumbf_exclusions(fragment_stream, &self.exclusions, self.bmff_version > 1)?;
+            let hash = hash_stream_by_alg(alg, fragment_stream, Some(fragment_exclusions), true)?;
+            let hash = salted_hash(alg, self.salt.as_ref(), hash);
+
+            if !mm.verify_proof_to_root(alg, &hash, bmff_mm.location, &bmff_mm.hashes, root) {
+                return Err(Error::HashMismatch(
+                    "Fragment does not match the published root".to_string(),
+                ));
+            }
         }
 
         Ok(())
     }
 
-    // Used to verify fragmented BMFF assets spread across multiple file.
+    /// Verifies a single fragment of a fragmented BMFF asset spread across multiple files.
+    ///
+    /// The fragment doesn't need to be in memory, any `Read + Seek` works, e.g. a reader
+    /// fetching HTTP ranges on demand. The fragment is accessed as follows:
+    /// * its length is queried with `SeekFrom::End(0)`
+    /// * the box headers are read front to back, seeking over the box payloads
+    /// * the `uuid` boxes holding the `BmffMerkleMap` are read in full
+    /// * the fragment is hashed in a single pass from start to end
+    ///
+    /// Apart from the box headers every byte is read once, so the number of range requests
+    /// depends on the number of boxes but not on the size of the fragment. With
+    /// `verify.tolerate_trailing_boxes` enabled, fragments with trailing boxes are buffered
+    /// in memory.
     pub fn verify_stream_segment(
         &self,
         init_stream: &mut dyn CAIRead,
         fragment_stream: &mut dyn CAIRead,
         alg: Option<&str>,
     ) -> crate::Result<()> {
+        let mut signed_fragment = without_trailing_boxes(fragment_stream)?;
+        let fragment_stream: &mut dyn CAIRead = match signed_fragment.as_mut() {
+            Some(signed) => signed,
+            None => fragment_stream,
+        };
+
         let curr_alg = match &self.alg {
             Some(a) => a.clone(),
             None => match alg {
@@ -958,15 +1833,15 @@ impl BmffHash {
         }
 
         if self.merkle().is_some() && self.rolling_hash().is_some() {
-            return Err(Error::HashMismatch(
-                "A BMFF asset should not have both MerkleMap and RollingHash".to_string(),
+            return Err(Error::InvalidAsset(
+                "conflicting BMFF hash modes: both MerkleMap and RollingHash present".to_string(),
             ));
         }
 
         // Merkle hashed BMFF
         if let Some(mm_vec) = self.merkle() {
             // get merkle boxes from segment
-            let c2pa_boxes = read_bmff_c2pa_boxes(fragment_stream)?;
+            let c2pa_boxes = read_bmff_fragment_c2pa_boxes(fragment_stream)?;
             let bmff_merkle = c2pa_boxes.bmff_merkle;
 
             if bmff_merkle.is_empty() {
@@ -1019,6 +1894,7 @@ impl BmffHash {
                             Some(fragment_exclusions),
                             true,
                         )?;
+                        let hash = salted_hash(alg, self.salt.as_ref(), hash);
 
                         // check MerkleMap for the hash
                         if !mm.check_merkle_tree(alg, &hash, bmff_mm.location, &bmff_mm.hashes) {
@@ -1031,50 +1907,38 @@ impl BmffHash {
             }
         } else if let Some(rh) = self.rolling_hash() {
             // validate init hash
-            self.verify_stream_hash(init_stream, Some(&curr_alg))?;
-
-            // validate previous hash with fragment anchor point
-            if let Some(prev_hash) = rh.previous_hash() {
-                let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
-
-                // ensure there aren't more than one uuid box
-                if c2pa_boxes.rolling_hashes.len() > 1 || c2pa_boxes.bmff_merkle_box_infos.len() > 1
-                {
-                    return Err(Error::HashMismatch(
-                        "BMFF Fragments shouldn't have more than 1 BmffMerkleMap".to_string(),
-                    ));
-                }
-
-                if let Some(anchor_point) = &c2pa_boxes.rolling_hashes[0].anchor_point {
-                    if *prev_hash != **anchor_point {
+            self.verify_stream_hash(init_stream, Some(&curr_alg), None)?;
+
+            // validate previous hash with fragment anchor point, only the first
+            // fragment has no previous hash and carries no anchor box
+            let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
+            match (rh.previous_hash(), fragment_anchor_point(&c2pa_boxes)?) {
+                (Some(prev_hash), Some(anchor_point)) => {
+                    if prev_hash != anchor_point {
                         return Err(Error::HashMismatch(
                             "Previous Hash does not match Fragment Anchor Point".to_string(),
                         ));
                     }
-                } else {
+                }
+                (Some(_), None) => {
                     return Err(Error::HashMismatch("Missing Anchor Point".to_string()));
                 }
+                (None, Some(_)) => {
+                    return Err(Error::HashMismatch(
+                        "First Fragment must not carry an Anchor Point".to_string(),
+                    ));
+                }
+                (None, None) => (),
             }
 
             // validate rolling hash
             if let Some(roll_hash) = rh.rolling_hash() {
-                let exclusions = bmff_to_jumbf_exclusions(fragment_stream, &self.exclusions, true)?;
-
-                let frag_hash =
-                    hash_stream_by_alg(&curr_alg, fragment_stream, Some(exclusions), true)?;
-
-                let (left, right) = if let Some(prev_hash) = rh.previous_hash() {
-                    (prev_hash, Some(frag_hash.as_slice()))
-                } else {
-                    (&frag_hash, None)
-                };
-                let ref_hash = concat_and_hash(&curr_alg, left, right);
-
-                if ref_hash != *roll_hash {
-                    return Err(Error::HashMismatch(
-                        "Fragment Hash does not match Rolling Hash".to_string(),
-                    ));
-                }
+                self.check_rolling_hash(
+                    &curr_alg,
+                    fragment_stream,
+                    rh.previous_hash().map(Vec::as_slice),
+                    roll_hash,
+                )?;
             } else {
                 return Err(Error::HashMismatch(
                     "Asset File has no Rolling Hash".to_string(),
@@ -1089,6 +1953,46 @@ impl BmffHash {
         Ok(())
     }
 
+    /// Same as [`BmffHash::verify_stream_segment`] for the assertions of older producers
+    /// which don't record the algorithm of the content hashes.
+    ///
+    /// Without an algorithm in the assertion and its hashes, the algorithms of
+    /// [`CONTENT_HASH_ALGS`] are tried in order instead of assuming sha256, the one the
+    /// fragment verified with is returned. A recorded algorithm is used as is and `None`
+    /// is returned, so a genuine mismatch isn't masked by another algorithm.
+    pub fn verify_stream_segment_any_alg(
+        &self,
+        init_stream: &mut dyn CAIRead,
+        fragment_stream: &mut dyn CAIRead,
+    ) -> crate::Result<Option<String>> {
+        let recorded = self.alg.is_some()
+            || self.merkle.iter().flatten().any(|mm| mm.alg.is_some())
+            || self
+                .rolling_hash
+                .as_ref()
+                .is_some_and(|rh| rh.alg().is_some());
+        if recorded {
+            return self
+                .verify_stream_segment(init_stream, fragment_stream, None)
+                .map(|_| None);
+        }
+
+        // the error of sha256 is the one of the verification without fallback
+        let mut first_err = None;
+        for alg in CONTENT_HASH_ALGS {
+            init_stream.rewind()?;
+            fragment_stream.rewind()?;
+            match self.verify_stream_segment(init_stream, fragment_stream, Some(alg)) {
+                Ok(()) => return Ok(Some(alg.to_owned())),
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+
+        Err(first_err.unwrap_or_else(|| Error::HashMismatch("no hash algorithm".to_string())))
+    }
+
     pub fn verify_fragment(
         &self,
         init_stream: &mut dyn CAIRead,
@@ -1096,8 +2000,14 @@ impl BmffHash {
         alg: Option<&str>,
         previous_hash: &[u8],
     ) -> crate::Result<()> {
+        let mut signed_fragment = without_trailing_boxes(fragment_stream)?;
+        let fragment_stream: &mut dyn CAIRead = match signed_fragment.as_mut() {
+            Some(signed) => signed,
+            None => fragment_stream,
+        };
+
         // validate init hash
-        self.verify_stream_hash(init_stream, alg)?;
+        self.verify_stream_hash(init_stream, alg, None)?;
 
         if let Some(rh) = self.rolling_hash() {
             let curr_alg = match &self.alg {
@@ -1111,26 +2021,17 @@ impl BmffHash {
             if let Some(roll_hash) = rh.rolling_hash() {
                 let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
 
-                // ensure there aren't more than one uuid box
-                if c2pa_boxes.rolling_hashes.len() > 1 || c2pa_boxes.bmff_merkle_box_infos.len() > 1
-                {
-                    return Err(Error::HashMismatch(
-                        "BMFF Fragments shouldn't have more than 1 BmffMerkleMap".to_string(),
-                    ));
+                // a fragment following `previous_hash` carries an anchor box
+                if fragment_anchor_point(&c2pa_boxes)?.is_none() {
+                    return Err(Error::HashMismatch("Missing Anchor Point".to_string()));
                 }
 
-                let exclusions = bmff_to_jumbf_exclusions(fragment_stream, &self.exclusions, true)?;
-
-                let frag_hash =
-                    hash_stream_by_alg(&curr_alg, fragment_stream, Some(exclusions), true)?;
-
-                let ref_hash = concat_and_hash(&curr_alg, previous_hash, Some(&frag_hash));
-
-                if ref_hash != *roll_hash {
-                    return Err(Error::HashMismatch(
-                        "Fragment Hash does not match Rolling Hash".to_string(),
-                    ));
-                }
+                self.check_rolling_hash(
+                    &curr_alg,
+                    fragment_stream,
+                    Some(previous_hash),
+                    roll_hash,
+                )?;
             }
         } else {
             return Err(Error::HashMismatch("Missing RollingHash".to_string()));
@@ -1139,43 +2040,202 @@ impl BmffHash {
         Ok(())
     }
 
-    pub fn verify_fragment_memory(
+    /// Verifies only the last link of a rolling hash signed stream.
+    ///
+    /// `fragment_stream` hashed onto the trusted `previous_hash` must result in the
+    /// rolling hash of the manifest. Unlike [`RollingHashVerifier`] no earlier
+    /// fragment is hashed and the init segment isn't checked, a fragment anchored to
+    /// another hash is rejected before it is hashed.
+    pub fn verify_tip(
         &self,
         fragment_stream: &mut dyn CAIRead,
-        alg: Option<&str>,
-        rolling_hash: &[u8],
-        anchor_point: &Option<Vec<u8>>,
-    ) -> crate::Result<Vec<u8>> {
-        let curr_alg = match alg {
+        previous_hash: &[u8],
+    ) -> crate::Result<()> {
+        let mut signed_fragment = without_trailing_boxes(fragment_stream)?;
+        let fragment_stream: &mut dyn CAIRead = match signed_fragment.as_mut() {
+            Some(signed) => signed,
+            None => fragment_stream,
+        };
+
+        let rh = self
+            .rolling_hash()
+            .ok_or(Error::HashMismatch("Missing RollingHash".to_string()))?;
+        let roll_hash = rh.rolling_hash().ok_or(Error::HashMismatch(
+            "Asset File has no Rolling Hash".to_string(),
+        ))?;
+        let curr_alg = match rh.alg().or(self.alg.as_deref()) {
             Some(a) => a.to_owned(),
             None => "sha256".to_string(),
         };
 
         let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
+        match fragment_anchor_point(&c2pa_boxes)? {
+            Some(anchor_point) if anchor_point.as_slice() == previous_hash => {}
+            Some(_) => {
+                return Err(Error::HashMismatch(
+                    "Anchor Point does not match the previous hash".to_string(),
+                ))
+            }
+            None => return Err(Error::HashMismatch("Missing Anchor Point".to_string())),
+        }
 
-        // ensure there aren't more than one uuid box
-        if c2pa_boxes.rolling_hashes.len() > 1 || c2pa_boxes.bmff_merkle_box_infos.len() > 1 {
-            return Err(Error::HashMismatch(
-                "BMFF Fragments shouldn't have more than 1 BmffMerkleMap".to_string(),
-            ));
+        self.check_rolling_hash(&curr_alg, fragment_stream, Some(previous_hash), roll_hash)
+    }
+
+    /// Locates the top level boxes of a signed fragment altered since signing, e.g. to
+    /// diagnose a fragment failing verification.
+    ///
+    /// The boxes are compared in file order against the digests recorded with
+    /// [`FragmentOptions::box_digests`], a box added or removed also reports every
+    /// following box. Returns an empty list for an unaltered fragment and fails if no
+    /// digests were recorded for the fragment.
+    pub fn locate_tampering(
+        &self,
+        fragment_stream: &mut dyn CAIRead,
+    ) -> crate::Result<Vec<TamperedBox>> {
+        let (box_infos, bmff_mm) = fragment_boxes(fragment_stream)?;
+        let not_recorded =
+            || Error::BadParam("no box digests recorded for the fragment".to_string());
+        let (local_id, location) = match &bmff_mm {
+            Some(mm) => (Some(mm.local_id), mm.location),
+            None => {
+                // only the newest rolling hash signed fragment has digests, it is
+                // anchored to the previous hash
+                let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
+                if fragment_anchor_point(&c2pa_boxes)? != self.previous_hash() {
+                    return Err(not_recorded());
+                }
+                (None, 0)
+            }
+        };
+        let recorded = self
+            .box_digests
+            .iter()
+            .flatten()
+            .find(|entry| entry.local_id == local_id && entry.location == location)
+            .ok_or_else(not_recorded)?;
+
+        let current = self.digest_boxes(&recorded.alg, box_infos, fragment_stream)?;
+        let mut tampered = Vec::new();
+        for i in 0..recorded.boxes.len().max(current.len()) {
+            match (recorded.boxes.get(i), current.get(i)) {
+                (Some(signed), Some((offset, boxed))) if signed != boxed => {
+                    tampered.push(TamperedBox {
+                        path: boxed.path.clone(),
+                        offset: Some(*offset),
+                    })
+                }
+                (None, Some((offset, boxed))) => tampered.push(TamperedBox {
+                    path: boxed.path.clone(),
+                    offset: Some(*offset),
+                }),
+                (Some(signed), None) => tampered.push(TamperedBox {
+                    path: signed.path.clone(),
+                    offset: None,
+                }),
+                _ => (),
+            }
+        }
+
+        Ok(tampered)
+    }
+
+    // Checks that `fragment_stream` hashed onto `previous_hash` results in `rolling_hash`.
+    //
+    // Like in `verify_fragment_memory`, the fragment is hashed with the exclusions embedded
+    // in its rolling hash box as long as the manifest's cover them, see `fragment_exclusions`.
+    // On a mismatch the error tells whether the embedded exclusions differ from the
+    // manifest's.
+    fn check_rolling_hash(
+        &self,
+        alg: &str,
+        fragment_stream: &mut dyn CAIRead,
+        previous_hash: Option<&[u8]>,
+        rolling_hash: &[u8],
+    ) -> crate::Result<()> {
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
+        let bmff_exclusions = fragment_exclusions(&c2pa_boxes, &self.exclusions)?;
+
+        let exclusions =
+            bmff_to_jumbf_exclusions(fragment_stream, bmff_exclusions, self.bmff_version > 1)?;
+        let frag_hash = hash_stream_by_alg(alg, fragment_stream, Some(exclusions), true)?;
+        let frag_hash = salted_hash(alg, self.salt.as_ref(), frag_hash);
+        let hash = match previous_hash {
+            Some(prev_hash) => concat_and_hash(alg, prev_hash, Some(&frag_hash)),
+            None => concat_and_hash(alg, &frag_hash, None),
+        };
+
+        if hash == rolling_hash {
+            Ok(())
+        } else if bmff_exclusions != self.exclusions.as_slice() {
+            Err(Error::HashMismatch(
+                "Fragment Hash does not match Rolling Hash, the exclusions embedded in the fragment differ from the manifest's".to_string(),
+            ))
+        } else {
+            Err(Error::HashMismatch(
+                "Fragment Hash does not match Rolling Hash".to_string(),
+            ))
         }
+    }
+
+    /// Returns the byte ranges of a rolling hash signed fragment excluded from its hash.
+    ///
+    /// The exclusions embedded in the rolling hash box of the fragment are flattened
+    /// into ranges, the first fragment of a stream carries no box and the exclusions of
+    /// this `BmffHash` are used. Hashing the fragment minus these ranges with
+    /// [`hash_stream_by_alg`](crate::hash_stream_by_alg) gives the fragment hash the
+    /// rolling hash is built from, before the salt is applied.
+    ///
+    /// The embedded exclusions are not signed, they sit in the C2PA box which is itself
+    /// excluded from the hash. They are only used if each of them is one of the signed
+    /// exclusions of this `BmffHash`.
+    ///
+    /// # Errors
+    /// * Returns [`Error::HashMismatch`] if the embedded exclusions aren't covered by
+    ///   the exclusions of this `BmffHash`.
+    pub fn rolling_hash_fragment_exclusions(
+        &self,
+        fragment_stream: &mut dyn CAIRead,
+    ) -> crate::Result<Vec<HashRange>> {
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
+        let exclusions = fragment_exclusions(&c2pa_boxes, &self.exclusions)?;
+
+        bmff_to_jumbf_exclusions(fragment_stream, exclusions, self.bmff_version > 1)
+    }
+
+    pub fn verify_fragment_memory(
+        &self,
+        fragment_stream: &mut dyn CAIRead,
+        alg: Option<&str>,
+        rolling_hash: &[u8],
+        anchor_point: &Option<Vec<u8>>,
+    ) -> crate::Result<Vec<u8>> {
+        let mut signed_fragment = without_trailing_boxes(fragment_stream)?;
+        let fragment_stream: &mut dyn CAIRead = match signed_fragment.as_mut() {
+            Some(signed) => signed,
+            None => fragment_stream,
+        };
+
+        let curr_alg = match alg {
+            Some(a) => a.to_owned(),
+            None => "sha256".to_string(),
+        };
+
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
+        let frag_anchor_point = fragment_anchor_point(&c2pa_boxes)?;
 
         let anchor_point = if let Some(ap) = anchor_point {
             // use given anchor point
             Some(ap.to_owned())
         } else {
             // otherwise attempt to use the one found in the fragment
-            c2pa_boxes.rolling_hashes[0]
-                .anchor_point
-                .as_ref()
-                .map(|ap| ap.to_vec())
-                .clone()
+            frag_anchor_point.cloned()
         };
 
         // hash fragment stream
-        let exclusions = &c2pa_boxes.rolling_hashes[0].exclusions;
-        let exclusions = bmff_to_jumbf_exclusions(fragment_stream, exclusions, true)?;
+        let exclusions = self.rolling_hash_fragment_exclusions(fragment_stream)?;
         let frag_hash = hash_stream_by_alg(&curr_alg, fragment_stream, Some(exclusions), true)?;
+        let frag_hash = salted_hash(&curr_alg, self.salt.as_ref(), frag_hash);
 
         // create rolling hash from fragment hash and optional anchor point
         let (left, right) = match anchor_point {
@@ -1190,7 +2250,59 @@ impl BmffHash {
         Ok(rolling_hash.to_vec())
     }
 
+    // hashes a signed fragment (minus exclusions) into its Merkle leaf
+    #[cfg(feature = "file_io")]
+    fn fragment_leaf(
+        &self,
+        alg: &str,
+        path: &std::path::Path,
+    ) -> crate::Result<crate::utils::merkle::MerkleNode> {
+        let mut fragment_stream = std::fs::File::open(path)?;
+
+        let fragment_exclusions = bmff_to_jumbf_exclusions(
+            &mut fragment_stream,
+            &self.exclusions,
+            self.bmff_version > 1,
+        )?;
+
+        // hash the entire fragment minus fragment exclusions
+        let hash = hash_stream_by_alg(alg, &mut fragment_stream, Some(fragment_exclusions), true)?;
+        let hash = salted_hash(alg, self.salt.as_ref(), hash);
+
+        Ok(crate::utils::merkle::MerkleNode(hash))
+    }
+
+    // Merkle leaves of the signed fragments, in the order of `paths`
+    #[cfg(feature = "file_io")]
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    fn fragment_leaves(
+        &self,
+        alg: &str,
+        paths: &[&std::path::PathBuf],
+    ) -> crate::Result<Vec<crate::utils::merkle::MerkleNode>> {
+        paths
+            .iter()
+            .map(|path| self.fragment_leaf(alg, path))
+            .collect()
+    }
+
+    // same as `fragment_leaves`, but hashes the fragments on the rayon thread pool
+    #[cfg(all(feature = "file_io", feature = "rayon"))]
+    fn fragment_leaves_par(
+        &self,
+        alg: &str,
+        paths: &[&std::path::PathBuf],
+    ) -> crate::Result<Vec<crate::utils::merkle::MerkleNode>> {
+        use rayon::prelude::*;
+
+        paths
+            .par_iter()
+            .map(|path| self.fragment_leaf(alg, path))
+            .collect()
+    }
+
     #[cfg(feature = "file_io")]
+    #[allow(clippy::too_many_arguments)]
     pub fn add_merkle_for_fragmented(
         &mut self,
         alg: &str,
@@ -1199,12 +2311,17 @@ impl BmffHash {
         output_file: &std::path::Path,
         local_id: u32,
         unique_id: Option<u32>,
-    ) -> crate::Result<()> {
-        // set Merkle hash to be the Root of the Merkle Tree
-        // (number of proofs needed = Merkle Tree height - 1)
-        let max_proofs: usize = (fragment_paths.len() as f32).log2().ceil() as usize;
+        options: &FragmentOptions,
+    ) -> crate::Result<MerkleStats> {
+        // store the configured row of the Merkle Tree, the root by default
+        // (number of proofs needed = rows below the stored one)
+        let max_proofs = options.stored_merkle_row(fragment_paths.len());
         let unique_id = unique_id.unwrap_or(local_id);
 
+        if let Some(salt) = &options.salt {
+            self.set_salt(salt.clone());
+        }
+
         // create output dir, if it doesn't exist
         let output_dir = output_file
             .parent()
@@ -1242,9 +2359,7 @@ impl BmffHash {
                 .file_name()
                 .ok_or(Error::BadParam("file name not found".to_string()))?,
         );
-        if !init_output.exists() {
-            std::fs::copy(asset_path, &init_output)?;
-        }
+        crate::utils::io_utils::copy_if_missing(asset_path, &init_output)?;
 
         // create dummy tree to figure out the layout and proof size
         let dummy_tree = C2PAMerkleTree::dummy_tree(fragments.len(), alg);
@@ -1255,15 +2370,9 @@ impl BmffHash {
         for (location, seg) in fragments.iter().enumerate() {
             let mut seg_reader = std::fs::File::open(seg)?;
 
-            let c2pa_boxes = read_bmff_c2pa_boxes(&mut seg_reader)?;
+            let c2pa_boxes = read_bmff_fragment_c2pa_boxes(&mut seg_reader)?;
             let box_infos = &c2pa_boxes.box_infos;
-
-            if box_infos.iter().filter(|b| b.path == "moof").count() != 1 {
-                return Err(Error::BadParam("expected 1 moof in fragment".to_string()));
-            }
-            if box_infos.iter().filter(|b| b.path == "mdat").count() != 1 {
-                return Err(Error::BadParam("expected 1 mdat in fragment".to_string()));
-            }
+            check_fragment_boxes(&mut seg_reader, box_infos, options)?;
 
             // ensure there aren't more than one uuid box
             if c2pa_boxes.bmff_merkle.len() > 1 || c2pa_boxes.bmff_merkle_box_infos.len() > 1 {
@@ -1287,6 +2396,10 @@ impl BmffHash {
                 local_id,
                 location: location as u32,
                 hashes: None,
+                box_count: Some(signed_box_count(
+                    box_infos,
+                    !c2pa_boxes.bmff_merkle.is_empty(),
+                )),
             };
 
             // fill proof hashes with dummy hashes
@@ -1313,23 +2426,36 @@ impl BmffHash {
                 &mm_cbor,
             )?;
 
+            // a signed fragment keeps its box while the layout is unchanged,
+            // the proof is only rewritten below if it differs
+            if options.skip_signed_fragments {
+                if let (Some(signed), Some(signed_info)) = (
+                    c2pa_boxes.bmff_merkle.first(),
+                    c2pa_boxes.bmff_merkle_box_infos.first(),
+                ) {
+                    if signed.unique_id == unique_id
+                        && signed.local_id == local_id
+                        && signed.location == location as u32
+                        && signed_info.size == uuid_box_data.len() as u64
+                    {
+                        location_to_fragment_map.insert(location as u32, dest_path.to_path_buf());
+                        continue;
+                    }
+                }
+            }
+
             let mut source = std::fs::OpenOptions::new()
                 .read(true)
                 .write(true)
                 .open(seg)?;
             if c2pa_boxes.bmff_merkle.is_empty() {
-                // insert uuid box before moof box
+                // insert uuid box at the configured position
                 let mut dest = std::fs::OpenOptions::new().write(true).open(dest_path)?;
 
-                let first_moof = box_infos
-                    .iter()
-                    .find(|b| b.path == "moof")
-                    .ok_or(Error::BadParam("expected 1 moof in fragment".to_string()))?;
-
                 crate::utils::io_utils::insert_data_at(
                     &mut source,
                     &mut dest,
-                    first_moof.offset,
+                    options.box_position.offset(box_infos)?,
                     &uuid_box_data,
                 )?;
             } else {
@@ -1346,29 +2472,21 @@ impl BmffHash {
         }
 
         // fill in actual hashes now that we have inserted the C2PA box.
-        let bmff_exclusions = &self.exclusions;
-        let mut leaves: Vec<crate::utils::merkle::MerkleNode> = Vec::with_capacity(fragments.len());
-        for i in 0..fragments.len() as u32 {
-            if let Some(path) = location_to_fragment_map.get(&i) {
-                let mut fragment_stream = std::fs::File::open(path)?;
-
-                let fragment_exclusions = bmff_to_jumbf_exclusions(
-                    &mut fragment_stream,
-                    bmff_exclusions,
-                    self.bmff_version > 1,
-                )?;
-
-                // hash the entire fragment minus fragment exclusions
-                let hash =
-                    hash_stream_by_alg(alg, &mut fragment_stream, Some(fragment_exclusions), true)?;
-
-                // add merkle leaf
-                leaves.push(crate::utils::merkle::MerkleNode(hash));
-            }
-        }
+        let leaf_paths: Vec<&std::path::PathBuf> = (0..fragments.len() as u32)
+            .filter_map(|i| location_to_fragment_map.get(&i))
+            .collect();
+        #[cfg(feature = "rayon")]
+        let leaves = self.fragment_leaves_par(alg, &leaf_paths)?;
+        #[cfg(not(feature = "rayon"))]
+        let leaves = self.fragment_leaves(alg, &leaf_paths)?;
 
         // gen final merkle tree
         let m_tree = C2PAMerkleTree::from_leaves(leaves, alg, false);
+        let mut stats = MerkleStats {
+            count: fragments.len(),
+            depth: max_proofs,
+            ..Default::default()
+        };
         for i in 0..fragments.len() as u32 {
             if let Some(dest_path) = location_to_fragment_map.get(&i) {
                 let mut fragment_stream = std::fs::OpenOptions::new()
@@ -1391,6 +2509,8 @@ impl BmffHash {
 
                 // get proof for this location and replace temp proof
                 let proof = m_tree.get_proof_by_index(bmff_mm.location as usize, max_proofs)?;
+                stats.max_proof_len = stats.max_proof_len.max(proof.len());
+                stats.total_proof_bytes += proof.iter().map(Vec::len).sum::<usize>();
                 if !proof.is_empty() {
                     let mut proof_vec = Vec::new();
                     for v in proof {
@@ -1401,6 +2521,10 @@ impl BmffHash {
                     bmff_mm.hashes = Some(VecByteBuf(proof_vec));
                 }
 
+                if options.skip_signed_fragments && bmff_mm == merkle_boxes[0] {
+                    continue;
+                }
+
                 let mm_cbor = serde_cbor::to_vec(&bmff_mm)
                     .map_err(|err| Error::AssertionEncoding(err.to_string()))?;
 
@@ -1422,7 +2546,19 @@ impl BmffHash {
             }
         }
 
-        // save desired Merkle tree row (here the root)
+        if options.box_digests {
+            for (location, dest_path) in leaf_paths.iter().enumerate() {
+                let mut fragment_stream = std::fs::File::open(dest_path)?;
+                self.record_box_digests(
+                    alg,
+                    Some(local_id),
+                    location as u32,
+                    &mut fragment_stream,
+                )?;
+            }
+        }
+
+        // save desired Merkle tree row
         let merkle_row = m_tree.layers[max_proofs].clone();
         let mut hashes = Vec::new();
         for mn in merkle_row {
@@ -1435,131 +2571,570 @@ impl BmffHash {
             local_id,
             count: fragments.len() as u32,
             alg: Some(alg.to_owned()),
-            init_hash: match alg {
-                // placeholder init hash to be filled once manifest is inserted
-                "sha256" => Some(ByteBuf::from([0u8; 32].to_vec())),
-                "sha384" => Some(ByteBuf::from([0u8; 48].to_vec())),
-                "sha512" => Some(ByteBuf::from([0u8; 64].to_vec())),
-                _ => return Err(Error::UnsupportedType),
-            },
+            // placeholder init hash to be filled once manifest is inserted
+            init_hash: Some(placeholder_hash(alg)?),
             hashes: VecByteBuf(hashes),
         };
+        self.insert_merkle_map(mm);
+
+        Ok(stats)
+    }
+
+    /// Adds a Merkle tree over the in memory `fragments` and returns the signed
+    /// fragments, in the same order.
+    ///
+    /// In memory counterpart of [`BmffHash::add_merkle_for_fragmented`] for fragments
+    /// without C2PA data. The init hash is a placeholder until
+    /// [`BmffHash::update_fragmented_inithash_memory`] is called.
+    pub fn add_merkle_for_fragmented_memory(
+        &mut self,
+        alg: &str,
+        fragments: &[Vec<u8>],
+        local_id: u32,
+        unique_id: Option<u32>,
+        options: &FragmentOptions,
+    ) -> crate::Result<Vec<Vec<u8>>> {
+        if fragments.is_empty() {
+            return Err(Error::BadParam("no fragments given".to_string()));
+        }
+
+        let max_proofs = options.stored_merkle_row(fragments.len());
+        let unique_id = unique_id.unwrap_or(local_id);
+
+        // create dummy tree to figure out the layout and proof size
+        let dummy_tree = C2PAMerkleTree::dummy_tree(fragments.len(), alg);
+
+        // insert a placeholder C2PA Merkle box into every fragment
+        let mut signed = Vec::with_capacity(fragments.len());
+        let mut merkle_boxes = Vec::with_capacity(fragments.len());
+        let mut box_counts = Vec::with_capacity(fragments.len());
+        for (location, fragment) in fragments.iter().enumerate() {
+            let c2pa_boxes = read_bmff_fragment_c2pa_boxes(&mut Cursor::new(fragment))?;
+            let box_infos = &c2pa_boxes.box_infos;
+            check_fragment_boxes(&mut Cursor::new(fragment), box_infos, options)?;
+            if c2pa_boxes.manifest_bytes.is_some() || !c2pa_boxes.bmff_merkle.is_empty() {
+                return Err(Error::BadParam(
+                    "fragment already contains C2PA data".to_string(),
+                ));
+            }
+
+            let proof = dummy_tree.get_proof_by_index(location, max_proofs)?;
+            let mut mm = merkle_map(unique_id, local_id, location as u32, proof);
+            mm.box_count = Some(signed_box_count(box_infos, false));
+            let uuid_box_data = merkle_map_box(&mm)?;
+            let offset = options.box_position.offset(box_infos)? as usize;
+
+            let mut signed_fragment = Vec::with_capacity(fragment.len() + uuid_box_data.len());
+            signed_fragment.extend_from_slice(&fragment[..offset]);
+            signed_fragment.extend_from_slice(&uuid_box_data);
+            signed_fragment.extend_from_slice(&fragment[offset..]);
+
+            signed.push(signed_fragment);
+            merkle_boxes.push(offset..offset + uuid_box_data.len());
+            box_counts.push(mm.box_count);
+        }
+
+        // hash the entire fragments minus exclusions
+        let mut hashes = Vec::with_capacity(signed.len());
+        for fragment in &signed {
+            let mut fragment_stream = Cursor::new(fragment);
+            let exclusions = bmff_to_jumbf_exclusions(
+                &mut fragment_stream,
+                &self.exclusions,
+                self.bmff_version > 1,
+            )?;
+            hashes.push(hash_stream_by_alg(
+                alg,
+                &mut fragment_stream,
+                Some(exclusions),
+                true,
+            )?);
+        }
+        let mut merkle_maps =
+            self.add_merkle_for_fragment_hashes(alg, &hashes, local_id, Some(unique_id), options)?;
+
+        // the final boxes have the same size as the placeholders, so they are patched in place
+        for (((fragment, range), mm), box_count) in signed
+            .iter_mut()
+            .zip(merkle_boxes)
+            .zip(&mut merkle_maps)
+            .zip(box_counts)
+        {
+            mm.box_count = box_count;
+            let uuid_box_data = merkle_map_box(mm)?;
+            if uuid_box_data.len() != range.len() {
+                return Err(Error::InvalidAsset(
+                    "fragment Merkle box size changed".to_string(),
+                ));
+            }
+            fragment[range].copy_from_slice(&uuid_box_data);
+        }
+
+        if options.box_digests {
+            for (location, fragment) in signed.iter().enumerate() {
+                self.record_box_digests(
+                    alg,
+                    Some(local_id),
+                    location as u32,
+                    &mut Cursor::new(fragment),
+                )?;
+            }
+        }
+
+        Ok(signed)
+    }
+
+    /// Adds a Merkle tree over precomputed fragment hashes and returns the C2PA Merkle
+    /// map to embed into each fragment, in the same order. The fragments aren't read.
+    ///
+    /// Each hash must be the unsalted `alg` hash of the fragment as it is stored once
+    /// signed, with the C2PA box of its Merkle map in place, minus the exclusions of
+    /// this `BmffHash` (see [`bmff_to_jumbf_exclusions`]). The box has the same size for
+    /// any proof of the same location, so a placeholder box can be hashed instead. A
+    /// hash breaking this contract isn't detected here, its fragment won't verify.
+    ///
+    /// The returned maps record no `box_count`, set it in the placeholder and the final
+    /// box alike for fragments to tolerate boxes appended after signing.
+    pub fn add_merkle_for_fragment_hashes(
+        &mut self,
+        alg: &str,
+        hashes: &[Vec<u8>],
+        local_id: u32,
+        unique_id: Option<u32>,
+        options: &FragmentOptions,
+    ) -> crate::Result<Vec<BmffMerkleMap>> {
+        if hashes.is_empty() {
+            return Err(Error::BadParam("no fragment hashes given".to_string()));
+        }
+        let hash_len = placeholder_hash(alg)?.len();
+        if let Some(location) = hashes.iter().position(|hash| hash.len() != hash_len) {
+            return Err(Error::BadParam(format!(
+                "hash of fragment {location} is not a {alg} hash"
+            )));
+        }
+
+        let max_proofs = options.stored_merkle_row(hashes.len());
+        let unique_id = unique_id.unwrap_or(local_id);
+
+        if let Some(salt) = &options.salt {
+            self.set_salt(salt.clone());
+        }
+
+        let leaves = hashes
+            .iter()
+            .map(|hash| {
+                crate::utils::merkle::MerkleNode(salted_hash(alg, self.salt.as_ref(), hash.clone()))
+            })
+            .collect();
+        let m_tree = C2PAMerkleTree::from_leaves(leaves, alg, false);
+
+        let mut merkle_maps = Vec::with_capacity(hashes.len());
+        for location in 0..hashes.len() {
+            let proof = m_tree.get_proof_by_index(location, max_proofs)?;
+            merkle_maps.push(merkle_map(unique_id, local_id, location as u32, proof));
+        }
+
+        let hashes = m_tree.layers[max_proofs]
+            .iter()
+            .map(|mn| ByteBuf::from(mn.0.clone()))
+            .collect();
+        self.insert_merkle_map(MerkleMap {
+            unique_id,
+            local_id,
+            count: merkle_maps.len() as u32,
+            alg: Some(alg.to_owned()),
+            // placeholder init hash to be filled once manifest is inserted
+            init_hash: Some(placeholder_hash(alg)?),
+            hashes: VecByteBuf(hashes),
+        });
+
+        Ok(merkle_maps)
+    }
 
-        // insert
+    // replaces the MerkleMap with matching unique/local IDs or appends it when it's new
+    fn insert_merkle_map(&mut self, mm: MerkleMap) {
         if let Some(merkle) = self.merkle.as_mut() {
             // if merkle is already initialized append or replace Merkle Map
             for m in merkle.iter_mut() {
-                // replace the MerkleMap with matching unique/local IDs
                 if m.local_id == mm.local_id && m.unique_id == mm.unique_id {
                     *m = mm;
-                    return Ok(());
+                    return;
                 }
             }
-            // otherwise append when it's new
             merkle.push(mm);
         } else {
             // initialisation, the first Merkle Tree
             self.merkle = Some(vec![mm]);
         }
+    }
 
-        Ok(())
+    // digests of the top level boxes of a signed fragment with their offsets, boxes
+    // overlapping an exclusion (e.g. the C2PA box) are left out
+    fn digest_boxes(
+        &self,
+        alg: &str,
+        box_infos: Vec<BoxInfoLite>,
+        fragment_stream: &mut dyn CAIRead,
+    ) -> crate::Result<Vec<(u64, BoxDigest)>> {
+        // the offsets of the v2 top level boxes aren't exclusions
+        let exclusions: Vec<HashRange> =
+            bmff_to_jumbf_exclusions(fragment_stream, &self.exclusions, self.bmff_version > 1)?
+                .into_iter()
+                .filter(|range| range.bmff_offset().is_none())
+                .collect();
+
+        let mut boxes = Vec::with_capacity(box_infos.len());
+        for info in box_infos {
+            let (start, end) = (info.offset as usize, (info.offset + info.size) as usize);
+            if exclusions
+                .iter()
+                .any(|range| range.start() < end && start < range.start() + range.length())
+            {
+                continue;
+            }
+
+            let range = HashRange::new(start, end - start);
+            let hash = hash_stream_by_alg(alg, fragment_stream, Some(vec![range]), false)?;
+            boxes.push((
+                info.offset,
+                BoxDigest {
+                    path: info.path,
+                    hash: ByteBuf::from(hash),
+                },
+            ));
+        }
+
+        Ok(boxes)
     }
 
-    pub fn add_rolling_hash_fragment<P1, P2, P3>(
+    // records the box digests of a signed fragment, replacing those of the same fragment
+    fn record_box_digests(
         &mut self,
         alg: &str,
-        asset_path: P1,
-        fragment: P2,
-        output_path: P3,
-    ) -> crate::Result<()>
-    where
-        P1: AsRef<std::path::Path>,
-        P2: AsRef<std::path::Path>,
-        P3: AsRef<std::path::Path>,
-    {
-        // create output dir, if it doesn't exist
-        let output_dir = output_path
-            .as_ref()
-            .parent()
-            .ok_or(Error::BadParam("invalid output path".to_string()))?;
-        if !output_dir.exists() {
-            std::fs::create_dir_all(output_dir)?;
-        } else if !output_dir.is_dir() {
-            // make sure it is a directory
-            return Err(Error::BadParam("output_dir is not a directory".to_string()));
+        local_id: Option<u32>,
+        location: u32,
+        fragment_stream: &mut dyn CAIRead,
+    ) -> crate::Result<()> {
+        let (box_infos, _) = fragment_boxes(fragment_stream)?;
+        let boxes = self
+            .digest_boxes(alg, box_infos, fragment_stream)?
+            .into_iter()
+            .map(|(_, boxed)| boxed)
+            .collect();
+        let entry = FragmentBoxDigests {
+            local_id,
+            location,
+            alg: alg.to_owned(),
+            boxes,
+        };
+
+        let box_digests = self.box_digests.get_or_insert_with(Vec::new);
+        match box_digests
+            .iter_mut()
+            .find(|e| e.local_id == local_id && e.location == location)
+        {
+            Some(e) => *e = entry,
+            None => box_digests.push(entry),
         }
 
-        // copy fragment to output dir
-        let file_name = fragment
-            .as_ref()
-            .file_name()
-            .ok_or(Error::BadParam("invalid fragment path".to_string()))?;
-        let fragment_output = output_dir.join(file_name);
-        std::fs::copy(&fragment, &fragment_output)?;
+        Ok(())
+    }
+
+    /// Merges the init segment at `asset_path` and the fragments into the single
+    /// asset `output_file`, containing all fragments.
+    ///
+    /// A placeholder C2PA Merkle box is inserted before every moof, the Merkle
+    /// tree over the moof ranges is generated by [`BmffHash::update_merged_merkle`]
+    /// once the manifest has been inserted into `output_file`.
+    ///
+    /// Note: Currently this does not support files with existing C2PA data.
+    #[cfg(feature = "file_io")]
+    pub fn add_merkle_for_merged(
+        &mut self,
+        alg: &str,
+        asset_path: &std::path::Path,
+        fragment_paths: &[std::path::PathBuf],
+        output_file: &std::path::Path,
+        local_id: u32,
+    ) -> crate::Result<()> {
+        use std::io::Write;
 
-        // copy init file, if its output doesn't exist
-        if !output_path.as_ref().exists() {
-            std::fs::copy(&asset_path, &output_path)?;
+        if fragment_paths.is_empty() {
+            return Err(Error::BadParam("no fragments given".to_string()));
         }
 
-        let mut reader = std::fs::File::open(&fragment)?;
-        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut reader)?;
-        let box_infos = &c2pa_boxes.box_infos;
+        let max_proofs: usize = (fragment_paths.len() as f32).log2().ceil() as usize;
 
-        if box_infos.iter().filter(|b| b.path == "moof").count() != 1 {
-            return Err(Error::BadParam("expected 1 moof in fragment".to_string()));
-        }
-        if box_infos.iter().filter(|b| b.path == "mdat").count() != 1 {
-            return Err(Error::BadParam("expected 1 mdat in fragment".to_string()));
+        let mut init_stream = std::fs::File::open(asset_path)?;
+        let c2pa_boxes = read_bmff_c2pa_boxes(&mut init_stream)?;
+        if c2pa_boxes.manifest_bytes.is_some() || !c2pa_boxes.bmff_merkle.is_empty() {
+            return Err(Error::BadParam(
+                "init segment already contains C2PA data".to_string(),
+            ));
         }
-
-        // ensure there aren't more than one uuid box
-        if c2pa_boxes.rolling_hashes.len() > 1 || c2pa_boxes.bmff_merkle_box_infos.len() > 1 {
+        if c2pa_boxes.box_infos.iter().any(|b| b.path == "moof") {
             return Err(Error::BadParam(
-                "BMFF Fragments shouldn't have more than 1 BmffMerkleMap".to_string(),
+                "init segment must not contain fragments".to_string(),
             ));
         }
 
-        // build the UUID Box of the Fragment
-        // box content is simply the previous rolling hash
-        let anchor_data = FragmentRollingHash {
-            anchor_point: self.previous_hash().cloned().map(|inner| inner.into()),
-            exclusions: self.exclusions.clone(),
-        };
-        let anchor_data = serde_cbor::to_vec(&anchor_data)
-            .map_err(|err| Error::AssertionEncoding(err.to_string()))?;
-
-        let mut uuid_box_data = Vec::with_capacity(anchor_data.len() * 2);
-        crate::asset_handlers::bmff_io::write_c2pa_box(
-            &mut uuid_box_data,
-            &[],
-            false,
-            &anchor_data,
-        )?;
+        let mut output = std::fs::File::create(output_file)?;
+        init_stream.rewind()?;
+        std::io::copy(&mut init_stream, &mut output)?;
 
-        // insert the UUID Box in the output Fragment
-        let mut source = std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(&fragment)?;
-        let mut dest = std::fs::OpenOptions::new()
-            .write(true)
-            .read(true)
-            .open(&fragment_output)?;
-        let first_moof = box_infos
-            .iter()
-            .find(|b| b.path == "moof")
-            .ok_or(Error::BadParam("expected 1 moof in fragment".to_string()))?;
-        crate::utils::io_utils::insert_data_at(
-            &mut source,
-            &mut dest,
-            first_moof.offset,
-            &uuid_box_data,
-        )?;
+        // create dummy tree to figure out the layout and proof size
+        let dummy_tree = C2PAMerkleTree::dummy_tree(fragment_paths.len(), alg);
+
+        // append the fragments with a placeholder C2PA Merkle box in front of the moof
+        for (location, fragment_path) in fragment_paths.iter().enumerate() {
+            let mut fragment_stream = std::fs::File::open(fragment_path)?;
+
+            let c2pa_boxes = read_bmff_fragment_c2pa_boxes(&mut fragment_stream)?;
+            let box_infos = &c2pa_boxes.box_infos;
+
+            if box_infos.iter().filter(|b| b.path == "moof").count() != 1 {
+                return Err(Error::BadParam("expected 1 moof in fragment".to_string()));
+            }
+            if box_infos.iter().filter(|b| b.path == "mdat").count() != 1 {
+                return Err(Error::BadParam("expected 1 mdat in fragment".to_string()));
+            }
+            if c2pa_boxes.manifest_bytes.is_some() || !c2pa_boxes.bmff_merkle.is_empty() {
+                return Err(Error::BadParam(
+                    "fragment already contains C2PA data".to_string(),
+                ));
+            }
+
+            let proof = dummy_tree.get_proof_by_index(location, max_proofs)?;
+            let uuid_box_data = merkle_box(local_id, local_id, location as u32, proof)?;
+
+            let moof_offset = C2paBoxPosition::BeforeMoof.offset(box_infos)?;
+            fragment_stream.rewind()?;
+            std::io::copy(&mut (&mut fragment_stream).take(moof_offset), &mut output)?;
+            output.write_all(&uuid_box_data)?;
+            std::io::copy(&mut fragment_stream, &mut output)?;
+        }
+        output.flush()?;
+
+        // placeholder root and init hash to be filled once manifest is inserted
+        let hashes = dummy_tree.layers[max_proofs]
+            .iter()
+            .map(|mn| ByteBuf::from(mn.0.clone()))
+            .collect();
+
+        self.merkle = Some(vec![MerkleMap {
+            unique_id: local_id,
+            local_id,
+            count: fragment_paths.len() as u32,
+            alg: Some(alg.to_owned()),
+            init_hash: Some(placeholder_hash(alg)?),
+            hashes: VecByteBuf(hashes),
+        }]);
+
+        Ok(())
+    }
+
+    /// Generates the Merkle tree over the moof ranges of an asset created by
+    /// [`BmffHash::add_merkle_for_merged`], replacing the placeholder C2PA Merkle
+    /// boxes in place and updating the root and the init hash.
+    #[cfg(feature = "file_io")]
+    pub fn update_merged_merkle(&mut self, asset_path: &std::path::Path) -> crate::Result<()> {
+        use std::io::Write;
+
+        let mut stream = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(asset_path)?;
+        let size = stream_len(&mut stream)?;
+
+        let exclusions =
+            bmff_to_jumbf_exclusions(&mut stream, &self.exclusions, self.bmff_version > 1)?;
+
+        let default_alg = self.alg.clone();
+        let mm = self
+            .merkle
+            .as_mut()
+            .and_then(|mm| mm.first_mut())
+            .ok_or(Error::BadParam("expected MerkleMap".to_string()))?;
+        let alg = mm
+            .alg
+            .clone()
+            .or(default_alg)
+            .unwrap_or("sha256".to_string());
+
+        let c2pa_boxes = read_bmff_c2pa_boxes(&mut stream)?;
+        let moof_chunks = BmffHash::split_fragment_boxes(&c2pa_boxes.box_infos);
+        if moof_chunks.len() != mm.count as usize
+            || c2pa_boxes.bmff_merkle.len() != mm.count as usize
+        {
+            return Err(Error::InvalidAsset(
+                "merged asset Merkle box count wrong".to_string(),
+            ));
+        }
+
+        let mut leaves = Vec::with_capacity(moof_chunks.len());
+        for boxes in &moof_chunks {
+            let hash = hash_fragment_chunk(&alg, &mut stream, &exclusions, boxes, size)?;
+            let hash = salted_hash(&alg, self.salt.as_ref(), hash);
+            leaves.push(crate::utils::merkle::MerkleNode(hash));
+        }
+
+        let max_proofs: usize = (moof_chunks.len() as f32).log2().ceil() as usize;
+        let m_tree = C2PAMerkleTree::from_leaves(leaves, &alg, false);
+
+        // the final boxes have the same size as the placeholders, so they are patched in place
+        for (bmff_mm, bmff_mm_info) in c2pa_boxes
+            .bmff_merkle
+            .iter()
+            .zip(&c2pa_boxes.bmff_merkle_box_infos)
+        {
+            let proof = m_tree.get_proof_by_index(bmff_mm.location as usize, max_proofs)?;
+            let uuid_box_data =
+                merkle_box(bmff_mm.unique_id, bmff_mm.local_id, bmff_mm.location, proof)?;
+            if uuid_box_data.len() as u64 != bmff_mm_info.size {
+                return Err(Error::InvalidAsset(
+                    "merged asset Merkle box size changed".to_string(),
+                ));
+            }
+
+            stream.seek(std::io::SeekFrom::Start(bmff_mm_info.offset))?;
+            stream.write_all(&uuid_box_data)?;
+        }
+        stream.flush()?;
+
+        mm.hashes = VecByteBuf(
+            m_tree.layers[max_proofs]
+                .iter()
+                .map(|mn| ByteBuf::from(mn.0.clone()))
+                .collect(),
+        );
+
+        // the init hash covers everything before the first moof
+        let first_moof = &moof_chunks[0][0];
+        let mut init_exclusions = exclusions;
+        init_exclusions.push(HashRange::new(
+            first_moof.offset as usize,
+            (size - first_moof.offset) as usize,
+        ));
+        let init_hash = hash_stream_by_alg(&alg, &mut stream, Some(init_exclusions), true)?;
+        mm.init_hash = Some(ByteBuf::from(init_hash));
+
+        Ok(())
+    }
+
+    pub fn add_rolling_hash_fragment<P1, P2, P3>(
+        &mut self,
+        alg: &str,
+        asset_path: P1,
+        fragment: P2,
+        output_path: P3,
+        options: &FragmentOptions,
+    ) -> crate::Result<()>
+    where
+        P1: AsRef<std::path::Path>,
+        P2: AsRef<std::path::Path>,
+        P3: AsRef<std::path::Path>,
+    {
+        if let Some(salt) = &options.salt {
+            self.set_salt(salt.clone());
+        }
+
+        // create output dir, if it doesn't exist
+        let output_dir = output_path
+            .as_ref()
+            .parent()
+            .ok_or(Error::BadParam("invalid output path".to_string()))?;
+        if !output_dir.exists() {
+            std::fs::create_dir_all(output_dir)?;
+        } else if !output_dir.is_dir() {
+            // make sure it is a directory
+            return Err(Error::BadParam("output_dir is not a directory".to_string()));
+        }
+
+        let file_name = fragment
+            .as_ref()
+            .file_name()
+            .ok_or(Error::BadParam("invalid fragment path".to_string()))?;
+
+        // copy init file, if its output doesn't exist, concurrent fragments of
+        // the same stream may race for it
+        crate::utils::io_utils::copy_if_missing(asset_path.as_ref(), output_path.as_ref())?;
+
+        let signed =
+            self.add_rolling_hash_fragment_memory(alg, &std::fs::read(&fragment)?, options)?;
+        std::fs::write(output_dir.join(file_name), signed)?;
+
+        Ok(())
+    }
+
+    /// Adds the in memory `fragment` to the rolling hash and returns the signed fragment.
+    ///
+    /// In memory counterpart of [`BmffHash::add_rolling_hash_fragment`], the
+    /// rolling hash has to be shifted with [`BmffHash::shift_rolling_hash`]
+    /// before each following fragment.
+    pub fn add_rolling_hash_fragment_memory(
+        &mut self,
+        alg: &str,
+        fragment: &[u8],
+        options: &FragmentOptions,
+    ) -> crate::Result<Vec<u8>> {
+        if let Some(salt) = &options.salt {
+            self.set_salt(salt.clone());
+        }
+
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut Cursor::new(fragment))?;
+        let box_infos = &c2pa_boxes.box_infos;
+        check_fragment_boxes(&mut Cursor::new(fragment), box_infos, options)?;
+
+        // ensure there aren't more than one uuid box
+        if c2pa_boxes.rolling_hashes.len() > 1 || c2pa_boxes.bmff_merkle_box_infos.len() > 1 {
+            return Err(Error::BadParam(
+                "BMFF Fragments shouldn't have more than 1 BmffMerkleMap".to_string(),
+            ));
+        }
+
+        // the first fragment has no anchor point, it carries no UUID Box at all,
+        // every following one carries the previous rolling hash as anchor point
+        let signed = match self.previous_hash() {
+            Some(previous_hash) => {
+                let anchor_data = FragmentRollingHash {
+                    anchor_point: Some(ByteBuf::from(previous_hash.clone())),
+                    exclusions: self.exclusions.clone(),
+                    metadata: options.metadata.clone(),
+                    box_count: Some(signed_box_count(box_infos, false)),
+                };
+                let anchor_data = serde_cbor::to_vec(&anchor_data)
+                    .map_err(|err| Error::AssertionEncoding(err.to_string()))?;
+
+                let mut uuid_box_data = Vec::with_capacity(anchor_data.len() * 2);
+                crate::asset_handlers::bmff_io::write_c2pa_box(
+                    &mut uuid_box_data,
+                    &[],
+                    false,
+                    &anchor_data,
+                )?;
+
+                // insert the UUID Box in the output Fragment
+                let offset = options.box_position.offset(box_infos)? as usize;
+                let mut signed = Vec::with_capacity(fragment.len() + uuid_box_data.len());
+                signed.extend_from_slice(&fragment[..offset]);
+                signed.extend_from_slice(&uuid_box_data);
+                signed.extend_from_slice(&fragment[offset..]);
+                signed
+            }
+            None => fragment.to_vec(),
+        };
+
+        // only the newest fragment is covered by the rolling hash of the manifest
+        if options.box_digests {
+            self.record_box_digests(alg, None, 0, &mut Cursor::new(signed.as_slice()))?;
+        }
 
         // create the new rolling hash: hash(previous hash + fragment hash)
-        let hash_ranges = bmff_to_jumbf_exclusions(&mut dest, self.exclusions(), true)?;
+        let mut dest = Cursor::new(signed.as_slice());
+        let hash_ranges =
+            bmff_to_jumbf_exclusions(&mut dest, self.exclusions(), self.bmff_version > 1)?;
         let fragment_hash = hash_stream_by_alg(alg, &mut dest, Some(hash_ranges), true)?;
+        let fragment_hash = salted_hash(alg, self.salt.as_ref(), fragment_hash);
 
         // prepare required hashes
         let (left, right) = if let Some(prev) = self.previous_hash() {
@@ -1577,19 +3152,292 @@ impl BmffHash {
             .replace(concat_and_hash(alg, left, right).into());
         self.rolling_hash.replace(rh);
 
-        Ok(())
+        Ok(signed)
     }
 
-    /// moves the rolling hash to the previous hash
-    pub fn shift_rolling_hash(&mut self) {
-        if let Some(rh) = &mut self.rolling_hash {
-            rh.shift_rolling_hash();
-        }
+    /// Moves the rolling hash to the previous hash and returns it, it is the anchor
+    /// point of the next fragment.
+    pub fn shift_rolling_hash(&mut self) -> Option<Vec<u8>> {
+        self.rolling_hash
+            .as_mut()
+            .and_then(RollingHash::shift_rolling_hash)
     }
 }
 
 impl AssertionCbor for BmffHash {}
 
+// ensures a fragment has a single moof and a single mdat with media data, so e.g. the
+// empty fragment of an encoder hiccup isn't signed
+fn check_fragment_boxes<R: Read + Seek + ?Sized>(
+    reader: &mut R,
+    box_infos: &[BoxInfoLite],
+    options: &FragmentOptions,
+) -> crate::Result<()> {
+    if box_infos.iter().filter(|b| b.path == "moof").count() != 1 {
+        return Err(Error::BadParam("expected 1 moof in fragment".to_string()));
+    }
+    let mut mdats = box_infos.iter().filter(|b| b.path == "mdat");
+    let (Some(mdat), None) = (mdats.next(), mdats.next()) else {
+        return Err(Error::BadParam("expected 1 mdat in fragment".to_string()));
+    };
+
+    // a large size follows the compact size of 1
+    reader.seek(std::io::SeekFrom::Start(mdat.offset))?;
+    let mut size = [0; 4];
+    reader.read_exact(&mut size)?;
+    let header_len = if u32::from_be_bytes(size) == 1 { 16 } else { 8 };
+
+    let payload_len = mdat.size.saturating_sub(header_len);
+    if payload_len < options.min_mdat_size.max(1) {
+        return Err(Error::BadParam(format!(
+            "mdat of {payload_len} bytes is below the minimum media data size"
+        )));
+    }
+
+    Ok(())
+}
+
+// top level boxes of a fragment signed with a Merkle tree or with the rolling hash,
+// along with the Merkle map of the former
+fn fragment_boxes(
+    fragment_stream: &mut dyn CAIRead,
+) -> crate::Result<(Vec<BoxInfoLite>, Option<BmffMerkleMap>)> {
+    match read_bmff_fragment_c2pa_boxes(fragment_stream) {
+        Ok(boxes) => Ok((boxes.box_infos, boxes.bmff_merkle.into_iter().next())),
+        // the boxes don't decode as Merkle maps, try the rolling hash
+        Err(err) => C2PABmffBoxesRollingHash::from_reader(fragment_stream)
+            .map(|boxes| (boxes.box_infos, None))
+            .map_err(|_| err),
+    }
+}
+
+// Zero filled hash used until the actual hash is known
+fn placeholder_hash(alg: &str) -> crate::Result<ByteBuf> {
+    match alg {
+        "sha256" => Ok(ByteBuf::from([0u8; 32].to_vec())),
+        "sha384" => Ok(ByteBuf::from([0u8; 48].to_vec())),
+        "sha512" => Ok(ByteBuf::from([0u8; 64].to_vec())),
+        _ => Err(Error::UnsupportedType),
+    }
+}
+
+// The C2PA Merkle box of the fragment at `location`
+fn merkle_box(
+    unique_id: u32,
+    local_id: u32,
+    location: u32,
+    proof: Vec<Vec<u8>>,
+) -> crate::Result<Vec<u8>> {
+    merkle_map_box(&merkle_map(unique_id, local_id, location, proof))
+}
+
+// The C2PA Merkle map of the fragment at `location`
+fn merkle_map(unique_id: u32, local_id: u32, location: u32, proof: Vec<Vec<u8>>) -> BmffMerkleMap {
+    BmffMerkleMap {
+        unique_id,
+        local_id,
+        location,
+        hashes: (!proof.is_empty())
+            .then(|| VecByteBuf(proof.into_iter().map(ByteBuf::from).collect())),
+        box_count: None,
+    }
+}
+
+// Serializes `mm` into a C2PA Merkle box
+fn merkle_map_box(mm: &BmffMerkleMap) -> crate::Result<Vec<u8>> {
+    let mm_cbor =
+        serde_cbor::to_vec(mm).map_err(|err| Error::AssertionEncoding(err.to_string()))?;
+
+    let mut uuid_box_data: Vec<u8> = Vec::with_capacity(mm_cbor.len() * 2);
+    crate::asset_handlers::bmff_io::write_c2pa_box(&mut uuid_box_data, &[], false, &mm_cbor)?;
+
+    Ok(uuid_box_data)
+}
+
+// Returns a copy of the fragment without the boxes appended after signing (e.g. `free`
+// padding added by a CDN) if `verify.tolerate_trailing_boxes` is enabled, see
+// [`signed_fragment_len`].
+// This is a relaxation: the appended bytes are not covered by the signature and are
+// ignored instead of failing the hash check. Returns `None` if there is nothing to strip.
+fn without_trailing_boxes(
+    fragment_stream: &mut dyn CAIRead,
+) -> crate::Result<Option<Cursor<Vec<u8>>>> {
+    if !get_settings_value::<bool>("verify.tolerate_trailing_boxes").unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let signed_len = signed_fragment_len(fragment_stream)?;
+    if signed_len == stream_len(fragment_stream)? {
+        return Ok(None);
+    }
+
+    let mut signed = Vec::with_capacity(signed_len as usize);
+    fragment_stream.rewind()?;
+    Read::take(&mut *fragment_stream, signed_len).read_to_end(&mut signed)?;
+    fragment_stream.rewind()?;
+
+    Ok(Some(Cursor::new(signed)))
+}
+
+// Logs the verification `result` of the Merkle leaf at `location` to `validation_log`.
+// Without a log the result is returned as is, with a log a mismatch is only returned
+// if the log stops on the first error.
+fn log_merkle_leaf(
+    validation_log: Option<&mut StatusTracker>,
+    label: &str,
+    location: u32,
+    result: crate::Result<()>,
+) -> crate::Result<()> {
+    let Some(validation_log) = validation_log else {
+        return result;
+    };
+
+    match result {
+        Ok(()) => {
+            log_item!(
+                label.to_owned(),
+                format!("fragment {location} hash valid"),
+                "verify_merkle_leaf"
+            )
+            .validation_status(validation_status::ASSERTION_BMFFHASH_MATCH)
+            .success(validation_log);
+            Ok(())
+        }
+        Err(e) => log_item!(
+            label.to_owned(),
+            format!("fragment {location} hash invalid"),
+            "verify_merkle_leaf"
+        )
+        .validation_status(validation_status::ASSERTION_BMFFHASH_MISMATCH)
+        .failure(validation_log, e),
+    }
+}
+
+// Mixes the optional per-stream salt into a fragment hash: hash(salt + fragment hash).
+// Without a salt the fragment hash is used as is.
+fn salted_hash(alg: &str, salt: Option<&ByteBuf>, hash: Vec<u8>) -> Vec<u8> {
+    match salt {
+        Some(salt) => concat_and_hash(alg, salt, Some(&hash)),
+        None => hash,
+    }
+}
+
+// Hashes the range of a moof chunk (see `BmffHash::split_fragment_boxes`) minus the exclusions
+fn hash_fragment_chunk(
+    alg: &str,
+    reader: &mut dyn CAIRead,
+    exclusions: &[HashRange],
+    boxes: &[BoxInfoLite],
+    size: u64,
+) -> crate::Result<Vec<u8>> {
+    // include just the range of this chunk so exclude boxes before and after
+    let mut curr_exclusions = exclusions.to_vec();
+
+    // before box exclusion starts at beginning of file until the start of this chunk
+    let before_box_start = 0;
+    let before_box_len = match boxes.first() {
+        Some(first) => first.offset as usize,
+        None => 0,
+    };
+    let before_box_exclusion = HashRange::new(before_box_start, before_box_len);
+    curr_exclusions.push(before_box_exclusion);
+
+    // after box exclusion continues to the end of the file
+    let after_box_start = match boxes.last() {
+        Some(last) => last.offset + last.size,
+        None => 0,
+    };
+    let after_box_len = size - after_box_start;
+    let after_box_exclusion = HashRange::new(after_box_start as usize, after_box_len as usize);
+    curr_exclusions.push(after_box_exclusion);
+
+    // hash the specified range
+    hash_stream_by_alg(alg, reader, Some(curr_exclusions), true)
+}
+
+/// hashes the samples of a track per chunk, indexed by the Merkle location of the chunk
+///
+/// The location of a chunk is its position in the chunk offset table (`stco`/`co64`).
+/// `stsc` numbers the chunks from the `first_chunk` of its first entry, which is 1 for
+/// conformant files, so the location is the chunk id minus that first chunk id.
+fn track_chunk_hashes<R: Read + Seek>(
+    mp4: &mut mp4::Mp4Reader<R>,
+    track_id: u32,
+    alg: &str,
+) -> crate::Result<Vec<Vec<u8>>> {
+    let track = {
+        // clone so we can borrow later
+        let tt = mp4
+            .tracks()
+            .get(&track_id)
+            .ok_or(Error::HashMismatch("Merkle location not found".to_owned()))?;
+
+        Mp4Track {
+            trak: tt.trak.clone(),
+            trafs: tt.trafs.clone(),
+            default_sample_duration: tt.default_sample_duration,
+        }
+    };
+
+    let sample_cnt = track.sample_count();
+    if sample_cnt == 0 {
+        return Err(Error::InvalidAsset("No samples".to_string()));
+    }
+
+    // create sample to chunk mapping
+    // create the Merkle tree per samples in a chunk
+    let mut chunk_hashers: Vec<Hasher> = Vec::new();
+    let stsc = &track.trak.mdia.minf.stbl.stsc;
+    let first_chunk_id = stsc
+        .entries
+        .first()
+        .ok_or(Error::InvalidAsset("BMFF has no stsc entries".to_string()))?
+        .first_chunk;
+    for sample_id in 1..=sample_cnt {
+        let stsc_idx = stsc_index(&track, sample_id)?;
+
+        let stsc_entry = &stsc.entries[stsc_idx];
+
+        let first_chunk = stsc_entry.first_chunk;
+        let first_sample = stsc_entry.first_sample;
+        let samples_per_chunk = stsc_entry.samples_per_chunk;
+
+        let chunk_id = first_chunk + (sample_id - first_sample) / samples_per_chunk;
+        let location = chunk_id
+            .checked_sub(first_chunk_id)
+            .ok_or(Error::InvalidAsset(format!(
+                "BMFF chunk {chunk_id} precedes the first chunk {first_chunk_id}"
+            )))? as usize;
+
+        // the samples are stored in chunk order, every chunk holds at least one sample
+        if location == chunk_hashers.len() {
+            // get hasher for algorithm
+            let hasher_enum = match alg {
+                "sha256" => Hasher::SHA256(Sha256::new()),
+                "sha384" => Hasher::SHA384(Sha384::new()),
+                "sha512" => Hasher::SHA512(Sha512::new()),
+                _ => return Err(Error::HashMismatch("no algorithm found".to_string())),
+            };
+
+            chunk_hashers.push(hasher_enum);
+        } else if location + 1 != chunk_hashers.len() {
+            return Err(Error::HashMismatch(
+                "Bad Merkle tree sample mapping".to_string(),
+            ));
+        }
+
+        if let Ok(Some(sample)) = &mp4.read_sample(track_id, sample_id) {
+            // add sample data to hash
+            chunk_hashers[location].update(&sample.bytes);
+        } else {
+            return Err(Error::HashMismatch("Merle location not found".to_owned()));
+        }
+    }
+
+    // finalize leaf hashes
+    Ok(chunk_hashers.into_iter().map(Hasher::finalize).collect())
+}
+
 impl AssertionBase for BmffHash {
     const LABEL: &'static str = Self::LABEL;
     const VERSION: Option<usize> = Some(ASSERTION_CREATION_VERSION);
@@ -1696,9 +3544,10 @@ impl RollingHash {
         self.previous_hash = None;
     }
 
-    /// moves the rolling hash to the previous hash
-    pub fn shift_rolling_hash(&mut self) {
+    /// moves the rolling hash to the previous hash and returns it
+    pub fn shift_rolling_hash(&mut self) -> Option<Vec<u8>> {
         self.previous_hash = self.rolling_hash.take();
+        self.previous_hash().cloned()
     }
 
     pub fn init_hash(&self) -> Option<&Vec<u8>> {
@@ -1712,71 +3561,3388 @@ impl RollingHash {
     pub fn clear_init_hash(&mut self) {
         self.init_hash = None;
     }
+
+    /// Returns the rolling hash through the given signed fragment.
+    ///
+    /// This is the anchor point a client joining the stream after this fragment
+    /// should verify the next one against. The fragment hash isn't salted and uses
+    /// the exclusions of the current assertion version, use [`RollingHashVerifier`]
+    /// for streams signed with a salt or an older version. The first fragment of a
+    /// stream carries no exclusions, it is hashed as a whole.
+    pub fn next_anchor_after(&self, fragment_stream: &mut dyn CAIRead) -> crate::Result<Vec<u8>> {
+        let mut signed_fragment = without_trailing_boxes(fragment_stream)?;
+        let fragment_stream: &mut dyn CAIRead = match signed_fragment.as_mut() {
+            Some(signed) => signed,
+            None => fragment_stream,
+        };
+
+        let alg = self.alg.as_deref().unwrap_or("sha256");
+        let (_, rolling_hash) = rolling_hash_through(
+            alg,
+            None,
+            ASSERTION_CREATION_VERSION > 1,
+            None,
+            fragment_stream,
+        )?;
+        Ok(rolling_hash)
+    }
 }
+
+// Returns the anchor point of a rolling hash signed fragment.
+// The first fragment of a stream carries no anchor box, every following fragment
+// carries exactly one holding the rolling hash of the fragments before it.
+fn fragment_anchor_point(c2pa_boxes: &C2PABmffBoxesRollingHash) -> crate::Result<Option<&Vec<u8>>> {
+    // ensure there aren't more than one uuid box
+    if c2pa_boxes.rolling_hashes.len() > 1 || c2pa_boxes.bmff_merkle_box_infos.len() > 1 {
+        return Err(Error::InvalidAsset(
+            "BMFF Fragments shouldn't have more than 1 FragmentRollingHash".to_string(),
+        ));
+    }
+
+    match c2pa_boxes.rolling_hashes.first() {
+        Some(frag_rh) => frag_rh
+            .anchor_point
+            .as_deref()
+            .map(Some)
+            .ok_or(Error::InvalidAsset(
+                "FragmentRollingHash without Anchor Point".to_string(),
+            )),
+        None => Ok(None),
+    }
+}
+
+// The exclusions a rolling hash signed fragment was hashed with, the ones embedded in its
+// rolling hash box or the `signed` ones of the manifest for the first fragment, which
+// carries no such box.
+//
+// The embedded exclusions sit in the C2PA box, which is excluded from the hash, so nothing
+// signs them. They are only accepted if each of them is one of the `signed` exclusions,
+// otherwise boxes appended to the fragment could be excluded at will.
+fn fragment_exclusions<'a>(
+    c2pa_boxes: &'a C2PABmffBoxesRollingHash,
+    signed: &'a [ExclusionsMap],
+) -> crate::Result<&'a [ExclusionsMap]> {
+    match c2pa_boxes.rolling_hashes.first() {
+        Some(frag_rh) if frag_rh.exclusions.iter().all(|e| signed.contains(e)) => {
+            Ok(&frag_rh.exclusions)
+        }
+        Some(_) => Err(Error::HashMismatch(
+            "The exclusions embedded in the fragment aren't covered by the manifest's".to_string(),
+        )),
+        None => Ok(signed),
+    }
+}
+
+// Reads the anchor point of a rolling hash signed fragment and hashes the fragment onto it,
+// with the exclusions of `fragment_exclusions` for the `signed` exclusions of the manifest.
+//
+// Without `signed` exclusions the embedded ones are used unchecked, the first fragment is
+// hashed as a whole. This only serves `RollingHash::next_anchor_after`, its result is an
+// anchor the following fragment is verified against, not a verification itself.
+fn rolling_hash_through(
+    alg: &str,
+    salt: Option<&ByteBuf>,
+    bmff_v2: bool,
+    signed: Option<&[ExclusionsMap]>,
+    fragment_stream: &mut dyn CAIRead,
+) -> crate::Result<(Option<Vec<u8>>, Vec<u8>)> {
+    let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(fragment_stream)?;
+    let anchor_point = fragment_anchor_point(&c2pa_boxes)?.cloned();
+    let exclusions = match signed {
+        Some(signed) => fragment_exclusions(&c2pa_boxes, signed)?,
+        None => c2pa_boxes
+            .rolling_hashes
+            .first()
+            .map_or(&[][..], |frag_rh| &frag_rh.exclusions),
+    };
+
+    // hash fragment stream
+    let exclusions = bmff_to_jumbf_exclusions(fragment_stream, exclusions, bmff_v2)?;
+    let frag_hash = hash_stream_by_alg(alg, fragment_stream, Some(exclusions), true)?;
+    let frag_hash = salted_hash(alg, salt, frag_hash);
+
+    let rolling_hash = match &anchor_point {
+        Some(ap) => concat_and_hash(alg, ap, Some(&frag_hash)),
+        None => concat_and_hash(alg, &frag_hash, None),
+    };
+    Ok((anchor_point, rolling_hash))
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct FragmentRollingHash {
     pub(crate) anchor_point: Option<ByteBuf>,
     exclusions: Vec<ExclusionsMap>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<FragmentMetadata>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    box_count: Option<u32>,
 }
 
-/* we need shippable examples
-#[cfg(test)]
-pub mod tests {
+impl FragmentRollingHash {
+    /// Returns the rolling hash of the previous fragment, the fragment is anchored to.
+    pub fn anchor_point(&self) -> Option<&[u8]> {
+        self.anchor_point.as_deref().map(Vec::as_slice)
+    }
+
+    /// Returns the exclusions the fragment was hashed with.
+    pub fn exclusions(&self) -> &[ExclusionsMap] {
+        &self.exclusions
+    }
+
+    /// Returns the stream metadata of the fragment, if it was signed with any.
+    pub fn metadata(&self) -> Option<&FragmentMetadata> {
+        self.metadata.as_ref()
+    }
+}
+
+/// Verifies the fragments of a rolling hash signed stream one at a time, in
+/// the order they were signed, e.g. while a live recording is still in progress.
+///
+/// The running hash is carried from fragment to fragment and compared against
+/// the rolling hash of the manifest by [`RollingHashVerifier::finalize`].
+#[derive(Debug)]
+pub struct RollingHashVerifier {
+    alg: String,
+    salt: Option<ByteBuf>,
+    bmff_v2: bool,
+    exclusions: Vec<ExclusionsMap>,
+    expected: Vec<u8>,
+    rolling_hash: Option<Vec<u8>>,
+    count: usize,
+}
+
+impl RollingHashVerifier {
+    /// Creates a verifier for the `RollingHash` of `bmff_hash`, usually taken from
+    /// the manifest of the signed init segment.
+    pub fn new(bmff_hash: &BmffHash) -> crate::Result<Self> {
+        let rh = bmff_hash
+            .rolling_hash()
+            .ok_or(Error::HashMismatch("Missing RollingHash".to_string()))?;
+        let expected = rh.rolling_hash().ok_or(Error::HashMismatch(
+            "Asset File has no Rolling Hash".to_string(),
+        ))?;
+
+        let alg = match rh.alg() {
+            Some(a) => a.to_owned(),
+            None => match bmff_hash.alg() {
+                Some(a) => a.to_owned(),
+                None => "sha256".to_string(),
+            },
+        };
+
+        Ok(Self {
+            alg,
+            salt: bmff_hash.salt.clone(),
+            bmff_v2: bmff_hash.bmff_version > 1,
+            exclusions: bmff_hash.exclusions.clone(),
+            expected: expected.clone(),
+            rolling_hash: None,
+            count: 0,
+        })
+    }
+
+    /// Adds the next fragment to the running hash.
+    ///
+    /// The anchor point of the fragment must match the running hash, the first
+    /// fragment pushed may start anywhere within the stream.
+    pub fn push_fragment(&mut self, fragment_stream: &mut dyn CAIRead) -> crate::Result<()> {
+        let mut signed_fragment = without_trailing_boxes(fragment_stream)?;
+        let fragment_stream: &mut dyn CAIRead = match signed_fragment.as_mut() {
+            Some(signed) => signed,
+            None => fragment_stream,
+        };
+
+        let (anchor_point, rolling_hash) = rolling_hash_through(
+            &self.alg,
+            self.salt.as_ref(),
+            self.bmff_v2,
+            Some(&self.exclusions),
+            fragment_stream,
+        )?;
+
+        if let Some(rolling_hash) = &self.rolling_hash {
+            if anchor_point.as_ref() != Some(rolling_hash) {
+                return Err(Error::HashMismatch(format!(
+                    "fragment #{} does not continue the rolling hash",
+                    self.count
+                )));
+            }
+        }
+
+        self.rolling_hash = Some(rolling_hash);
+        self.count += 1;
+
+        Ok(())
+    }
+
+    /// Returns the running hash of the fragments pushed so far.
+    pub fn rolling_hash(&self) -> Option<&[u8]> {
+        self.rolling_hash.as_deref()
+    }
+
+    /// Returns the number of fragments pushed so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Checks the running hash against the rolling hash of the manifest.
+    pub fn finalize(self) -> crate::Result<()> {
+        match self.rolling_hash {
+            Some(rolling_hash) if rolling_hash == self.expected => Ok(()),
+            Some(_) => Err(Error::HashMismatch(
+                "Fragment Hash does not match Rolling Hash".to_string(),
+            )),
+            None => Err(Error::HashMismatch("no fragments verified".to_string())),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "file_io"))]
+mod tests {
     #![allow(clippy::expect_used)]
     #![allow(clippy::panic)]
     #![allow(clippy::unwrap_used)]
 
-    //use tempfile::tempdir;
+    use std::path::PathBuf;
 
-    //use super::*;
+    use super::*;
     use crate::utils::test::fixture_path;
 
-    #[test]
-    fn test_fragemented_mp4() {
-        use crate::{
-            assertions::BmffHash, asset_handlers::bmff_io::BmffIO, asset_io::AssetIO,
-            status_tracker::DetailedStatusTracker, store::Store, AssertionBase,
-        };
+    const POSITIONS: [C2paBoxPosition; 3] = [
+        C2paBoxPosition::BeforeMoof,
+        C2paBoxPosition::AfterMdat,
+        C2paBoxPosition::FileStart,
+    ];
 
-        let init_stream_path = fixture_path("dashinit.mp4");
-        let segment_stream_path = fixture_path("dash1.m4s");
-        let segment_stream_path10 = fixture_path("dash10.m4s");
-        let segment_stream_path11 = fixture_path("dash11.m4s");
+    fn bunny(file_name: &str) -> PathBuf {
+        fixture_path(&format!("bunny/bunny_89283bps/{file_name}"))
+    }
 
+    fn bmff_hash() -> BmffHash {
+        let mut bmff_hash = BmffHash::new("jumbf manifest", "sha256", None);
+        let mut uuid = ExclusionsMap::new("/uuid".to_owned());
+        uuid.data = Some(vec![DataMap {
+            offset: 8,
+            value: vec![
+                216, 254, 195, 214, 27, 14, 72, 60, 146, 151, 88, 40, 135, 126, 196, 129,
+            ], // C2PA identifier
+        }]);
+        bmff_hash.exclusions_mut().push(uuid);
+        bmff_hash
+    }
 
-        let mut init_stream = std::fs::File::open(init_stream_path).unwrap();
-        let mut segment_stream = std::fs::File::open(segment_stream_path).unwrap();
-        let mut segment_stream10 = std::fs::File::open(segment_stream_path10).unwrap();
-        let mut segment_stream11 = std::fs::File::open(segment_stream_path11).unwrap();
+    fn builder() -> crate::Builder {
+        crate::Builder::from_json(
+            r#"{"assertions": [{"label": "c2pa.actions", "data": {"actions": [{"action": "c2pa.published"}]}}]}"#,
+        )
+        .unwrap()
+    }
 
+    fn signer() -> Box<dyn crate::Signer> {
+        crate::utils::test_signer::test_signer(c2pa_crypto::raw_signature::SigningAlg::Ps256)
+    }
 
-        let mut log = ();
+    /// reads the BMFF hash assertion of a signed asset
+    fn signed_bmff_hash(path: &std::path::Path) -> BmffHash {
+        let reader = crate::Reader::from_file(path).unwrap();
+        let mut bmff_hash: BmffHash = reader
+            .active_manifest()
+            .unwrap()
+            .find_assertion(labels::BMFF_HASH_2)
+            .unwrap();
+        bmff_hash.set_bmff_version(2);
+        bmff_hash
+    }
 
-        let bmff_io = BmffIO::new("mp4");
-        let bmff_handler = bmff_io.get_reader();
+    /// checks the top level box order of the fragment
+    fn assert_position(position: C2paBoxPosition, mut box_infos: Vec<BoxInfoLite>) {
+        box_infos.sort_by_key(|b| b.offset);
+        let order: Vec<_> = box_infos.into_iter().map(|b| b.path).collect();
 
-        let manifest_bytes = bmff_handler.read_cai(&mut init_stream).unwrap();
-        let store = Store::from_jumbf(&manifest_bytes, &mut log).unwrap();
+        let uuid = order.iter().position(|p| p == "uuid").unwrap();
+        match position {
+            C2paBoxPosition::BeforeMoof => assert_eq!(order[uuid + 1], "moof"),
+            C2paBoxPosition::AfterMdat => assert_eq!(order[uuid - 1], "mdat"),
+            C2paBoxPosition::FileStart => assert_eq!(uuid, 0),
+        }
+    }
 
-        // get the bmff hashes
-        let claim = store.provenance_claim().unwrap();
-        for dh_assertion in claim.hash_assertions() {
-            if dh_assertion.label_root() == BmffHash::LABEL {
-                let bmff_hash = BmffHash::from_assertion(dh_assertion).unwrap();
+    #[test]
+    fn rolling_hash_box_position() {
+        for box_position in POSITIONS {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+            let options = FragmentOptions {
+                box_position,
+                ..Default::default()
+            };
 
+            // the first fragment carries no box
+            let mut bmff_hash = bmff_hash();
+            for name in ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"] {
+                bmff_hash.shift_rolling_hash();
                 bmff_hash
-                    .verify_stream_segment(&mut init_stream, &mut segment_stream, None)
+                    .add_rolling_hash_fragment(
+                        "sha256",
+                        bunny("BigBuckBunny_2s_init.mp4"),
+                        bunny(name),
+                        &output,
+                        &options,
+                    )
                     .unwrap();
+            }
 
-                bmff_hash
-                    .verify_stream_segment(&mut init_stream, &mut segment_stream10, None)
-                    .unwrap();
+            let mut signed =
+                std::fs::File::open(temp_dir.path().join("BigBuckBunny_2s10.m4s")).unwrap();
+            let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut signed).unwrap();
+            assert_position(box_position, c2pa_boxes.box_infos);
 
-                bmff_hash
-                    .verify_stream_segment(&mut init_stream, &mut segment_stream11, None)
-                    .unwrap();
-            }
+            let rolling_hash = bmff_hash.rolling_hash().unwrap().rolling_hash().unwrap();
+            bmff_hash
+                .verify_fragment_memory(&mut signed, Some("sha256"), rolling_hash, &None)
+                .unwrap();
         }
     }
+
+    #[test]
+    fn merkle_box_position() {
+        for box_position in POSITIONS {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+            let fragments = vec![
+                bunny("BigBuckBunny_2s1.m4s"),
+                bunny("BigBuckBunny_2s10.m4s"),
+                bunny("BigBuckBunny_2s11.m4s"),
+            ];
+            let options = FragmentOptions {
+                box_position,
+                ..Default::default()
+            };
+
+            let mut bmff_hash = bmff_hash();
+            bmff_hash
+                .add_merkle_for_fragmented(
+                    "sha256",
+                    &bunny("BigBuckBunny_2s_init.mp4"),
+                    &fragments,
+                    &output,
+                    1,
+                    None,
+                    &options,
+                )
+                .unwrap();
+            let merkle_map = &bmff_hash.merkle().unwrap()[0];
+
+            for fragment in &fragments {
+                let signed = temp_dir.path().join(fragment.file_name().unwrap());
+                let mut reader = std::fs::File::open(&signed).unwrap();
+                let c2pa_boxes = read_bmff_c2pa_boxes(&mut reader).unwrap();
+                let bmff_merkle = c2pa_boxes.bmff_merkle[0].clone();
+                assert_position(box_position, c2pa_boxes.box_infos);
+
+                let exclusions = bmff_to_jumbf_exclusions(
+                    &mut reader,
+                    bmff_hash.exclusions(),
+                    bmff_hash.bmff_version() > 1,
+                )
+                .unwrap();
+                let hash =
+                    hash_stream_by_alg("sha256", &mut reader, Some(exclusions), true).unwrap();
+                assert!(merkle_map.check_merkle_tree(
+                    "sha256",
+                    &hash,
+                    bmff_merkle.location,
+                    &bmff_merkle.hashes
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn skip_signed_fragments() {
+        let init = bunny("BigBuckBunny_2s_init.mp4");
+        let fragments = vec![
+            bunny("BigBuckBunny_2s1.m4s"),
+            bunny("BigBuckBunny_2s10.m4s"),
+            bunny("BigBuckBunny_2s11.m4s"),
+            bunny("BigBuckBunny_2s100.m4s"),
+        ];
+
+        // growing window, signed one fragment at a time
+        let incremental = tempfile::tempdir().unwrap();
+        let options = FragmentOptions {
+            skip_signed_fragments: true,
+            ..Default::default()
+        };
+        let mut incremental_hash = bmff_hash();
+        for n in 1..=fragments.len() {
+            incremental_hash
+                .add_merkle_for_fragmented(
+                    "sha256",
+                    &init,
+                    &fragments[..n].to_vec(),
+                    &incremental.path().join("BigBuckBunny_2s_init.mp4"),
+                    1,
+                    None,
+                    &options,
+                )
+                .unwrap();
+        }
+
+        // full rebuild
+        let full = tempfile::tempdir().unwrap();
+        let mut full_hash = bmff_hash();
+        full_hash
+            .add_merkle_for_fragmented(
+                "sha256",
+                &init,
+                &fragments,
+                &full.path().join("BigBuckBunny_2s_init.mp4"),
+                1,
+                None,
+                &FragmentOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(incremental_hash.merkle(), full_hash.merkle());
+        for fragment in &fragments {
+            let name = fragment.file_name().unwrap();
+            assert_eq!(
+                std::fs::read(incremental.path().join(name)).unwrap(),
+                std::fs::read(full.path().join(name)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    /// fragments are verified against the published roots, not the manifest hashes
+    fn verify_fragment_with_roots() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init = bunny("BigBuckBunny_2s_init.mp4");
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let windows = [
+            vec![
+                bunny("BigBuckBunny_2s1.m4s"),
+                bunny("BigBuckBunny_2s10.m4s"),
+            ],
+            vec![
+                bunny("BigBuckBunny_2s11.m4s"),
+                bunny("BigBuckBunny_2s100.m4s"),
+            ],
+        ];
+
+        let mut bmff_hash = bmff_hash();
+        for (local_id, fragments) in (1..).zip(&windows) {
+            bmff_hash
+                .add_merkle_for_fragmented(
+                    "sha256",
+                    &init,
+                    fragments,
+                    &init_output,
+                    local_id,
+                    Some(1),
+                    &FragmentOptions::default(),
+                )
+                .unwrap();
+        }
+        let roots: Vec<Vec<u8>> = bmff_hash
+            .merkle()
+            .unwrap()
+            .iter()
+            .map(|mm| mm.hashes[0].to_vec())
+            .collect();
+        assert_eq!(roots.len(), 2);
+
+        // a spoofed manifest with different root hashes
+        for mm in bmff_hash.merkle.as_mut().unwrap() {
+            mm.hashes = VecByteBuf(vec![ByteBuf::from(vec![0u8; 32])]);
+        }
+
+        let signed = |path: &PathBuf| {
+            std::fs::File::open(temp_dir.path().join(path.file_name().unwrap())).unwrap()
+        };
+        for fragment in windows.iter().flatten() {
+            bmff_hash
+                .verify_fragment_with_roots(&mut signed(fragment), &roots, None)
+                .unwrap();
+        }
+
+        // the roots of the windows swapped
+        let swapped = vec![roots[1].clone(), roots[0].clone()];
+        assert!(matches!(
+            bmff_hash.verify_fragment_with_roots(&mut signed(&windows[0][0]), &swapped, None),
+            Err(Error::HashMismatch(_))
+        ));
+
+        // the second window hasn't been published
+        assert!(matches!(
+            bmff_hash.verify_fragment_with_roots(&mut signed(&windows[1][0]), &roots[..1], None),
+            Err(Error::HashMismatch(_))
+        ));
+    }
+
+    #[test]
+    /// only the fragments of the selected window are verified
+    fn verify_window() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init = bunny("BigBuckBunny_2s_init.mp4");
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let windows = [
+            vec![
+                bunny("BigBuckBunny_2s1.m4s"),
+                bunny("BigBuckBunny_2s10.m4s"),
+            ],
+            vec![
+                bunny("BigBuckBunny_2s11.m4s"),
+                bunny("BigBuckBunny_2s100.m4s"),
+            ],
+        ];
+
+        let mut bmff_hash = bmff_hash();
+        for (local_id, fragments) in (1..).zip(&windows) {
+            bmff_hash
+                .add_merkle_for_fragmented(
+                    "sha256",
+                    &init,
+                    fragments,
+                    &init_output,
+                    local_id,
+                    Some(1),
+                    &FragmentOptions::default(),
+                )
+                .unwrap();
+        }
+        bmff_hash.update_fragmented_inithash(&init).unwrap();
+
+        let signed: Vec<Vec<PathBuf>> = windows
+            .iter()
+            .map(|window| {
+                window
+                    .iter()
+                    .map(|f| temp_dir.path().join(f.file_name().unwrap()))
+                    .collect()
+            })
+            .collect();
+
+        // tamper with the first window, the newest one still verifies
+        let mut data = std::fs::read(&signed[0][1]).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&signed[0][1], data).unwrap();
+
+        let mut init_stream = std::fs::File::open(&init).unwrap();
+        bmff_hash
+            .verify_window(&mut init_stream, &signed[1], 2, 2, None)
+            .unwrap();
+        bmff_hash
+            .verify_window(&mut init_stream, &signed[1][..1], 2, 2, None)
+            .unwrap();
+        assert!(matches!(
+            bmff_hash.verify_window(&mut init_stream, &signed[0], 0, 2, None),
+            Err(Error::HashMismatch(_))
+        ));
+
+        // fragments of another window or out of order
+        assert!(matches!(
+            bmff_hash.verify_window(&mut init_stream, &signed[1], 0, 2, None),
+            Err(Error::HashMismatch(_))
+        ));
+        let swapped = vec![signed[1][1].clone(), signed[1][0].clone()];
+        assert!(matches!(
+            bmff_hash.verify_window(&mut init_stream, &swapped, 2, 2, None),
+            Err(Error::HashMismatch(_))
+        ));
+
+        // not the start of a window or too many fragments
+        assert!(matches!(
+            bmff_hash.verify_window(&mut init_stream, &signed[1], 1, 2, None),
+            Err(Error::BadParam(_))
+        ));
+        assert!(matches!(
+            bmff_hash.verify_window(&mut init_stream, &signed.concat(), 0, 2, None),
+            Err(Error::BadParam(_))
+        ));
+    }
+
+    #[test]
+    fn merkle_stats() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let fragments = vec![
+            bunny("BigBuckBunny_2s1.m4s"),
+            bunny("BigBuckBunny_2s10.m4s"),
+            bunny("BigBuckBunny_2s11.m4s"),
+            bunny("BigBuckBunny_2s100.m4s"),
+        ];
+
+        let stats = bmff_hash()
+            .add_merkle_for_fragmented(
+                "sha256",
+                &bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                &temp_dir.path().join("BigBuckBunny_2s_init.mp4"),
+                1,
+                None,
+                &FragmentOptions::default(),
+            )
+            .unwrap();
+
+        // every fragment stores its sibling and the hash of the other pair
+        assert_eq!(
+            stats,
+            MerkleStats {
+                count: 4,
+                depth: 2,
+                max_proof_len: 2,
+                total_proof_bytes: 4 * 2 * 32,
+            }
+        );
+    }
+
+    #[test]
+    fn verify_stream_segments_report() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init = bunny("BigBuckBunny_2s_init.mp4");
+        let fragments = vec![
+            bunny("BigBuckBunny_2s1.m4s"),
+            bunny("BigBuckBunny_2s10.m4s"),
+            bunny("BigBuckBunny_2s11.m4s"),
+        ];
+
+        let mut bmff_hash = bmff_hash();
+        bmff_hash
+            .add_merkle_for_fragmented(
+                "sha256",
+                &init,
+                &fragments,
+                &temp_dir.path().join("BigBuckBunny_2s_init.mp4"),
+                1,
+                None,
+                &FragmentOptions::default(),
+            )
+            .unwrap();
+        bmff_hash.update_fragmented_inithash(&init).unwrap();
+
+        let mut signed: Vec<_> = fragments
+            .iter()
+            .map(|f| temp_dir.path().join(f.file_name().unwrap()))
+            .collect();
+
+        // tamper with the last byte of the second fragment's mdat
+        let mut data = std::fs::read(&signed[1]).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&signed[1], data).unwrap();
+
+        // unsigned fragment without BmffMerkleMap
+        signed.push(bunny("BigBuckBunny_2s100.m4s"));
+
+        let mut init_stream = std::fs::File::open(&init).unwrap();
+        let report = bmff_hash
+            .verify_stream_segments_report(&mut init_stream, &signed, None)
+            .unwrap();
+
+        assert_eq!(report.len(), 4);
+        assert!(report[0].is_valid());
+        assert!(!report[1].is_valid());
+        assert!(report[2].is_valid());
+        assert!(!report[3].is_valid());
+
+        let locations: Vec<_> = report.iter().map(|r| r.location).collect();
+        assert_eq!(locations, [Some(0), Some(1), Some(2), None]);
+        assert_eq!(report[0].local_id, Some(1));
+        assert_eq!(report[3].path, signed[3]);
+
+        // the fail-fast version stops at the tampered fragment
+        assert!(bmff_hash
+            .verify_stream_segments(&mut init_stream, &signed, None, None)
+            .is_err());
+        assert!(bmff_hash
+            .verify_stream_segments(&mut init_stream, &signed[..1].to_vec(), None, None)
+            .is_ok());
+    }
+
+    #[test]
+    fn locate_tampering() {
+        let names = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s11.m4s",
+        ];
+        let signer = signer();
+        let mut builder = builder();
+        builder.fragment_options.box_digests = true;
+
+        // Merkle tree
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        builder
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+        let bmff_hash = signed_bmff_hash(&init_output);
+        assert_eq!(bmff_hash.box_digests().unwrap().len(), names.len());
+        let cbor = serde_cbor::to_vec(&bmff_hash.box_digests().unwrap()[0]).unwrap();
+        assert!(cbor.windows(7).any(|w| w == b"localId"));
+
+        let outputs: Vec<_> = names.iter().map(|n| temp_dir.path().join(n)).collect();
+        let mut fragment = std::fs::File::open(&outputs[1]).unwrap();
+        assert!(bmff_hash
+            .locate_tampering(&mut fragment)
+            .unwrap()
+            .is_empty());
+
+        // tamper with the last byte of the second fragment's mdat
+        let mut data = std::fs::read(&outputs[1]).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&outputs[1], &data).unwrap();
+        let mdat = read_bmff_fragment_c2pa_boxes(&mut Cursor::new(&data))
+            .unwrap()
+            .box_infos
+            .into_iter()
+            .find(|b| b.path == "mdat")
+            .unwrap();
+
+        let mut init_stream = std::fs::File::open(&init_output).unwrap();
+        let report = bmff_hash
+            .verify_stream_segments_report(&mut init_stream, &outputs, None)
+            .unwrap();
+        assert!(report[0].is_valid());
+        assert_eq!(report[0].tampered_boxes, None);
+        assert!(!report[1].is_valid());
+        assert_eq!(
+            report[1].tampered_boxes,
+            Some(vec![TamperedBox {
+                path: "mdat".to_string(),
+                offset: Some(mdat.offset),
+            }])
+        );
+
+        // rolling hash, the digests of the newest fragment are recorded
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+        let bmff_hash = signed_bmff_hash(&init_output);
+
+        let mut data = std::fs::read(temp_dir.path().join(names[2])).unwrap();
+        let moof = read_bmff_c2pa_boxes(&mut Cursor::new(&data))
+            .map(|b| b.box_infos)
+            .or_else(|_| {
+                C2PABmffBoxesRollingHash::from_reader(&mut Cursor::new(&data)).map(|b| b.box_infos)
+            })
+            .unwrap()
+            .into_iter()
+            .find(|b| b.path == "moof")
+            .unwrap();
+        data[moof.offset as usize + 20] ^= 0xff;
+        let tampered = bmff_hash.locate_tampering(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(
+            tampered,
+            [TamperedBox {
+                path: "moof".to_string(),
+                offset: Some(moof.offset),
+            }]
+        );
+
+        // no digests of earlier fragments
+        for name in &names[..2] {
+            let mut fragment = std::fs::File::open(temp_dir.path().join(name)).unwrap();
+            assert!(bmff_hash.locate_tampering(&mut fragment).is_err());
+        }
+    }
+
+    #[test]
+    /// the algorithm of a sha384 fragment is found although the assertion doesn't record it
+    fn verify_stream_segment_any_alg() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        let mut builder = builder();
+        builder.fragment_options.content_hash_alg = Some("sha384".to_string());
+        builder
+            .sign_live_bmff(
+                signer().as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+        let open = |name: &str| std::fs::File::open(temp_dir.path().join(name)).unwrap();
+
+        // the recorded algorithm is used
+        let mut bmff_hash = signed_bmff_hash(&init_output);
+        assert_eq!(
+            bmff_hash
+                .verify_stream_segment_any_alg(
+                    &mut open("BigBuckBunny_2s_init.mp4"),
+                    &mut open(names[1])
+                )
+                .unwrap(),
+            None
+        );
+
+        // an older producer without algorithm
+        bmff_hash.alg = None;
+        for mm in bmff_hash.merkle.iter_mut().flatten() {
+            mm.alg = None;
+        }
+        assert!(bmff_hash
+            .verify_stream_segment(
+                &mut open("BigBuckBunny_2s_init.mp4"),
+                &mut open(names[1]),
+                None
+            )
+            .is_err());
+        assert_eq!(
+            bmff_hash
+                .verify_stream_segment_any_alg(
+                    &mut open("BigBuckBunny_2s_init.mp4"),
+                    &mut open(names[1])
+                )
+                .unwrap()
+                .as_deref(),
+            Some("sha384")
+        );
+
+        // a tampered fragment matches no algorithm
+        let tampered = temp_dir.path().join(names[0]);
+        let mut data = std::fs::read(&tampered).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&tampered, data).unwrap();
+        assert!(bmff_hash
+            .verify_stream_segment_any_alg(
+                &mut open("BigBuckBunny_2s_init.mp4"),
+                &mut open(names[0])
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn verify_stream_segments_with_options() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init = bunny("BigBuckBunny_2s_init.mp4");
+        let fragments = vec![
+            bunny("BigBuckBunny_2s1.m4s"),
+            bunny("BigBuckBunny_2s10.m4s"),
+            bunny("BigBuckBunny_2s11.m4s"),
+        ];
+
+        let mut bmff_hash = bmff_hash();
+        bmff_hash
+            .add_merkle_for_fragmented(
+                "sha256",
+                &init,
+                &fragments,
+                &temp_dir.path().join("BigBuckBunny_2s_init.mp4"),
+                1,
+                None,
+                &FragmentOptions::default(),
+            )
+            .unwrap();
+        bmff_hash.update_fragmented_inithash(&init).unwrap();
+
+        let signed: Vec<_> = fragments
+            .iter()
+            .map(|f| temp_dir.path().join(f.file_name().unwrap()))
+            .collect();
+        let mut init_stream = std::fs::File::open(&init).unwrap();
+        let mut verify = |options: VerifyOptions| {
+            bmff_hash.verify_stream_segments_with_options(&mut init_stream, &signed, None, &options)
+        };
+        let options = VerifyOptions {
+            continue_on_error: true,
+            max_errors: 10,
+        };
+        assert!(verify(options).unwrap().failures.is_empty());
+
+        // corrupt the first and the last fragment
+        for path in [&signed[0], &signed[2]] {
+            let mut data = std::fs::read(path).unwrap();
+            *data.last_mut().unwrap() ^= 0xff;
+            std::fs::write(path, data).unwrap();
+        }
+
+        let report = verify(options).unwrap();
+        assert!(!report.truncated);
+        let paths: Vec<_> = report.failures.iter().map(|f| &f.path).collect();
+        assert_eq!(paths, [&signed[0], &signed[2]]);
+        assert!(report
+            .failures
+            .iter()
+            .all(|f| matches!(f.result, Err(Error::HashMismatch(_)))));
+
+        // stops once there are more failures than allowed, the ones found are kept
+        let report = verify(VerifyOptions {
+            continue_on_error: true,
+            max_errors: 1,
+        })
+        .unwrap();
+        assert!(report.truncated);
+        let paths: Vec<_> = report.failures.iter().map(|f| &f.path).collect();
+        assert_eq!(paths, [&signed[0], &signed[2]]);
+
+        let report = verify(VerifyOptions::default()).unwrap();
+        assert!(report.truncated);
+        let paths: Vec<_> = report.failures.iter().map(|f| &f.path).collect();
+        assert_eq!(paths, [&signed[0]]);
+    }
+
+    // stands in for a reader fetching HTTP ranges on demand: every read which doesn't
+    // continue the previous one needs a new range request
+    struct RangeReader {
+        inner: Cursor<Vec<u8>>,
+        next: Option<u64>,
+        requests: usize,
+        fetched: u64,
+    }
+
+    impl RangeReader {
+        fn new(data: Vec<u8>) -> Self {
+            Self {
+                inner: Cursor::new(data),
+                next: None,
+                requests: 0,
+                fetched: 0,
+            }
+        }
+    }
+
+    impl std::io::Read for RangeReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let pos = self.inner.position();
+            let len = self.inner.read(buf)?;
+            if len > 0 {
+                if self.next != Some(pos) {
+                    self.requests += 1;
+                }
+                self.next = Some(pos + len as u64);
+                self.fetched += len as u64;
+            }
+            Ok(len)
+        }
+    }
+
+    impl std::io::Seek for RangeReader {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn verify_range_reader() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        builder()
+            .sign_live_bmff(
+                signer().as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+        let bmff_hash = signed_bmff_hash(&init_output);
+
+        for name in names {
+            let data = std::fs::read(temp_dir.path().join(name)).unwrap();
+            let len = data.len() as u64;
+            let mut fragment = RangeReader::new(data);
+            bmff_hash
+                .verify_stream_segment(
+                    &mut std::fs::File::open(&init_output).unwrap(),
+                    &mut fragment,
+                    None,
+                )
+                .unwrap();
+
+            // the box headers plus a single pass over the fragment
+            assert!(fragment.fetched < len + 1024);
+            assert!(fragment.requests <= 32);
+        }
+    }
+
+    #[test]
+    fn verify_stream_segments_log() {
+        use c2pa_status_tracker::ErrorBehavior;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init = bunny("BigBuckBunny_2s_init.mp4");
+        let fragments = vec![
+            bunny("BigBuckBunny_2s1.m4s"),
+            bunny("BigBuckBunny_2s10.m4s"),
+            bunny("BigBuckBunny_2s11.m4s"),
+        ];
+
+        let mut bmff_hash = bmff_hash();
+        bmff_hash
+            .add_merkle_for_fragmented(
+                "sha256",
+                &init,
+                &fragments,
+                &temp_dir.path().join("BigBuckBunny_2s_init.mp4"),
+                1,
+                None,
+                &FragmentOptions::default(),
+            )
+            .unwrap();
+        bmff_hash.update_fragmented_inithash(&init).unwrap();
+
+        let signed: Vec<_> = fragments
+            .iter()
+            .map(|f| temp_dir.path().join(f.file_name().unwrap()))
+            .collect();
+        let mut init_stream = std::fs::File::open(&init).unwrap();
+
+        // one entry per fragment
+        let mut log = StatusTracker::with_error_behavior(ErrorBehavior::ContinueWhenPossible);
+        bmff_hash
+            .verify_stream_segments(&mut init_stream, &signed, None, Some(&mut log))
+            .unwrap();
+        assert_eq!(log.logged_items().len(), 3);
+        assert!(log
+            .logged_items()
+            .iter()
+            .all(|i| i.validation_status.as_deref()
+                == Some(validation_status::ASSERTION_BMFFHASH_MATCH)));
+        assert_eq!(log.logged_items()[2].label, signed[2].to_string_lossy());
+
+        // tamper with the last byte of the second fragment's mdat
+        let mut data = std::fs::read(&signed[1]).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&signed[1], data).unwrap();
+
+        let mut log = StatusTracker::with_error_behavior(ErrorBehavior::ContinueWhenPossible);
+        bmff_hash
+            .verify_stream_segments(&mut init_stream, &signed, None, Some(&mut log))
+            .unwrap();
+        let statuses: Vec<_> = log
+            .logged_items()
+            .iter()
+            .map(|i| i.validation_status.as_deref())
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                Some(validation_status::ASSERTION_BMFFHASH_MATCH),
+                Some(validation_status::ASSERTION_BMFFHASH_MISMATCH),
+                Some(validation_status::ASSERTION_BMFFHASH_MATCH),
+            ]
+        );
+        assert_eq!(log.logged_items()[1].description, "fragment 1 hash invalid");
+
+        // stops at the tampered fragment
+        let mut log = StatusTracker::with_error_behavior(ErrorBehavior::StopOnFirstError);
+        assert!(bmff_hash
+            .verify_stream_segments(&mut init_stream, &signed, None, Some(&mut log))
+            .is_err());
+        assert_eq!(log.logged_items().len(), 2);
+    }
+
+    #[test]
+    fn moof_first_fragments() {
+        let no_styp = |name: &str| fixture_path(&format!("bunny/no_styp/{name}"));
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let fragments: Vec<_> = names.iter().map(|n| no_styp(n)).collect();
+        let signer = signer();
+
+        // a fragment starting with its moof is fragmented content
+        let c2pa_boxes =
+            read_bmff_c2pa_boxes(&mut std::fs::File::open(&fragments[0]).unwrap()).unwrap();
+        assert_eq!(c2pa_boxes.box_infos[0].path, "moof");
+        let moof_chunks = BmffHash::split_fragment_boxes(&c2pa_boxes.box_infos);
+        assert_eq!(moof_chunks.len(), 1);
+        assert_eq!(moof_chunks[0].len(), 2);
+
+        // Merkle signed, C2PA box after the mdat so the moof stays first
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let mut live_builder = builder();
+        live_builder.fragment_options.box_position = C2paBoxPosition::AfterMdat;
+        live_builder
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+
+        let outputs: Vec<_> = names.iter().map(|n| temp_dir.path().join(n)).collect();
+        let reader = crate::Reader::from_fragmented_files(&init_output, &outputs).unwrap();
+        assert_ne!(reader.validation_state(), crate::ValidationState::Invalid);
+
+        // merged into a single asset
+        let output = temp_dir.path().join("merged.mp4");
+        builder()
+            .sign_merged_fragments(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                output.clone(),
+            )
+            .unwrap();
+
+        let reader = crate::Reader::from_file(&output).unwrap();
+        assert_ne!(reader.validation_state(), crate::ValidationState::Invalid);
+        let bmff_hash = signed_bmff_hash(&output);
+        assert_eq!(bmff_hash.merkle().unwrap()[0].count, 2);
+    }
+
+    #[test]
+    fn verify_proof_to_root() {
+        let leaves: Vec<_> = (0u8..5)
+            .map(|i| crate::utils::merkle::MerkleNode(vec![i; 32]))
+            .collect();
+        let m_tree = C2PAMerkleTree::from_leaves(leaves.clone(), "sha256", false);
+        let root = m_tree.get_root().unwrap().clone();
+        let max_proofs = C2PAMerkleTree::to_layout(leaves.len()).len() - 1;
+
+        // the manifest stores a root which isn't the one supplied out of band
+        let mm = MerkleMap {
+            unique_id: 1,
+            local_id: 1,
+            count: leaves.len() as u32,
+            alg: Some("sha256".to_owned()),
+            init_hash: None,
+            hashes: VecByteBuf(vec![ByteBuf::from(vec![0u8; 32])]),
+        };
+
+        for (location, leaf) in leaves.iter().enumerate() {
+            let proof = m_tree.get_proof_by_index(location, max_proofs).unwrap();
+            let proof = Some(VecByteBuf(proof.into_iter().map(ByteBuf::from).collect()));
+            let location = location as u32;
+
+            assert!(!mm.check_merkle_tree("sha256", &leaf.0, location, &proof));
+            assert!(mm.verify_proof_to_root("sha256", &leaf.0, location, &proof, &root));
+            assert!(!mm.verify_proof_to_root("sha256", &leaf.0, location, &proof, &[0u8; 32]));
+
+            // wrong leaf or location
+            assert!(!mm.verify_proof_to_root("sha256", &[9u8; 32], location, &proof, &root));
+            assert!(!mm.verify_proof_to_root("sha256", &leaf.0, location + 5, &proof, &root));
+        }
+    }
+
+    #[test]
+    /// in an odd sized tree the edge leaves have shorter proofs, every proof must have
+    /// exactly the length of its position
+    fn proof_length() {
+        let leaves: Vec<_> = (0u8..7)
+            .map(|i| crate::utils::merkle::MerkleNode(vec![i; 32]))
+            .collect();
+        let m_tree = C2PAMerkleTree::from_leaves(leaves.clone(), "sha256", false);
+        let to_proof =
+            |proof: Vec<Vec<u8>>| Some(VecByteBuf(proof.into_iter().map(ByteBuf::from).collect()));
+
+        // the manifest stores the root and the row below it
+        for max_proofs in [3, 2] {
+            let mm = MerkleMap {
+                unique_id: 1,
+                local_id: 1,
+                count: leaves.len() as u32,
+                alg: Some("sha256".to_owned()),
+                init_hash: None,
+                hashes: VecByteBuf(
+                    m_tree.layers[max_proofs]
+                        .iter()
+                        .map(|node| ByteBuf::from(node.0.clone()))
+                        .collect(),
+                ),
+            };
+
+            let mut lengths = Vec::new();
+            for (location, leaf) in leaves.iter().enumerate() {
+                let proof = m_tree.get_proof_by_index(location, max_proofs).unwrap();
+                lengths.push(proof.len());
+                let location = location as u32;
+
+                assert!(mm.check_merkle_tree(
+                    "sha256",
+                    &leaf.0,
+                    location,
+                    &to_proof(proof.clone())
+                ));
+
+                // truncated and extended proofs
+                if !proof.is_empty() {
+                    let truncated = proof[..proof.len() - 1].to_vec();
+                    assert!(!mm.check_merkle_tree(
+                        "sha256",
+                        &leaf.0,
+                        location,
+                        &to_proof(truncated)
+                    ));
+                }
+                let mut extended = proof.clone();
+                extended.push(vec![0u8; 32]);
+                assert!(!mm.check_merkle_tree("sha256", &leaf.0, location, &to_proof(extended)));
+            }
+
+            // the last leaf has no sibling on the first level
+            assert_eq!(lengths[6], max_proofs - 1);
+            assert!(lengths[..6].iter().all(|len| *len == max_proofs));
+        }
+    }
+
+    #[test]
+    /// the climb of a tampered proof diverges from the tree on the level of the
+    /// tampered hash
+    fn debug_climb() {
+        let leaves: Vec<_> = (0u8..7)
+            .map(|i| crate::utils::merkle::MerkleNode(vec![i; 32]))
+            .collect();
+        let m_tree = C2PAMerkleTree::from_leaves(leaves.clone(), "sha256", false);
+        let mm = MerkleMap {
+            unique_id: 1,
+            local_id: 1,
+            count: leaves.len() as u32,
+            alg: Some("sha256".to_owned()),
+            init_hash: None,
+            hashes: VecByteBuf(vec![ByteBuf::from(m_tree.get_root().unwrap().clone())]),
+        };
+        let location = 2;
+        let proof = m_tree.get_proof_by_index(location, 3).unwrap();
+        let to_proof =
+            |proof: Vec<Vec<u8>>| Some(VecByteBuf(proof.into_iter().map(ByteBuf::from).collect()));
+        // index of each level reached from the leaf
+        let indices = [2, 1, 0, 0];
+
+        let levels = mm.debug_climb(
+            "sha256",
+            &leaves[location].0,
+            location as u32,
+            &to_proof(proof.clone()),
+        );
+        assert_eq!(levels.len(), 4);
+        for climbed in &levels {
+            assert_eq!(
+                climbed.computed,
+                m_tree.layers[climbed.level][indices[climbed.level]].0
+            );
+            assert_eq!(climbed.matched, (climbed.level == 3).then_some(true));
+        }
+        assert_eq!(levels[3].expected.as_ref(), m_tree.get_root());
+        assert!(mm.check_merkle_tree(
+            "sha256",
+            &leaves[location].0,
+            location as u32,
+            &to_proof(proof.clone())
+        ));
+
+        // tampered hash of the second level
+        let mut tampered = proof.clone();
+        tampered[1][0] ^= 0xff;
+        let levels = mm.debug_climb(
+            "sha256",
+            &leaves[location].0,
+            location as u32,
+            &to_proof(tampered),
+        );
+        let diverged: Vec<_> = levels
+            .iter()
+            .filter(|l| l.computed != m_tree.layers[l.level][indices[l.level]].0)
+            .map(|l| l.level)
+            .collect();
+        assert_eq!(diverged, [2, 3]);
+        assert_eq!(levels[3].matched, Some(false));
+        assert_eq!(levels[3].expected.as_ref(), m_tree.get_root());
+
+        // the climb stops at the missing hash
+        let levels = mm.debug_climb(
+            "sha256",
+            &leaves[location].0,
+            location as u32,
+            &to_proof(proof[..1].to_vec()),
+        );
+        assert_eq!(levels.len(), 2);
+        assert!(levels
+            .iter()
+            .all(|l| l.matched.is_none() && l.expected.is_none()));
+
+        // a proof with an unused hash
+        let mut extended = proof;
+        extended.push(vec![0u8; 32]);
+        let levels = mm.debug_climb(
+            "sha256",
+            &leaves[location].0,
+            location as u32,
+            &to_proof(extended),
+        );
+        assert_eq!(levels[3].matched, Some(false));
+    }
+
+    #[test]
+    fn merged_fragments_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = temp_dir.path().join("merged.mp4");
+        let fragments: Vec<_> = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s11.m4s",
+        ]
+        .iter()
+        .map(|f| bunny(f))
+        .collect();
+
+        let signer = signer();
+        builder()
+            .sign_merged_fragments(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                output.clone(),
+            )
+            .unwrap();
+
+        let reader = crate::Reader::from_file(&output).unwrap();
+        assert_ne!(reader.validation_state(), crate::ValidationState::Invalid);
+
+        let mut bmff_hash: BmffHash = reader
+            .active_manifest()
+            .unwrap()
+            .find_assertion(labels::BMFF_HASH_2)
+            .unwrap();
+        bmff_hash.set_bmff_version(2);
+        assert_eq!(bmff_hash.merkle().unwrap()[0].count, 3);
+
+        let mut data = std::fs::read(&output).unwrap();
+        bmff_hash
+            .verify_stream_hash(&mut Cursor::new(&data), None, None)
+            .unwrap();
+
+        // tampering with the media of the last fragment is detected
+        let c2pa_boxes = read_bmff_c2pa_boxes(&mut Cursor::new(&data)).unwrap();
+        let last_mdat = c2pa_boxes
+            .box_infos
+            .iter()
+            .rfind(|b| b.path == "mdat")
+            .unwrap();
+        data[(last_mdat.offset + last_mdat.size - 1) as usize] ^= 0xff;
+        assert!(matches!(
+            bmff_hash.verify_stream_hash(&mut Cursor::new(&data), None, None),
+            Err(Error::HashMismatch(_))
+        ));
+        let reader = crate::Reader::from_stream("mp4", Cursor::new(data)).unwrap();
+        assert_eq!(reader.validation_state(), crate::ValidationState::Invalid);
+    }
+
+    #[test]
+    fn fragment_count_layouts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        let signer = signer();
+
+        // single file containing all fragments
+        let merged = temp_dir.path().join("merged.mp4");
+        builder()
+            .sign_merged_fragments(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                merged.clone(),
+            )
+            .unwrap();
+        let bmff_hash = signed_bmff_hash(&merged);
+        let data = std::fs::read(&merged).unwrap();
+        bmff_hash
+            .verify_stream_hash(&mut Cursor::new(&data), None, None)
+            .unwrap();
+
+        // which is missing its last fragment
+        let c2pa_boxes = read_bmff_c2pa_boxes(&mut Cursor::new(&data)).unwrap();
+        let last_moof = c2pa_boxes
+            .box_infos
+            .iter()
+            .rfind(|b| b.path == "moof")
+            .unwrap();
+        let truncated = &data[..last_moof.offset as usize];
+        assert!(matches!(
+            bmff_hash.verify_stream_hash(&mut Cursor::new(truncated), None, None),
+            Err(Error::HashMismatch(msg)) if msg == "Incorrect number of fragments hashes"
+        ));
+
+        // single fragment in a multi-file set
+        let init_output = temp_dir.path().join("live/BigBuckBunny_2s_init.mp4");
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+        let bmff_hash = signed_bmff_hash(&init_output);
+        assert_eq!(bmff_hash.merkle().unwrap()[0].count, 2);
+        let fragment = temp_dir.path().join("live").join(names[1]);
+
+        assert!(matches!(
+            bmff_hash.verify_stream_hash(&mut std::fs::File::open(&fragment).unwrap(), None, None),
+            Err(Error::BadParam(_))
+        ));
+        bmff_hash
+            .verify_stream_segment(
+                &mut std::fs::File::open(&init_output).unwrap(),
+                &mut std::fs::File::open(&fragment).unwrap(),
+                None,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn stream_digest() {
+        let init = bunny("BigBuckBunny_2s_init.mp4");
+        let fragments = vec![
+            bunny("BigBuckBunny_2s1.m4s"),
+            bunny("BigBuckBunny_2s10.m4s"),
+        ];
+        let digest = |fragments: &[std::path::PathBuf], alg: &str| {
+            BmffHash::stream_digest(&mut std::fs::File::open(&init).unwrap(), fragments, alg)
+        };
+
+        let expected = digest(&fragments, "sha256").unwrap();
+        assert_eq!(expected.len(), 32);
+        assert_eq!(digest(&fragments, "sha256").unwrap(), expected);
+        assert_eq!(digest(&fragments, "sha512").unwrap().len(), 64);
+
+        // reordering the fragments changes the digest
+        let reordered: Vec<_> = fragments.iter().rev().cloned().collect();
+        assert_ne!(digest(&reordered, "sha256").unwrap(), expected);
+
+        // as does dropping one
+        assert_ne!(digest(&fragments[..1], "sha256").unwrap(), expected);
+
+        assert!(matches!(
+            digest(&fragments, "md5"),
+            Err(Error::UnsupportedType)
+        ));
+    }
+
+    #[test]
+    fn finalize_init() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s11.m4s",
+        ];
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        let signer = signer();
+
+        // an unsigned init can't be finalized
+        let unsigned = temp_dir.path().join("unsigned/BigBuckBunny_2s_init.mp4");
+        std::fs::create_dir_all(unsigned.parent().unwrap()).unwrap();
+        std::fs::copy(bunny("BigBuckBunny_2s_init.mp4"), &unsigned).unwrap();
+        assert!(matches!(
+            builder().finalize_init(signer.as_ref(), &unsigned),
+            Err(Error::BadParam(_))
+        ));
+
+        // sign in windows of 2 as the fragments arrive
+        let mut live_builder = builder();
+        for count in 1..=fragments.len() {
+            live_builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &fragments[..count].to_vec(),
+                    init_output.clone(),
+                    Some(2),
+                )
+                .unwrap();
+        }
+        let signed = signed_bmff_hash(&init_output);
+
+        live_builder
+            .finalize_init(signer.as_ref(), &init_output)
+            .unwrap();
+
+        // the init holds the trees of both windows
+        let finalized = signed_bmff_hash(&init_output);
+        let mm = finalized.merkle().unwrap();
+        assert_eq!(mm.len(), 2);
+        assert_eq!(mm.iter().map(|m| m.count).collect::<Vec<_>>(), [2, 1]);
+        assert_eq!(finalized.merkle(), signed.merkle());
+
+        let outputs: Vec<_> = names.iter().map(|n| temp_dir.path().join(n)).collect();
+        let reader = crate::Reader::from_fragmented_files(&init_output, &outputs).unwrap();
+        assert_ne!(reader.validation_state(), crate::ValidationState::Invalid);
+        finalized
+            .verify_stream_segments(
+                &mut std::fs::File::open(&init_output).unwrap(),
+                &outputs,
+                None,
+                None,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn verify_init() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let fragments = vec![
+            bunny("BigBuckBunny_2s1.m4s"),
+            bunny("BigBuckBunny_2s10.m4s"),
+        ];
+        let signer = signer();
+
+        let mut live_builder = builder();
+        live_builder
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+        live_builder
+            .finalize_init(signer.as_ref(), &init_output)
+            .unwrap();
+
+        // the finalized init verifies without its fragments
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let init = std::fs::read(&init_output).unwrap();
+        bmff_hash.verify_init(&mut Cursor::new(&init)).unwrap();
+
+        // a change to the moov is detected
+        let mvhd = init.windows(4).position(|w| w == b"mvhd").unwrap();
+        let mut tampered = init.clone();
+        tampered[mvhd + 8] ^= 0xff;
+        assert!(matches!(
+            bmff_hash.verify_init(&mut Cursor::new(&tampered)),
+            Err(Error::HashMismatch(_))
+        ));
+
+        // as is an init hash which was never set
+        let mut placeholder = signed_bmff_hash(&init_output);
+        for mm in placeholder.merkle.iter_mut().flatten() {
+            mm.init_hash = Some(ByteBuf::from(vec![0; 32]));
+        }
+        assert!(matches!(
+            placeholder.verify_init(&mut Cursor::new(&init)),
+            Err(Error::HashMismatch(_))
+        ));
+
+        // without an init hash there is nothing to verify against
+        let mut missing = bmff_hash;
+        for mm in missing.merkle.iter_mut().flatten() {
+            mm.init_hash = None;
+        }
+        assert!(matches!(
+            missing.verify_init(&mut Cursor::new(&init)),
+            Err(Error::BadParam(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// a fragment delivered through a FIFO is buffered before it's signed
+    fn sign_from_fifo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pipes = temp_dir.path().join("pipes");
+        std::fs::create_dir(&pipes).unwrap();
+        let fifo = pipes.join("BigBuckBunny_2s1.m4s");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        // the encoder writes the fragment once, blocking until it's read
+        let writer = {
+            let fifo = fifo.clone();
+            std::thread::spawn(move || {
+                let fragment = std::fs::read(bunny("BigBuckBunny_2s1.m4s")).unwrap();
+                std::fs::write(fifo, fragment).unwrap();
+            })
+        };
+
+        let init_output = temp_dir.path().join("live/BigBuckBunny_2s_init.mp4");
+        builder()
+            .sign_live_bmff(
+                signer().as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &vec![fifo],
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+        writer.join().unwrap();
+
+        let signed = vec![temp_dir.path().join("live/BigBuckBunny_2s1.m4s")];
+        let reader = crate::Reader::from_fragmented_files(&init_output, &signed).unwrap();
+        assert_eq!(reader.validation_status(), None);
+    }
+
+    #[test]
+    fn effective_algs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let signer = signer();
+
+        // Merkle tree over the moof ranges
+        let merged = temp_dir.path().join("merged.mp4");
+        builder()
+            .sign_merged_fragments(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &[bunny("BigBuckBunny_2s1.m4s")],
+                merged.clone(),
+            )
+            .unwrap();
+        assert_eq!(
+            signed_bmff_hash(&merged).effective_algs(),
+            vec![(1, "sha256".to_string())]
+        );
+
+        // rolling hash
+        let init_output = temp_dir
+            .path()
+            .join("live")
+            .join("BigBuckBunny_2s_init.mp4");
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &vec![bunny("BigBuckBunny_2s1.m4s")],
+                init_output.clone(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            signed_bmff_hash(&init_output).effective_algs(),
+            vec![(0, "sha256".to_string())]
+        );
+
+        // maps without an algorithm use the top-level one
+        let mut bmff_hash = BmffHash::new("jumbf manifest", "sha384", None);
+        bmff_hash.set_merkle(vec![
+            MerkleMap {
+                unique_id: 1,
+                local_id: 1,
+                count: 1,
+                alg: None,
+                init_hash: None,
+                hashes: VecByteBuf(Vec::new()),
+            },
+            MerkleMap {
+                unique_id: 1,
+                local_id: 2,
+                count: 1,
+                alg: Some("sha512".to_owned()),
+                init_hash: None,
+                hashes: VecByteBuf(Vec::new()),
+            },
+        ]);
+        assert_eq!(
+            bmff_hash.effective_algs(),
+            vec![(1, "sha384".to_string()), (2, "sha512".to_string())]
+        );
+    }
+
+    #[test]
+    fn rolling_hash_verifier() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s11.m4s",
+        ];
+
+        let signer = signer();
+        let mut builder = builder();
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let signed = |name: &str| std::fs::File::open(temp_dir.path().join(name)).unwrap();
+
+        // fed one fragment at a time
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        for name in names {
+            verifier.push_fragment(&mut signed(name)).unwrap();
+        }
+        assert_eq!(verifier.count(), 3);
+        verifier.finalize().unwrap();
+
+        // a missing fragment breaks the chain
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        verifier.push_fragment(&mut signed(names[0])).unwrap();
+        assert!(matches!(
+            verifier.push_fragment(&mut signed(names[2])),
+            Err(Error::HashMismatch(_))
+        ));
+
+        // and an incomplete stream doesn't match the manifest
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        verifier.push_fragment(&mut signed(names[0])).unwrap();
+        verifier.push_fragment(&mut signed(names[1])).unwrap();
+        assert!(verifier.finalize().is_err());
+    }
+
+    #[test]
+    /// the last fragment verifies against the previous hash without the rest of the chain
+    fn verify_tip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s11.m4s",
+        ];
+
+        let signer = signer();
+        let mut builder = builder();
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let signed = |name: &str| std::fs::File::open(temp_dir.path().join(name)).unwrap();
+
+        // the trusted hash the tip is anchored to
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        verifier.push_fragment(&mut signed(names[0])).unwrap();
+        verifier.push_fragment(&mut signed(names[1])).unwrap();
+        let previous_hash = verifier.rolling_hash().unwrap().to_vec();
+
+        bmff_hash
+            .verify_tip(&mut signed(names[2]), &previous_hash)
+            .unwrap();
+
+        // the tip must be anchored to the previous hash
+        let err = bmff_hash
+            .verify_tip(&mut signed(names[2]), &[0u8; 32])
+            .unwrap_err();
+        assert!(err.to_string().contains("Anchor Point does not match"));
+        assert!(matches!(
+            bmff_hash.verify_tip(&mut signed(names[1]), &previous_hash),
+            Err(Error::HashMismatch(_))
+        ));
+
+        // a tampered tip doesn't result in the rolling hash
+        let mut tampered = std::fs::read(temp_dir.path().join(names[2])).unwrap();
+        let mdat = tampered.windows(4).position(|w| w == b"mdat").unwrap();
+        tampered[mdat + 100] ^= 0xff;
+        let err = bmff_hash
+            .verify_tip(&mut std::io::Cursor::new(tampered), &previous_hash)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Fragment Hash does not match Rolling Hash"));
+    }
+
+    #[test]
+    /// a tree over precomputed hashes matches the one hashed from the fragments
+    fn fragment_hashes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let fragments: Vec<_> = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s11.m4s",
+            "BigBuckBunny_2s100.m4s",
+            "BigBuckBunny_2s101.m4s",
+        ]
+        .iter()
+        .map(|name| bunny(name))
+        .collect();
+        let options = FragmentOptions {
+            salt: Some(vec![7u8; 16]),
+            ..Default::default()
+        };
+
+        let mut from_fragments = bmff_hash();
+        from_fragments
+            .add_merkle_for_fragmented(
+                "sha256",
+                &bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                &temp_dir.path().join("BigBuckBunny_2s_init.mp4"),
+                1,
+                None,
+                &options,
+            )
+            .unwrap();
+
+        // the hashes of the fragments as signed, minus the exclusions
+        let signed: Vec<_> = fragments
+            .iter()
+            .map(|path| std::fs::read(temp_dir.path().join(path.file_name().unwrap())).unwrap())
+            .collect();
+        let hashes: Vec<_> = signed
+            .iter()
+            .map(|fragment| {
+                let mut fragment_stream = std::io::Cursor::new(fragment);
+                let exclusions = bmff_to_jumbf_exclusions(
+                    &mut fragment_stream,
+                    &from_fragments.exclusions,
+                    from_fragments.bmff_version > 1,
+                )
+                .unwrap();
+                hash_stream_by_alg("sha256", &mut fragment_stream, Some(exclusions), true).unwrap()
+            })
+            .collect();
+
+        let mut from_hashes = bmff_hash();
+        let merkle_maps = from_hashes
+            .add_merkle_for_fragment_hashes("sha256", &hashes, 1, None, &options)
+            .unwrap();
+        assert_eq!(from_hashes.merkle(), from_fragments.merkle());
+        for (fragment, mm) in signed.iter().zip(&merkle_maps) {
+            let c2pa_boxes =
+                read_bmff_fragment_c2pa_boxes(&mut std::io::Cursor::new(fragment)).unwrap();
+
+            // the box count is left to the caller of the hashes API
+            let mut embedded = c2pa_boxes.bmff_merkle[0].clone();
+            assert_eq!(embedded.box_count, Some(c2pa_boxes.box_infos.len() as u32));
+            embedded.box_count = None;
+            assert_eq!(&embedded, mm);
+        }
+
+        // the hashes must match the algorithm
+        let mut short = hashes.clone();
+        short[3].pop();
+        let err = bmff_hash()
+            .add_merkle_for_fragment_hashes("sha256", &short, 1, None, &options)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("hash of fragment 3 is not a sha256 hash"));
+        assert!(bmff_hash()
+            .add_merkle_for_fragment_hashes("sha384", &hashes, 1, None, &options)
+            .is_err());
+        assert!(bmff_hash()
+            .add_merkle_for_fragment_hashes("sha256", &[], 1, None, &options)
+            .is_err());
+    }
+
+    #[test]
+    /// fragments signed concurrently against the same init output share one complete copy
+    fn rolling_hash_concurrent_init_copy() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s11.m4s",
+            "BigBuckBunny_2s100.m4s",
+        ];
+
+        let barrier = std::sync::Barrier::new(names.len());
+        std::thread::scope(|scope| {
+            for name in names {
+                let (barrier, init_output) = (&barrier, &init_output);
+                scope.spawn(move || {
+                    let mut bmff_hash = bmff_hash();
+                    barrier.wait();
+                    bmff_hash
+                        .add_rolling_hash_fragment(
+                            "sha256",
+                            bunny("BigBuckBunny_2s_init.mp4"),
+                            bunny(name),
+                            init_output,
+                            &FragmentOptions::default(),
+                        )
+                        .unwrap();
+                });
+            }
+        });
+
+        assert_eq!(
+            std::fs::read(&init_output).unwrap(),
+            std::fs::read(bunny("BigBuckBunny_2s_init.mp4")).unwrap()
+        );
+        // the init and the signed fragments, no temporary copies
+        assert_eq!(
+            std::fs::read_dir(temp_dir.path()).unwrap().count(),
+            names.len() + 1
+        );
+    }
+
+    #[test]
+    /// a rolling hash of a version 1 assertion is verified with version 1 exclusions
+    fn rolling_hash_v1() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+
+        let mut bmff_hash = bmff_hash();
+        bmff_hash.set_bmff_version(1);
+        for name in names {
+            bmff_hash.shift_rolling_hash();
+            bmff_hash
+                .add_rolling_hash_fragment(
+                    "sha256",
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    bunny(name),
+                    temp_dir.path().join("BigBuckBunny_2s_init.mp4"),
+                    &FragmentOptions::default(),
+                )
+                .unwrap();
+        }
+        let signed = |name: &str| std::fs::File::open(temp_dir.path().join(name)).unwrap();
+
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        for name in names {
+            verifier.push_fragment(&mut signed(name)).unwrap();
+        }
+        verifier.finalize().unwrap();
+
+        let rh = bmff_hash.rolling_hash().unwrap();
+        bmff_hash
+            .verify_fragment_memory(
+                &mut signed(names[1]),
+                Some("sha256"),
+                rh.rolling_hash().unwrap(),
+                &rh.previous_hash().cloned(),
+            )
+            .unwrap();
+
+        // version 2 exclusions hash the top level box offsets as well
+        bmff_hash.set_bmff_version(2);
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        verifier.push_fragment(&mut signed(names[0])).unwrap();
+        assert!(matches!(
+            verifier.push_fragment(&mut signed(names[1])),
+            Err(Error::HashMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn next_anchor_after() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+
+        let signer = signer();
+        let mut builder = builder();
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let rolling_hash = bmff_hash.rolling_hash().unwrap();
+        let signed = |name: &str| std::fs::File::open(temp_dir.path().join(name)).unwrap();
+
+        // the anchor after the 1st fragment is the anchor point of the 2nd
+        let anchor = rolling_hash
+            .next_anchor_after(&mut signed(names[0]))
+            .unwrap();
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut signed(names[1])).unwrap();
+        assert_eq!(
+            c2pa_boxes.rolling_hashes[0].anchor_point.as_deref(),
+            Some(&anchor)
+        );
+
+        // a client joining with it verifies the 2nd fragment
+        bmff_hash
+            .verify_fragment_memory(
+                &mut signed(names[1]),
+                Some("sha256"),
+                rolling_hash.rolling_hash().unwrap(),
+                &Some(anchor),
+            )
+            .unwrap();
+
+        // and the anchor after the last fragment is the stream's rolling hash
+        let last = rolling_hash
+            .next_anchor_after(&mut signed(names[1]))
+            .unwrap();
+        assert_eq!(Some(&last), rolling_hash.rolling_hash());
+    }
+
+    #[test]
+    /// the first fragment of a rolling hash stream carries no anchor box, every following
+    /// one does, and the verification expects exactly that
+    fn first_fragment_anchor_box() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let first_init = temp_dir.path().join("first_init.mp4");
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+
+        let signer = signer();
+        let mut builder = builder();
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+            if !first_init.exists() {
+                std::fs::copy(&init_output, &first_init).unwrap();
+            }
+        }
+        let open = |path: &std::path::Path| std::fs::File::open(path).unwrap();
+        let signed = |name: &str| open(&temp_dir.path().join(name));
+        let first = signed_bmff_hash(&first_init);
+        let last = signed_bmff_hash(&init_output);
+        let first_rh = first.rolling_hash().unwrap().rolling_hash().unwrap();
+
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut signed(names[0])).unwrap();
+        assert!(c2pa_boxes.rolling_hashes.is_empty());
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut signed(names[1])).unwrap();
+        assert_eq!(
+            c2pa_boxes.rolling_hashes[0].anchor_point.as_deref(),
+            Some(first_rh)
+        );
+
+        // each fragment verifies against the init signed along with it
+        first
+            .verify_stream_segment(&mut open(&first_init), &mut signed(names[0]), None)
+            .unwrap();
+        last.verify_stream_segment(&mut open(&init_output), &mut signed(names[1]), None)
+            .unwrap();
+        first
+            .verify_fragment_memory(&mut signed(names[0]), Some("sha256"), first_rh, &None)
+            .unwrap();
+
+        // a first fragment with an anchor box and a following one without are rejected
+        assert!(matches!(
+            first.verify_stream_segment(&mut open(&first_init), &mut signed(names[1]), None),
+            Err(Error::HashMismatch(_))
+        ));
+        assert!(matches!(
+            last.verify_stream_segment(&mut open(&init_output), &mut open(&bunny(names[1])), None),
+            Err(Error::HashMismatch(_))
+        ));
+        assert!(matches!(
+            last.verify_fragment(
+                &mut open(&init_output),
+                &mut signed(names[0]),
+                None,
+                first_rh
+            ),
+            Err(Error::HashMismatch(_))
+        ));
+    }
+
+    #[test]
+    /// a fragment signed with fewer exclusions than the manifest's verifies with the ones
+    /// embedded in it, a tampered one is reported along with the difference, embedded
+    /// exclusions beyond the manifest's are rejected
+    fn exclusion_set_mismatch() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+
+        // the signer excludes the sidx box as well
+        let mut signer = bmff_hash();
+        signer
+            .exclusions_mut()
+            .push(ExclusionsMap::new("/sidx".to_owned()));
+        for name in names {
+            signer.shift_rolling_hash();
+            signer
+                .add_rolling_hash_fragment(
+                    "sha256",
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    bunny(name),
+                    temp_dir.path().join("BigBuckBunny_2s_init.mp4"),
+                    &FragmentOptions::default(),
+                )
+                .unwrap();
+        }
+        let rh = signer.rolling_hash().unwrap();
+        let previous_hash = rh.previous_hash().map(Vec::as_slice);
+        let rolling_hash = rh.rolling_hash().unwrap();
+        let signed = std::fs::read(temp_dir.path().join(names[1])).unwrap();
+        let mut tampered = signed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+
+        let check = |bmff_hash: &BmffHash, fragment: &[u8]| match bmff_hash.check_rolling_hash(
+            "sha256",
+            &mut Cursor::new(fragment),
+            previous_hash,
+            rolling_hash,
+        ) {
+            Ok(()) => None,
+            Err(Error::HashMismatch(msg)) => Some(msg),
+            Err(err) => panic!("unexpected error {}", err),
+        };
+        assert_eq!(check(&signer, &signed), None);
+        assert_eq!(
+            check(&signer, &tampered).as_deref(),
+            Some("Fragment Hash does not match Rolling Hash")
+        );
+
+        // the manifest excludes the sidx and mdat boxes, a fragment with fewer exclusions
+        // is hashed with its own
+        let mut verifier = bmff_hash();
+        verifier.exclusions_mut().extend([
+            ExclusionsMap::new("/sidx".to_owned()),
+            ExclusionsMap::new("/mdat".to_owned()),
+        ]);
+        assert_eq!(check(&verifier, &signed), None);
+        assert!(check(&verifier, &tampered)
+            .unwrap()
+            .contains("the exclusions embedded in the fragment differ from the manifest's"));
+
+        // the manifest only lists the C2PA box, the unsigned embedded exclusions can't
+        // widen it
+        let verifier = bmff_hash();
+        for fragment in [&signed, &tampered] {
+            assert_eq!(
+                check(&verifier, fragment).as_deref(),
+                Some("The exclusions embedded in the fragment aren't covered by the manifest's")
+            );
+        }
+    }
+
+    #[test]
+    /// additional exclusions are stored in the assertion and used for every fragment
+    fn fragment_exclusions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let sidx = ExclusionsMap::new("/sidx".to_owned());
+
+        let signer = signer();
+        let mut builder = builder();
+        builder.fragment_options.exclusions = vec![sidx.clone()];
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        assert_eq!(
+            bmff_hash
+                .exclusions()
+                .iter()
+                .filter(|exclusion| **exclusion == sidx)
+                .count(),
+            1
+        );
+
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        for name in names {
+            verifier
+                .push_fragment(&mut std::fs::File::open(temp_dir.path().join(name)).unwrap())
+                .unwrap();
+        }
+        verifier.finalize().unwrap();
+    }
+
+    #[test]
+    /// an external verifier hashing the fragment minus the exclusions gets the rolling hash,
+    /// embedded exclusions beyond the manifest's are rejected
+    fn rolling_hash_fragment_exclusions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+
+        let signer = signer();
+        let mut builder = builder();
+        builder.fragment_options.exclusions = vec![ExclusionsMap::new("/sidx".to_owned())];
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let open = |name: &str| std::fs::File::open(temp_dir.path().join(name)).unwrap();
+
+        // the first fragment has no rolling hash box, the manifest's exclusions apply
+        let ranges = bmff_hash
+            .rolling_hash_fragment_exclusions(&mut open(names[0]))
+            .unwrap();
+        let expected =
+            bmff_to_jumbf_exclusions(&mut open(names[0]), bmff_hash.exclusions(), true).unwrap();
+        assert_eq!(ranges, expected);
+
+        // the following one embeds its exclusions
+        let mut fragment = open(names[1]);
+        let ranges = bmff_hash
+            .rolling_hash_fragment_exclusions(&mut fragment)
+            .unwrap();
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut fragment).unwrap();
+        let fragment_rh = &c2pa_boxes.rolling_hashes[0];
+        let expected =
+            bmff_to_jumbf_exclusions(&mut fragment, fragment_rh.exclusions(), true).unwrap();
+        assert_eq!(ranges, expected);
+
+        let hash = hash_stream_by_alg("sha256", &mut fragment, Some(ranges), true).unwrap();
+        let rolling_hash =
+            concat_and_hash("sha256", fragment_rh.anchor_point().unwrap(), Some(&hash));
+        assert_eq!(
+            Some(&rolling_hash),
+            bmff_hash.rolling_hash().unwrap().rolling_hash()
+        );
+
+        // the embedded exclusions are unsigned, they can't exclude more than the manifest
+        let narrow = self::bmff_hash();
+        assert!(matches!(
+            narrow.rolling_hash_fragment_exclusions(&mut open(names[1])),
+            Err(Error::HashMismatch(_))
+        ));
+        assert!(matches!(
+            narrow.verify_fragment_memory(
+                &mut open(names[1]),
+                Some("sha256"),
+                &rolling_hash,
+                &fragment_rh.anchor_point().map(<[u8]>::to_vec),
+            ),
+            Err(Error::HashMismatch(_))
+        ));
+    }
+
+    #[test]
+    /// an ingested init signed before, e.g. by a camera, starts a new chain, its
+    /// manifest is replaced and the init hash covers the final box layout
+    fn resign_init() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let camera_init = temp_dir.path().join("camera/BigBuckBunny_2s_init.mp4");
+        let init_output = temp_dir.path().join("live/BigBuckBunny_2s_init.mp4");
+        let signer = signer();
+
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &vec![bunny("BigBuckBunny_2s1.m4s")],
+                camera_init.clone(),
+                None,
+            )
+            .unwrap();
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                camera_init.clone(),
+                &vec![bunny("BigBuckBunny_2s10.m4s")],
+                init_output.clone(),
+                None,
+            )
+            .unwrap();
+
+        let fragment = temp_dir.path().join("live/BigBuckBunny_2s10.m4s");
+        let c2pa_boxes =
+            C2PABmffBoxesRollingHash::from_reader(&mut std::fs::File::open(&fragment).unwrap())
+                .unwrap();
+        assert!(c2pa_boxes.rolling_hashes.is_empty());
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        assert!(bmff_hash.previous_hash().is_none());
+        bmff_hash
+            .verify_stream_segment(
+                &mut std::fs::File::open(&init_output).unwrap(),
+                &mut std::fs::File::open(&fragment).unwrap(),
+                None,
+            )
+            .unwrap();
+
+        let reader = crate::Reader::from_fragment(
+            "mp4",
+            std::fs::File::open(&init_output).unwrap(),
+            std::fs::File::open(&fragment).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(reader.validation_status(), None);
+        assert_eq!(reader.manifests().len(), 1);
+    }
+
+    #[test]
+    fn from_manifest_bytes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+
+        let signer = signer();
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &vec![bunny("BigBuckBunny_2s1.m4s")],
+                init_output.clone(),
+                None,
+            )
+            .unwrap();
+
+        let mut init = std::fs::File::open(&init_output).unwrap();
+        let manifest_bytes = crate::jumbf_io::load_jumbf_from_stream("mp4", &mut init).unwrap();
+        let bmff_hash = BmffHash::from_manifest_bytes(&manifest_bytes)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bmff_hash, signed_bmff_hash(&init_output));
+        assert_eq!(bmff_hash.bmff_version(), 2);
+        assert!(bmff_hash.rolling_hash().is_some());
+
+        // signed again with the live init as parent ingredient, the rolling hash is found
+        // in the manifest of the ingredient
+        let resigned = temp_dir.path().join("resigned.mp4");
+        let mut resign = builder();
+        resign
+            .add_ingredient_from_stream(
+                serde_json::json!({ "title": "live", "relationship": "parentOf" }).to_string(),
+                "mp4",
+                &mut std::fs::File::open(&init_output).unwrap(),
+            )
+            .unwrap();
+        resign
+            .sign_file(signer.as_ref(), &init_output, &resigned)
+            .unwrap();
+
+        let mut init = std::fs::File::open(&resigned).unwrap();
+        let manifest_bytes = crate::jumbf_io::load_jumbf_from_stream("mp4", &mut init).unwrap();
+        let reader = crate::Reader::from_file(&resigned).unwrap();
+        assert!(reader.manifests().len() > 1);
+        assert!(reader
+            .active_manifest()
+            .unwrap()
+            .find_assertion::<BmffHash>(labels::BMFF_HASH_2)
+            .map_or(true, |hash| hash.rolling_hash().is_none()));
+        assert_eq!(
+            BmffHash::from_manifest_bytes(&manifest_bytes).unwrap(),
+            Some(bmff_hash)
+        );
+
+        // a manifest binding a JPEG has no BMFF hash
+        let mut jpeg = std::fs::File::open(fixture_path("C.jpg")).unwrap();
+        let manifest_bytes = crate::jumbf_io::load_jumbf_from_stream("jpeg", &mut jpeg).unwrap();
+        assert!(BmffHash::from_manifest_bytes(&manifest_bytes)
+            .unwrap()
+            .is_none());
+
+        assert!(BmffHash::from_manifest_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn fragment_metadata_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let metadata = FragmentMetadata {
+            stream: Some("bunny".to_string()),
+            rep_id: Some("video".to_string()),
+            index: Some(10),
+        };
+
+        // only the 2nd fragment carries metadata
+        let signer = signer();
+        let mut builder = builder();
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+            builder.fragment_options.metadata = Some(metadata.clone());
+        }
+        let signed = |name: &str| std::fs::File::open(temp_dir.path().join(name)).unwrap();
+
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut signed(names[0])).unwrap();
+        assert!(c2pa_boxes.rolling_hashes.is_empty());
+        let c2pa_boxes = C2PABmffBoxesRollingHash::from_reader(&mut signed(names[1])).unwrap();
+        assert_eq!(c2pa_boxes.rolling_hashes[0].metadata(), Some(&metadata));
+
+        // both verify
+        let mut verifier = RollingHashVerifier::new(&signed_bmff_hash(&init_output)).unwrap();
+        for name in names {
+            verifier.push_fragment(&mut signed(name)).unwrap();
+        }
+        verifier.finalize().unwrap();
+    }
+
+    #[test]
+    /// a lower row of the tree is stored instead of the root, the fragments carry
+    /// shorter proofs which are climbed up to that row
+    fn stored_merkle_row() {
+        let names = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s100.m4s",
+            "BigBuckBunny_2s101.m4s",
+            "BigBuckBunny_2s102.m4s",
+        ];
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        let layout = C2PAMerkleTree::to_layout(names.len());
+        assert_eq!(layout, [5, 3, 2, 1]);
+        let signer = signer();
+
+        // rows above the root store the root
+        for (merkle_row, stored) in [
+            (Some(0), 0),
+            (Some(1), 1),
+            (Some(2), 2),
+            (None, 3),
+            (Some(9), 3),
+        ] {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+            let mut builder = builder();
+            builder.fragment_options.merkle_row = merkle_row;
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &fragments,
+                    init_output.clone(),
+                    Some(0),
+                )
+                .unwrap();
+
+            let bmff_hash = signed_bmff_hash(&init_output);
+            assert_eq!(bmff_hash.merkle().unwrap()[0].hashes.len(), layout[stored]);
+
+            let outputs: Vec<_> = names.iter().map(|n| temp_dir.path().join(n)).collect();
+            let reader = crate::Reader::from_fragmented_files(&init_output, &outputs).unwrap();
+            assert_eq!(reader.validation_status(), None);
+
+            let init = std::fs::read(&init_output).unwrap();
+            for output in &outputs {
+                let mut fragment = std::fs::read(output).unwrap();
+                let c2pa_boxes = read_bmff_c2pa_boxes(&mut Cursor::new(&fragment)).unwrap();
+                let proof_len = c2pa_boxes.bmff_merkle[0]
+                    .hashes
+                    .as_ref()
+                    .map_or(0, |h| h.len());
+                assert!(proof_len <= stored);
+
+                bmff_hash
+                    .verify_stream_segment(
+                        &mut Cursor::new(&init),
+                        &mut Cursor::new(&fragment),
+                        None,
+                    )
+                    .unwrap();
+                let last = fragment.len() - 1;
+                fragment[last] ^= 0xff;
+                assert!(bmff_hash
+                    .verify_stream_segment(
+                        &mut Cursor::new(&init),
+                        &mut Cursor::new(&fragment),
+                        None
+                    )
+                    .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn salted_round_trip() {
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let signer = signer();
+        let mut builder = builder();
+        builder.fragment_options.salt = Some(b"per stream salt!".to_vec());
+
+        // Merkle tree
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        builder
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+
+        let outputs: Vec<_> = names.iter().map(|n| temp_dir.path().join(n)).collect();
+        let reader = crate::Reader::from_fragmented_files(&init_output, &outputs).unwrap();
+        assert_ne!(reader.validation_state(), crate::ValidationState::Invalid);
+
+        let mut bmff_hash = signed_bmff_hash(&init_output);
+        assert_eq!(bmff_hash.salt(), Some(&b"per stream salt!".to_vec()));
+        let mut init = std::fs::File::open(&init_output).unwrap();
+        let mut fragment = std::fs::File::open(&outputs[0]).unwrap();
+        bmff_hash
+            .verify_stream_segment(&mut init, &mut fragment, None)
+            .unwrap();
+
+        // the leaves don't match without the salt
+        bmff_hash.salt = None;
+        assert!(bmff_hash
+            .verify_stream_segment(&mut init, &mut fragment, None)
+            .is_err());
+
+        // rolling hash
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+
+        let mut bmff_hash = signed_bmff_hash(&init_output);
+        let verify = |bmff_hash: &BmffHash| {
+            let mut verifier = RollingHashVerifier::new(bmff_hash)?;
+            for name in names {
+                verifier.push_fragment(&mut std::fs::File::open(temp_dir.path().join(name))?)?;
+            }
+            verifier.finalize()
+        };
+        verify(&bmff_hash).unwrap();
+
+        bmff_hash.salt = None;
+        assert!(verify(&bmff_hash).is_err());
+    }
+
+    #[test]
+    fn init_hash_matches_between_modes() {
+        let init = bunny("BigBuckBunny_2s_init.mp4");
+
+        let mut merkle_hash = bmff_hash();
+        merkle_hash.set_merkle(vec![MerkleMap {
+            unique_id: 1,
+            local_id: 1,
+            count: 1,
+            alg: None,
+            init_hash: None,
+            hashes: VecByteBuf(Vec::new()),
+        }]);
+        merkle_hash.update_fragmented_inithash(&init).unwrap();
+
+        let mut rolling_hash = bmff_hash();
+        rolling_hash.rolling_hash = Some(RollingHash::new("sha256").unwrap());
+        assert!(rolling_hash.has_placeholder_init_hash());
+        rolling_hash.update_fragmented_inithash(&init).unwrap();
+        assert!(!rolling_hash.has_placeholder_init_hash());
+        assert!(!merkle_hash.has_placeholder_init_hash());
+
+        let merkle_init = merkle_hash.merkle().unwrap()[0].init_hash.as_deref();
+        let rolling_init = rolling_hash.rolling_hash().unwrap().init_hash();
+        assert_eq!(merkle_init, rolling_init);
+
+        // and both match what verification computes
+        let mut stream = std::fs::File::open(&init).unwrap();
+        let hash = merkle_hash
+            .hash_init_segment("sha256", &mut stream)
+            .unwrap();
+        assert_eq!(merkle_init, Some(&hash));
+    }
+
+    #[test]
+    fn cenc_fragments() {
+        let cenc = |name: &str| fixture_path(&format!("bunny/cenc/{name}"));
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s2.m4s"];
+        let signer = signer();
+
+        assert!(crate::asset_handlers::bmff_io::is_cenc_protected(
+            &mut std::fs::File::open(cenc("BigBuckBunny_2s_init.mp4")).unwrap()
+        )
+        .unwrap());
+        assert!(!crate::asset_handlers::bmff_io::is_cenc_protected(
+            &mut std::fs::File::open(bunny("BigBuckBunny_2s_init.mp4")).unwrap()
+        )
+        .unwrap());
+
+        // new key period: same ciphertext, different license data
+        let rotate_key = |data: &mut Vec<u8>| {
+            let pos = data
+                .windows(12)
+                .position(|w| w == b"key period 1" || w == b"key period 2")
+                .unwrap();
+            data[pos..pos + 12].copy_from_slice(b"key period 9");
+        };
+        let tamper_media = |data: &mut Vec<u8>| {
+            let last = data.len() - 1;
+            data[last] ^= 0xff;
+        };
+        // the IVs of the samples are part of the signed media
+        let tamper_iv = |data: &mut Vec<u8>| {
+            let senc = data.windows(4).position(|w| w == b"senc").unwrap();
+            data[senc + 12] ^= 0xff;
+        };
+
+        // Merkle tree
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let fragments: Vec<_> = names.iter().map(|n| cenc(n)).collect();
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                cenc("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let xpaths: Vec<_> = bmff_hash.exclusions().iter().map(|e| &e.xpath).collect();
+        assert!(xpaths.iter().any(|x| *x == "/moof/pssh"));
+        assert!(!xpaths.iter().any(|x| x.starts_with("/moof/traf")));
+
+        let init = std::fs::read(&init_output).unwrap();
+        let verify = |fragment: &[u8]| {
+            bmff_hash.verify_stream_segment(
+                &mut Cursor::new(&init),
+                &mut Cursor::new(fragment),
+                None,
+            )
+        };
+        let mut fragment = std::fs::read(temp_dir.path().join(names[0])).unwrap();
+        verify(&fragment).unwrap();
+        rotate_key(&mut fragment);
+        verify(&fragment).unwrap();
+        let mut iv_changed = fragment.clone();
+        tamper_iv(&mut iv_changed);
+        assert!(verify(&iv_changed).is_err());
+        tamper_media(&mut fragment);
+        assert!(verify(&fragment).is_err());
+
+        // rolling hash
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let mut builder = builder();
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    cenc("BigBuckBunny_2s_init.mp4"),
+                    &vec![cenc(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let verify = |change: &dyn Fn(&mut Vec<u8>)| {
+            let mut verifier = RollingHashVerifier::new(&bmff_hash)?;
+            for name in names {
+                let mut fragment = std::fs::read(temp_dir.path().join(name))?;
+                change(&mut fragment);
+                verifier.push_fragment(&mut Cursor::new(fragment))?;
+            }
+            verifier.finalize()
+        };
+        verify(&|_| ()).unwrap();
+        verify(&rotate_key).unwrap();
+        assert!(verify(&tamper_iv).is_err());
+        assert!(verify(&tamper_media).is_err());
+    }
+
+    #[test]
+    /// an audio-only stream, a single AAC track without video sample tables, is signed
+    /// and verified like the video fixtures (the fragments are cut from `sample1.m4a`)
+    fn audio_only_fragments() {
+        let audio = |name: &str| fixture_path(&format!("audio/{name}"));
+        let names = ["sample1_2s1.m4s", "sample1_2s2.m4s", "sample1_2s3.m4s"];
+        let fragments: Vec<_> = names.iter().map(|n| audio(n)).collect();
+        let signer = signer();
+
+        // Merkle tree over separate fragments
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("sample1_2s_init.mp4");
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                audio("sample1_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        assert_eq!(bmff_hash.merkle().unwrap()[0].count, 3);
+        let init = std::fs::read(&init_output).unwrap();
+        for name in names {
+            let mut fragment = std::fs::read(temp_dir.path().join(name)).unwrap();
+            bmff_hash
+                .verify_stream_segment(&mut Cursor::new(&init), &mut Cursor::new(&fragment), None)
+                .unwrap();
+            let reader =
+                crate::Reader::from_fragment("mp4", Cursor::new(&init), Cursor::new(&fragment))
+                    .unwrap();
+            assert_eq!(reader.validation_status(), None);
+
+            let last = fragment.len() - 1;
+            fragment[last] ^= 0xff;
+            assert!(matches!(
+                bmff_hash.verify_stream_segment(
+                    &mut Cursor::new(&init),
+                    &mut Cursor::new(&fragment),
+                    None
+                ),
+                Err(Error::HashMismatch(_))
+            ));
+        }
+
+        // all fragments merged into a single file
+        let output = temp_dir.path().join("merged.m4a");
+        builder()
+            .sign_merged_fragments(
+                signer.as_ref(),
+                audio("sample1_2s_init.mp4"),
+                &fragments,
+                output.clone(),
+            )
+            .unwrap();
+        let reader = crate::Reader::from_file(&output).unwrap();
+        assert_eq!(reader.validation_status(), None);
+
+        // rolling hash
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("sample1_2s_init.mp4");
+        let mut builder = builder();
+        for fragment in &fragments {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    audio("sample1_2s_init.mp4"),
+                    &vec![fragment.clone()],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        for name in names {
+            let fragment = std::fs::File::open(temp_dir.path().join(name)).unwrap();
+            verifier
+                .push_fragment(&mut BufReader::new(fragment))
+                .unwrap();
+        }
+        verifier.finalize().unwrap();
+
+        // the chunks of the unfragmented source, its last chunk holds fewer samples
+        let data = std::fs::read(fixture_path("sample1.m4a")).unwrap();
+        let size = data.len() as u64;
+        let mut mp4 = mp4::Mp4Reader::read_header(Cursor::new(data), size).unwrap();
+        let (&track_id, track) = mp4.tracks().iter().next().unwrap();
+        assert!(matches!(track.track_type(), Ok(TrackType::Audio)));
+        let chunk_count = track
+            .trak
+            .mdia
+            .minf
+            .stbl
+            .stco
+            .as_ref()
+            .unwrap()
+            .entries
+            .len();
+        let chunk_hashes = track_chunk_hashes(&mut mp4, track_id, "sha256").unwrap();
+        assert_eq!(chunk_hashes.len(), chunk_count);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn parallel_fragment_leaves() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let names = [
+            "BigBuckBunny_2s1.m4s",
+            "BigBuckBunny_2s10.m4s",
+            "BigBuckBunny_2s11.m4s",
+        ];
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+
+        let signer = signer();
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let outputs: Vec<_> = names.iter().map(|n| temp_dir.path().join(n)).collect();
+        let paths: Vec<_> = outputs.iter().collect();
+
+        let serial = bmff_hash.fragment_leaves("sha256", &paths).unwrap();
+        let parallel = bmff_hash.fragment_leaves_par("sha256", &paths).unwrap();
+        assert_eq!(serial, parallel);
+
+        let serial = C2PAMerkleTree::from_leaves(serial, "sha256", false);
+        let parallel = C2PAMerkleTree::from_leaves(parallel, "sha256", false);
+        assert_eq!(serial.layers, parallel.layers);
+        assert_eq!(
+            serial.get_root().map(|r| r.as_slice()),
+            bmff_hash.merkle().unwrap()[0]
+                .hashes
+                .0
+                .first()
+                .map(|h| h.as_slice())
+        );
+    }
+
+    #[test]
+    fn tolerate_trailing_boxes() {
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let signer = signer();
+
+        // `free` padding appended by a CDN after signing
+        let pad = |fragment: &mut Vec<u8>| {
+            fragment.extend_from_slice(&[0, 0, 0, 16]);
+            fragment.extend_from_slice(b"free");
+            fragment.extend_from_slice(&[0; 8]);
+        };
+
+        // Merkle tree
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        builder()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let init = std::fs::read(&init_output).unwrap();
+        let mut fragment = std::fs::read(temp_dir.path().join(names[0])).unwrap();
+        pad(&mut fragment);
+        let verify = |fragment: &[u8]| {
+            bmff_hash.verify_stream_segment(
+                &mut Cursor::new(&init),
+                &mut Cursor::new(fragment),
+                None,
+            )
+        };
+        assert!(verify(&fragment).is_err());
+
+        crate::settings::set_settings_value("verify.tolerate_trailing_boxes", true).unwrap();
+        verify(&fragment).unwrap();
+
+        // only the appended boxes are ignored, the media data is still covered
+        let mdat_end = fragment.len() - 17;
+        fragment[mdat_end] ^= 0xff;
+        assert!(verify(&fragment).is_err());
+        crate::settings::reset_default_settings().unwrap();
+
+        // rolling hash
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let mut builder = builder();
+        for name in names {
+            builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let verify = || {
+            let mut verifier = RollingHashVerifier::new(&bmff_hash)?;
+            for name in names {
+                let mut fragment = std::fs::read(temp_dir.path().join(name))?;
+                pad(&mut fragment);
+                verifier.push_fragment(&mut Cursor::new(fragment))?;
+            }
+            verifier.finalize()
+        };
+        assert!(verify().is_err());
+
+        crate::settings::set_settings_value("verify.tolerate_trailing_boxes", true).unwrap();
+        verify().unwrap();
+        crate::settings::reset_default_settings().unwrap();
+    }
+
+    #[test]
+    fn tolerate_trailing_boxes_after_mdat() {
+        let names = ["BigBuckBunny_2s1.m4s", "BigBuckBunny_2s10.m4s"];
+        let signer = signer();
+        let pad = |fragment: &mut Vec<u8>| {
+            fragment.extend_from_slice(&[0, 0, 0, 16]);
+            fragment.extend_from_slice(b"free");
+            fragment.extend_from_slice(&[0; 8]);
+        };
+
+        // Merkle tree, the C2PA box follows the mdat and is kept
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let fragments: Vec<_> = names.iter().map(|n| bunny(n)).collect();
+        let mut live_builder = builder();
+        live_builder.fragment_options.box_position = C2paBoxPosition::AfterMdat;
+        live_builder
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &fragments,
+                init_output.clone(),
+                Some(0),
+            )
+            .unwrap();
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        let init = std::fs::read(&init_output).unwrap();
+        let mut fragment = std::fs::read(temp_dir.path().join(names[0])).unwrap();
+        let signed_len = fragment.len() as u64;
+        pad(&mut fragment);
+        assert_eq!(
+            signed_fragment_len(&mut Cursor::new(&fragment)).unwrap(),
+            signed_len
+        );
+
+        crate::settings::set_settings_value("verify.tolerate_trailing_boxes", true).unwrap();
+        bmff_hash
+            .verify_stream_segment(&mut Cursor::new(&init), &mut Cursor::new(&fragment), None)
+            .unwrap();
+        crate::settings::reset_default_settings().unwrap();
+
+        // rolling hash, the first fragment carries no C2PA box
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let mut live_builder = builder();
+        live_builder.fragment_options.box_position = C2paBoxPosition::AfterMdat;
+        for name in names {
+            live_builder
+                .sign_live_bmff(
+                    signer.as_ref(),
+                    bunny("BigBuckBunny_2s_init.mp4"),
+                    &vec![bunny(name)],
+                    init_output.clone(),
+                    None,
+                )
+                .unwrap();
+        }
+
+        let bmff_hash = signed_bmff_hash(&init_output);
+        crate::settings::set_settings_value("verify.tolerate_trailing_boxes", true).unwrap();
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        for name in names {
+            let mut fragment = std::fs::read(temp_dir.path().join(name)).unwrap();
+            pad(&mut fragment);
+            verifier.push_fragment(&mut Cursor::new(fragment)).unwrap();
+        }
+        verifier.finalize().unwrap();
+        crate::settings::reset_default_settings().unwrap();
+    }
+
+    /// signs the video track of the muxed `video1.mp4` with one Merkle leaf per chunk,
+    /// the proofs are appended in the order of `locations` (all locations if `None`)
+    ///
+    /// returns the signed asset, its BmffHash, the unsigned asset and the video track id
+    fn sign_video_track(
+        locations: Option<Vec<usize>>,
+    ) -> (Vec<u8>, BmffHash, mp4::Mp4Reader<Cursor<Vec<u8>>>, u32) {
+        let mut data = std::fs::read(fixture_path("video1.mp4")).unwrap();
+        let size = data.len() as u64;
+        let mut mp4 = mp4::Mp4Reader::read_header(Cursor::new(data.clone()), size).unwrap();
+        assert_eq!(mp4.tracks().len(), 2);
+        let video = *mp4
+            .tracks()
+            .iter()
+            .find(|(_, t)| matches!(t.track_type(), Ok(TrackType::Video)))
+            .unwrap()
+            .0;
+
+        let chunk_hashes = track_chunk_hashes(&mut mp4, video, "sha256").unwrap();
+        let count = chunk_hashes.len();
+        let leaves = chunk_hashes
+            .into_iter()
+            .map(crate::utils::merkle::MerkleNode)
+            .collect();
+        let max_proofs = (count as f32).log2().ceil() as usize;
+        let m_tree = C2PAMerkleTree::from_leaves(leaves, "sha256", false);
+
+        // append the chunk proofs, keeps the chunk offsets intact
+        for location in locations.unwrap_or_else(|| (0..count).collect()) {
+            // locations beyond the last chunk get the proof of the last chunk
+            let proof = m_tree
+                .get_proof_by_index(location.min(count - 1), max_proofs)
+                .unwrap();
+            let bmff_mm = BmffMerkleMap {
+                unique_id: video,
+                local_id: video,
+                location: location as u32,
+                hashes: (!proof.is_empty())
+                    .then(|| VecByteBuf(proof.into_iter().map(ByteBuf::from).collect())),
+                box_count: None,
+            };
+            let mm_cbor = serde_cbor::to_vec(&bmff_mm).unwrap();
+            crate::asset_handlers::bmff_io::write_c2pa_box(&mut data, &[], false, &mm_cbor)
+                .unwrap();
+        }
+
+        let mut bmff_hash = bmff_hash();
+        bmff_hash.set_merkle(vec![MerkleMap {
+            unique_id: video,
+            local_id: video,
+            count: count as u32,
+            alg: Some("sha256".to_owned()),
+            init_hash: None,
+            hashes: VecByteBuf(
+                m_tree.layers[max_proofs]
+                    .iter()
+                    .map(|mn| ByteBuf::from(mn.0.clone()))
+                    .collect(),
+            ),
+        }]);
+
+        (data, bmff_hash, mp4, video)
+    }
+
+    #[test]
+    fn verify_signed_track_subset() {
+        // muxed asset with a video and an audio track, only the video track gets signed
+        let (data, bmff_hash, mut mp4, video) = sign_video_track(None);
+
+        // the unsigned audio track is ignored by default
+        let mut stream = Cursor::new(data);
+        bmff_hash
+            .verify_stream_hash(&mut stream, None, None)
+            .unwrap();
+
+        // but rejected when all tracks must be signed
+        assert!(matches!(
+            bmff_hash.verify_stream_hash_tracks(&mut stream, None, true, None),
+            Err(Error::HashMismatch(_))
+        ));
+
+        // a tampered video sample is still detected
+        let mut data = stream.into_inner();
+        let sample = mp4.read_sample(video, 1).unwrap().unwrap();
+        let pos = data
+            .windows(sample.bytes.len())
+            .position(|w| w == &sample.bytes[..])
+            .unwrap();
+        data[pos] ^= 0xff;
+        assert!(bmff_hash
+            .verify_stream_hash(&mut Cursor::new(data), None, None)
+            .is_err());
+    }
+
+    #[test]
+    /// the chunk leaves are matched by location, not by the order of the proof boxes
+    fn verify_chunk_locations() {
+        let (_, _, mut mp4, video) = sign_video_track(None);
+        let count = track_chunk_hashes(&mut mp4, video, "sha256").unwrap().len();
+        assert!(count > 2);
+
+        // proofs stored in reverse, the first leaf used to be compared against the last chunk
+        let (data, bmff_hash, ..) = sign_video_track(Some((0..count).rev().collect()));
+        bmff_hash
+            .verify_stream_hash(&mut Cursor::new(data), None, None)
+            .unwrap();
+
+        // a location beyond the last chunk
+        let mut locations: Vec<_> = (0..count).collect();
+        locations[count - 1] = count;
+        let (data, bmff_hash, ..) = sign_video_track(Some(locations));
+        assert!(matches!(
+            bmff_hash.verify_stream_hash(&mut Cursor::new(data), None, None),
+            Err(Error::HashMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn conflicting_hash_modes() {
+        let mut bmff_hash = bmff_hash();
+        bmff_hash.set_merkle(vec![MerkleMap {
+            unique_id: 1,
+            local_id: 1,
+            count: 1,
+            alg: None,
+            init_hash: None,
+            hashes: VecByteBuf(Vec::new()),
+        }]);
+        bmff_hash.rolling_hash = Some(RollingHash::new("sha256").unwrap());
+
+        let mut init = std::fs::File::open(bunny("BigBuckBunny_2s_init.mp4")).unwrap();
+        let mut fragment = std::fs::File::open(bunny("BigBuckBunny_2s1.m4s")).unwrap();
+        assert!(matches!(
+            bmff_hash.verify_stream_segment(&mut init, &mut fragment, None),
+            Err(Error::InvalidAsset(_))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod memory_tests {
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+
+    use super::*;
+
+    // serializes a BMFF box
+    fn bmff_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn init_segment() -> Vec<u8> {
+        let mut init = bmff_box(b"ftyp", b"iso6\0\0\0\0iso6dash");
+        init.extend(bmff_box(b"moov", &bmff_box(b"mvhd", &[0; 100])));
+        init
+    }
+
+    // a fragment with sequence number `seq` and some media data
+    fn fragment(seq: u32) -> Vec<u8> {
+        let mut mfhd = vec![0; 4];
+        mfhd.extend_from_slice(&seq.to_be_bytes());
+
+        let mut fragment = bmff_box(b"styp", b"msdh\0\0\0\0msdhmsix");
+        fragment.extend(bmff_box(b"moof", &bmff_box(b"mfhd", &mfhd)));
+        fragment.extend(bmff_box(b"mdat", &[seq as u8; 64]));
+        fragment
+    }
+
+    fn bmff_hash() -> BmffHash {
+        let mut bmff_hash = BmffHash::new("jumbf manifest", "sha256", None);
+        let mut uuid = ExclusionsMap::new("/uuid".to_owned());
+        uuid.data = Some(vec![DataMap {
+            offset: 8,
+            value: vec![
+                216, 254, 195, 214, 27, 14, 72, 60, 146, 151, 88, 40, 135, 126, 196, 129,
+            ], // C2PA identifier
+        }]);
+        bmff_hash.exclusions_mut().push(uuid);
+        bmff_hash
+    }
+
+    #[test]
+    fn merkle_round_trip() {
+        let fragments: Vec<_> = (1..=3).map(fragment).collect();
+
+        let mut bmff_hash = bmff_hash();
+        let signed = bmff_hash
+            .add_merkle_for_fragmented_memory(
+                "sha256",
+                &fragments,
+                1,
+                None,
+                &FragmentOptions::default(),
+            )
+            .unwrap();
+        bmff_hash
+            .update_fragmented_inithash_memory(&init_segment())
+            .unwrap();
+
+        assert_eq!(signed.len(), 3);
+        for fragment in &signed {
+            bmff_hash
+                .verify_stream_segment(
+                    &mut Cursor::new(init_segment()),
+                    &mut Cursor::new(fragment),
+                    None,
+                )
+                .unwrap();
+        }
+
+        // tampered media data
+        let mut tampered = signed[1].clone();
+        *tampered.last_mut().unwrap() ^= 0xff;
+        assert!(matches!(
+            bmff_hash.verify_stream_segment(
+                &mut Cursor::new(init_segment()),
+                &mut Cursor::new(tampered),
+                None,
+            ),
+            Err(Error::HashMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn rolling_hash_round_trip() {
+        let mut bmff_hash = bmff_hash();
+        let mut signed = Vec::new();
+        for seq in 1..=3 {
+            bmff_hash.shift_rolling_hash();
+            signed.push(
+                bmff_hash
+                    .add_rolling_hash_fragment_memory(
+                        "sha256",
+                        &fragment(seq),
+                        &FragmentOptions::default(),
+                    )
+                    .unwrap(),
+            );
+        }
+        bmff_hash
+            .update_fragmented_inithash_memory(&init_segment())
+            .unwrap();
+
+        let mut verifier = RollingHashVerifier::new(&bmff_hash).unwrap();
+        for fragment in &signed {
+            verifier.push_fragment(&mut Cursor::new(fragment)).unwrap();
+        }
+        verifier.finalize().unwrap();
+
+        // the newest fragment is anchored to the previous hash
+        bmff_hash
+            .verify_stream_segment(
+                &mut Cursor::new(init_segment()),
+                &mut Cursor::new(&signed[2]),
+                None,
+            )
+            .unwrap();
+        assert!(bmff_hash
+            .verify_stream_segment(
+                &mut Cursor::new(init_segment()),
+                &mut Cursor::new(&signed[1]),
+                None,
+            )
+            .is_err());
+    }
+
+    #[test]
+    /// fragments without media data aren't signed
+    fn empty_mdat() {
+        let mut empty = bmff_box(b"styp", b"msdh\0\0\0\0msdhmsix");
+        empty.extend(bmff_box(b"moof", &bmff_box(b"mfhd", &[0; 8])));
+        empty.extend(bmff_box(b"mdat", &[]));
+        // a large size with an empty payload
+        let mut large = empty[..empty.len() - 8].to_vec();
+        large.extend_from_slice(&1_u32.to_be_bytes());
+        large.extend_from_slice(b"mdat");
+        large.extend_from_slice(&16_u64.to_be_bytes());
+
+        let options = FragmentOptions::default();
+        for unsigned in [&empty, &large] {
+            let err = bmff_hash()
+                .add_rolling_hash_fragment_memory("sha256", unsigned, &options)
+                .unwrap_err();
+            assert!(err.to_string().contains("mdat of 0 bytes"));
+            assert!(bmff_hash()
+                .add_merkle_for_fragmented_memory(
+                    "sha256",
+                    &[fragment(1), unsigned.clone()],
+                    1,
+                    None,
+                    &options,
+                )
+                .is_err());
+        }
+
+        // the media data of the fragment is below the configured minimum
+        let options = FragmentOptions {
+            min_mdat_size: 65,
+            ..Default::default()
+        };
+        assert!(bmff_hash()
+            .add_rolling_hash_fragment_memory("sha256", &fragment(1), &options)
+            .is_err());
+        let options = FragmentOptions {
+            min_mdat_size: 64,
+            ..Default::default()
+        };
+        bmff_hash()
+            .add_rolling_hash_fragment_memory("sha256", &fragment(1), &options)
+            .unwrap();
+    }
+
+    #[test]
+    /// the shifted hash is returned as the anchor point of the next fragment
+    fn shift_rolling_hash() {
+        let mut bmff_hash = bmff_hash();
+        assert_eq!(bmff_hash.shift_rolling_hash(), None);
+
+        let mut signed = Vec::new();
+        for seq in 1..=2 {
+            let rolling_hash = bmff_hash
+                .rolling_hash()
+                .and_then(|rh| rh.rolling_hash().cloned());
+            let anchor_point = bmff_hash.shift_rolling_hash();
+            assert_eq!(anchor_point, rolling_hash);
+            assert_eq!(
+                bmff_hash.rolling_hash().and_then(|rh| rh.previous_hash()),
+                anchor_point.as_ref()
+            );
+
+            signed.push(
+                bmff_hash
+                    .add_rolling_hash_fragment_memory(
+                        "sha256",
+                        &fragment(seq),
+                        &FragmentOptions::default(),
+                    )
+                    .unwrap(),
+            );
+        }
+
+        // the second fragment is anchored to the hash returned before signing it
+        let first = bmff_hash.rolling_hash().unwrap().previous_hash().cloned();
+        let c2pa_boxes =
+            C2PABmffBoxesRollingHash::from_reader(&mut Cursor::new(&signed[1])).unwrap();
+        assert_eq!(
+            c2pa_boxes.rolling_hashes[0]
+                .anchor_point()
+                .map(<[u8]>::to_vec),
+            first
+        );
+
+        // shifting twice leaves no rolling hash to anchor to
+        assert!(bmff_hash.shift_rolling_hash().is_some());
+        assert_eq!(bmff_hash.shift_rolling_hash(), None);
+    }
 }
-*/
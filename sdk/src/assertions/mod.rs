@@ -22,7 +22,10 @@ pub use asset_types::{AssetTypeEnum, AssetTypes};
 
 mod bmff_hash;
 pub use bmff_hash::{
-    BmffHash, BmffMerkleMap, DataMap, ExclusionsMap, FragmentRollingHash, SubsetMap,
+    BmffHash, BmffMerkleMap, BoxDigest, C2paBoxPosition, ClimbLevel, DataMap, ExclusionsMap,
+    FragmentBoxDigests, FragmentMetadata, FragmentOptions, FragmentRollingHash,
+    FragmentVerification, MerkleStats, RollingHashVerifier, SubsetMap, TamperedBox, VerifyOptions,
+    VerifyReport, CONTENT_HASH_ALGS,
 };
 
 mod box_hash;
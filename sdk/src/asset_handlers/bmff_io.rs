@@ -1108,6 +1108,193 @@ fn get_uuid_token(
     None
 }
 
+// Finds the C2PA box holding the manifest store, C2PA Merkle boxes are skipped
+fn get_manifest_uuid_token<R: Read + Seek + ?Sized>(
+    reader: &mut R,
+    bmff_tree: &Arena<BoxInfo>,
+    bmff_map: &HashMap<String, Vec<Token>>,
+) -> Result<Option<Token>> {
+    if let Some(uuid_list) = bmff_map.get("/uuid") {
+        for uuid_token in uuid_list {
+            let box_info = &bmff_tree[*uuid_token].data;
+
+            if box_info.box_type != BoxType::UuidBox {
+                continue;
+            }
+            match &box_info.user_type {
+                Some(uuid) if vec_compare(&C2PA_UUID, uuid) => (),
+                _ => continue,
+            }
+
+            // the purpose follows the UUID and the version/flags
+            skip_bytes_to(reader, box_info.offset + HEADER_SIZE + 16 + 4)?;
+            let mut purpose = [0u8; MANIFEST.len() + 1];
+            reader.read_exact(&mut purpose)?;
+
+            if purpose[..MANIFEST.len()] == *MANIFEST.as_bytes() && purpose[MANIFEST.len()] == 0 {
+                return Ok(Some(*uuid_token));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Checks whether a BMFF asset is protected by Common Encryption (CENC), i.e. it has
+// a `pssh` box in `moov` or a protected (`enc*`) sample entry in one of its `stsd` boxes
+pub(crate) fn is_cenc_protected<R: Read + Seek + ?Sized>(reader: &mut R) -> Result<bool> {
+    let size = stream_len(reader)?;
+    reader.rewind()?;
+
+    // create root node
+    let root_box = BoxInfo {
+        path: "".to_string(),
+        offset: 0,
+        size,
+        box_type: BoxType::Empty,
+        parent: None,
+        user_type: None,
+        version: None,
+        flags: None,
+    };
+
+    let (mut bmff_tree, root_token) = Arena::with_data(root_box);
+    let mut bmff_map: HashMap<String, Vec<Token>> = HashMap::new();
+
+    build_bmff_tree(reader, size, &mut bmff_tree, &root_token, &mut bmff_map)?;
+
+    let mut protected = bmff_map.contains_key("/moov/pssh");
+
+    if let Some(stsd_list) = bmff_map.get("/moov/trak/mdia/minf/stbl/stsd") {
+        for stsd_token in stsd_list {
+            let stsd = &bmff_tree[*stsd_token].data;
+            let end = stsd.offset + stsd.size;
+
+            // sample entries follow the version/flags and the entry count
+            let mut current = skip_bytes_to(reader, stsd.offset + HEADER_SIZE + 4 + 4)?;
+            while !protected && current + HEADER_SIZE <= end {
+                let header = BoxHeaderLite::read(reader)?;
+                if header.size == 0 {
+                    break;
+                }
+
+                protected = matches!(header.fourcc.as_str(), "encv" | "enca" | "enct" | "encs");
+                current = skip_bytes_to(reader, current + header.size)?;
+            }
+        }
+    }
+    reader.rewind()?;
+
+    Ok(protected)
+}
+
+// Returns the length of a fragment as signed, i.e. without any boxes appended after signing.
+// The signed length ends with the top level box at the position recorded in the C2PA box of the
+// fragment. A fragment without a C2PA box (the first of a rolling hash stream) ends with its last
+// `mdat`, a fragment whose C2PA box records no position is returned in full.
+pub(crate) fn signed_fragment_len<R: Read + Seek + ?Sized>(reader: &mut R) -> Result<u64> {
+    let size = stream_len(reader)?;
+    let mut box_ends = Vec::new();
+    let mut mdat_end = None;
+    let mut c2pa_box = false;
+    let mut box_count = None;
+
+    let mut current = 0;
+    while current + HEADER_SIZE <= size {
+        reader.seek(SeekFrom::Start(current))?;
+        let header = BoxHeaderLite::read(reader)?;
+        let header_size = if header.large_size {
+            HEADER_SIZE_LARGE
+        } else {
+            HEADER_SIZE
+        };
+
+        // a size zero box extends to the end of the stream
+        let box_size = if header.size == 0 {
+            size - current
+        } else {
+            header.size
+        };
+        if box_size < header_size {
+            return Err(Error::InvalidAsset(format!(
+                "Bad BMFF box size {box_size} at {current}"
+            )));
+        }
+
+        if header.name == BoxType::UuidBox && !c2pa_box {
+            if let Some(layout) = read_signed_layout(reader, box_size - header_size)? {
+                c2pa_box = true;
+                box_count = layout.box_count;
+            }
+        }
+
+        current = current.saturating_add(box_size);
+        box_ends.push(current.min(size));
+        if header.name == BoxType::MdatBox {
+            mdat_end = Some(current.min(size));
+        }
+    }
+    reader.rewind()?;
+
+    if !c2pa_box {
+        return Ok(mdat_end.unwrap_or(size));
+    }
+    Ok(box_count
+        .and_then(|count| box_ends.get((count as usize).checked_sub(1)?))
+        .map_or(size, |end| *end))
+}
+
+// The top level layout of a fragment when it was signed, recorded in its C2PA box
+#[derive(serde::Deserialize)]
+struct SignedLayout {
+    #[serde(rename = "boxCount", alias = "box_count")]
+    box_count: Option<u32>,
+}
+
+// Reads the layout recorded in the C2PA Merkle box whose contents (after the box header) the
+// reader is positioned at, returns `None` if it isn't a C2PA Merkle box
+fn read_signed_layout<R: Read + Seek + ?Sized>(
+    reader: &mut R,
+    mut data_len: u64,
+) -> Result<Option<SignedLayout>> {
+    // UUID + Fullbox version and flags
+    if data_len < 16 + 4 {
+        return Ok(None);
+    }
+    let mut uuid = [0u8; 16];
+    reader.read_exact(&mut uuid)?;
+    if !vec_compare(&C2PA_UUID, &uuid) {
+        return Ok(None);
+    }
+    let (_version, _flags) = read_box_header_ext(reader)?;
+    data_len -= 16 + 4;
+
+    let mut purpose = Vec::with_capacity(64);
+    loop {
+        if data_len == 0 {
+            return Ok(None);
+        }
+        let mut buf = [0; 1];
+        reader.read_exact(&mut buf)?;
+        data_len -= 1;
+        if buf[0] == 0x00 {
+            break;
+        } else {
+            purpose.push(buf[0]);
+        }
+    }
+    if !vec_compare(&purpose, MERKLE.as_bytes()) {
+        return Ok(None);
+    }
+
+    let mut merkle = Vec::new();
+    Read::take(&mut *reader, data_len).read_to_end(&mut merkle)?;
+
+    // use this method since it will strip trailing zeros padding if there
+    let mut deserializer = serde_cbor::de::Deserializer::from_slice(&merkle);
+    let layout: SignedLayout = serde::Deserialize::deserialize(&mut deserializer)?;
+    Ok(Some(layout))
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct C2PABmffBoxes {
@@ -1118,6 +1305,63 @@ pub struct C2PABmffBoxes {
     pub xmp: Option<String>,
 }
 
+/// Checks that the top level boxes fit into the `size` bytes of the stream.
+///
+/// Reports partially uploaded fragments as truncated, instead of failing
+/// with a generic parse error while reading them.
+fn check_box_boundaries(reader: &mut dyn CAIRead, size: u64) -> Result<()> {
+    let truncated = |expected: u64| {
+        Error::InvalidAsset(format!(
+            "truncated fragment: expected {expected} bytes, got {size}"
+        ))
+    };
+
+    if size == 0 {
+        return Err(truncated(HEADER_SIZE));
+    }
+
+    let mut offset = 0;
+    while offset < size {
+        reader.seek(SeekFrom::Start(offset))?;
+        let header = BoxHeaderLite::read(reader).map_err(|_| {
+            let header_size = if size - offset < HEADER_SIZE {
+                HEADER_SIZE
+            } else {
+                HEADER_SIZE_LARGE
+            };
+            truncated(offset + header_size)
+        })?;
+
+        // same as build_bmff_tree, nothing is parsed after a size zero box
+        let box_size = header.size;
+        if box_size == 0 {
+            break;
+        }
+        if box_size < HEADER_SIZE {
+            return Err(Error::InvalidAsset(format!(
+                "Bad BMFF box size {box_size} at {offset}"
+            )));
+        }
+
+        offset = offset.saturating_add(box_size);
+        if offset > size {
+            return Err(truncated(offset));
+        }
+    }
+
+    reader.rewind()?;
+    Ok(())
+}
+
+/// Same as [read_bmff_c2pa_boxes], but reports truncated fragments
+/// (i.e. partial uploads) before parsing them.
+pub fn read_bmff_fragment_c2pa_boxes(reader: &mut dyn CAIRead) -> Result<C2PABmffBoxes> {
+    let size = stream_len(reader)?;
+    check_box_boundaries(reader, size)?;
+
+    read_bmff_c2pa_boxes(reader)
+}
+
 pub fn read_bmff_c2pa_boxes(mut reader: &mut dyn CAIRead) -> Result<C2PABmffBoxes> {
     let size = stream_len(reader)?;
     reader.rewind()?;
@@ -1260,7 +1504,7 @@ pub struct C2PABmffBoxesRollingHash {
 impl C2PABmffBoxesRollingHash {
     pub fn from_reader(mut reader: &mut dyn CAIRead) -> Result<Self> {
         let size = stream_len(reader)?;
-        reader.rewind()?;
+        check_box_boundaries(reader, size)?;
 
         // create root node
         let root_box = BoxInfo {
@@ -1520,14 +1764,15 @@ impl CAIWriter for BmffIO {
         let ftyp_size = ftyp_info.size;
 
         // get position to insert c2pa
-        let (c2pa_start, c2pa_length) =
-            if let Some(c2pa_token) = get_uuid_token(&bmff_tree, &bmff_map, &C2PA_UUID) {
-                let uuid_info = &bmff_tree[c2pa_token].data;
+        let (c2pa_start, c2pa_length) = if let Some(c2pa_token) =
+            get_manifest_uuid_token(input_stream, &bmff_tree, &bmff_map)?
+        {
+            let uuid_info = &bmff_tree[c2pa_token].data;
 
-                (uuid_info.offset, Some(uuid_info.size))
-            } else {
-                ((ftyp_offset + ftyp_size), None)
-            };
+            (uuid_info.offset, Some(uuid_info.size))
+        } else {
+            ((ftyp_offset + ftyp_size), None)
+        };
 
         let mut new_c2pa_box: Vec<u8> = Vec::with_capacity(store_bytes.len() * 2);
         let merkle_data: &[u8] = &[]; // not yet supported
@@ -1651,16 +1896,17 @@ impl CAIWriter for BmffIO {
         )?;
 
         // get position of c2pa manifest
-        let (c2pa_start, c2pa_length) =
-            if let Some(c2pa_token) = get_uuid_token(&bmff_tree, &bmff_map, &C2PA_UUID) {
-                let uuid_info = &bmff_tree[c2pa_token].data;
+        let (c2pa_start, c2pa_length) = if let Some(c2pa_token) =
+            get_manifest_uuid_token(input_stream, &bmff_tree, &bmff_map)?
+        {
+            let uuid_info = &bmff_tree[c2pa_token].data;
 
-                (uuid_info.offset, Some(uuid_info.size))
-            } else {
-                input_stream.rewind()?;
-                std::io::copy(input_stream, output_stream)?;
-                return Ok(()); // no box to remove, propagate source to output
-            };
+            (uuid_info.offset, Some(uuid_info.size))
+        } else {
+            input_stream.rewind()?;
+            std::io::copy(input_stream, output_stream)?;
+            return Ok(()); // no box to remove, propagate source to output
+        };
 
         let (start, end) = if let Some(c2pa_length) = c2pa_length {
             let start = usize::value_from(c2pa_start)
@@ -1992,6 +2238,50 @@ pub mod tests {
         test::{fixture_path, temp_dir_path},
     };
 
+    #[test]
+    fn test_truncated_fragment() {
+        let fragment =
+            std::fs::read(fixture_path("bunny/bunny_89283bps/BigBuckBunny_2s1.m4s")).unwrap();
+        let mut reader = Cursor::new(fragment.clone());
+        let c2pa_boxes = read_bmff_c2pa_boxes(&mut reader).unwrap();
+        let mdat = c2pa_boxes
+            .box_infos
+            .iter()
+            .find(|b| b.path == "mdat")
+            .unwrap();
+
+        // cut off in the middle of the mdat
+        let cut = (mdat.offset + mdat.size / 2) as usize;
+        let expected = format!(
+            "truncated fragment: expected {} bytes, got {cut}",
+            mdat.offset + mdat.size
+        );
+        let mut truncated = Cursor::new(fragment[..cut].to_vec());
+
+        match read_bmff_fragment_c2pa_boxes(&mut truncated) {
+            Err(Error::InvalidAsset(msg)) => assert_eq!(msg, expected),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+        match C2PABmffBoxesRollingHash::from_reader(&mut truncated) {
+            Err(Error::InvalidAsset(msg)) => assert_eq!(msg, expected),
+            res => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+
+        // cut off within a box header
+        let mut truncated = Cursor::new(fragment[..mdat.offset as usize + 3].to_vec());
+        assert!(matches!(
+            read_bmff_fragment_c2pa_boxes(&mut truncated),
+            Err(Error::InvalidAsset(msg)) if msg.starts_with("truncated fragment")
+        ));
+
+        // zero-length fragment
+        let mut empty = Cursor::new(Vec::new());
+        assert!(matches!(
+            C2PABmffBoxesRollingHash::from_reader(&mut empty),
+            Err(Error::InvalidAsset(msg)) if msg == "truncated fragment: expected 8 bytes, got 0"
+        ));
+    }
+
     #[cfg(all(feature = "v1_api", feature = "file_io"))]
     #[test]
     fn test_read_mp4() {
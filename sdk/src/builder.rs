@@ -29,8 +29,8 @@ use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
 use crate::{
     assertion::AssertionDecodeError,
     assertions::{
-        labels, Actions, BmffHash, BoxHash, CreativeWork, DataHash, Exif, Metadata, SoftwareAgent,
-        Thumbnail, User, UserCbor,
+        labels, Actions, BmffHash, BoxHash, CreativeWork, DataHash, Exif, FragmentOptions,
+        Metadata, SoftwareAgent, Thumbnail, User, UserCbor,
     },
     claim::Claim,
     error::{Error, Result},
@@ -228,6 +228,11 @@ pub struct Builder {
     #[cfg(feature = "file_io")]
     pub base_path: Option<PathBuf>,
 
+    /// Options for embedding the C2PA boxes into BMFF fragments.
+    #[cfg(feature = "file_io")]
+    #[serde(skip)]
+    pub fragment_options: FragmentOptions,
+
     /// Container for binary assets (like thumbnails).
     #[serde(skip)]
     resources: ResourceStore,
@@ -1093,6 +1098,51 @@ impl Builder {
             output_path.as_ref(),
             signer,
             Some(0),
+            &self.fragment_options,
+        )
+    }
+
+    /// Merge a fragmented BMFF file set into a single signed asset.
+    ///
+    /// The init segment and the fragments are concatenated into `output_path`,
+    /// which is hashed with a Merkle tree over its moof ranges.
+    ///
+    /// Note: Currently this does not support files with existing C2PA data.
+    ///
+    /// # Arguments
+    /// * `signer` - The signer to use.
+    /// * `asset_path` - The path to the init segment.
+    /// * `fragment_paths` - The paths to the fragments, in playback order.
+    /// * `output_path` - The path to the merged output file.
+    ///
+    /// # Errors
+    /// * Returns an [`Error`] if the manifest cannot be signed.
+    #[cfg(feature = "file_io")]
+    pub fn sign_merged_fragments<P: AsRef<Path>>(
+        &mut self,
+        signer: &dyn Signer,
+        asset_path: P,
+        fragment_paths: &[std::path::PathBuf],
+        output_path: P,
+    ) -> Result<()> {
+        if output_path.as_ref().exists() {
+            return Err(crate::Error::BadParam(
+                "Destination file already exists".to_string(),
+            ));
+        }
+        if let Some(output_dir) = output_path.as_ref().parent() {
+            std::fs::create_dir_all(output_dir)?;
+        }
+
+        // convert the manifest to a store
+        let mut store = self.to_store()?;
+
+        // sign and write our store to the merged asset
+        store.save_to_bmff_merged(
+            asset_path.as_ref(),
+            fragment_paths,
+            output_path.as_ref(),
+            signer,
         )
     }
 
@@ -1133,6 +1183,7 @@ impl Builder {
         window_size: Option<usize>,
     ))]
     #[cfg(feature = "file_io")]
+    #[allow(clippy::ptr_arg)] // public signature, the paths are only read
     pub fn sign_live_bmff<P>(
         &mut self,
         signer: &dyn Signer,
@@ -1162,6 +1213,17 @@ impl Builder {
             }
         }
 
+        if let Some(base_path) = &self.base_path {
+            self.resources.set_base_path(base_path);
+        }
+
+        // inputs delivered through a pipe are buffered, they are read several times
+        let mut inputs = vec![asset_path.as_ref().to_path_buf()];
+        inputs.extend(fragment_paths.iter().cloned());
+        let (_buffer, mut inputs) = crate::utils::live::buffer_unseekable(&inputs)?;
+        let fragment_paths = &inputs.split_off(1);
+        let asset_path = &inputs[0];
+
         // convert the manifest to a store
         let mut store = self.to_store()?;
 
@@ -1173,6 +1235,7 @@ impl Builder {
                 output_path.as_ref(),
                 signer,
                 window_size,
+                &self.fragment_options,
             )
         } else {
             store
@@ -1182,11 +1245,52 @@ impl Builder {
                     output_path.as_ref(),
                     signer,
                     window_size,
+                    &self.fragment_options,
                 )
                 .await
         }
     }
 
+    /// Finalize a signed init segment in place.
+    ///
+    /// Re-signs the init segment at `init_path` with the `BmffHash` already embedded
+    /// in it, i.e. the Merkle trees of all signed windows or the current rolling hash,
+    /// and recomputes its init hash. Call this once signing with
+    /// [`Builder::sign_live_bmff`] has finished.
+    ///
+    /// # Arguments
+    /// * `signer` - The signer to use.
+    /// * `init_path` - The path to the signed init segment.
+    ///
+    /// # Errors
+    /// * Returns an [`Error`] if the init segment isn't signed or cannot be re-signed.
+    #[cfg(feature = "file_io")]
+    pub fn finalize_init<P: AsRef<Path>>(
+        &mut self,
+        signer: &dyn Signer,
+        init_path: P,
+    ) -> Result<()> {
+        let path = init_path.as_ref();
+        self.definition.format =
+            crate::format_from_path(path).ok_or(crate::Error::UnsupportedType)?;
+        self.definition.instance_id = format!("xmp:iid:{}", Uuid::new_v4());
+        if self.definition.title.is_none() {
+            if let Some(title) = path.file_name() {
+                self.definition.title = Some(title.to_string_lossy().to_string());
+            }
+        }
+
+        if let Some(base_path) = &self.base_path {
+            self.resources.set_base_path(base_path);
+        }
+
+        // convert the manifest to a store
+        let mut store = self.to_store()?;
+
+        // sign and write our store back to the init segment
+        store.save_to_bmff_init(path, signer)
+    }
+
     #[cfg(feature = "file_io")]
     /// Sign a file using a supplied signer.
     /// # Arguments
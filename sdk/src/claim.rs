@@ -2199,7 +2199,7 @@ impl Claim {
                             dh.verify_in_memory_hash(asset_bytes, Some(claim.alg()))
                         }
                         ClaimAssetData::Stream(stream_data, _) => {
-                            dh.verify_stream_hash(*stream_data, Some(claim.alg()))
+                            dh.verify_stream_hash(*stream_data, Some(claim.alg()), None)
                         }
                         ClaimAssetData::StreamFragment(initseg_data, fragment_data, _) => dh
                             .verify_stream_segment(
@@ -2213,6 +2213,7 @@ impl Claim {
                                 *initseg_data,
                                 fragment_paths,
                                 Some(claim.alg()),
+                                None,
                             ),
                         ClaimAssetData::RollingHash(init_data, fragment_data, _, previous_hash) => {
                             dh.verify_fragment(
@@ -2250,16 +2251,19 @@ impl Claim {
                             continue;
                         }
                         Err(e) => {
+                            let desc = format!("asset hash error, name: {name}, error: {e}");
+                            // keep structural problems distinct from tampering
+                            let err = match e {
+                                Error::InvalidAsset(_) => e,
+                                _ => Error::HashMismatch(format!("Asset hash failure: {e}")),
+                            };
                             log_item!(
                                 claim.assertion_uri(&hash_binding_assertion.label()),
-                                format!("asset hash error, name: {name}, error: {e}"),
+                                desc,
                                 "verify_internal"
                             )
                             .validation_status(validation_status::ASSERTION_BMFFHASH_MISMATCH)
-                            .failure(
-                                validation_log,
-                                Error::HashMismatch(format!("Asset hash failure: {e}")),
-                            )?;
+                            .failure(validation_log, err)?;
                         }
                     }
                 } else if hash_binding_assertion.label_root() == BoxHash::LABEL {
@@ -142,6 +142,33 @@ pub(crate) fn cose_sign(
     }
 }
 
+/// Bytes added to a probed reserve size, time stamps and OCSP responses differ in
+/// size from one request to the next.
+pub(crate) const RESERVE_SIZE_MARGIN: usize = 1024;
+
+/// Measures the COSE signature of `signer`, see [`Signer::probe_reserve_size`].
+pub(crate) fn probe_reserve_size<S: Signer + ?Sized>(signer: &S) -> Result<usize> {
+    if signer.direct_cose_handling() {
+        return Ok(signer.reserve_size());
+    }
+
+    // the payload is detached, it doesn't change the size of the signature
+    let data = b"c2pa reserve size probe";
+    let mut size = 0;
+    for tss in [
+        TimeStampStorage::V1_sigTst,
+        TimeStampStorage::V2_sigTst2_CTT,
+    ] {
+        let cose = match signer.raw_signer() {
+            Some(raw_signer) => sign(*raw_signer, data, None, tss)?,
+            None => sign(&SignerWrapper(signer), data, None, tss)?,
+        };
+        size = size.max(cose.len());
+    }
+
+    Ok(size + RESERVE_SIZE_MARGIN)
+}
+
 fn signing_cert_valid(signing_cert: &[u8]) -> Result<()> {
     // make sure signer certs are valid
     let mut cose_log = StatusTracker::with_error_behavior(ErrorBehavior::StopOnFirstError);
@@ -160,9 +187,9 @@ fn signing_cert_valid(signing_cert: &[u8]) -> Result<()> {
     )?)
 }
 
-struct SignerWrapper<'a>(&'a dyn Signer);
+struct SignerWrapper<'a, S: Signer + ?Sized>(&'a S);
 
-impl RawSigner for SignerWrapper<'_> {
+impl<S: Signer + ?Sized> RawSigner for SignerWrapper<'_, S> {
     fn sign(&self, data: &[u8]) -> std::result::Result<Vec<u8>, RawSignerError> {
         Ok(self.0.sign(data)?)
     }
@@ -184,7 +211,7 @@ impl RawSigner for SignerWrapper<'_> {
     }
 }
 
-impl TimeStampProvider for SignerWrapper<'_> {
+impl<S: Signer + ?Sized> TimeStampProvider for SignerWrapper<'_, S> {
     fn time_stamp_service_url(&self) -> Option<String> {
         self.0.time_authority_url()
     }
@@ -268,9 +295,12 @@ impl AsyncTimeStampProvider for AsyncSignerWrapper<'_> {
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
-    use c2pa_crypto::raw_signature::SigningAlg;
+    use c2pa_crypto::{
+        cose::{sign, TimeStampStorage},
+        raw_signature::SigningAlg,
+    };
 
-    use super::sign_claim;
+    use super::{sign_claim, SignerWrapper, RESERVE_SIZE_MARGIN};
     #[cfg(feature = "file_io")]
     use crate::utils::test_signer::async_test_signer;
     use crate::{claim::Claim, utils::test_signer::test_signer, Result, Signer};
@@ -360,6 +390,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_probe_reserve_size() {
+        crate::settings::set_settings_value("verify.verify_trust", false).unwrap();
+
+        for alg in [SigningAlg::Ps256, SigningAlg::Es256] {
+            let signer = test_signer(alg);
+            let probed = signer.probe_reserve_size().unwrap();
+
+            let mut actual = 0;
+            for (version, tss) in [
+                (1, TimeStampStorage::V1_sigTst),
+                (2, TimeStampStorage::V2_sigTst2_CTT),
+            ] {
+                let mut claim = Claim::new("probe_test", Some("contentauth"), version);
+                claim.add_claim_generator_info(crate::ClaimGeneratorInfo::new("probe_test"));
+                claim.build().unwrap();
+                let claim_bytes = claim.data().unwrap();
+
+                let unpadded =
+                    sign(&SignerWrapper(signer.as_ref()), &claim_bytes, None, tss).unwrap();
+                actual = actual.max(unpadded.len());
+
+                // the probed size fits the signature of a real claim
+                let cose_sign1 = sign_claim(&claim_bytes, signer.as_ref(), probed).unwrap();
+                assert_eq!(cose_sign1.len(), probed);
+            }
+            assert_eq!(probed, actual + RESERVE_SIZE_MARGIN);
+        }
+    }
+
     #[test]
     fn test_bogus_signer() {
         let mut claim = Claim::new("bogus_sign_test", Some("contentauth"), 1);
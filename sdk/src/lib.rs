@@ -140,7 +140,9 @@ pub use resource_store::{ResourceRef, ResourceStore};
 #[cfg(feature = "v1_api")]
 pub use signer::RemoteSigner;
 pub use signer::{AsyncSigner, Signer};
-pub use utils::mime::format_from_path;
+#[cfg(feature = "add_thumbnails")]
+pub use utils::thumbnail::make_thumbnail_from_stream;
+pub use utils::{live::verify_init_signature, mime::format_from_path};
 pub use validation_results::{ValidationResults, ValidationState};
 
 // Internal modules
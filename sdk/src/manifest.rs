@@ -1338,6 +1338,7 @@ impl Manifest {
             output_path.as_ref(),
             signer,
             Some(0),
+            &Default::default(),
         )
     }
 
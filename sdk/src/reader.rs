@@ -711,7 +711,7 @@ impl Reader {
     }
 
     #[async_generic()]
-    fn from_store(store: Store, validation_log: &StatusTracker) -> Result<Self> {
+    pub(crate) fn from_store(store: Store, validation_log: &StatusTracker) -> Result<Self> {
         let mut validation_results = ValidationResults::from_store(&store, validation_log);
 
         let active_manifest = store.provenance_label();
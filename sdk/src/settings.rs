@@ -202,6 +202,8 @@ pub(crate) struct Verify {
     ocsp_fetch: bool,
     remote_manifest_fetch: bool,
     check_ingredient_trust: bool,
+    allowed_clock_skew: u64, // seconds a signing time may lie outside of a certificate's validity
+    tolerate_trailing_boxes: bool, // ignore boxes appended to a BMFF fragment after signing
 }
 
 impl Default for Verify {
@@ -213,6 +215,8 @@ impl Default for Verify {
             ocsp_fetch: false,
             remote_manifest_fetch: true,
             check_ingredient_trust: true,
+            allowed_clock_skew: 0,
+            tolerate_trailing_boxes: false,
         }
     }
 }
@@ -40,6 +40,18 @@ pub trait Signer {
     /// than this value.
     fn reserve_size(&self) -> usize;
 
+    /// Measures the size of an actual signature, including the time stamp and
+    /// OCSP response if any, and adds a safety margin.
+    ///
+    /// The result can be used as [`Self::reserve_size`] when a static value risks
+    /// being too small for the algorithm or time stamp authority. This performs a
+    /// dummy signature and time stamp request, so it should be done once when the
+    /// signer is created. Signers handling the COSE structure directly return
+    /// [`Self::reserve_size`].
+    fn probe_reserve_size(&self) -> Result<usize> {
+        crate::cose_sign::probe_reserve_size(self)
+    }
+
     /// URL for time authority to time stamp the signature
     fn time_authority_url(&self) -> Option<String> {
         None
@@ -43,9 +43,10 @@ use crate::{
     },
     assertions::{
         labels::{self, CLAIM},
-        BmffHash, DataBox, DataHash, DataMap, ExclusionsMap, Ingredient, Relationship, SubsetMap,
-        User, UserCbor,
+        BmffHash, DataBox, DataHash, DataMap, ExclusionsMap, FragmentOptions, Ingredient,
+        Relationship, SubsetMap, User, UserCbor,
     },
+    asset_handlers::bmff_io::is_cenc_protected,
     asset_io::{
         CAIRead, CAIReadWrite, HashBlockObjectType, HashObjectPositions, RemoteRefEmbedType,
     },
@@ -171,6 +172,10 @@ impl Store {
             }
         });
 
+        if let Ok(skew) = get_settings_value::<u64>("verify.allowed_clock_skew") {
+            store.ctp.set_allowed_clock_skew(skew);
+        }
+
         store
     }
 
@@ -1648,6 +1653,8 @@ impl Store {
         // The spec has mandatory BMFF exclusion ranges for certain atoms.
         // The function makes sure those are included.
 
+        let cenc_protected = is_cenc_protected(asset_stream)?;
+
         let mut dh = BmffHash::new("jumbf manifest", alg, None);
         let exclusions = dh.exclusions_mut();
 
@@ -1671,6 +1678,14 @@ impl Store {
         let mfra = ExclusionsMap::new("/mfra".to_owned());
         exclusions.push(mfra);
 
+        // Common Encryption license data changes with key rotation, the per-sample
+        // encryption data (senc, saiz, saio, seig sample groups) stays covered
+        if cenc_protected {
+            for xpath in ["/moov/pssh", "/moof/pssh"] {
+                exclusions.push(ExclusionsMap::new(xpath.to_owned()));
+            }
+        }
+
         /*  no longer mandatory
         // meta/iloc exclusion
         let iloc = ExclusionsMap::new("/meta/iloc".to_owned());
@@ -2126,6 +2141,47 @@ impl Store {
         Ok(true)
     }
 
+    #[cfg(feature = "file_io")]
+    fn start_save_bmff_init(&mut self, init_path: &Path, reserve_size: usize) -> Result<Vec<u8>> {
+        // get the provenance claim changing mutability
+        let pc = self.provenance_claim_mut().ok_or(Error::ClaimEncoding)?;
+        pc.clear_data(); // clear since we are reusing an existing claim
+
+        // the BMFF Hashes of the signed init hold the Merkle trees or rolling hash so far
+        let init_stream = std::fs::File::open(init_path)?;
+        let mut bmff_hash = crate::Reader::from_stream("m4s", init_stream)
+            .ok()
+            .and_then(|reader| {
+                reader
+                    .active_manifest()?
+                    .find_assertion::<BmffHash>(crate::assertions::labels::BMFF_HASH_2)
+                    .ok()
+            })
+            .ok_or(Error::BadParam("init segment is not signed".to_string()))?;
+        bmff_hash.set_bmff_version(2);
+
+        // add in the BMFF assertion
+        pc.add_assertion(&bmff_hash)?;
+
+        // write preliminary jumbf store to the init
+        let mut data = self.to_jumbf_internal(reserve_size)?;
+        let jumbf_size = data.len();
+        save_jumbf_to_file(&data, init_path, Some(init_path))?;
+
+        // generate actual init hash values
+        let pc = self.provenance_claim_mut().ok_or(Error::ClaimEncoding)?; // reborrow to change mutability
+        bmff_hash.update_fragmented_inithash(init_path)?;
+        pc.update_bmff_hash(bmff_hash)?;
+
+        // regenerate the jumbf because the cbor changed
+        data = self.to_jumbf_internal(reserve_size)?;
+        if jumbf_size != data.len() {
+            return Err(Error::JumbfCreationError);
+        }
+
+        Ok(data) // return JUMBF data
+    }
+
     #[cfg(feature = "file_io")]
     fn start_save_bmff_fragmented(
         &mut self,
@@ -2134,38 +2190,49 @@ impl Store {
         output_dir: &Path,
         reserve_size: usize,
         window_size: Option<usize>,
+        options: &FragmentOptions,
     ) -> Result<Vec<u8>> {
         // get the provenance claim changing mutability
         let pc = self.provenance_claim_mut().ok_or(Error::ClaimEncoding)?;
         pc.clear_data(); // clear since we are reusing an existing claim
+        let alg = options.content_hash_alg(pc.alg())?.to_owned();
 
         // use existing signed asset file if available
-        let asset_path = crate::utils::live::signed_output(asset_path, output_dir)?
-            .unwrap_or(asset_path.to_path_buf());
+        let signed_output = crate::utils::live::signed_output(asset_path, output_dir)?;
+        let resume = signed_output.is_some();
+        let asset_path = signed_output.unwrap_or(asset_path.to_path_buf());
 
         // 2) Get hash ranges if needed
         let mut asset_stream = std::fs::File::open(&asset_path)?;
 
-        // attempt to extract BMFF Hashes from asset
+        // attempt to extract BMFF Hashes from the signed output, the manifest of an
+        // asset signed before (e.g. by a camera) belongs to another stream and is replaced
         let mut bmff_hash = None;
-        if let Ok(reader) = crate::Reader::from_stream("m4s", &asset_stream) {
-            if let Some(manifest) = reader.active_manifest() {
-                if let Ok(mut b) =
-                    manifest.find_assertion::<BmffHash>(crate::assertions::labels::BMFF_HASH_2)
-                {
-                    b.set_bmff_version(2);
-                    bmff_hash = Some(b);
+        if resume {
+            if let Ok(reader) = crate::Reader::from_stream("m4s", &asset_stream) {
+                if let Some(manifest) = reader.active_manifest() {
+                    if let Ok(mut b) =
+                        manifest.find_assertion::<BmffHash>(crate::assertions::labels::BMFF_HASH_2)
+                    {
+                        b.set_bmff_version(2);
+                        bmff_hash = Some(b);
+                    }
                 }
             }
         }
         // init new ones if none found
         let mut bmff_hash = bmff_hash.unwrap_or(Store::generate_bmff_data_hash_for_stream(
             &mut asset_stream,
-            pc.alg(),
+            &alg,
             false,
             false,
         )?);
         bmff_hash.clear_hash();
+        for exclusion in &options.exclusions {
+            if !bmff_hash.exclusions().contains(exclusion) {
+                bmff_hash.exclusions_mut().push(exclusion.clone());
+            }
+        }
 
         match window_size {
             Some(window) => {
@@ -2206,14 +2273,16 @@ impl Store {
                 };
 
                 // generate fragments and produce Merkle tree
-                bmff_hash.add_merkle_for_fragmented(
-                    pc.alg(),
+                let stats = bmff_hash.add_merkle_for_fragmented(
+                    &alg,
                     &asset_path,
                     sign_fragments,
                     output_dir,
                     local_id,
                     unique_id,
+                    options,
                 )?;
+                log::debug!("Merkle tree {local_id}: {stats:?}");
             }
             None => {
                 // rolling hash signing
@@ -2226,7 +2295,13 @@ impl Store {
 
                 bmff_hash.shift_rolling_hash();
 
-                bmff_hash.add_rolling_hash_fragment(pc.alg(), &asset_path, fragment, output_dir)?;
+                bmff_hash.add_rolling_hash_fragment(
+                    &alg,
+                    &asset_path,
+                    fragment,
+                    output_dir,
+                    options,
+                )?;
             }
         }
 
@@ -2260,6 +2335,97 @@ impl Store {
         Ok(data) // return JUMBF data
     }
 
+    #[cfg(feature = "file_io")]
+    fn start_save_bmff_merged(
+        &mut self,
+        asset_path: &Path,
+        fragments: &[std::path::PathBuf],
+        output_path: &Path,
+        reserve_size: usize,
+    ) -> Result<Vec<u8>> {
+        // get the provenance claim changing mutability
+        let pc = self.provenance_claim_mut().ok_or(Error::ClaimEncoding)?;
+        pc.clear_data(); // clear since we are reusing an existing claim
+
+        let mut asset_stream = std::fs::File::open(asset_path)?;
+        let mut bmff_hash =
+            Store::generate_bmff_data_hash_for_stream(&mut asset_stream, pc.alg(), false, false)?;
+        bmff_hash.clear_hash();
+
+        // merge the fragments and insert the placeholder Merkle boxes
+        bmff_hash.add_merkle_for_merged(pc.alg(), asset_path, fragments, output_path, 1)?;
+
+        // add in the BMFF assertion
+        pc.add_assertion(&bmff_hash)?;
+
+        // write preliminary jumbf store to the merged asset
+        let mut data = self.to_jumbf_internal(reserve_size)?;
+        let jumbf_size = data.len();
+        save_jumbf_to_file(&data, output_path, Some(output_path))?;
+
+        // generate actual hash values now that the layout is final
+        let pc = self.provenance_claim_mut().ok_or(Error::ClaimEncoding)?; // reborrow to change mutability
+
+        let bmff_hashes = pc.bmff_hash_assertions();
+
+        if !bmff_hashes.is_empty() {
+            let mut bmff_hash = BmffHash::from_assertion(bmff_hashes[0])?;
+            bmff_hash.update_merged_merkle(output_path)?;
+            pc.update_bmff_hash(bmff_hash)?;
+        }
+
+        // regenerate the jumbf because the cbor changed
+        data = self.to_jumbf_internal(reserve_size)?;
+        if jumbf_size != data.len() {
+            return Err(Error::JumbfCreationError);
+        }
+
+        Ok(data) // return JUMBF data
+    }
+
+    /// Embed the claims store as jumbf into a single asset merged from an
+    /// init segment and its fragments.
+    #[cfg(feature = "file_io")]
+    pub fn save_to_bmff_merged(
+        &mut self,
+        asset_path: &Path,
+        fragments: &[std::path::PathBuf],
+        output_path: &Path,
+        signer: &dyn Signer,
+    ) -> Result<()> {
+        match get_supported_file_extension(asset_path) {
+            Some(ext) => {
+                if !is_bmff_format(&ext) {
+                    return Err(Error::UnsupportedType);
+                }
+            }
+            None => return Err(Error::UnsupportedType),
+        }
+
+        let mut validation_log =
+            StatusTracker::with_error_behavior(ErrorBehavior::StopOnFirstError);
+
+        let jumbf = self.to_jumbf(signer)?;
+
+        // use temp store so mulitple calls will work (the Store is not finalized this way)
+        let mut temp_store = Store::from_jumbf(&jumbf, &mut validation_log)?;
+
+        let jumbf_bytes = temp_store.start_save_bmff_merged(
+            asset_path,
+            fragments,
+            output_path,
+            signer.reserve_size(),
+        )?;
+
+        let pc = temp_store.provenance_claim().ok_or(Error::ClaimEncoding)?;
+        let sig = temp_store.sign_claim(pc, signer, signer.reserve_size())?;
+        let sig_placeholder = Store::sign_claim_placeholder(pc, signer.reserve_size());
+
+        temp_store.finish_save(jumbf_bytes, output_path, sig, &sig_placeholder)?;
+
+        Ok(())
+    }
+
     /// Embed the claims store as jumbf into fragmented assets.
     #[async_generic(async_signature(
         &mut self,
@@ -2268,6 +2434,7 @@ impl Store {
         output_path: &Path,
         signer: &dyn AsyncSigner,
         window_size: Option<usize>,
+        options: &FragmentOptions,
     ))]
     #[cfg(feature = "file_io")]
     pub fn save_to_bmff_fragmented(
@@ -2277,6 +2444,7 @@ impl Store {
         output_path: &Path,
         signer: &dyn Signer,
         window_size: Option<usize>,
+        options: &FragmentOptions,
     ) -> Result<()> {
         match get_supported_file_extension(asset_path) {
             Some(ext) => {
@@ -2305,6 +2473,7 @@ impl Store {
             output_path,
             signer.reserve_size(),
             window_size,
+            options,
         )?;
 
         let pc = temp_store.provenance_claim().ok_or(Error::ClaimEncoding)?;
@@ -2323,6 +2492,37 @@ impl Store {
         }
     }
 
+    /// Re-embed the `BmffHash` of a signed init segment into it, in place.
+    #[cfg(feature = "file_io")]
+    pub fn save_to_bmff_init(&mut self, init_path: &Path, signer: &dyn Signer) -> Result<()> {
+        match get_supported_file_extension(init_path) {
+            Some(ext) => {
+                if !is_bmff_format(&ext) {
+                    return Err(Error::UnsupportedType);
+                }
+            }
+            None => return Err(Error::UnsupportedType),
+        }
+
+        let mut validation_log =
+            StatusTracker::with_error_behavior(ErrorBehavior::StopOnFirstError);
+
+        let jumbf = self.to_jumbf(signer)?;
+
+        // use temp store so mulitple calls will work (the Store is not finalized this way)
+        let mut temp_store = Store::from_jumbf(&jumbf, &mut validation_log)?;
+
+        let jumbf_bytes = temp_store.start_save_bmff_init(init_path, signer.reserve_size())?;
+
+        let pc = temp_store.provenance_claim().ok_or(Error::ClaimEncoding)?;
+        let sig = temp_store.sign_claim(pc, signer, signer.reserve_size())?;
+        let sig_placeholder = Store::sign_claim_placeholder(pc, signer.reserve_size());
+
+        temp_store.finish_save(jumbf_bytes, init_path, sig, &sig_placeholder)?;
+
+        Ok(())
+    }
+
     /// Embed the claims store as jumbf into a stream. Updates XMP with provenance record.
     /// When called, the stream should contain an asset matching format.
     /// on return, the stream will contain the new manifest signed with signer
@@ -4113,6 +4313,16 @@ pub mod tests {
         .expect_err("Should not verify");
     }
 
+    #[test]
+    fn test_allowed_clock_skew_setting() {
+        assert_eq!(Store::new().ctp.allowed_clock_skew(), 0);
+
+        crate::settings::set_settings_value("verify.allowed_clock_skew", 5u64).unwrap();
+        assert_eq!(Store::new().ctp.allowed_clock_skew(), 5);
+
+        crate::settings::reset_default_settings().unwrap();
+    }
+
     #[test]
     #[cfg(feature = "file_io")]
     fn test_claim_v2_generation() {
@@ -6698,6 +6908,7 @@ BigBuckBunny_2s_init.mp4")
                             new_output_path.as_path(),
                             signer.as_ref(),
                             Some(0),
+                            &FragmentOptions::default(),
                         )
                         .unwrap();
 
@@ -14,6 +14,7 @@
 use std::{
     ffi::OsStr,
     io::{Read, Seek, SeekFrom, Write},
+    path::Path,
 };
 
 #[allow(unused)] // different code path for WASI
@@ -166,6 +167,27 @@ pub(crate) fn tempfile_builder<T: AsRef<OsStr> + Sized>(prefix: T) -> Result<Nam
         .map_err(Error::IoError);
 }
 
+// Copies `source` to `dest` unless `dest` already exists, returns whether it was copied.
+// The copy is written to a temporary file next to `dest` and moved into place without
+// replacing an existing file, so concurrent callers never see a partially written `dest`.
+pub(crate) fn copy_if_missing(source: &Path, dest: &Path) -> Result<bool> {
+    if dest.exists() {
+        return Ok(false);
+    }
+
+    let dir = dest
+        .parent()
+        .ok_or(Error::BadParam("invalid destination path".to_string()))?;
+    let mut temp = Builder::new().prefix(".c2pa").tempfile_in(dir)?;
+    std::io::copy(&mut std::fs::File::open(source)?, temp.as_file_mut())?;
+
+    match temp.persist_noclobber(dest) {
+        Ok(_) => Ok(true),
+        Err(err) if err.error.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(err) => Err(Error::IoError(err.error)),
+    }
+}
+
 #[allow(dead_code)] // used in tests
 pub(crate) fn tempdirectory() -> Result<TempDir> {
     #[cfg(target_os = "wasi")]
@@ -305,4 +327,23 @@ mod tests {
         )
         .is_err());
     }
+
+    #[test]
+    fn test_copy_if_missing() {
+        let dir = tempdirectory().unwrap();
+        let source = dir.path().join("source.mp4");
+        let dest = dir.path().join("dest.mp4");
+        std::fs::write(&source, b"init").unwrap();
+
+        assert!(copy_if_missing(&source, &dest).unwrap());
+        assert_eq!(std::fs::read(&dest).unwrap(), b"init");
+
+        // an existing destination is kept
+        std::fs::write(&dest, b"signed init").unwrap();
+        assert!(!copy_if_missing(&source, &dest).unwrap());
+        assert_eq!(std::fs::read(&dest).unwrap(), b"signed init");
+
+        // no temporary files are left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
 }
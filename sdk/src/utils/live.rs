@@ -3,7 +3,13 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::{Error, Result};
+use c2pa_crypto::cose::{CertificateInfo, CertificateTrustPolicy};
+use c2pa_status_tracker::{ErrorBehavior, StatusTracker};
+
+use crate::{
+    asset_io::CAIRead, cose_validator::verify_cose, jumbf_io::load_jumbf_from_stream, store::Store,
+    Error, Result,
+};
 
 pub fn signed_output<P>(file: P, output: P) -> Result<Option<PathBuf>>
 where
@@ -30,6 +36,45 @@ where
     }
 }
 
+/// Copies the inputs which aren't regular files, e.g. FIFOs, into a temporary
+/// directory, keeping their file names. Signing seeks within and re-reads its
+/// inputs, which a pipe doesn't support.
+///
+/// Returns the directory, if any input was copied, and the paths to sign from.
+pub fn buffer_unseekable(paths: &[PathBuf]) -> Result<(Option<tempfile::TempDir>, Vec<PathBuf>)> {
+    let mut dir = None;
+    let mut buffered = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        if std::fs::metadata(path)?.is_file() {
+            buffered.push(path.clone());
+            continue;
+        }
+
+        let file_name = path
+            .file_name()
+            .ok_or(Error::BadParam("file name missing".to_string()))?;
+        let dir = match &mut dir {
+            Some(dir) => dir,
+            None => dir.insert(tempfile::tempdir()?),
+        };
+        let copy = dir.path().join(file_name);
+        if copy.exists() {
+            return Err(Error::BadParam(format!(
+                "duplicate input file name {file_name:?}"
+            )));
+        }
+
+        std::io::copy(
+            &mut std::fs::File::open(path)?,
+            &mut std::fs::File::create(&copy)?,
+        )?;
+        buffered.push(copy);
+    }
+
+    Ok((dir, buffered))
+}
+
 pub fn replace_c2pa_box<W>(file: &mut W, buf: &[u8], offset: Option<u64>) -> Result<()>
 where
     W: Read + Write + Seek,
@@ -61,8 +106,43 @@ where
     Ok(())
 }
 
+/// Verifies the COSE signature and certificate chain of the manifest in a
+/// signed init segment, without hashing any media.
+///
+/// Neither the hash of the init segment nor any fragments are checked, use this
+/// for a quick trust check of a stream before verifying its fragments.
+///
+/// Returns the signer certificate info (chain, issuer and the signing time if
+/// the signature is time-stamped).
+pub fn verify_init_signature(
+    init_stream: &mut dyn CAIRead,
+    trust_policy: &CertificateTrustPolicy,
+) -> Result<CertificateInfo> {
+    let mut validation_log = StatusTracker::with_error_behavior(ErrorBehavior::StopOnFirstError);
+
+    let manifest_bytes = load_jumbf_from_stream("mp4", init_stream)?;
+    let store = Store::from_jumbf(&manifest_bytes, &mut validation_log)?;
+    let claim = store.provenance_claim().ok_or(Error::ProvenanceMissing)?;
+
+    let info = verify_cose(
+        claim.signature_val(),
+        &claim.data()?,
+        b"",
+        true,
+        trust_policy,
+        &mut validation_log,
+    )?;
+    if !info.validated {
+        return Err(Error::CoseSignature);
+    }
+
+    Ok(info)
+}
+
 #[cfg(test)]
 mod tests {
+    #![allow(clippy::unwrap_used)]
+
     use std::fs::{create_dir_all, remove_dir_all, remove_file, File, OpenOptions};
 
     use super::*;
@@ -189,4 +269,51 @@ mod tests {
             unreachable!()
         };
     }
+
+    #[test]
+    #[cfg(feature = "file_io")]
+    fn verify_init_signature_test() {
+        let bunny =
+            |name: &str| crate::utils::test::fixture_path(&format!("bunny/bunny_89283bps/{name}"));
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let init_output = temp_dir.path().join("BigBuckBunny_2s_init.mp4");
+        let signer =
+            crate::utils::test_signer::test_signer(c2pa_crypto::raw_signature::SigningAlg::Ps256);
+        crate::Builder::from_json(
+            r#"{"assertions": [{"label": "c2pa.actions", "data": {"actions": [{"action": "c2pa.published"}]}}]}"#,
+        )
+            .unwrap()
+            .sign_live_bmff(
+                signer.as_ref(),
+                bunny("BigBuckBunny_2s_init.mp4"),
+                &vec![bunny("BigBuckBunny_2s1.m4s")],
+                init_output.clone(),
+                None,
+            )
+            .unwrap();
+
+        let ctp = crate::utils::test::test_certificate_acceptance_policy();
+        let mut init = std::fs::read(&init_output).unwrap();
+        let info = verify_init_signature(&mut std::io::Cursor::new(&init), &ctp).unwrap();
+        assert!(info.validated);
+        assert!(info.issuer_org.is_some());
+        assert!(!info.cert_chain.is_empty());
+
+        // the signer isn't trusted without the test roots
+        assert!(verify_init_signature(
+            &mut std::io::Cursor::new(&init),
+            &CertificateTrustPolicy::default()
+        )
+        .is_err());
+
+        // the media isn't hashed, only the signature of the manifest is checked
+        let ftyp_brand = 8;
+        init[ftyp_brand] ^= 0xff;
+        verify_init_signature(&mut std::io::Cursor::new(&init), &ctp).unwrap();
+
+        // unsigned init
+        let mut unsigned = File::open(bunny("BigBuckBunny_2s_init.mp4")).unwrap();
+        assert!(verify_init_signature(&mut unsigned, &ctp).is_err());
+    }
 }
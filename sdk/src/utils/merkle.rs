@@ -12,12 +12,13 @@
 // each license.
 
 use extfmt::Hexlify;
+use serde::{Deserialize, Serialize};
 
 use super::hash_utils::{concat_and_hash, hash_by_alg};
 use crate::{Error, Result};
 
-#[derive(Default, Clone, PartialEq, Debug)]
-pub struct MerkleNode(pub Vec<u8>);
+#[derive(Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MerkleNode(#[serde(with = "serde_bytes")] pub Vec<u8>);
 
 // Implements Merkle tree support corresponding to the C2PA spec variant.  The Merkle tree is not reduced and
 // all leaves live at the bottom most level.  If the last layer node is an odd index (lacking a matching pair),
@@ -48,6 +49,64 @@ impl C2PAMerkleTree {
         C2PAMerkleTree { leaves, layers }
     }
 
+    // restore a tree from its persisted layers (i.e. serialized `layers` of a previous tree),
+    // the node values are trusted and not re-hashed
+    pub fn from_persisted(layers: Vec<Vec<MerkleNode>>) -> Result<C2PAMerkleTree> {
+        let num_leaves = layers.first().map_or(0, |l| l.len());
+        let layout: Vec<usize> = layers.iter().map(|l| l.len()).collect();
+
+        if num_leaves > 0 && layout != C2PAMerkleTree::to_layout(num_leaves) {
+            return Err(Error::BadParam(
+                "persisted Merkle tree layers do not match the tree layout".to_string(),
+            ));
+        }
+
+        let leaves = layers.first().cloned().unwrap_or_default();
+
+        Ok(C2PAMerkleTree { leaves, layers })
+    }
+
+    // append a leaf to the tree, only the nodes on the path from the new leaf to the root are hashed
+    pub fn append_leaf(&mut self, leaf: MerkleNode, alg: &str) {
+        self.leaves.push(leaf.clone());
+
+        if self.layers.is_empty() {
+            self.layers.push(vec![leaf]);
+            return;
+        }
+        self.layers[0].push(leaf);
+
+        let mut level = 0;
+        while self.layers[level].len() > 1 {
+            let layer = &self.layers[level];
+            let index = layer.len() - 1;
+
+            let parent = if index % 2 == 0 {
+                // unbalanced last node is propagated
+                MerkleNode(layer[index].0.clone())
+            } else {
+                MerkleNode(concat_and_hash(
+                    alg,
+                    &layer[index - 1].0,
+                    Some(&layer[index].0),
+                ))
+            };
+
+            if level + 1 == self.layers.len() {
+                self.layers.push(Vec::new());
+            }
+            let parent_layer = &mut self.layers[level + 1];
+            let parent_index = index / 2;
+            if parent_index < parent_layer.len() {
+                parent_layer[parent_index] = parent;
+            } else {
+                parent_layer.push(parent);
+            }
+
+            level += 1;
+        }
+    }
+
     // create dummy tree to figure out the layout and proof sizes
     pub fn dummy_tree(num_leaves: usize, alg: &str) -> Self {
         let mut leaves: Vec<MerkleNode> = Vec::with_capacity(num_leaves);
@@ -172,3 +231,45 @@ impl C2PAMerkleTree {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn leaves(range: std::ops::Range<u8>) -> Vec<MerkleNode> {
+        range
+            .map(|i| MerkleNode(hash_by_alg("sha256", &[i], None)))
+            .collect()
+    }
+
+    #[test]
+    fn resume_from_persisted() {
+        let alg = "sha256";
+
+        for (persisted, total) in [(0, 1), (1, 2), (3, 4), (4, 7), (5, 16), (8, 9)] {
+            let tree = C2PAMerkleTree::from_leaves(leaves(0..persisted), alg, false);
+            let data = serde_cbor::to_vec(&tree.layers).unwrap();
+
+            let layers: Vec<Vec<MerkleNode>> = serde_cbor::from_slice(&data).unwrap();
+            let mut restored = C2PAMerkleTree::from_persisted(layers).unwrap();
+            for leaf in leaves(persisted..total) {
+                restored.append_leaf(leaf, alg);
+            }
+
+            let expected = C2PAMerkleTree::from_leaves(leaves(0..total), alg, false);
+            assert_eq!(restored.leaves, expected.leaves);
+            assert_eq!(restored.layers, expected.layers);
+            assert_eq!(restored.get_root(), expected.get_root());
+        }
+    }
+
+    #[test]
+    fn from_persisted_bad_layout() {
+        let mut layers = C2PAMerkleTree::from_leaves(leaves(0..5), "sha256", false).layers;
+        layers[1].pop();
+
+        assert!(C2PAMerkleTree::from_persisted(layers).is_err());
+    }
+}
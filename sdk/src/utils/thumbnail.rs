@@ -51,7 +51,7 @@ pub fn make_thumbnail(path: &std::path::Path) -> Result<(String, Vec<u8>)> {
     Ok((format, cursor.into_inner()))
 }
 
-///  utility to generate a thumbnail from a file at path
+///  utility to generate a thumbnail from a stream of the given format (extension or mime type)
 /// returns Result (format, image_bits) if successful, otherwise Error
 pub fn make_thumbnail_from_stream<R: Read + Seek + ?Sized>(
     format: &str,